@@ -240,6 +240,7 @@ fn build_candidate_tags(
             limit: AUTO_TAGGING_NEIGHBOR_LIMIT,
             semantic_query: Some(semantic_query.clone()),
             exclude_document_id: Some(document_id.to_owned()),
+            plain_text: true,
             ..SearchOptions::default()
         },
     ) {
@@ -1,4 +1,4 @@
-use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Debug, Parser)]
 #[command(
@@ -33,11 +33,14 @@ pub enum Commands {
     // Discovery
     List(ListArgs),
     Search(SearchArgs),
+    Related(RelatedArgs),
+    Eval(EvalArgs),
     Read(ReadArgs),
 
     // Operations
     Create(CreateArgs),
     Tag(TagArgs),
+    Migrate(MigrateArgs),
 
     // Deferred operations
     Edit(EditArgs),
@@ -50,6 +53,18 @@ pub enum Commands {
         #[command(subcommand)]
         command: FolderCommands,
     },
+
+    // Backup subcommands
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
+
+    // Embedding model subcommands
+    Model {
+        #[command(subcommand)]
+        command: ModelCommands,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -102,6 +117,12 @@ pub struct SearchArgs {
     #[arg(long)]
     pub snippets: bool,
 
+    #[arg(long, help = "Rescore the top results with the local cross-encoder model")]
+    pub rerank: bool,
+
+    #[arg(long, help = "Report per-hit rank and score contributions")]
+    pub explain: bool,
+
     #[arg(long, conflicts_with = "files", help = "Output search results in stable JSON schema")]
     pub json: bool,
 
@@ -109,6 +130,32 @@ pub struct SearchArgs {
     pub files: bool,
 }
 
+#[derive(Debug, Args)]
+pub struct RelatedArgs {
+    pub document_id: String,
+
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct EvalArgs {
+    /// JSON lines of labelled queries: {"id"?, "query", "relevant": [document ids]}
+    pub qrels: String,
+
+    #[arg(long, help = "Ranking depth for MRR, recall, and nDCG (default 10)")]
+    pub k: Option<usize>,
+
+    #[arg(long, help = "Compare against a report saved with --save-baseline")]
+    pub baseline: Option<String>,
+
+    #[arg(long, help = "Write this run's report as a baseline file")]
+    pub save_baseline: Option<String>,
+
+    #[arg(long, help = "Rescore the top results with the local cross-encoder model")]
+    pub rerank: bool,
+}
+
 #[derive(Debug, Args)]
 pub struct ReadArgs {
     pub document_id: String,
@@ -148,43 +195,126 @@ pub struct TagArgs {
     pub replace: bool,
 }
 
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    #[arg(
+        long,
+        help = "Only migrate documents in this folder and its sub-folders"
+    )]
+    pub folder: Option<String>,
+
+    #[arg(
+        long,
+        help = "Report the documents that would be converted without writing them"
+    )]
+    pub dry_run: bool,
+}
+
 #[derive(Debug, Args)]
 pub struct EditArgs {
     pub document_id: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportSource {
+    Notion,
+    Enex,
+    Html,
+    Pdf,
+    Conversations,
+    Mail,
+    Bookmarks,
+}
+
 #[derive(Debug, Args)]
 pub struct ImportArgs {
     pub source_path: String,
 
+    #[arg(
+        long,
+        value_enum,
+        help = "Format of the import source (detected from the extension when omitted)"
+    )]
+    pub from: Option<ImportSource>,
+
     #[arg(long)]
     pub folder: Option<String>,
 
     #[arg(long)]
     pub tags: Option<String>,
 
-    #[arg(long)]
+    #[arg(long, help = "Title for a single imported HTML page or PDF")]
     pub title: Option<String>,
+
+    #[arg(
+        long,
+        help = "Only import conversations updated on or after this date (YYYY-MM-DD)"
+    )]
+    pub since: Option<String>,
+
+    #[arg(
+        long,
+        help = "Only import conversations updated on or before this date (YYYY-MM-DD)"
+    )]
+    pub until: Option<String>,
+
+    #[arg(
+        long,
+        help = "Only import conversations mentioning any of these comma-separated keywords"
+    )]
+    pub keywords: Option<String>,
+
+    #[arg(
+        long,
+        help = "Import each email as its own document instead of one document per thread"
+    )]
+    pub per_message: bool,
 }
 
 #[derive(Debug, Args)]
-#[command(group(
-    ArgGroup::new("export_source")
-        .required(true)
-        .args(["document_id", "folder"])
-))]
 pub struct ExportArgs {
-    #[arg(value_name = "DOCUMENT_ID")]
-    pub document_id: Option<String>,
+    /// Document id and destination path, or only the destination path together with `--folder`
+    /// or `--tag`. Epub exports take the path of the book file to write.
+    #[arg(value_names = ["DOCUMENT_ID", "DESTINATION_PATH"], num_args = 1..=2, required = true)]
+    pub targets: Vec<String>,
 
-    #[arg(value_name = "DESTINATION_PATH")]
-    pub destination_path: String,
-
-    #[arg(long, conflicts_with = "document_id")]
+    #[arg(long, help = "Export a folder and its sub-folders instead of a single document")]
     pub folder: Option<String>,
 
+    #[arg(
+        long,
+        conflicts_with = "folder",
+        help = "Export every document with this tag instead of a single document"
+    )]
+    pub tag: Option<String>,
+
+    #[arg(long, value_enum, default_value = "markdown")]
+    pub format: ExportFormatArg,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Order documents like `list --sort` (default: folder, then title)"
+    )]
+    pub sort: Option<ListSort>,
+
     #[arg(long)]
-    pub format: Option<String>,
+    pub desc: bool,
+
+    #[arg(
+        long,
+        help = "Drop tentacle-specific frontmatter (id, timestamps, tags_locked) from markdown files"
+    )]
+    pub strip_frontmatter: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormatArg {
+    Markdown,
+    Json,
+    Jsonl,
+    Html,
+    Epub,
 }
 
 #[derive(Debug, Args)]
@@ -212,3 +342,63 @@ pub enum FolderCommands {
         new_name: String,
     },
 }
+
+#[derive(Debug, Subcommand)]
+pub enum BackupCommands {
+    Create {
+        file: String,
+
+        #[arg(long, help = "Also store config.db settings in the archive")]
+        include_config: bool,
+
+        #[arg(long, help = "Leave the search cache out; restore rebuilds it")]
+        exclude_cache: bool,
+    },
+    Restore {
+        file: String,
+
+        #[arg(
+            long,
+            help = "Restore into this folder instead of the configured documents folder"
+        )]
+        into: Option<String>,
+
+        #[arg(long, help = "Restore into a folder that is not empty")]
+        force: bool,
+
+        #[arg(long, help = "Also restore config.db settings from the archive")]
+        restore_config: bool,
+    },
+    Verify {
+        file: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ModelCommands {
+    Install {
+        #[arg(long, help = "Model directory or .tar/.tar.gz archive of one")]
+        from: String,
+
+        #[arg(
+            long,
+            help = "Model id or preset the files are for (default: the vault's embedding_model)"
+        )]
+        model: Option<String>,
+    },
+    Status,
+    Verify {
+        #[arg(
+            long,
+            help = "Installed model to check (default: the one model_path loads)"
+        )]
+        model: Option<String>,
+    },
+    Remove {
+        #[arg(
+            long,
+            help = "Installed model to delete (default: the one model_path loads)"
+        )]
+        model: Option<String>,
+    },
+}
@@ -34,6 +34,9 @@ pub enum CliError {
     #[error("{message}")]
     InvalidArguments { message: String },
 
+    #[error("{message} at column {column}")]
+    InvalidQuery { message: String, column: usize },
+
     #[error("{message}")]
     DocumentNotFound { message: String },
 
@@ -63,7 +66,9 @@ impl CliError {
     pub const fn code(&self) -> ErrorCode {
         match self {
             Self::NotImplemented { .. } => ErrorCode::NotImplemented,
-            Self::InvalidArguments { .. } => ErrorCode::InvalidArguments,
+            Self::InvalidArguments { .. } | Self::InvalidQuery { .. } => {
+                ErrorCode::InvalidArguments
+            }
             Self::DocumentNotFound { .. } => ErrorCode::DocumentNotFound,
             Self::FolderNotFound { .. } => ErrorCode::FolderNotFound,
             Self::PermissionDenied { .. } => ErrorCode::PermissionDenied,
@@ -79,6 +84,9 @@ impl CliError {
             Self::DocumentNotFound { .. } => "Use 'tentacle list' to see available documents.",
             Self::FolderNotFound { .. } => "Use 'tentacle folder list' to see available folders.",
             Self::InvalidArguments { .. } => "Run with --help to see valid command usage.",
+            Self::InvalidQuery { .. } => {
+                "Quote phrases with \"...\" and write filters as field:value, e.g. tag:infra or created:>2026-01-01."
+            }
             Self::PermissionDenied { .. } => {
                 "Check filesystem permissions for the configured documents folder."
             }
@@ -94,6 +102,10 @@ impl CliError {
                 code: self.code().as_str().to_owned(),
                 message: self.to_string(),
                 suggestion: self.suggestion().to_owned(),
+                column: match self {
+                    Self::InvalidQuery { column, .. } => Some(*column),
+                    _ => None,
+                },
             },
         }
     }
@@ -113,6 +125,9 @@ pub struct PublicError {
     pub code: String,
     pub message: String,
     pub suggestion: String,
+    /// 1-based position of the problem in the search query, for query syntax errors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
 }
 
 // Single source of truth for CLI exit-code mapping.
@@ -174,4 +189,25 @@ mod tests {
         assert_eq!(payload.error.code, "permission_denied");
         assert!(payload.error.suggestion.contains("permissions"));
     }
+
+    #[test]
+    fn output_error_payload_reports_query_error_column() {
+        let error = CliError::InvalidQuery {
+            message: "unterminated quote".to_owned(),
+            column: 8,
+        };
+        assert_eq!(error.exit_code(), 4);
+
+        let payload = serde_json::to_value(error.to_payload()).expect("payload serializes");
+        assert_eq!(payload["error"]["code"], "invalid_arguments");
+        assert_eq!(
+            payload["error"]["message"],
+            "unterminated quote at column 8"
+        );
+        assert_eq!(payload["error"]["column"], 8);
+
+        let payload = serde_json::to_value(CliError::invalid_arguments("bad").to_payload())
+            .expect("payload serializes");
+        assert!(payload["error"].get("column").is_none());
+    }
 }
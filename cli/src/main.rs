@@ -7,29 +7,73 @@ mod notifications;
 
 use clap::Parser;
 use serde::Serialize;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::process::ExitCode;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
-use tentacle_core::config::{default_data_dir, ConfigError, ConfigStore, KEY_DOCUMENTS_FOLDER};
-use tentacle_core::document_cache::{DocumentCacheError, DocumentCacheStore};
+use tentacle_core::backup::{
+    create_backup, restore_backup, verify_backup, BackupError, BackupOptions, RestoreOptions,
+};
+use tentacle_core::config::{
+    default_data_dir, ConfigError, ConfigStore, CONFIG_DATABASE_FILE_NAME, KEY_DOCUMENTS_FOLDER,
+    KEY_MODEL_PATH,
+};
+use tentacle_core::document_cache::{
+    DocumentCacheError, DocumentCacheStore, HighlightedTextPayload, HybridHitExplanationPayload,
+    SearchExplanationPayload, SearchPassagePayload,
+};
 use tentacle_core::document_folders::{
     DeleteDocumentFolderInputPayload, DocumentFolderPayload, DocumentFoldersError,
     DocumentFoldersService, RenameDocumentFolderInputPayload,
 };
 use tentacle_core::document_store::{
-    self, CreateDocumentInput, DocumentStoreError, StoredDocument, StoredDocumentListItem,
-    TagUpdateMode,
+    self, CreateDocumentInput, DocumentSort, DocumentStoreError, StoredDocument,
+    StoredDocumentListItem, TagUpdateMode, UpdateDocumentInput,
+};
+use tentacle_core::embeddings::{
+    configured_embedding_model, embedding_model_directory, is_local_model_path,
+    resolve_embedding_model_preset, set_embedding_model_path, set_installed_models_directory,
+    LOCAL_EMBEDDING_MODEL_ID,
+};
+use tentacle_core::evaluation::{
+    compare_reports, evaluate_qrels, load_qrels, read_report, write_report,
+    EvaluationComparisonPayload, EvaluationError, EvaluationMetricsPayload, EvaluationOptions,
+    QueryEvaluationPayload, DEFAULT_EVALUATION_CUTOFF,
+};
+use tentacle_core::export::{
+    export_documents, ExportError, ExportFormat, ExportOptions, ExportScope,
+    EXPORT_MANIFEST_FILE_NAME,
+};
+use tentacle_core::importers::{
+    bookmarks::{import_bookmarks, is_netscape_bookmark_file},
+    conversations::{import_conversations, ConversationImportOptions},
+    enex::import_enex,
+    html::import_html,
+    mail::{import_mail, MailImportOptions},
+    notion::import_notion_export,
+    pdf::import_pdf,
+    ImportError, ImportOptions,
 };
 use tentacle_core::knowledge_base::{KnowledgeBaseError, KnowledgeBaseService, SearchOptions};
+use tentacle_core::languages::SearchLanguage;
+use tentacle_core::models::{
+    install_model, installed_model_directory, list_installed_models, models_directory,
+    read_installed_model, remove_model, validate_model_directory, verify_model,
+    InstalledModelPayload, ModelArtifactStatus, ModelStoreError,
+};
+use tentacle_core::text_processing::tiptap_json_to_markdown;
+use tentacle_core::vault_settings::{
+    load_vault_settings, save_vault_settings, VaultSettings, VaultSettingsError,
+};
 
 use crate::auto_tagging::{apply_after_create, CreateAutoTaggingPayload};
 use crate::cli::{
-    Cli, Commands, ConfigCommands, CreateArgs, DeleteArgs, FolderCommands, ListArgs, ListSort,
-    ReadArgs, ReindexArgs, SearchArgs, TagArgs,
+    BackupCommands, Cli, Commands, ConfigCommands, CreateArgs, DeleteArgs, EvalArgs, ExportArgs,
+    ExportFormatArg, FolderCommands, ImportArgs, ImportSource, ListArgs, ListSort, MigrateArgs,
+    ModelCommands, ReadArgs, ReindexArgs, RelatedArgs, SearchArgs, TagArgs,
 };
 use crate::errors::{clap_exit_code, exit_code_for, summarize_clap_error, CliError};
 use crate::output::{format_bytes, humanize_datetime, normalize_iso8601, print_json};
@@ -38,10 +82,13 @@ const KEY_EDITOR: &str = "editor";
 const KEY_DEFAULT_FOLDER: &str = "default_folder";
 const KEY_AUTO_TAG: &str = "auto_tag";
 const KEY_OPENAI_API_KEY: &str = "openai_api_key";
+const KEY_AUTHOR: &str = "author";
+const KEY_RERANK: &str = "rerank";
 
 const DEFAULT_EDITOR: &str = "vi";
 const DEFAULT_FOLDER: &str = "inbox";
 const DEFAULT_AUTO_TAG: bool = true;
+const DEFAULT_RERANK: bool = false;
 const DEFAULT_DOCUMENTS_FOLDER_VALUE: &str = "~/Tentacle";
 
 fn main() -> ExitCode {
@@ -88,6 +135,7 @@ fn run(cli: &Cli) -> Result<(), CliError> {
     // Auto-initialize before any command (except explicit init)
     if !matches!(cli.command, Commands::Init) {
         ensure_initialized(cli.json)?;
+        apply_model_path_setting()?;
     }
 
     if !cli.json {
@@ -101,9 +149,12 @@ fn run(cli: &Cli) -> Result<(), CliError> {
         Commands::Reindex(args) => handle_reindex(args, cli.json),
         Commands::List(args) => handle_list(args, cli.json),
         Commands::Search(args) => handle_search(args, cli.json),
+        Commands::Related(args) => handle_related(args, cli.json),
+        Commands::Eval(args) => handle_eval(args, cli.json),
         Commands::Read(args) => handle_read(args, cli.json),
         Commands::Create(args) => handle_create(args, cli.json),
         Commands::Tag(args) => handle_tag(args, cli.json),
+        Commands::Migrate(args) => handle_migrate(args, cli.json),
         Commands::Folder { command } => match command {
             FolderCommands::List => handle_folder_list(cli.json),
             FolderCommands::Create { name } => handle_folder_create(name, cli.json),
@@ -113,9 +164,33 @@ fn run(cli: &Cli) -> Result<(), CliError> {
             }
         },
 
+        Commands::Backup { command } => match command {
+            BackupCommands::Create {
+                file,
+                include_config,
+                exclude_cache,
+            } => handle_backup_create(file, *include_config, *exclude_cache, cli.json),
+            BackupCommands::Restore {
+                file,
+                into,
+                force,
+                restore_config,
+            } => handle_backup_restore(file, into.as_deref(), *force, *restore_config, cli.json),
+            BackupCommands::Verify { file } => handle_backup_verify(file, cli.json),
+        },
+
+        Commands::Model { command } => match command {
+            ModelCommands::Install { from, model } => {
+                handle_model_install(from, model.as_deref(), cli.json)
+            }
+            ModelCommands::Status => handle_model_status(cli.json),
+            ModelCommands::Verify { model } => handle_model_verify(model.as_deref(), cli.json),
+            ModelCommands::Remove { model } => handle_model_remove(model.as_deref(), cli.json),
+        },
+
         Commands::Edit(_) => Err(CliError::not_implemented("edit")),
-        Commands::Import(_) => Err(CliError::not_implemented("import")),
-        Commands::Export(_) => Err(CliError::not_implemented("export")),
+        Commands::Import(args) => handle_import(args, cli.json),
+        Commands::Export(args) => handle_export(args, cli.json),
         Commands::Delete(args) => handle_delete(args, cli.json),
     }
 }
@@ -127,6 +202,14 @@ enum ConfigKey {
     DefaultFolder,
     AutoTag,
     OpenAiApiKey,
+    Author,
+    Rerank,
+    ChunkTokens,
+    ChunkOverlapTokens,
+    Abbreviations,
+    EmbeddingModel,
+    SearchLanguages,
+    ModelPath,
 }
 
 impl ConfigKey {
@@ -137,8 +220,16 @@ impl ConfigKey {
             "default_folder" => Ok(Self::DefaultFolder),
             "auto_tag" => Ok(Self::AutoTag),
             "openai_api_key" => Ok(Self::OpenAiApiKey),
+            "author" => Ok(Self::Author),
+            "rerank" => Ok(Self::Rerank),
+            "chunk_tokens" => Ok(Self::ChunkTokens),
+            "chunk_overlap_tokens" => Ok(Self::ChunkOverlapTokens),
+            "abbreviations" => Ok(Self::Abbreviations),
+            "embedding_model" => Ok(Self::EmbeddingModel),
+            "search_languages" => Ok(Self::SearchLanguages),
+            "model_path" => Ok(Self::ModelPath),
             _ => Err(CliError::invalid_arguments(format!(
-                "unsupported config key \"{raw_key}\"; supported keys: documents_folder, editor, default_folder, auto_tag, openai_api_key"
+                "unsupported config key \"{raw_key}\"; supported keys: documents_folder, editor, default_folder, auto_tag, openai_api_key, author, rerank, chunk_tokens, chunk_overlap_tokens, abbreviations, embedding_model, search_languages, model_path"
             ))),
         }
     }
@@ -150,6 +241,14 @@ impl ConfigKey {
             Self::DefaultFolder => KEY_DEFAULT_FOLDER,
             Self::AutoTag => KEY_AUTO_TAG,
             Self::OpenAiApiKey => KEY_OPENAI_API_KEY,
+            Self::Author => KEY_AUTHOR,
+            Self::Rerank => KEY_RERANK,
+            Self::ChunkTokens => "chunk_tokens",
+            Self::ChunkOverlapTokens => "chunk_overlap_tokens",
+            Self::Abbreviations => "abbreviations",
+            Self::EmbeddingModel => "embedding_model",
+            Self::SearchLanguages => "search_languages",
+            Self::ModelPath => KEY_MODEL_PATH,
         }
     }
 
@@ -160,6 +259,14 @@ impl ConfigKey {
             Self::DefaultFolder => "default_folder",
             Self::AutoTag => "auto_tag",
             Self::OpenAiApiKey => "openai_api_key",
+            Self::Author => "author",
+            Self::Rerank => "rerank",
+            Self::ChunkTokens => "chunk_tokens",
+            Self::ChunkOverlapTokens => "chunk_overlap_tokens",
+            Self::Abbreviations => "abbreviations",
+            Self::EmbeddingModel => "embedding_model",
+            Self::SearchLanguages => "search_languages",
+            Self::ModelPath => "model_path",
         }
     }
 }
@@ -169,6 +276,9 @@ impl ConfigKey {
 enum ConfigValuePayload {
     Text(String),
     Bool(bool),
+    Number(usize),
+    Map(BTreeMap<String, String>),
+    List(Vec<String>),
 }
 
 #[derive(Debug, Serialize)]
@@ -186,6 +296,14 @@ struct ConfigViewPayload {
     default_folder: String,
     auto_tag: bool,
     openai_api_key: String,
+    author: String,
+    rerank: bool,
+    chunk_tokens: usize,
+    chunk_overlap_tokens: usize,
+    abbreviations: BTreeMap<String, String>,
+    embedding_model: String,
+    search_languages: Vec<String>,
+    model_path: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -241,6 +359,27 @@ struct SearchResultPayload {
     snippet: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     matched_chunks: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    highlight: Option<HighlightedTextPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    passages: Vec<SearchPassageOutputPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<HybridHitExplanationPayload>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SearchPassageOutputPayload {
+    text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    heading_path: Vec<String>,
+    locator: String,
+    line_start: usize,
+    line_end: usize,
+    score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -249,6 +388,10 @@ struct SearchResponsePayload {
     results: Vec<SearchResultPayload>,
     total_results: usize,
     search_time_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rerank_time_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<SearchExplanationPayload>,
 }
 
 #[derive(Debug, Serialize)]
@@ -257,6 +400,18 @@ struct AgentSearchResultPayload {
     score: f32,
     path: String,
     context: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locator: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    heading_path: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<HighlightedTextPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    passages: Vec<SearchPassageOutputPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<HybridHitExplanationPayload>,
 }
 
 #[derive(Debug, Serialize)]
@@ -265,6 +420,55 @@ struct AgentSearchResponsePayload {
     results: Vec<AgentSearchResultPayload>,
     total_results: usize,
     search_time_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rerank_time_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<SearchExplanationPayload>,
+}
+
+#[derive(Debug, Serialize)]
+struct RelatedChunkPairOutputPayload {
+    source: SearchPassageOutputPayload,
+    related: SearchPassageOutputPayload,
+}
+
+#[derive(Debug, Serialize)]
+struct RelatedResultOutputPayload {
+    docid: String,
+    title: String,
+    score: f32,
+    path: String,
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    similarity: Option<f32>,
+    shared_tags: Vec<String>,
+    linked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chunk_pair: Option<RelatedChunkPairOutputPayload>,
+}
+
+#[derive(Debug, Serialize)]
+struct RelatedResponseOutputPayload {
+    docid: String,
+    path: String,
+    results: Vec<RelatedResultOutputPayload>,
+    total_results: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct EvalResponsePayload {
+    qrels: String,
+    k: usize,
+    rerank: bool,
+    query_count: usize,
+    metrics: EvaluationMetricsPayload,
+    queries: Vec<QueryEvaluationPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    baseline_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comparison: Option<EvaluationComparisonPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    saved_baseline: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -277,6 +481,8 @@ struct ReadResponsePayload {
     modified_at: String,
     content: String,
     size_bytes: u64,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -290,6 +496,123 @@ struct CreateResponsePayload {
     auto_tagging: Option<CreateAutoTaggingPayload>,
 }
 
+#[derive(Debug, Serialize)]
+struct ImportDocumentPayload {
+    id: String,
+    title: String,
+    folder: String,
+    source_path: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportSkippedItemPayload {
+    source_path: String,
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportResponsePayload {
+    status: &'static str,
+    source: &'static str,
+    documents_imported: usize,
+    folders_created: Vec<String>,
+    attachments_imported: usize,
+    skipped: Vec<ImportSkippedItemPayload>,
+    documents: Vec<ImportDocumentPayload>,
+    duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportResponsePayload {
+    status: &'static str,
+    format: &'static str,
+    destination: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manifest: Option<String>,
+    documents_exported: usize,
+    attachments_exported: usize,
+    documents: Vec<ExportDocumentPayload>,
+    duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportDocumentPayload {
+    id: String,
+    title: String,
+    folder: String,
+    path: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BackupCreateResponsePayload {
+    status: &'static str,
+    file: String,
+    documents_folder: String,
+    file_count: usize,
+    total_bytes: u64,
+    includes_config: bool,
+    includes_cache: bool,
+    duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct BackupRestoreResponsePayload {
+    status: &'static str,
+    file: String,
+    documents_folder: String,
+    files_restored: usize,
+    config_restored: bool,
+    documents_indexed: usize,
+    duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct BackupVerifyResponsePayload {
+    status: &'static str,
+    file: String,
+    created_at: String,
+    file_count: usize,
+    total_bytes: u64,
+    includes_config: bool,
+    includes_cache: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ModelInstallResponsePayload {
+    status: &'static str,
+    model_path: String,
+    model: InstalledModelPayload,
+}
+
+#[derive(Debug, Serialize)]
+struct HuggingFaceCachePayload {
+    directory: String,
+    total_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ModelStatusResponsePayload {
+    embedding_model: String,
+    /// Where the vault's model is read from: `model_path`, `local_directory`,
+    /// `huggingface_cache` or `download`.
+    loads_from: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model_path: Option<String>,
+    models_directory: String,
+    installed: Vec<InstalledModelPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    huggingface_cache: Option<HuggingFaceCachePayload>,
+}
+
+#[derive(Debug, Serialize)]
+struct ModelRemoveResponsePayload {
+    status: &'static str,
+    model_id: String,
+    directory: String,
+    total_bytes: u64,
+    model_path_cleared: bool,
+}
+
 #[derive(Debug, Serialize)]
 struct TagResponsePayload {
     id: String,
@@ -298,6 +621,21 @@ struct TagResponsePayload {
     tags_removed: Vec<String>,
 }
 
+#[derive(Debug, Serialize)]
+struct MigrateDocumentPayload {
+    id: String,
+    title: String,
+    folder: String,
+}
+
+#[derive(Debug, Serialize)]
+struct MigrateResponsePayload {
+    dry_run: bool,
+    documents_scanned: usize,
+    documents_converted: usize,
+    documents: Vec<MigrateDocumentPayload>,
+}
+
 #[derive(Debug, Serialize)]
 struct FolderItemPayload {
     path: String,
@@ -337,7 +675,7 @@ struct DeleteResponsePayload {
 
 fn ensure_initialized(json: bool) -> Result<bool, CliError> {
     let app_data_dir = resolve_app_data_dir()?;
-    let config_db = app_data_dir.join("config.db");
+    let config_db = app_data_dir.join(CONFIG_DATABASE_FILE_NAME);
 
     if config_db.exists() {
         return Ok(false); // Already initialized
@@ -435,6 +773,14 @@ fn handle_config_view(json: bool) -> Result<(), CliError> {
         default_folder: get_config_text_or_default(&store, ConfigKey::DefaultFolder)?,
         auto_tag: get_config_bool_or_default(&store, ConfigKey::AutoTag)?,
         openai_api_key: openai_api_key_display.clone(),
+        author: get_config_text_or_default(&store, ConfigKey::Author)?,
+        rerank: get_config_bool_or_default(&store, ConfigKey::Rerank)?,
+        chunk_tokens: get_vault_setting(&store, ConfigKey::ChunkTokens)?,
+        chunk_overlap_tokens: get_vault_setting(&store, ConfigKey::ChunkOverlapTokens)?,
+        abbreviations: load_configured_vault_settings(&store)?.abbreviations,
+        embedding_model: get_config_text_or_default(&store, ConfigKey::EmbeddingModel)?,
+        search_languages: get_configured_search_languages(&store)?,
+        model_path: get_config_text_or_default(&store, ConfigKey::ModelPath)?,
     };
 
     if json {
@@ -445,6 +791,17 @@ fn handle_config_view(json: bool) -> Result<(), CliError> {
         println!("default_folder = {}", payload.default_folder);
         println!("auto_tag = {}", payload.auto_tag);
         println!("openai_api_key = {}", openai_api_key_display);
+        println!("author = {}", payload.author);
+        println!("rerank = {}", payload.rerank);
+        println!("chunk_tokens = {}", payload.chunk_tokens);
+        println!("chunk_overlap_tokens = {}", payload.chunk_overlap_tokens);
+        println!(
+            "abbreviations = {}",
+            format_abbreviations(&payload.abbreviations)
+        );
+        println!("embedding_model = {}", payload.embedding_model);
+        println!("search_languages = {}", payload.search_languages.join(","));
+        println!("model_path = {}", payload.model_path);
         Ok(())
     }
 }
@@ -465,6 +822,9 @@ fn handle_config_get(key: &str, json: bool) -> Result<(), CliError> {
     match value {
         ConfigValuePayload::Text(value) => println!("{value}"),
         ConfigValuePayload::Bool(value) => println!("{value}"),
+        ConfigValuePayload::Number(value) => println!("{value}"),
+        ConfigValuePayload::Map(value) => println!("{}", format_abbreviations(&value)),
+        ConfigValuePayload::List(value) => println!("{}", value.join(",")),
     }
 
     Ok(())
@@ -482,11 +842,12 @@ fn handle_config_set(key: &str, value: &str, json: bool) -> Result<(), CliError>
 
     let config_store = open_config_store()?;
     let response_value = match config_key {
-        ConfigKey::AutoTag => {
+        ConfigKey::AutoTag | ConfigKey::Rerank => {
             let parsed = parse_bool(&normalized_value).ok_or_else(|| {
-                CliError::invalid_arguments(
-                    "auto_tag must be one of: true, false, 1, 0, yes, no, on, off",
-                )
+                CliError::invalid_arguments(format!(
+                    "{} must be one of: true, false, 1, 0, yes, no, on, off",
+                    config_key.canonical_name()
+                ))
             })?;
             normalized_value = parsed.to_string();
             ConfigValuePayload::Bool(parsed)
@@ -504,9 +865,22 @@ fn handle_config_set(key: &str, value: &str, json: bool) -> Result<(), CliError>
             }
             ConfigValuePayload::Text(normalized_value.clone())
         }
-        ConfigKey::Editor | ConfigKey::DefaultFolder => {
+        ConfigKey::Editor | ConfigKey::DefaultFolder | ConfigKey::Author => {
             ConfigValuePayload::Text(normalized_value.clone())
         }
+        ConfigKey::ChunkTokens | ConfigKey::ChunkOverlapTokens => {
+            return handle_config_set_vault_setting(config_key, &normalized_value, json);
+        }
+        ConfigKey::Abbreviations => {
+            return handle_config_set_abbreviations(&normalized_value, json)
+        }
+        ConfigKey::EmbeddingModel => {
+            return handle_config_set_embedding_model(&normalized_value, json)
+        }
+        ConfigKey::SearchLanguages => {
+            return handle_config_set_search_languages(&normalized_value, json)
+        }
+        ConfigKey::ModelPath => return handle_config_set_model_path(&normalized_value, json),
     };
 
     config_store
@@ -548,6 +922,16 @@ fn handle_status(json: bool) -> Result<(), CliError> {
             .unwrap_or_else(|| "never".to_owned())
     );
     println!("Index Size:   {}", format_bytes(payload.index_size_bytes));
+    println!(
+        "Embeddings:   {} ({} dimensions)",
+        payload.embedding_model, payload.embedding_dimensions
+    );
+    if payload.stale_embeddings > 0 {
+        println!(
+            "Stale:        {} documents embedded with another model; run reindex",
+            payload.stale_embeddings
+        );
+    }
 
     Ok(())
 }
@@ -558,7 +942,7 @@ fn handle_reindex(args: &ReindexArgs, json: bool) -> Result<(), CliError> {
     let started = Instant::now();
 
     // Show first-time model download message
-    if !is_embedding_model_cached() && !json {
+    if !is_embedding_model_cached(&documents_folder) && !json {
         println!("\nFirst run: downloading embedding model (~100MB)...");
         println!("This only happens once. Future operations will be faster.\n");
     }
@@ -663,6 +1047,8 @@ fn handle_search(args: &SearchArgs, json: bool) -> Result<(), CliError> {
     let folder_filter = normalize_folder_filter(args.folder.as_deref())?;
     let tag_filter = parse_csv_values(args.tags.as_deref().unwrap_or_default());
     let json_mode = json || args.json;
+    let rerank =
+        args.rerank || get_config_bool_or_default(&open_config_store()?, ConfigKey::Rerank)?;
 
     let started = Instant::now();
     let response = KnowledgeBaseService::search(
@@ -671,6 +1057,10 @@ fn handle_search(args: &SearchArgs, json: bool) -> Result<(), CliError> {
         SearchOptions {
             limit: args.limit.unwrap_or(20),
             folder_filter: folder_filter.clone(),
+            tags: tag_filter,
+            preprocess: true,
+            rerank,
+            explain: args.explain,
             ..SearchOptions::default()
         },
     )
@@ -678,16 +1068,16 @@ fn handle_search(args: &SearchArgs, json: bool) -> Result<(), CliError> {
 
     let mut results = Vec::new();
     for result in response.results {
-        if !tags_match_filter(&result.tags, &tag_filter) {
-            continue;
-        }
-
-        let snippet = match document_store::read_document(&documents_folder, &result.id) {
-            Ok(document) => build_snippet(&document.body, &args.query),
-            Err(DocumentStoreError::NotFound(_)) => None,
-            Err(error) => return Err(map_document_store_error(error)),
-        };
-        let matched_chunks = if snippet.is_some() { Some(1) } else { None };
+        let path = build_agent_path(&result.folder_path, &result.id);
+        let passages = result
+            .passages
+            .into_iter()
+            .map(|passage| map_search_passage(&path, passage))
+            .collect::<Vec<_>>();
+        let snippet = passages
+            .first()
+            .map(|passage| passage.text.clone())
+            .filter(|text| !text.is_empty());
 
         results.push(SearchResultPayload {
             id: result.id,
@@ -695,8 +1085,12 @@ fn handle_search(args: &SearchArgs, json: bool) -> Result<(), CliError> {
             relevance_score: result.relevance_score,
             folder: result.folder_path,
             tags: result.tags,
-            snippet: if args.snippets { snippet } else { None },
-            matched_chunks: if args.snippets { matched_chunks } else { None },
+            snippet,
+            matched_chunks: args.snippets.then_some(passages.len()),
+            highlight: result.snippet,
+            page: result.page,
+            passages,
+            explanation: result.explanation,
         });
     }
 
@@ -709,14 +1103,19 @@ fn handle_search(args: &SearchArgs, json: bool) -> Result<(), CliError> {
         total_results: results.len(),
         results,
         search_time_ms: duration_ms(started.elapsed()),
+        rerank_time_ms: response.rerank_time_ms,
+        explanation: response.explanation,
     };
 
     if args.files {
         for result in &payload.results {
             let path = build_agent_path(&result.folder, &result.id);
             let context = result
-                .snippet
-                .clone()
+                .highlight
+                .as_ref()
+                .map(|highlight| highlight.text.clone())
+                .or_else(|| result.snippet.clone())
+                .filter(|_| args.snippets)
                 .unwrap_or_else(|| result.title.clone())
                 .replace('\t', " ")
                 .replace('\n', " ");
@@ -730,23 +1129,41 @@ fn handle_search(args: &SearchArgs, json: bool) -> Result<(), CliError> {
             query: payload.query.clone(),
             total_results: payload.total_results,
             search_time_ms: payload.search_time_ms,
+            rerank_time_ms: payload.rerank_time_ms,
+            explanation: payload.explanation.clone(),
             results: payload
                 .results
                 .iter()
-                .map(|result| AgentSearchResultPayload {
-                    docid: result.id.clone(),
-                    score: result.relevance_score,
-                    path: build_agent_path(&result.folder, &result.id),
-                    context: result
-                        .snippet
-                        .clone()
-                        .unwrap_or_else(|| result.title.clone()),
+                .map(|result| {
+                    let best_passage = result.passages.first();
+                    AgentSearchResultPayload {
+                        docid: result.id.clone(),
+                        score: result.relevance_score,
+                        path: build_agent_path(&result.folder, &result.id),
+                        context: result
+                            .snippet
+                            .clone()
+                            .unwrap_or_else(|| result.title.clone()),
+                        locator: best_passage.map(|passage| passage.locator.clone()),
+                        heading_path: best_passage
+                            .map(|passage| passage.heading_path.clone())
+                            .unwrap_or_default(),
+                        snippet: result.highlight.clone().filter(|_| args.snippets),
+                        page: result.page,
+                        passages: if args.snippets {
+                            result.passages.clone()
+                        } else {
+                            Vec::new()
+                        },
+                        explanation: result.explanation.clone(),
+                    }
                 })
                 .collect(),
         };
         return print_json(&agent_payload);
     }
 
+    let use_color = io::stdout().is_terminal();
     println!(
         "{:<14} {:<30} {:<10} {:<16} {}",
         "ID", "TITLE", "RELEVANCE", "FOLDER", "TAGS"
@@ -760,8 +1177,143 @@ fn handle_search(args: &SearchArgs, json: bool) -> Result<(), CliError> {
             truncate_display(&result.folder, 16),
             truncate_display(&result.tags.join(","), 32)
         );
-        if let Some(snippet) = result.snippet.as_deref() {
-            println!("  {}", truncate_display(snippet, 100));
+        if let Some(page) = result.page {
+            println!("  page {page}");
+        }
+        if let Some(explanation) = result.explanation.as_ref() {
+            println!("  {}", format_hit_explanation(explanation));
+        }
+        if !args.snippets {
+            continue;
+        }
+        if let Some(highlight) = result.highlight.as_ref() {
+            println!("  {}", render_highlighted_text(highlight, use_color));
+        } else if let Some(snippet) = result.snippet.as_deref() {
+            println!("  {}", truncate_display(&snippet.replace('\n', " "), 100));
+        }
+        for passage in &result.passages {
+            if passage.heading_path.is_empty() {
+                println!("  {}", passage.locator);
+            } else {
+                let headings = passage.heading_path.join(" > ");
+                println!("  {}  {headings}", passage.locator);
+            }
+        }
+    }
+    println!("Results: {}", payload.total_results);
+    if let Some(explanation) = payload.explanation.as_ref() {
+        println!("{}", format_search_explanation(explanation));
+    }
+
+    Ok(())
+}
+
+fn format_rank(rank: Option<usize>) -> String {
+    rank.map_or_else(|| "-".to_owned(), |rank| format!("#{rank}"))
+}
+
+fn format_hit_explanation(explanation: &HybridHitExplanationPayload) -> String {
+    let mut line = format!(
+        "bm25 {} +{:.4}  semantic {} +{:.4}",
+        format_rank(explanation.bm25_rank),
+        explanation.bm25_contribution,
+        format_rank(explanation.semantic_rank),
+        explanation.semantic_contribution
+    );
+    if let Some(score) = explanation.semantic_score {
+        line.push_str(&format!(" (cos {score:.3})"));
+    }
+    line.push_str(&format!(
+        "  title +{:.4}  fused {:.4}",
+        explanation.title_boost, explanation.fused_score
+    ));
+    if let Some(score) = explanation.rerank_score {
+        line.push_str(&format!("  rerank {score:.3}"));
+    }
+    if let Some(chunk) = explanation.best_chunk_index {
+        line.push_str(&format!("  chunk {chunk}"));
+    }
+    line
+}
+
+fn format_search_explanation(explanation: &SearchExplanationPayload) -> String {
+    let mut line = format!(
+        "Explain: weights semantic {:.2} / bm25 {:.2}, rrf k {}, candidates bm25 {} / semantic {} of {}, semantic floor {:.2}",
+        explanation.semantic_weight,
+        explanation.bm25_weight,
+        explanation.rrf_k,
+        explanation.bm25_candidates,
+        explanation.semantic_candidates,
+        explanation.candidate_k,
+        explanation.semantic_floor
+    );
+    if explanation.dropped_by_semantic_floor.is_empty() {
+        line.push_str(" dropped none");
+    } else {
+        line.push_str(&format!(
+            " dropped {}",
+            explanation.dropped_by_semantic_floor.join(",")
+        ));
+    }
+    line
+}
+
+fn handle_related(args: &RelatedArgs, json: bool) -> Result<(), CliError> {
+    let documents_folder = load_documents_folder()?;
+    let document = document_store::read_document(&documents_folder, &args.document_id)
+        .map_err(map_document_store_error)?;
+    let response =
+        KnowledgeBaseService::related(&documents_folder, &document.id, args.limit.unwrap_or(10))
+            .map_err(map_knowledge_base_error)?;
+
+    let source_path = build_agent_path(&document.folder_path, &document.id);
+    let payload = RelatedResponseOutputPayload {
+        docid: response.document_id,
+        path: source_path.clone(),
+        total_results: response.total_results,
+        results: response
+            .results
+            .into_iter()
+            .map(|result| {
+                let path = build_agent_path(&result.folder_path, &result.id);
+                let chunk_pair = result.chunk_pair.map(|pair| RelatedChunkPairOutputPayload {
+                    source: map_search_passage(&source_path, pair.source),
+                    related: map_search_passage(&path, pair.related),
+                });
+                RelatedResultOutputPayload {
+                    docid: result.id,
+                    title: result.title,
+                    score: result.relevance_score,
+                    path,
+                    tags: result.tags,
+                    similarity: result.similarity,
+                    shared_tags: result.shared_tags,
+                    linked: result.linked,
+                    chunk_pair,
+                }
+            })
+            .collect(),
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    println!("ID             TITLE                          SCORE      PATH                     SHARED TAGS");
+    for result in &payload.results {
+        println!(
+            "{:<14} {:<30} {:<10.3} {:<24} {}",
+            truncate_display(&result.docid, 14),
+            truncate_display(&result.title, 30),
+            result.score,
+            truncate_display(&result.path, 24),
+            truncate_display(&result.shared_tags.join(","), 32)
+        );
+        if let Some(pair) = &result.chunk_pair {
+            println!("  {} ~ {}", pair.source.locator, pair.related.locator);
+        }
+        if result.linked {
+            println!("  linked");
         }
     }
     println!("Results: {}", payload.total_results);
@@ -769,6 +1321,136 @@ fn handle_search(args: &SearchArgs, json: bool) -> Result<(), CliError> {
     Ok(())
 }
 
+fn handle_eval(args: &EvalArgs, json: bool) -> Result<(), CliError> {
+    let documents_folder = load_documents_folder()?;
+    let qrels_path = expand_tilde_path(args.qrels.trim())?;
+    let qrels = load_qrels(&qrels_path).map_err(map_evaluation_error)?;
+    let baseline_path = args
+        .baseline
+        .as_deref()
+        .map(|path| expand_tilde_path(path.trim()))
+        .transpose()?;
+    let baseline = baseline_path
+        .as_deref()
+        .map(read_report)
+        .transpose()
+        .map_err(map_evaluation_error)?;
+
+    let report = evaluate_qrels(
+        &documents_folder,
+        &qrels,
+        EvaluationOptions {
+            k: args.k.unwrap_or(DEFAULT_EVALUATION_CUTOFF),
+            rerank: args.rerank,
+        },
+    )
+    .map_err(map_evaluation_error)?;
+    let comparison = baseline
+        .as_ref()
+        .map(|baseline| compare_reports(&report, baseline))
+        .transpose()
+        .map_err(map_evaluation_error)?;
+
+    let saved_baseline = match args.save_baseline.as_deref() {
+        Some(path) => {
+            let path = expand_tilde_path(path.trim())?;
+            write_report(&path, &report).map_err(map_evaluation_error)?;
+            Some(path.to_string_lossy().into_owned())
+        }
+        None => None,
+    };
+
+    let payload = EvalResponsePayload {
+        qrels: qrels_path.to_string_lossy().into_owned(),
+        k: report.k,
+        rerank: report.rerank,
+        query_count: report.query_count,
+        metrics: report.metrics,
+        queries: report.queries,
+        baseline_file: baseline_path.map(|path| path.to_string_lossy().into_owned()),
+        comparison,
+        saved_baseline,
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    let k = payload.k;
+    let deltas = payload
+        .comparison
+        .as_ref()
+        .map(|comparison| {
+            comparison
+                .queries
+                .iter()
+                .map(|query| (query.id.as_str(), query.delta.ndcg))
+                .collect::<BTreeMap<_, _>>()
+        })
+        .unwrap_or_default();
+    let header = format!(
+        "{:<30} {:<7} {:<10} {:<10} {:<6}",
+        "QUERY",
+        "RR",
+        format!("RECALL@{k}"),
+        format!("NDCG@{k}"),
+        "FIRST"
+    );
+    if payload.comparison.is_some() {
+        println!("{header} \u{0394}NDCG");
+    } else {
+        println!("{}", header.trim_end());
+    }
+    for query in &payload.queries {
+        let first = query
+            .first_relevant_rank
+            .map_or_else(|| "-".to_owned(), |rank| rank.to_string());
+        let row = format!(
+            "{:<30} {:<7.3} {:<10.3} {:<10.3} {:<6}",
+            truncate_display(&query.id, 30),
+            query.metrics.mrr,
+            query.metrics.recall,
+            query.metrics.ndcg,
+            first
+        );
+        match deltas.get(query.id.as_str()) {
+            Some(delta) => println!("{row} {delta:+.3}"),
+            None if payload.comparison.is_some() => println!("{row} new"),
+            None => println!("{}", row.trim_end()),
+        }
+        if let Some(error) = &query.error {
+            println!("  failed: {error}");
+        }
+    }
+    println!(
+        "Queries: {}  MRR {:.3}  Recall@{k} {:.3}  nDCG@{k} {:.3}",
+        payload.query_count, payload.metrics.mrr, payload.metrics.recall, payload.metrics.ndcg
+    );
+    if let Some(comparison) = &payload.comparison {
+        println!(
+            "Baseline: MRR {:.3} ({:+.3})  Recall@{k} {:.3} ({:+.3})  nDCG@{k} {:.3} ({:+.3}) over {} shared queries",
+            comparison.baseline.mrr,
+            comparison.delta.mrr,
+            comparison.baseline.recall,
+            comparison.delta.recall,
+            comparison.baseline.ndcg,
+            comparison.delta.ndcg,
+            comparison.queries.len()
+        );
+        if !comparison.regressed.is_empty() {
+            println!("Regressed: {}", comparison.regressed.join(", "));
+        }
+        if !comparison.removed.is_empty() {
+            println!("Not in this run: {}", comparison.removed.join(", "));
+        }
+    }
+    if let Some(path) = &payload.saved_baseline {
+        println!("Saved baseline to {path}.");
+    }
+
+    Ok(())
+}
+
 fn handle_read(args: &ReadArgs, json: bool) -> Result<(), CliError> {
     let documents_folder = load_documents_folder()?;
     let document = document_store::read_document(&documents_folder, &args.document_id)
@@ -789,6 +1471,9 @@ fn handle_read(args: &ReadArgs, json: bool) -> Result<(), CliError> {
         println!("Folder: {}", payload.folder);
         println!("Tags: {}", payload.tags.join(", "));
         println!("Modified: {}", humanize_datetime(&payload.modified_at));
+        for (key, value) in &payload.properties {
+            println!("{key}: {value}");
+        }
         println!();
     }
     println!("{}", payload.content);
@@ -820,6 +1505,7 @@ fn handle_create(args: &CreateArgs, json: bool) -> Result<(), CliError> {
             tags,
             tags_locked: Some(false),
             id: None,
+            ..CreateDocumentInput::default()
         },
     )
     .map_err(map_document_store_error)?;
@@ -838,7 +1524,7 @@ fn handle_create(args: &CreateArgs, json: bool) -> Result<(), CliError> {
     }
 
     // Show first-time model download message
-    if !is_embedding_model_cached() && !json {
+    if !is_embedding_model_cached(&documents_folder) && !json {
         println!("\nFirst run: downloading embedding model (~100MB)...");
         println!("This only happens once. Future operations will be faster.\n");
     }
@@ -901,27 +1587,676 @@ fn handle_create(args: &CreateArgs, json: bool) -> Result<(), CliError> {
     Ok(())
 }
 
-fn handle_tag(args: &TagArgs, json: bool) -> Result<(), CliError> {
+fn handle_import(args: &ImportArgs, json: bool) -> Result<(), CliError> {
     let documents_folder = load_documents_folder()?;
+    let source_path = expand_tilde_path(args.source_path.trim())?;
+    let source = match args.from {
+        Some(source) => source,
+        None => detect_import_source(&source_path)?,
+    };
+    if args.title.is_some() && !matches!(source, ImportSource::Html | ImportSource::Pdf) {
+        return Err(CliError::invalid_arguments(format!(
+            "--title is not supported for {} imports; source titles are kept",
+            import_source_name(source)
+        )));
+    }
+    let has_conversation_filters =
+        args.since.is_some() || args.until.is_some() || args.keywords.is_some();
+    if has_conversation_filters && source != ImportSource::Conversations {
+        return Err(CliError::invalid_arguments(format!(
+            "--since, --until, and --keywords only apply to conversations imports, not {}",
+            import_source_name(source)
+        )));
+    }
+    if args.per_message && source != ImportSource::Mail {
+        return Err(CliError::invalid_arguments(format!(
+            "--per-message only applies to mail imports, not {}",
+            import_source_name(source)
+        )));
+    }
+    let conversation_filters = ConversationImportOptions {
+        since: args.since.clone(),
+        until: args.until.clone(),
+        keywords: parse_csv_values(args.keywords.as_deref().unwrap_or_default()),
+    };
+    let options = ImportOptions {
+        folder_path: resolve_create_folder(args.folder.as_deref())?,
+        tags: parse_csv_values(args.tags.as_deref().unwrap_or_default()),
+        title: args.title.clone(),
+    };
+    let started = Instant::now();
 
-    if args.tags.is_none() {
-        if args.remove || args.replace {
-            return Err(CliError::invalid_arguments(
-                "tags are required when using --remove or --replace",
-            ));
-        }
+    let spinner = if !json {
+        crate::progress::create_spinner_with_message("Importing documents...")
+    } else {
+        None
+    };
 
-        let document = document_store::read_document(&documents_folder, &args.document_id)
-            .map_err(map_document_store_error)?;
-        let payload = TagResponsePayload {
-            id: document.id,
-            tags: document.tags,
-            tags_added: Vec::new(),
-            tags_removed: Vec::new(),
-        };
+    let result = match source {
+        ImportSource::Notion => import_notion_export(&documents_folder, &source_path, &options),
+        ImportSource::Enex => import_enex(&documents_folder, &source_path, &options),
+        ImportSource::Html => import_html(&documents_folder, &source_path, &options),
+        ImportSource::Pdf => import_pdf(&documents_folder, &source_path, &options),
+        ImportSource::Conversations => import_conversations(
+            &documents_folder,
+            &source_path,
+            &options,
+            &conversation_filters,
+        ),
+        ImportSource::Bookmarks => import_bookmarks(&documents_folder, &source_path, &options),
+        ImportSource::Mail => import_mail(
+            &documents_folder,
+            &source_path,
+            &options,
+            &MailImportOptions {
+                per_message: args.per_message,
+            },
+        ),
+    }
+    .map_err(map_import_error)?;
 
-        if json {
-            return print_json(&payload);
+    if !result.documents.is_empty() {
+        sync_cache_full(&documents_folder)?;
+    }
+
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+
+    let payload = ImportResponsePayload {
+        status: "completed",
+        source: import_source_name(source),
+        documents_imported: result.documents.len(),
+        folders_created: result.folders_created,
+        attachments_imported: result.attachments_imported,
+        skipped: result
+            .skipped
+            .into_iter()
+            .map(|skipped| ImportSkippedItemPayload {
+                source_path: skipped.source_path,
+                reason: skipped.reason,
+            })
+            .collect(),
+        documents: result
+            .documents
+            .into_iter()
+            .map(|document| ImportDocumentPayload {
+                id: document.id,
+                title: document.title,
+                folder: document.folder_path,
+                source_path: document.source_path,
+            })
+            .collect(),
+        duration_ms: duration_ms(started.elapsed()),
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    println!(
+        "Imported {} document(s) from {}.",
+        payload.documents_imported, payload.source
+    );
+    if !payload.folders_created.is_empty() {
+        println!("Folders created: {}", payload.folders_created.join(", "));
+    }
+    println!("Attachments: {}", payload.attachments_imported);
+    if !payload.skipped.is_empty() {
+        println!("Skipped: {}", payload.skipped.len());
+        for skipped in &payload.skipped {
+            println!("  {} ({})", skipped.source_path, skipped.reason);
+        }
+    }
+
+    Ok(())
+}
+
+/// Picks the importer from the file extension when `--from` is omitted. Directories are
+/// detected from the files directly inside them and must contain a single importable kind.
+fn detect_import_source(source_path: &Path) -> Result<ImportSource, CliError> {
+    let undetected = || {
+        CliError::invalid_arguments(format!(
+            "could not detect the format of \"{}\"; pass --from",
+            source_path.display()
+        ))
+    };
+
+    if !source_path.is_dir() {
+        return import_source_for_file(source_path).ok_or_else(undetected);
+    }
+
+    let mut detected: Vec<ImportSource> = Vec::new();
+    for entry in fs::read_dir(source_path).map_err(map_io_error)? {
+        let path = entry.map_err(map_io_error)?.path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(source) = import_source_for_file(&path) {
+            if source != ImportSource::Notion && !detected.contains(&source) {
+                detected.push(source);
+            }
+        }
+    }
+
+    match detected.as_slice() {
+        [source] => Ok(*source),
+        _ => Err(undetected()),
+    }
+}
+
+fn import_source_for_file(path: &Path) -> Option<ImportSource> {
+    let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" if is_bookmark_export(path) => Some(ImportSource::Bookmarks),
+        "html" | "htm" => Some(ImportSource::Html),
+        "enex" => Some(ImportSource::Enex),
+        "pdf" => Some(ImportSource::Pdf),
+        "zip" => Some(ImportSource::Notion),
+        "json" => Some(ImportSource::Conversations),
+        "eml" | "mbox" => Some(ImportSource::Mail),
+        _ => None,
+    }
+}
+
+/// Browser bookmark exports are `.html` too; they are told apart by their doctype.
+fn is_bookmark_export(path: &Path) -> bool {
+    let mut head = [0u8; 1024];
+    let Ok(mut file) = fs::File::open(path) else {
+        return false;
+    };
+    let read = file.read(&mut head).unwrap_or(0);
+    is_netscape_bookmark_file(&head[..read])
+}
+
+const fn import_source_name(source: ImportSource) -> &'static str {
+    match source {
+        ImportSource::Notion => "notion",
+        ImportSource::Enex => "enex",
+        ImportSource::Html => "html",
+        ImportSource::Pdf => "pdf",
+        ImportSource::Conversations => "conversations",
+        ImportSource::Mail => "mail",
+        ImportSource::Bookmarks => "bookmarks",
+    }
+}
+
+fn handle_export(args: &ExportArgs, json: bool) -> Result<(), CliError> {
+    let documents_folder = load_documents_folder()?;
+    let group_scope = match (args.folder.as_deref(), args.tag.as_deref()) {
+        (Some(folder), _) => Some(ExportScope::Folder(
+            normalize_folder_filter(Some(folder))?.unwrap_or_default(),
+        )),
+        (None, Some(tag)) => {
+            let tag = tag.trim().to_lowercase();
+            if tag.is_empty() {
+                return Err(CliError::invalid_arguments("tag must not be empty"));
+            }
+            Some(ExportScope::Tag(tag))
+        }
+        (None, None) => None,
+    };
+    let (scope, destination) = match (group_scope, args.targets.as_slice()) {
+        (Some(scope), [destination]) => (scope, destination),
+        (None, [document_id, destination]) => (
+            ExportScope::Document(document_id.trim().to_owned()),
+            destination,
+        ),
+        (Some(_), _) => {
+            return Err(CliError::invalid_arguments(
+                "export --folder/--tag takes only a destination path",
+            ))
+        }
+        (None, _) => {
+            return Err(CliError::invalid_arguments(
+                "export needs a document id and a destination path, or --folder/--tag and a destination path",
+            ))
+        }
+    };
+    let destination = expand_tilde_path(destination.trim())?;
+    let options = ExportOptions {
+        format: match args.format {
+            ExportFormatArg::Markdown => ExportFormat::Markdown,
+            ExportFormatArg::Json => ExportFormat::Json,
+            ExportFormatArg::Jsonl => ExportFormat::Jsonl,
+            ExportFormatArg::Html => ExportFormat::Html,
+            ExportFormatArg::Epub => ExportFormat::Epub,
+        },
+        strip_frontmatter: args.strip_frontmatter,
+        sort: args.sort.as_ref().map(document_sort),
+        desc: args.desc,
+        author: Some(get_config_text_or_default(
+            &open_config_store()?,
+            ConfigKey::Author,
+        )?)
+        .filter(|author| !author.is_empty()),
+    };
+    let started = Instant::now();
+
+    let manifest = export_documents(&documents_folder, &scope, &destination, &options)
+        .map_err(map_export_error)?;
+
+    let payload = ExportResponsePayload {
+        status: "completed",
+        format: manifest.format.as_str(),
+        destination: destination.to_string_lossy().into_owned(),
+        manifest: (manifest.format != ExportFormat::Epub).then(|| {
+            destination
+                .join(EXPORT_MANIFEST_FILE_NAME)
+                .to_string_lossy()
+                .into_owned()
+        }),
+        documents_exported: manifest.document_count,
+        attachments_exported: manifest.attachment_count,
+        documents: manifest
+            .documents
+            .into_iter()
+            .map(|document| ExportDocumentPayload {
+                id: document.id,
+                title: document.title,
+                folder: document.folder_path,
+                path: document.path,
+            })
+            .collect(),
+        duration_ms: duration_ms(started.elapsed()),
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    println!(
+        "Exported {} document(s) as {} to {}.",
+        payload.documents_exported, payload.format, payload.destination
+    );
+    println!("Attachments: {}", payload.attachments_exported);
+    if let Some(manifest) = &payload.manifest {
+        println!("Manifest: {manifest}");
+    }
+
+    Ok(())
+}
+
+fn handle_backup_create(
+    file: &str,
+    include_config: bool,
+    exclude_cache: bool,
+    json: bool,
+) -> Result<(), CliError> {
+    let documents_folder = load_documents_folder()?;
+    let archive_path = expand_tilde_path(file.trim())?;
+    let options = BackupOptions {
+        config_database: include_config
+            .then(|| resolve_app_data_dir().map(|dir| dir.join(CONFIG_DATABASE_FILE_NAME)))
+            .transpose()?,
+        include_cache: !exclude_cache,
+    };
+    let started = Instant::now();
+
+    let manifest =
+        create_backup(&documents_folder, &archive_path, &options).map_err(map_backup_error)?;
+
+    let payload = BackupCreateResponsePayload {
+        status: "created",
+        file: archive_path.to_string_lossy().into_owned(),
+        documents_folder: manifest.documents_folder,
+        file_count: manifest.file_count,
+        total_bytes: manifest.total_bytes,
+        includes_config: manifest.includes_config,
+        includes_cache: manifest.includes_cache,
+        duration_ms: duration_ms(started.elapsed()),
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    println!(
+        "Backed up {} file(s) ({}) to {}.",
+        payload.file_count,
+        format_bytes(payload.total_bytes),
+        payload.file
+    );
+    Ok(())
+}
+
+fn handle_backup_restore(
+    file: &str,
+    into: Option<&str>,
+    force: bool,
+    restore_config: bool,
+    json: bool,
+) -> Result<(), CliError> {
+    let archive_path = expand_tilde_path(file.trim())?;
+    let documents_folder = match into {
+        Some(folder) => expand_tilde_path(folder.trim())?,
+        None => load_documents_folder()?,
+    };
+    let options = RestoreOptions {
+        force,
+        config_database: restore_config
+            .then(|| resolve_app_data_dir().map(|dir| dir.join(CONFIG_DATABASE_FILE_NAME)))
+            .transpose()?,
+    };
+    let started = Instant::now();
+
+    let restored =
+        restore_backup(&archive_path, &documents_folder, &options).map_err(map_backup_error)?;
+    if restored.config_restored {
+        // The archived settings point at the vault they were taken from.
+        open_config_store()?
+            .set(KEY_DOCUMENTS_FOLDER, &documents_folder.to_string_lossy())
+            .map_err(map_config_error)?;
+    }
+    let reindexed =
+        KnowledgeBaseService::reindex(&documents_folder, None).map_err(map_knowledge_base_error)?;
+
+    let payload = BackupRestoreResponsePayload {
+        status: "restored",
+        file: archive_path.to_string_lossy().into_owned(),
+        documents_folder: documents_folder.to_string_lossy().into_owned(),
+        files_restored: restored.files_restored,
+        config_restored: restored.config_restored,
+        documents_indexed: reindexed.documents_indexed,
+        duration_ms: duration_ms(started.elapsed()),
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    println!(
+        "Restored {} file(s) into {}.",
+        payload.files_restored, payload.documents_folder
+    );
+    if payload.config_restored {
+        println!("Settings restored from the backup.");
+    }
+    println!("Documents indexed: {}", payload.documents_indexed);
+    Ok(())
+}
+
+fn handle_backup_verify(file: &str, json: bool) -> Result<(), CliError> {
+    let archive_path = expand_tilde_path(file.trim())?;
+    let manifest = verify_backup(&archive_path).map_err(map_backup_error)?;
+
+    let payload = BackupVerifyResponsePayload {
+        status: "verified",
+        file: archive_path.to_string_lossy().into_owned(),
+        created_at: manifest.created_at,
+        file_count: manifest.file_count,
+        total_bytes: manifest.total_bytes,
+        includes_config: manifest.includes_config,
+        includes_cache: manifest.includes_cache,
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    println!(
+        "Backup is intact: {} file(s), {}, created {}.",
+        payload.file_count,
+        format_bytes(payload.total_bytes),
+        humanize_datetime(&payload.created_at)
+    );
+    Ok(())
+}
+
+/// Installs model files from a directory or tar archive for offline use and points
+/// `model_path` at the installed copy.
+fn handle_model_install(from: &str, model: Option<&str>, json: bool) -> Result<(), CliError> {
+    let source = expand_tilde_path(from.trim())?;
+    let store = open_config_store()?;
+    let configured_model = get_configured_embedding_model(&store)?;
+    let model_id = match model.map(str::trim) {
+        Some(model) => resolve_embedding_model_preset(model)
+            .unwrap_or(model)
+            .to_owned(),
+        None => configured_model.clone(),
+    };
+    if is_local_model_path(&model_id) {
+        return Err(CliError::invalid_arguments(format!(
+            "embedding_model is the local directory \"{model_id}\"; pass --model with the id these files are for"
+        )));
+    }
+
+    let models_dir = models_directory(&resolve_app_data_dir()?);
+    let installed =
+        install_model(&source, &models_dir, &model_id).map_err(map_model_store_error)?;
+    store
+        .set(KEY_MODEL_PATH, &installed.directory)
+        .map_err(map_config_error)?;
+
+    if json {
+        let payload = ModelInstallResponsePayload {
+            status: "installed",
+            model_path: installed.directory.clone(),
+            model: installed,
+        };
+        return print_json(&payload);
+    }
+
+    println!(
+        "Installed {}: {} file(s), {}.",
+        installed.model_id,
+        installed.artifacts.len(),
+        format_bytes(installed.total_bytes)
+    );
+    println!("model_path = {}", installed.directory);
+    if configured_model != installed.model_id {
+        println!(
+            "The vault uses {configured_model}; run `tentacle config set embedding_model {}` to switch.",
+            installed.model_id
+        );
+    }
+    Ok(())
+}
+
+fn handle_model_status(json: bool) -> Result<(), CliError> {
+    let store = open_config_store()?;
+    let embedding_model = get_configured_embedding_model(&store)?;
+    let model_path = get_configured_model_path(&store)?;
+    let models_dir = models_directory(&resolve_app_data_dir()?);
+    let mut installed = list_installed_models(&models_dir).map_err(map_model_store_error)?;
+    if let Some(model_path) = model_path
+        .as_deref()
+        .filter(|model_path| !model_path.starts_with(&models_dir))
+    {
+        if let Ok(model) = read_installed_model(model_path) {
+            installed.push(model);
+        }
+    }
+
+    let huggingface_cache = huggingface_cache_directory(&embedding_model)
+        .filter(|directory| directory.is_dir())
+        .map(|directory| HuggingFaceCachePayload {
+            total_bytes: directory_size(&directory),
+            directory: directory.to_string_lossy().into_owned(),
+        });
+    let loads_from = match embedding_model_directory(&embedding_model) {
+        Some(directory) if model_path.as_deref() == Some(directory.as_path()) => "model_path",
+        Some(_) => "local_directory",
+        None if huggingface_cache.is_some() => "huggingface_cache",
+        None => "download",
+    };
+
+    let payload = ModelStatusResponsePayload {
+        embedding_model,
+        loads_from,
+        model_path: model_path.map(|path| path.to_string_lossy().into_owned()),
+        models_directory: models_dir.to_string_lossy().into_owned(),
+        installed,
+        huggingface_cache,
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    println!("Embedding model: {}", payload.embedding_model);
+    println!("Loads from:      {}", payload.loads_from.replace('_', " "));
+    if let Some(model_path) = &payload.model_path {
+        println!("Model path:      {model_path}");
+    }
+    if let Some(cache) = &payload.huggingface_cache {
+        println!(
+            "Hub cache:       {} ({})",
+            cache.directory,
+            format_bytes(cache.total_bytes)
+        );
+    }
+    if payload.installed.is_empty() {
+        println!("No models installed in {}.", payload.models_directory);
+        return Ok(());
+    }
+    for model in &payload.installed {
+        println!(
+            "\n{} ({}, installed {})",
+            model.model_id,
+            format_bytes(model.total_bytes),
+            humanize_datetime(&model.installed_at)
+        );
+        println!("  {}", model.directory);
+        for artifact in &model.artifacts {
+            println!(
+                "  {:>10}  {}  {}",
+                format_bytes(artifact.size_bytes),
+                &artifact.sha256[..artifact.sha256.len().min(12)],
+                artifact.path
+            );
+        }
+    }
+    Ok(())
+}
+
+fn handle_model_verify(model: Option<&str>, json: bool) -> Result<(), CliError> {
+    let store = open_config_store()?;
+    let directory = resolve_installed_model_directory(&store, model)?;
+    let verification = verify_model(&directory).map_err(map_model_store_error)?;
+
+    if !verification.verified {
+        let problems = verification
+            .artifacts
+            .iter()
+            .filter_map(|check| {
+                let problem = match check.status {
+                    ModelArtifactStatus::Ok => return None,
+                    ModelArtifactStatus::Missing => "is missing",
+                    ModelArtifactStatus::SizeMismatch => "has the wrong size",
+                    ModelArtifactStatus::HashMismatch => "does not match its checksum",
+                };
+                Some(format!("\"{}\" {problem}", check.path))
+            })
+            .collect::<Vec<_>>();
+        return Err(CliError::General {
+            message: format!(
+                "model verification failed for {}: {}",
+                verification.model_id,
+                problems.join("; ")
+            ),
+        });
+    }
+
+    if json {
+        return print_json(&verification);
+    }
+
+    println!(
+        "Model {} is intact: {} file(s) match their SHA-256 checksums.",
+        verification.model_id,
+        verification.artifacts.len()
+    );
+    Ok(())
+}
+
+fn handle_model_remove(model: Option<&str>, json: bool) -> Result<(), CliError> {
+    let store = open_config_store()?;
+    let directory = resolve_installed_model_directory(&store, model)?;
+    let removed = remove_model(&directory).map_err(map_model_store_error)?;
+    let model_path_cleared = get_configured_model_path(&store)?.as_deref() == Some(&directory);
+    if model_path_cleared {
+        store.delete(KEY_MODEL_PATH).map_err(map_config_error)?;
+    }
+
+    let payload = ModelRemoveResponsePayload {
+        status: "removed",
+        model_id: removed.model_id,
+        directory: removed.directory,
+        total_bytes: removed.total_bytes,
+        model_path_cleared,
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    println!(
+        "Removed {} ({}).",
+        payload.model_id,
+        format_bytes(payload.total_bytes)
+    );
+    if model_path_cleared {
+        println!("model_path was cleared; the model will be resolved through hf-hub again.");
+    }
+    Ok(())
+}
+
+/// Install folder of `model` (an id or preset), or the `model_path` directory without one.
+fn resolve_installed_model_directory(
+    store: &ConfigStore,
+    model: Option<&str>,
+) -> Result<PathBuf, CliError> {
+    if let Some(model) = model.map(str::trim) {
+        let model_id = resolve_embedding_model_preset(model).unwrap_or(model);
+        let models_dir = models_directory(&resolve_app_data_dir()?);
+        return installed_model_directory(&models_dir, model_id).map_err(map_model_store_error);
+    }
+
+    get_configured_model_path(store)?.ok_or_else(|| {
+        CliError::invalid_arguments(
+            "model_path is not configured; pass --model or run 'tentacle model install' first",
+        )
+    })
+}
+
+/// Total size of the regular files under `directory`, without following symlinks, so hub
+/// snapshots linking into `blobs/` are counted once.
+fn directory_size(directory: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.path().symlink_metadata() {
+            Ok(metadata) if metadata.is_dir() => directory_size(&entry.path()),
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn handle_tag(args: &TagArgs, json: bool) -> Result<(), CliError> {
+    let documents_folder = load_documents_folder()?;
+
+    if args.tags.is_none() {
+        if args.remove || args.replace {
+            return Err(CliError::invalid_arguments(
+                "tags are required when using --remove or --replace",
+            ));
+        }
+
+        let document = document_store::read_document(&documents_folder, &args.document_id)
+            .map_err(map_document_store_error)?;
+        let payload = TagResponsePayload {
+            id: document.id,
+            tags: document.tags,
+            tags_added: Vec::new(),
+            tags_removed: Vec::new(),
+        };
+
+        if json {
+            return print_json(&payload);
         }
 
         if payload.tags.is_empty() {
@@ -974,6 +2309,81 @@ fn handle_tag(args: &TagArgs, json: bool) -> Result<(), CliError> {
     Ok(())
 }
 
+fn handle_migrate(args: &MigrateArgs, json: bool) -> Result<(), CliError> {
+    let documents_folder = load_documents_folder()?;
+    let folder_filter = normalize_folder_filter(args.folder.as_deref())?;
+    let mut documents =
+        document_store::list_documents(&documents_folder).map_err(map_document_store_error)?;
+
+    if let Some(filter) = folder_filter.as_deref() {
+        documents.retain(|document| folder_matches_filter(&document.folder_path, filter));
+    }
+
+    let documents_scanned = documents.len();
+    let mut converted = Vec::new();
+    for item in documents {
+        let document = document_store::read_document(&documents_folder, &item.id)
+            .map_err(map_document_store_error)?;
+        let Some(markdown) = tiptap_json_to_markdown(&document.body) else {
+            continue;
+        };
+
+        if !args.dry_run {
+            // Keep the original modification time: the content is the same, only its encoding
+            // changes.
+            document_store::update_document(
+                &documents_folder,
+                &document.id,
+                &UpdateDocumentInput {
+                    body: Some(markdown),
+                    properties: None,
+                    updated_at: Some(document.updated_at.clone()),
+                },
+            )
+            .map_err(map_document_store_error)?;
+        }
+
+        converted.push(MigrateDocumentPayload {
+            id: document.id,
+            title: document.title,
+            folder: document.folder_path,
+        });
+    }
+
+    if !args.dry_run && !converted.is_empty() {
+        sync_cache_full(&documents_folder)?;
+    }
+
+    let payload = MigrateResponsePayload {
+        dry_run: args.dry_run,
+        documents_scanned,
+        documents_converted: converted.len(),
+        documents: converted,
+    };
+
+    if json {
+        return print_json(&payload);
+    }
+
+    let verb = if payload.dry_run {
+        "Would convert"
+    } else {
+        "Converted"
+    };
+    for document in &payload.documents {
+        println!(
+            "{verb} {} \"{}\" ({})",
+            document.id, document.title, document.folder
+        );
+    }
+    println!(
+        "{verb} {} of {} documents from Tiptap JSON to markdown.",
+        payload.documents_converted, payload.documents_scanned
+    );
+
+    Ok(())
+}
+
 fn handle_delete(args: &DeleteArgs, json: bool) -> Result<(), CliError> {
     let documents_folder = load_documents_folder()?;
 
@@ -1181,6 +2591,19 @@ fn open_config_store() -> Result<ConfigStore, CliError> {
     ConfigStore::new(&app_data_dir).map_err(map_config_error)
 }
 
+/// Points the embedding engine at the `model_path` directory, when one is configured, and
+/// the engines at the installed models.
+fn apply_model_path_setting() -> Result<(), CliError> {
+    let store = open_config_store()?;
+    set_embedding_model_path(get_configured_model_path(&store)?);
+    set_installed_models_directory(Some(models_directory(&resolve_app_data_dir()?)));
+    Ok(())
+}
+
+fn get_configured_model_path(store: &ConfigStore) -> Result<Option<PathBuf>, CliError> {
+    store.model_path().map_err(map_config_error)
+}
+
 fn resolve_app_data_dir() -> Result<PathBuf, CliError> {
     default_data_dir().ok_or_else(|| CliError::General {
         message: "unable to determine a default application data directory".to_owned(),
@@ -1253,51 +2676,328 @@ fn get_config_text_or_default(store: &ConfigStore, key: ConfigKey) -> Result<Str
             .map_err(map_config_error)?
             .filter(|value| !value.trim().is_empty())
             .unwrap_or_else(|| DEFAULT_FOLDER.to_owned()),
-        ConfigKey::OpenAiApiKey => store
+        ConfigKey::OpenAiApiKey | ConfigKey::Author | ConfigKey::ModelPath => store
             .get(key.store_key())
             .map_err(map_config_error)?
             .filter(|value| !value.trim().is_empty())
             .unwrap_or_default(),
-        ConfigKey::AutoTag => return Err(CliError::invalid_arguments("auto_tag is a boolean key")),
+        ConfigKey::EmbeddingModel => get_configured_embedding_model(store)?,
+        ConfigKey::AutoTag | ConfigKey::Rerank => {
+            return Err(CliError::invalid_arguments(format!(
+                "{} is a boolean key",
+                key.canonical_name()
+            )))
+        }
+        ConfigKey::ChunkTokens | ConfigKey::ChunkOverlapTokens => {
+            return Err(CliError::invalid_arguments("requested key is numeric"))
+        }
+        ConfigKey::Abbreviations => {
+            return Err(CliError::invalid_arguments(
+                "abbreviations is a dictionary key",
+            ))
+        }
+        ConfigKey::SearchLanguages => {
+            return Err(CliError::invalid_arguments(
+                "search_languages is a list key",
+            ))
+        }
     };
 
     Ok(value)
 }
 
 fn get_config_bool_or_default(store: &ConfigStore, key: ConfigKey) -> Result<bool, CliError> {
-    match key {
-        ConfigKey::AutoTag => {
-            let value = store.get(key.store_key()).map_err(map_config_error)?;
-            let parsed = value
-                .as_deref()
-                .map(str::trim)
-                .filter(|value| !value.is_empty())
-                .map(|value| {
-                    parse_bool(value).ok_or_else(|| {
-                        CliError::invalid_arguments(format!(
-                            "stored config value for auto_tag is invalid: \"{value}\""
-                        ))
-                    })
-                })
-                .transpose()?;
+    let default_value = match key {
+        ConfigKey::AutoTag => DEFAULT_AUTO_TAG,
+        ConfigKey::Rerank => DEFAULT_RERANK,
+        _ => return Err(CliError::invalid_arguments("requested key is not boolean")),
+    };
 
-            Ok(parsed.unwrap_or(DEFAULT_AUTO_TAG))
-        }
-        _ => Err(CliError::invalid_arguments("requested key is not boolean")),
-    }
+    let value = store.get(key.store_key()).map_err(map_config_error)?;
+    let parsed = value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            parse_bool(value).ok_or_else(|| {
+                CliError::invalid_arguments(format!(
+                    "stored config value for {} is invalid: \"{value}\"",
+                    key.canonical_name()
+                ))
+            })
+        })
+        .transpose()?;
+
+    Ok(parsed.unwrap_or(default_value))
 }
 
 fn get_config_value(store: &ConfigStore, key: ConfigKey) -> Result<ConfigValuePayload, CliError> {
     match key {
-        ConfigKey::DocumentsFolder | ConfigKey::Editor | ConfigKey::DefaultFolder | ConfigKey::OpenAiApiKey => Ok(
-            ConfigValuePayload::Text(get_config_text_or_default(store, key)?),
-        ),
-        ConfigKey::AutoTag => Ok(ConfigValuePayload::Bool(get_config_bool_or_default(
+        ConfigKey::DocumentsFolder
+        | ConfigKey::Editor
+        | ConfigKey::DefaultFolder
+        | ConfigKey::OpenAiApiKey
+        | ConfigKey::Author
+        | ConfigKey::EmbeddingModel
+        | ConfigKey::ModelPath => Ok(ConfigValuePayload::Text(get_config_text_or_default(
             store, key,
         )?)),
+        ConfigKey::AutoTag | ConfigKey::Rerank => Ok(ConfigValuePayload::Bool(
+            get_config_bool_or_default(store, key)?,
+        )),
+        ConfigKey::ChunkTokens | ConfigKey::ChunkOverlapTokens => {
+            Ok(ConfigValuePayload::Number(get_vault_setting(store, key)?))
+        }
+        ConfigKey::Abbreviations => Ok(ConfigValuePayload::Map(
+            load_configured_vault_settings(store)?.abbreviations,
+        )),
+        ConfigKey::SearchLanguages => Ok(ConfigValuePayload::List(
+            get_configured_search_languages(store)?,
+        )),
     }
 }
 
+/// Embedding model of the configured documents folder, including the default when unset.
+fn get_configured_embedding_model(store: &ConfigStore) -> Result<String, CliError> {
+    Ok(load_configured_vault_settings(store)?
+        .embedding_model
+        .unwrap_or_else(|| LOCAL_EMBEDDING_MODEL_ID.to_owned()))
+}
+
+/// Language codes of the configured documents folder, including the default when unset.
+fn get_configured_search_languages(store: &ConfigStore) -> Result<Vec<String>, CliError> {
+    Ok(load_configured_vault_settings(store)?
+        .search_languages()
+        .iter()
+        .map(|language| language.code().to_owned())
+        .collect())
+}
+
+/// Settings of the configured documents folder; defaults when the folder has none yet.
+fn load_configured_vault_settings(store: &ConfigStore) -> Result<VaultSettings, CliError> {
+    let documents_folder = resolve_documents_folder_path(&get_config_text_or_default(
+        store,
+        ConfigKey::DocumentsFolder,
+    )?)?;
+    load_vault_settings(&documents_folder).map_err(map_vault_settings_error)
+}
+
+fn get_vault_setting(store: &ConfigStore, key: ConfigKey) -> Result<usize, CliError> {
+    let settings = load_configured_vault_settings(store)?;
+    match key {
+        ConfigKey::ChunkTokens => Ok(settings.chunking.chunk_tokens),
+        ConfigKey::ChunkOverlapTokens => Ok(settings.chunking.overlap_tokens),
+        _ => Err(CliError::invalid_arguments(
+            "requested key is not a vault setting",
+        )),
+    }
+}
+
+/// Writes a key stored in the vault settings of the configured documents folder. Changed
+/// chunk sizes take effect on the next sync, which re-embeds the affected chunks.
+fn handle_config_set_vault_setting(
+    key: ConfigKey,
+    value: &str,
+    json: bool,
+) -> Result<(), CliError> {
+    let parsed = value.parse::<usize>().map_err(|_| {
+        CliError::invalid_arguments(format!(
+            "{} must be a non-negative integer",
+            key.canonical_name()
+        ))
+    })?;
+
+    let documents_folder = load_documents_folder()?;
+    let mut settings = load_vault_settings(&documents_folder).map_err(map_vault_settings_error)?;
+    match key {
+        ConfigKey::ChunkTokens => settings.chunking.chunk_tokens = parsed,
+        ConfigKey::ChunkOverlapTokens => settings.chunking.overlap_tokens = parsed,
+        _ => {
+            return Err(CliError::invalid_arguments(
+                "requested key is not a vault setting",
+            ))
+        }
+    }
+    save_vault_settings(&documents_folder, &settings).map_err(map_vault_settings_error)?;
+
+    if json {
+        let payload = ConfigSetPayload {
+            status: "updated",
+            key: key.canonical_name().to_owned(),
+            value: ConfigValuePayload::Number(parsed),
+        };
+        return print_json(&payload);
+    }
+
+    println!("{} = {}", key.canonical_name(), parsed);
+    Ok(())
+}
+
+/// Adds or replaces the vault's search abbreviations from comma-separated `abbr=expansion`
+/// entries; an empty expansion (`abbr=`) removes the entry.
+fn handle_config_set_abbreviations(value: &str, json: bool) -> Result<(), CliError> {
+    let documents_folder = load_documents_folder()?;
+    let mut settings = load_vault_settings(&documents_folder).map_err(map_vault_settings_error)?;
+    for entry in value.split(',').filter(|entry| !entry.trim().is_empty()) {
+        let Some((abbreviation, expansion)) = entry.split_once('=') else {
+            return Err(CliError::invalid_arguments(format!(
+                "abbreviation entry \"{}\" must look like abbr=expansion",
+                entry.trim()
+            )));
+        };
+        let abbreviation = abbreviation.trim().to_lowercase();
+        let expansion = expansion.split_whitespace().collect::<Vec<_>>().join(" ");
+        if expansion.is_empty() {
+            settings.abbreviations.remove(&abbreviation);
+        } else {
+            settings.abbreviations.insert(abbreviation, expansion);
+        }
+    }
+    save_vault_settings(&documents_folder, &settings).map_err(map_vault_settings_error)?;
+
+    if json {
+        let payload = ConfigSetPayload {
+            status: "updated",
+            key: ConfigKey::Abbreviations.canonical_name().to_owned(),
+            value: ConfigValuePayload::Map(settings.abbreviations),
+        };
+        return print_json(&payload);
+    }
+
+    println!(
+        "abbreviations = {}",
+        format_abbreviations(&settings.abbreviations)
+    );
+    Ok(())
+}
+
+/// Sets the vault's embedding model: a preset name (`default`, `multilingual`), a Hugging
+/// Face repository id, or a local model directory (stored as an absolute path). The cache
+/// switches models and re-embeds every document on the next sync.
+fn handle_config_set_embedding_model(value: &str, json: bool) -> Result<(), CliError> {
+    let value = resolve_embedding_model_preset(value).unwrap_or(value);
+    let embedding_model = if value == LOCAL_EMBEDDING_MODEL_ID {
+        None
+    } else if value == "~" || value.starts_with("~/") || is_local_model_path(value) {
+        let directory = expand_tilde_path(value)?;
+        let directory = fs::canonicalize(&directory).map_err(|_| {
+            CliError::invalid_arguments(format!(
+                "embedding model directory \"{}\" does not exist",
+                directory.display()
+            ))
+        })?;
+        if !directory.is_dir() {
+            return Err(CliError::invalid_arguments(format!(
+                "embedding model path \"{}\" is not a directory",
+                directory.display()
+            )));
+        }
+        Some(directory.to_string_lossy().into_owned())
+    } else {
+        Some(value.to_owned())
+    };
+
+    let documents_folder = load_documents_folder()?;
+    let mut settings = load_vault_settings(&documents_folder).map_err(map_vault_settings_error)?;
+    settings.embedding_model = embedding_model;
+    save_vault_settings(&documents_folder, &settings).map_err(map_vault_settings_error)?;
+    let effective_model = settings
+        .embedding_model
+        .unwrap_or_else(|| LOCAL_EMBEDDING_MODEL_ID.to_owned());
+
+    if json {
+        let payload = ConfigSetPayload {
+            status: "updated",
+            key: ConfigKey::EmbeddingModel.canonical_name().to_owned(),
+            value: ConfigValuePayload::Text(effective_model),
+        };
+        return print_json(&payload);
+    }
+
+    println!("embedding_model = {effective_model}");
+    println!("Run `tentacle reindex` to re-embed documents with this model.");
+    Ok(())
+}
+
+/// Replaces the vault's search languages with comma-separated codes or names (`en,pt,es`).
+fn handle_config_set_search_languages(value: &str, json: bool) -> Result<(), CliError> {
+    let mut languages = Vec::new();
+    for entry in value.split(',').filter(|entry| !entry.trim().is_empty()) {
+        let language = entry
+            .parse::<SearchLanguage>()
+            .map_err(CliError::invalid_arguments)?;
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+
+    let documents_folder = load_documents_folder()?;
+    let mut settings = load_vault_settings(&documents_folder).map_err(map_vault_settings_error)?;
+    settings.search_languages = languages;
+    save_vault_settings(&documents_folder, &settings).map_err(map_vault_settings_error)?;
+    let codes = settings
+        .search_languages()
+        .iter()
+        .map(|language| language.code().to_owned())
+        .collect::<Vec<_>>();
+
+    if json {
+        let payload = ConfigSetPayload {
+            status: "updated",
+            key: ConfigKey::SearchLanguages.canonical_name().to_owned(),
+            value: ConfigValuePayload::List(codes),
+        };
+        return print_json(&payload);
+    }
+
+    println!("search_languages = {}", codes.join(","));
+    Ok(())
+}
+
+/// Sets the per-machine directory the embedding model is read from instead of hf-hub;
+/// `none` clears it.
+fn handle_config_set_model_path(value: &str, json: bool) -> Result<(), CliError> {
+    let store = open_config_store()?;
+    let model_path = if value.eq_ignore_ascii_case("none") {
+        store.delete(KEY_MODEL_PATH).map_err(map_config_error)?;
+        String::new()
+    } else {
+        let directory = expand_tilde_path(value)?;
+        let directory = fs::canonicalize(&directory).map_err(|_| {
+            CliError::invalid_arguments(format!(
+                "model directory \"{}\" does not exist",
+                directory.display()
+            ))
+        })?;
+        validate_model_directory(&directory).map_err(map_model_store_error)?;
+        let directory = directory.to_string_lossy().into_owned();
+        store
+            .set(KEY_MODEL_PATH, &directory)
+            .map_err(map_config_error)?;
+        directory
+    };
+
+    if json {
+        let payload = ConfigSetPayload {
+            status: "updated",
+            key: ConfigKey::ModelPath.canonical_name().to_owned(),
+            value: ConfigValuePayload::Text(model_path),
+        };
+        return print_json(&payload);
+    }
+
+    println!("model_path = {model_path}");
+    Ok(())
+}
+
+fn format_abbreviations(abbreviations: &BTreeMap<String, String>) -> String {
+    abbreviations
+        .iter()
+        .map(|(abbreviation, expansion)| format!("{abbreviation}={expansion}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 fn resolve_create_folder(requested_folder: Option<&str>) -> Result<Option<String>, CliError> {
     if let Some(raw_folder) = requested_folder {
         return require_folder_path(raw_folder, "folder").map(Some);
@@ -1439,11 +3139,24 @@ fn confirm_delete(document_id: &str) -> Result<bool, CliError> {
     Ok(normalized == "y" || normalized == "yes")
 }
 
-fn is_embedding_model_cached() -> bool {
-    let cache_dir = home_dir()
-        .map(|h| h.join(".cache/huggingface/hub/models--onnx-community--all-MiniLM-L6-v2-ONNX"))
-        .unwrap_or_default();
-    cache_dir.exists()
+/// Whether the vault's embedding model is on disk: a local model directory, or the hf-hub
+/// cache entry of a repository id.
+fn is_embedding_model_cached(documents_folder: &Path) -> bool {
+    let model_id = configured_embedding_model(documents_folder)
+        .unwrap_or_else(|_| LOCAL_EMBEDDING_MODEL_ID.to_owned());
+    if embedding_model_directory(&model_id).is_some() {
+        return true;
+    }
+
+    huggingface_cache_directory(&model_id).is_some_and(|directory| directory.exists())
+}
+
+/// hf-hub cache folder of `model_id`, whether or not it has been downloaded.
+fn huggingface_cache_directory(model_id: &str) -> Option<PathBuf> {
+    home_dir().map(|h| {
+        h.join(".cache/huggingface/hub")
+            .join(format!("models--{}", model_id.replace('/', "--")))
+    })
 }
 
 fn sync_cache_for_document_folder(
@@ -1510,31 +3223,15 @@ fn sort_list_documents(
     sort: Option<&ListSort>,
     desc: bool,
 ) {
-    let sort_mode = sort.cloned().unwrap_or(ListSort::Modified);
-    let descending = desc || sort.is_none();
-
-    documents.sort_by(|left, right| {
-        let order = match sort_mode {
-            ListSort::Created => left
-                .created_at
-                .cmp(&right.created_at)
-                .then_with(|| left.id.cmp(&right.id)),
-            ListSort::Modified => left
-                .updated_at
-                .cmp(&right.updated_at)
-                .then_with(|| left.id.cmp(&right.id)),
-            ListSort::Title => left
-                .title
-                .cmp(&right.title)
-                .then_with(|| left.id.cmp(&right.id)),
-        };
+    document_store::sort_documents(documents, sort.map(document_sort), desc);
+}
 
-        if descending {
-            order.reverse()
-        } else {
-            order
-        }
-    });
+fn document_sort(sort: &ListSort) -> DocumentSort {
+    match sort {
+        ListSort::Created => DocumentSort::Created,
+        ListSort::Modified => DocumentSort::Modified,
+        ListSort::Title => DocumentSort::Title,
+    }
 }
 
 fn parse_csv_values(raw: &str) -> Vec<String> {
@@ -1552,65 +3249,48 @@ fn parse_csv_values(raw: &str) -> Vec<String> {
     values
 }
 
-fn tags_match_filter(tags: &[String], requested_tags: &[String]) -> bool {
-    if requested_tags.is_empty() {
-        return true;
+fn map_search_passage(path: &str, passage: SearchPassagePayload) -> SearchPassageOutputPayload {
+    SearchPassageOutputPayload {
+        locator: build_passage_locator(path, passage.line_start, passage.line_end),
+        text: passage.text,
+        heading_path: passage.heading_path,
+        line_start: passage.line_start,
+        line_end: passage.line_end,
+        score: passage.score,
+        page: passage.page,
     }
-
-    let normalized_tags = tags
-        .iter()
-        .map(|tag| tag.to_lowercase())
-        .collect::<HashSet<String>>();
-    requested_tags
-        .iter()
-        .all(|requested_tag| normalized_tags.contains(requested_tag))
 }
 
-fn build_snippet(content: &str, query: &str) -> Option<String> {
-    let normalized_content = content.trim();
-    if normalized_content.is_empty() {
-        return None;
-    }
-
-    let query = query.trim();
-    if query.is_empty() {
-        return Some(truncate_display(normalized_content, 160));
-    }
-
-    let content_lower = normalized_content.to_lowercase();
-    let query_lower = query.to_lowercase();
-    let query_start = content_lower.find(&query_lower).or_else(|| {
-        query_lower
-            .split_whitespace()
-            .filter(|token| !token.is_empty())
-            .find_map(|token| content_lower.find(token))
-    });
-
-    let Some(start_index) = query_start else {
-        return Some(truncate_display(normalized_content, 160));
-    };
-
-    let mut start = start_index.saturating_sub(70);
-    let mut end = (start_index + query_lower.len() + 90).min(normalized_content.len());
-    while start > 0 && !normalized_content.is_char_boundary(start) {
-        start -= 1;
-    }
-    while end < normalized_content.len() && !normalized_content.is_char_boundary(end) {
-        end += 1;
+/// Flattens `highlighted` to one line, wrapping its query matches in bold yellow when
+/// `use_color` is set.
+fn render_highlighted_text(highlighted: &HighlightedTextPayload, use_color: bool) -> String {
+    let text = highlighted.text.replace('\n', " ");
+    if !use_color || highlighted.matches.is_empty() {
+        return text;
     }
 
-    let snippet = normalized_content[start..end].trim();
-    if snippet.is_empty() {
-        return None;
+    let mut rendered = String::with_capacity(text.len() + highlighted.matches.len() * 12);
+    let mut matches = highlighted.matches.iter().peekable();
+    for (position, character) in text.chars().enumerate() {
+        if matches.peek().is_some_and(|range| range.start == position) {
+            rendered.push_str("\x1b[1;33m");
+        }
+        rendered.push(character);
+        let closes_match = matches
+            .peek()
+            .is_some_and(|range| range.end == position + 1);
+        if closes_match {
+            rendered.push_str("\x1b[0m");
+            matches.next();
+        }
     }
+    rendered
+}
 
-    let prefix = if start > 0 { "..." } else { "" };
-    let suffix = if end < normalized_content.len() {
-        "..."
-    } else {
-        ""
-    };
-    Some(format!("{prefix}{snippet}{suffix}"))
+/// `path#L<start>-L<end>`; the same range reads back with `read --from <start> --length
+/// <end - start + 1>`.
+fn build_passage_locator(path: &str, line_start: usize, line_end: usize) -> String {
+    format!("{path}#L{line_start}-L{line_end}")
 }
 
 fn build_agent_path(folder: &str, document_id: &str) -> String {
@@ -1641,6 +3321,7 @@ fn map_document_to_read_payload(document: StoredDocument) -> ReadResponsePayload
         modified_at: normalize_iso8601(&document.updated_at),
         content: document.body,
         size_bytes,
+        properties: document.properties,
     }
 }
 
@@ -1729,10 +3410,88 @@ fn map_knowledge_base_error(error: KnowledgeBaseError) -> CliError {
             message: error.to_string(),
         },
         KnowledgeBaseError::Io(error) => map_io_error(error),
+        KnowledgeBaseError::InvalidQuery(error) => CliError::InvalidQuery {
+            message: error.message,
+            column: error.column,
+        },
+        KnowledgeBaseError::VaultSettings(error) => map_vault_settings_error(error),
         KnowledgeBaseError::Validation(message) => CliError::invalid_arguments(message),
     }
 }
 
+fn map_import_error(error: ImportError) -> CliError {
+    match error {
+        ImportError::DocumentStore(error) => map_document_store_error(error),
+        ImportError::DocumentFolders(error) => map_document_folders_error(error),
+        ImportError::Io(error) => map_io_error(error),
+        ImportError::Validation(message) => CliError::invalid_arguments(message),
+        ImportError::Zip(_) | ImportError::Csv(_) | ImportError::Xml(_) => CliError::General {
+            message: error.to_string(),
+        },
+    }
+}
+
+fn map_export_error(error: ExportError) -> CliError {
+    match error {
+        ExportError::DocumentStore(error) => map_document_store_error(error),
+        ExportError::DocumentCache(error) => map_document_cache_error(error),
+        ExportError::FolderNotFound(message) => CliError::FolderNotFound { message },
+        ExportError::Io(error) => map_io_error(error),
+        ExportError::Validation(message) => CliError::invalid_arguments(message),
+        ExportError::Json(_) | ExportError::Zip(_) => CliError::General {
+            message: error.to_string(),
+        },
+    }
+}
+
+fn map_evaluation_error(error: EvaluationError) -> CliError {
+    match error {
+        EvaluationError::Io(error) => map_io_error(error),
+        EvaluationError::KnowledgeBase(error) => map_knowledge_base_error(error),
+        EvaluationError::Qrels { .. } | EvaluationError::Validation(_) => {
+            CliError::invalid_arguments(error.to_string())
+        }
+        EvaluationError::Json(_) => CliError::General {
+            message: error.to_string(),
+        },
+    }
+}
+
+fn map_backup_error(error: BackupError) -> CliError {
+    match error {
+        BackupError::Io(error) => map_io_error(error),
+        BackupError::Validation(message) => CliError::invalid_arguments(message),
+        BackupError::Zip(_)
+        | BackupError::Json(_)
+        | BackupError::Sqlite(_)
+        | BackupError::Verification(_) => CliError::General {
+            message: error.to_string(),
+        },
+    }
+}
+
+fn map_model_store_error(error: ModelStoreError) -> CliError {
+    match error {
+        ModelStoreError::Io(error) => map_io_error(error),
+        ModelStoreError::Validation(_) | ModelStoreError::NotInstalled(_) => {
+            CliError::invalid_arguments(error.to_string())
+        }
+        ModelStoreError::Json(_) => CliError::General {
+            message: error.to_string(),
+        },
+    }
+}
+
+fn map_vault_settings_error(error: VaultSettingsError) -> CliError {
+    match error {
+        VaultSettingsError::Io(error) => map_io_error(error),
+        VaultSettingsError::Validation(message) => CliError::invalid_arguments(message),
+        VaultSettingsError::Json(_) => CliError::General {
+            message: error.to_string(),
+        },
+    }
+}
+
 fn map_io_error(error: std::io::Error) -> CliError {
     match error.kind() {
         std::io::ErrorKind::PermissionDenied => CliError::PermissionDenied {
@@ -1746,7 +3505,8 @@ fn map_io_error(error: std::io::Error) -> CliError {
 
 #[cfg(test)]
 mod tests {
-    use super::{build_agent_path, slice_lines};
+    use super::{build_agent_path, build_passage_locator, render_highlighted_text, slice_lines};
+    use tentacle_core::document_cache::{HighlightedTextPayload, MatchRangePayload};
 
     #[test]
     fn build_agent_path_includes_folder_and_extension() {
@@ -1759,6 +3519,33 @@ mod tests {
         assert_eq!(build_agent_path("   ", "doc-123"), "doc-123.md");
     }
 
+    #[test]
+    fn build_passage_locator_appends_line_range() {
+        assert_eq!(
+            build_passage_locator("docs/doc-123.md", 120, 140),
+            "docs/doc-123.md#L120-L140"
+        );
+    }
+
+    #[test]
+    fn render_highlighted_text_colours_matches_only_on_request() {
+        let highlighted = HighlightedTextPayload {
+            text: "…the hybrid\nsearch leg".to_string(),
+            matches: vec![
+                MatchRangePayload { start: 5, end: 11 },
+                MatchRangePayload { start: 12, end: 18 },
+            ],
+        };
+        assert_eq!(
+            render_highlighted_text(&highlighted, false),
+            "…the hybrid search leg"
+        );
+        assert_eq!(
+            render_highlighted_text(&highlighted, true),
+            "…the \x1b[1;33mhybrid\x1b[0m \x1b[1;33msearch\x1b[0m leg"
+        );
+    }
+
     #[test]
     fn slice_lines_respects_from_and_length() {
         let content = "l1\nl2\nl3\nl4";
@@ -5,7 +5,7 @@ use std::ffi::OsStr;
 use std::fs;
 use std::io::{Read, Write};
 use std::net::TcpListener;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::Duration;
 use tempfile::{tempdir, TempDir};
@@ -658,6 +658,766 @@ fn deferred_commands_return_not_implemented_with_exit_code_four() {
         .contains("edit"));
 }
 
+#[test]
+fn import_notion_export_rebuilds_folders_and_database_rows() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let page_hash = "0123456789abcdef0123456789abcdef";
+    let child_hash = "fedcba9876543210fedcba9876543210";
+    let database_hash = "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbb";
+    let export_dir = env.temp_dir.path().join("notion-export");
+    fs::create_dir_all(export_dir.join(format!("Roadmap {page_hash}")))
+        .expect("failed to create notion fixture directory");
+    fs::write(
+        export_dir.join(format!("Roadmap {page_hash}.md")),
+        format!("# Roadmap\n\nDetails in [Q3](Roadmap%20{page_hash}/Q3%20{child_hash}.md)."),
+    )
+    .expect("failed to write notion page fixture");
+    fs::write(
+        export_dir.join(format!("Roadmap {page_hash}/Q3 {child_hash}.md")),
+        "# Q3\n\nLaunch the importer.",
+    )
+    .expect("failed to write notion child fixture");
+    fs::write(
+        export_dir.join(format!("Ideas {database_hash}.csv")),
+        "Name,Owner\nOffline sync,Ana\n",
+    )
+    .expect("failed to write notion database fixture");
+
+    let import_payload = env.run_json_success(vec![
+        "import".to_owned(),
+        "--from".to_owned(),
+        "notion".to_owned(),
+        export_dir.to_string_lossy().into_owned(),
+        "--folder".to_owned(),
+        "notion".to_owned(),
+    ]);
+    assert_eq!(import_payload["status"], "completed");
+    assert_eq!(import_payload["documents_imported"].as_u64(), Some(3));
+
+    let documents = import_payload["documents"]
+        .as_array()
+        .expect("documents should be an array");
+    let find_document = |title: &str| {
+        documents
+            .iter()
+            .find(|document| document["title"] == title)
+            .unwrap_or_else(|| panic!("missing imported document: {title}"))
+            .clone()
+    };
+    let roadmap = find_document("Roadmap");
+    let q3 = find_document("Q3");
+    let idea = find_document("Offline sync");
+    assert_eq!(roadmap["folder"], "notion");
+    assert_eq!(q3["folder"], "notion/Roadmap");
+    assert_eq!(idea["folder"], "notion/Ideas");
+
+    let roadmap_read = env.run_json_success(["read", roadmap["id"].as_str().unwrap()]);
+    assert!(roadmap_read["content"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("(Roadmap/Q3.md)"));
+
+    let idea_read = env.run_json_success(["read", idea["id"].as_str().unwrap()]);
+    assert_eq!(idea_read["properties"]["owner"], "Ana");
+
+    let folder_payload = env.run_json_success(["folder", "list"]);
+    let folder_paths = folder_payload["folders"]
+        .as_array()
+        .expect("folders should be an array")
+        .iter()
+        .filter_map(|folder| folder["path"].as_str())
+        .collect::<Vec<_>>();
+    assert!(folder_paths.contains(&"notion/Roadmap"));
+    assert!(folder_paths.contains(&"notion/Ideas"));
+}
+
+#[test]
+fn import_enex_maps_notebook_tags_and_timestamps() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let export_path = env.temp_dir.path().join("Work Notes.enex");
+    fs::write(
+        &export_path,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export>
+  <note>
+    <title>Standup</title>
+    <content><![CDATA[<en-note><div><en-todo checked="true"/>Ship ENEX import</div><table><tr><td>Owner</td><td>Ana</td></tr></table></en-note>]]></content>
+    <created>20220301T090000Z</created>
+    <updated>20220302T170000Z</updated>
+    <tag>meetings</tag>
+  </note>
+</en-export>"#,
+    )
+    .expect("failed to write enex fixture");
+
+    let import_payload = env.run_json_success(vec![
+        "import".to_owned(),
+        "--from".to_owned(),
+        "enex".to_owned(),
+        export_path.to_string_lossy().into_owned(),
+    ]);
+    assert_eq!(import_payload["source"], "enex");
+    assert_eq!(import_payload["documents_imported"].as_u64(), Some(1));
+    assert_eq!(import_payload["documents"][0]["folder"], "Work Notes");
+
+    let read_payload = env.run_json_success([
+        "read",
+        import_payload["documents"][0]["id"].as_str().unwrap(),
+    ]);
+    assert_eq!(read_payload["title"], "Standup");
+    assert_eq!(read_payload["created_at"], "2022-03-01T09:00:00Z");
+    assert_eq!(json_string_array(&read_payload, "tags"), vec!["meetings"]);
+    let content = read_payload["content"].as_str().unwrap_or_default();
+    assert!(content.contains("- [x] Ship ENEX import"));
+    assert!(content.contains("| Owner | Ana |"));
+}
+
+#[test]
+fn import_html_page_detects_format_and_records_canonical_source() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let page_path = env.temp_dir.path().join("saved-page.html");
+    fs::write(
+        &page_path,
+        r#"<html><head><title>Incident Review</title>
+<link rel="canonical" href="https://wiki.example.com/incident-review"></head>
+<body><nav>Home | About</nav><main><h2>Timeline</h2><ul><li>Pager fired</li><li>Rolled back</li></ul>
+<p>Root cause was a stale <code>config.yaml</code> that shipped with the canary deploy and was only caught after the rollout finished.</p></main></body></html>"#,
+    )
+    .expect("failed to write html fixture");
+
+    let import_payload = env.run_json_success(vec![
+        "import".to_owned(),
+        page_path.to_string_lossy().into_owned(),
+    ]);
+    assert_eq!(import_payload["source"], "html");
+    assert_eq!(import_payload["documents"][0]["title"], "Incident Review");
+
+    let read_payload = env.run_json_success([
+        "read",
+        import_payload["documents"][0]["id"].as_str().unwrap(),
+    ]);
+    assert_eq!(
+        read_payload["properties"]["source"],
+        "https://wiki.example.com/incident-review"
+    );
+    let content = read_payload["content"].as_str().unwrap_or_default();
+    assert!(content.contains("## Timeline\n\n- Pager fired\n- Rolled back"));
+    assert!(!content.contains("Home | About"));
+}
+
+#[test]
+fn import_claude_conversations_skips_unchanged_on_reimport() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let export_path = env.temp_dir.path().join("conversations.json");
+    fs::write(
+        &export_path,
+        r#"[{"uuid":"c-1","name":"Cache invalidation","created_at":"2025-03-01T09:00:00Z","updated_at":"2025-03-01T09:05:00Z",
+"chat_messages":[{"sender":"human","text":"When should the cache be rebuilt?"},{"sender":"assistant","text":"After every reindex."}]},
+{"uuid":"c-2","name":"Lunch ideas","created_at":"2024-01-01T12:00:00Z","updated_at":"2024-01-01T12:00:00Z",
+"chat_messages":[{"sender":"human","text":"Soup or salad?"}]}]"#,
+    )
+    .expect("failed to write conversations fixture");
+    let export_arg = export_path.to_string_lossy().into_owned();
+
+    let import_payload =
+        env.run_json_success(["import", export_arg.as_str(), "--since", "2025-01-01"]);
+    assert_eq!(import_payload["source"], "conversations");
+    assert_eq!(import_payload["documents_imported"], 1);
+    assert_eq!(
+        import_payload["documents"][0]["title"],
+        "Cache invalidation"
+    );
+
+    let read_payload = env.run_json_success([
+        "read",
+        import_payload["documents"][0]["id"].as_str().unwrap(),
+    ]);
+    assert_eq!(read_payload["properties"]["conversation_source"], "claude");
+    let content = read_payload["content"].as_str().unwrap_or_default();
+    assert!(content.contains(
+        "## User\n\nWhen should the cache be rebuilt?\n\n## Assistant\n\nAfter every reindex."
+    ));
+
+    let reimport_payload =
+        env.run_json_success(["import", export_arg.as_str(), "--since", "2025-01-01"]);
+    assert_eq!(reimport_payload["documents_imported"], 0);
+    assert_eq!(
+        reimport_payload["skipped"][0]["reason"],
+        "unchanged since last import"
+    );
+}
+
+#[test]
+fn import_eml_converts_html_body_and_records_addresses() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let message_path = env.temp_dir.path().join("decision.eml");
+    fs::write(
+        &message_path,
+        "Message-ID: <launch@example.com>\r\nFrom: Ana Lima <ana@example.com>\r\nTo: team@example.com\r\nSubject: Launch date\r\nDate: Wed, 5 Mar 2025 14:30:00 +0000\r\nContent-Type: text/html; charset=utf-8\r\n\r\n<p>We ship on <strong>April 2</strong>.</p>\r\n",
+    )
+    .expect("failed to write eml fixture");
+
+    let import_payload = env.run_json_success(vec![
+        "import".to_owned(),
+        message_path.to_string_lossy().into_owned(),
+    ]);
+    assert_eq!(import_payload["source"], "mail");
+    assert_eq!(import_payload["documents"][0]["title"], "Launch date");
+
+    let read_payload = env.run_json_success([
+        "read",
+        import_payload["documents"][0]["id"].as_str().unwrap(),
+    ]);
+    assert_eq!(
+        read_payload["properties"]["from"],
+        "Ana Lima <ana@example.com>"
+    );
+    assert_eq!(read_payload["properties"]["to"], "team@example.com");
+    assert_eq!(read_payload["properties"]["date"], "2025-03-05T14:30:00Z");
+    assert!(read_payload["content"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("We ship on **April 2**."));
+}
+
+#[test]
+fn import_bookmarks_detects_export_and_deduplicates_by_url() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let export_path = env.temp_dir.path().join("bookmarks.html");
+    fs::write(
+        &export_path,
+        r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<TITLE>Bookmarks</TITLE>
+<DL><p>
+    <DT><H3>Dev</H3>
+    <DL><p>
+        <DT><A HREF="https://docs.rs/" ADD_DATE="1700000000" TAGS="rust">Docs.rs</A>
+    </DL><p>
+</DL>"#,
+    )
+    .expect("failed to write bookmarks fixture");
+    let export_arg = export_path.to_string_lossy().into_owned();
+
+    let import_payload = env.run_json_success(["import", export_arg.as_str()]);
+    assert_eq!(import_payload["source"], "bookmarks");
+    assert_eq!(import_payload["documents"][0]["folder"], "Dev");
+
+    let read_payload = env.run_json_success([
+        "read",
+        import_payload["documents"][0]["id"].as_str().unwrap(),
+    ]);
+    assert_eq!(read_payload["properties"]["source"], "https://docs.rs/");
+    assert_eq!(read_payload["tags"], serde_json::json!(["rust"]));
+
+    let reimport_payload = env.run_json_success(["import", export_arg.as_str()]);
+    assert_eq!(reimport_payload["documents_imported"], 0);
+    assert_eq!(
+        reimport_payload["skipped"][0]["reason"],
+        "a note with this URL already exists"
+    );
+}
+
+#[test]
+fn export_folder_writes_markdown_json_and_manifest() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let created = env.run_json_success_with_stdin(
+        [
+            "create", "--title", "Runbook", "--folder", "ops", "--tags", "oncall",
+        ],
+        "Restart the workers.",
+    );
+    env.run_json_success_with_stdin(["create", "--title", "Elsewhere"], "Not exported.");
+
+    let markdown_dir = env.temp_dir.path().join("export-md");
+    let markdown_payload = env.run_json_success(vec![
+        "export".to_owned(),
+        "--folder".to_owned(),
+        "ops".to_owned(),
+        markdown_dir.to_string_lossy().into_owned(),
+        "--strip-frontmatter".to_owned(),
+    ]);
+    assert_eq!(markdown_payload["format"], "markdown");
+    assert_eq!(markdown_payload["documents_exported"], 1);
+    assert_eq!(markdown_payload["documents"][0]["path"], "ops/Runbook.md");
+    let exported = fs::read_to_string(markdown_dir.join("ops/Runbook.md"))
+        .expect("exported markdown should exist");
+    assert!(exported.starts_with("---\ntags: [\"oncall\"]\n---\n# Runbook"));
+    assert!(!exported.contains("tags_locked"));
+    let manifest: Value = serde_json::from_str(
+        &fs::read_to_string(markdown_dir.join("manifest.json")).expect("manifest should exist"),
+    )
+    .expect("manifest should be JSON");
+    assert_eq!(manifest["documents"][0]["id"], created["id"]);
+
+    let jsonl_dir = env.temp_dir.path().join("export-jsonl");
+    let jsonl_payload = env.run_json_success(vec![
+        "export".to_owned(),
+        created["id"].as_str().unwrap().to_owned(),
+        jsonl_dir.to_string_lossy().into_owned(),
+        "--format".to_owned(),
+        "jsonl".to_owned(),
+    ]);
+    assert_eq!(jsonl_payload["documents"][0]["path"], "documents.jsonl");
+    let record: Value = serde_json::from_str(
+        fs::read_to_string(jsonl_dir.join("documents.jsonl"))
+            .expect("jsonl should exist")
+            .trim(),
+    )
+    .expect("jsonl line should be JSON");
+    assert_eq!(record["body"], "Restart the workers.");
+    assert_eq!(record["folder_path"], "ops");
+}
+
+#[test]
+fn export_folder_as_html_writes_static_site() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let runbook = env.run_json_success_with_stdin(
+        [
+            "create", "--title", "Runbook", "--folder", "handbook", "--tags", "oncall",
+        ],
+        "Restart the **workers**.",
+    );
+    env.run_json_success_with_stdin(
+        [
+            "create",
+            "--title",
+            "Start here",
+            "--folder",
+            "handbook/intro",
+        ],
+        "Read the [runbook](../Runbook.md).",
+    );
+
+    let site_dir = env.temp_dir.path().join("site");
+    let payload = env.run_json_success(vec![
+        "export".to_owned(),
+        "--format".to_owned(),
+        "html".to_owned(),
+        "--folder".to_owned(),
+        "handbook".to_owned(),
+        site_dir.to_string_lossy().into_owned(),
+    ]);
+    assert_eq!(payload["format"], "html");
+    assert_eq!(payload["documents_exported"], 2);
+    assert_eq!(payload["documents"][0]["path"], "handbook/Runbook.html");
+
+    let runbook_page = fs::read_to_string(site_dir.join("handbook/Runbook.html"))
+        .expect("runbook page should exist");
+    assert!(runbook_page.contains("<strong>workers</strong>"));
+    assert!(runbook_page.contains("href=\"../handbook/intro/Start%20here.html\">Start here</a>"));
+    assert!(runbook_page.contains("href=\"../tags/oncall.html\""));
+    let intro_page = fs::read_to_string(site_dir.join("handbook/intro/Start here.html"))
+        .expect("intro page should exist");
+    assert!(intro_page.contains("href=\"../../handbook/Runbook.html\">runbook</a>"));
+    assert!(site_dir.join("index.html").is_file());
+    assert!(site_dir.join("tags/oncall.html").is_file());
+
+    let index: Value = serde_json::from_str(
+        &fs::read_to_string(site_dir.join("search-index.json")).expect("search index should exist"),
+    )
+    .expect("search index should be JSON");
+    assert_eq!(index[0]["id"], runbook["id"]);
+    assert!(index[0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("Restart the **workers**."));
+}
+
+#[test]
+fn export_tag_as_epub_follows_sort_order() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+    env.run_json_success(["config", "set", "author", "Design Team"]);
+
+    let alpha = env.run_json_success_with_stdin(
+        ["create", "--title", "Alpha notes", "--tags", "longread"],
+        "First in the alphabet.",
+    );
+    let zebra = env.run_json_success_with_stdin(
+        ["create", "--title", "Zebra notes", "--tags", "longread"],
+        "Last in the alphabet.",
+    );
+    env.run_json_success_with_stdin(["create", "--title", "Unrelated"], "Not in the book.");
+
+    let book = env.temp_dir.path().join("books/longread.epub");
+    let payload = env.run_json_success(vec![
+        "export".to_owned(),
+        "--format".to_owned(),
+        "epub".to_owned(),
+        "--tag".to_owned(),
+        "longread".to_owned(),
+        "--sort".to_owned(),
+        "title".to_owned(),
+        "--desc".to_owned(),
+        book.to_string_lossy().into_owned(),
+    ]);
+    assert_eq!(payload["format"], "epub");
+    assert_eq!(payload["documents_exported"], 2);
+    assert!(payload.get("manifest").is_none());
+    assert_eq!(payload["documents"][0]["id"], zebra["id"]);
+    assert_eq!(payload["documents"][1]["id"], alpha["id"]);
+
+    let bytes = fs::read(&book).expect("epub should exist");
+    assert_eq!(&bytes[..2], b"PK");
+    assert_eq!(&bytes[30..38], b"mimetype");
+    assert_eq!(&bytes[38..58], b"application/epub+zip");
+
+    let author = env.run_json_success(["config", "get", "author"]);
+    assert_eq!(author["value"], "Design Team");
+}
+
+#[test]
+fn backup_create_verify_and_restore_round_trip() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let created = env.run_json_success_with_stdin(
+        ["create", "--title", "Keep me", "--folder", "notes"],
+        "Backed up body.",
+    );
+    let archive = env.temp_dir.path().join("backups/vault.zip");
+    let archive_arg = archive.to_string_lossy().into_owned();
+
+    let backup = env.run_json_success([
+        "backup",
+        "create",
+        archive_arg.as_str(),
+        "--include-config",
+        "--exclude-cache",
+    ]);
+    assert_eq!(backup["status"], "created");
+    assert_eq!(backup["includes_config"], true);
+    assert_eq!(backup["includes_cache"], false);
+    assert_eq!(backup["file_count"], 2);
+
+    let verified = env.run_json_success(["backup", "verify", archive_arg.as_str()]);
+    assert_eq!(verified["status"], "verified");
+    assert_eq!(verified["file_count"], 2);
+
+    env.command()
+        .arg("--json")
+        .args(["backup", "restore", archive_arg.as_str()])
+        .assert()
+        .code(4)
+        .stderr(contains("not empty"));
+
+    let restored_dir = env.temp_dir.path().join("restored");
+    let restored = env.run_json_success(vec![
+        "backup".to_owned(),
+        "restore".to_owned(),
+        archive_arg.clone(),
+        "--into".to_owned(),
+        restored_dir.to_string_lossy().into_owned(),
+    ]);
+    assert_eq!(restored["status"], "restored");
+    assert_eq!(restored["files_restored"], 1);
+    assert_eq!(restored["config_restored"], false);
+    assert_eq!(restored["documents_indexed"], 1);
+    let restored_markdown = fs::read_to_string(restored_dir.join("notes/Keep me.md"))
+        .expect("restored markdown should exist");
+    assert!(restored_markdown.contains(created["id"].as_str().unwrap()));
+    assert!(restored_dir.join(".document-data.db").is_file());
+}
+
+#[test]
+fn migrate_rewrites_tiptap_json_bodies_as_markdown() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let legacy = env.run_json_success_with_stdin(
+        ["create", "--title", "Legacy", "--folder", "notes"],
+        r#"{"type":"doc","content":[{"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"Legacy"}]},{"type":"bulletList","content":[{"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"bold","marks":[{"type":"bold"}]}]}]}]}]}"#,
+    );
+    let legacy_id = legacy["id"]
+        .as_str()
+        .expect("id should be present")
+        .to_owned();
+    env.run_json_success_with_stdin(
+        ["create", "--title", "Modern", "--folder", "notes"],
+        "Already markdown.",
+    );
+    let before = env.run_json_success(["read", legacy_id.as_str()]);
+
+    let dry_run = env.run_json_success(["migrate", "--dry-run"]);
+    assert_eq!(dry_run["dry_run"], true);
+    assert_eq!(dry_run["documents_scanned"], 2);
+    assert_eq!(dry_run["documents_converted"], 1);
+    assert_eq!(dry_run["documents"][0]["id"], legacy_id.as_str());
+    let unchanged = env.run_json_success(["read", legacy_id.as_str()]);
+    assert_eq!(unchanged["content"], before["content"]);
+
+    let migrated = env.run_json_success(["migrate", "--folder", "notes"]);
+    assert_eq!(migrated["dry_run"], false);
+    assert_eq!(migrated["documents_converted"], 1);
+
+    let after = env.run_json_success(["read", legacy_id.as_str()]);
+    assert_eq!(after["content"], "# Legacy\n\n- **bold**");
+    assert_eq!(after["modified_at"], before["modified_at"]);
+
+    let rerun = env.run_json_success(["migrate"]);
+    assert_eq!(rerun["documents_converted"], 0);
+}
+
+#[test]
+fn config_chunk_settings_are_stored_in_the_vault() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let defaults = env.run_json_success(["config"]);
+    assert_eq!(defaults["chunk_tokens"], 256);
+    assert_eq!(defaults["chunk_overlap_tokens"], 32);
+
+    let updated = env.run_json_success(["config", "set", "chunk_tokens", "128"]);
+    assert_eq!(updated["key"], "chunk_tokens");
+    assert_eq!(updated["value"], 128);
+    assert_eq!(
+        env.run_json_success(["config", "get", "chunk_tokens"])["value"],
+        128
+    );
+
+    let settings = fs::read_to_string(env.documents_dir.join(".tentacle-settings.json"))
+        .expect("vault settings should be written to the documents folder");
+    assert!(settings.contains("\"chunk_tokens\": 128"));
+
+    env.command()
+        .arg("--json")
+        .args(["config", "set", "chunk_overlap_tokens", "100"])
+        .assert()
+        .code(4)
+        .stderr(contains("at most half"));
+    env.command()
+        .arg("--json")
+        .args(["config", "set", "chunk_tokens", "many"])
+        .assert()
+        .code(4)
+        .stderr(contains("non-negative integer"));
+
+    env.run_json_success_with_stdin(
+        ["create", "--title", "Chunked", "--folder", "notes"],
+        "# Heading\n\nBody text that is indexed with the vault chunk size.",
+    );
+}
+
+#[test]
+fn config_embedding_model_is_stored_in_the_vault_and_reported_by_status() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let defaults = env.run_json_success(["config"]);
+    assert_eq!(
+        defaults["embedding_model"],
+        "onnx-community/all-MiniLM-L6-v2-ONNX"
+    );
+    let status = env.run_json_success(["status"]);
+    assert_eq!(
+        status["embedding_model"],
+        "onnx-community/all-MiniLM-L6-v2-ONNX"
+    );
+    assert_eq!(status["embedding_dimensions"], 384);
+    assert_eq!(status["stale_embeddings"], 0);
+
+    let updated = env.run_json_success([
+        "config",
+        "set",
+        "embedding_model",
+        "intfloat/multilingual-e5-small",
+    ]);
+    assert_eq!(updated["value"], "intfloat/multilingual-e5-small");
+    let settings = fs::read_to_string(env.documents_dir.join(".tentacle-settings.json"))
+        .expect("vault settings should be written to the documents folder");
+    assert!(settings.contains("\"embedding_model\": \"intfloat/multilingual-e5-small\""));
+    assert_eq!(
+        env.run_json_success(["status"])["embedding_model"],
+        "intfloat/multilingual-e5-small"
+    );
+
+    // The model cannot load offline; documents are still indexed and searchable by BM25.
+    let created = env.run_json_success_with_stdin(
+        ["create", "--title", "Model switch", "--folder", "notes"],
+        "Searchable while the configured model is unavailable.",
+    );
+    let search = env.run_json_success(["search", "configured model unavailable"]);
+    assert_eq!(search["results"][0]["docid"], created["id"]);
+
+    let model_dir = env.temp_dir.path().join("local-model");
+    fs::create_dir_all(&model_dir).expect("create model directory");
+    let local = env.run_json_success([
+        "config",
+        "set",
+        "embedding_model",
+        model_dir.to_str().expect("utf-8 path"),
+    ]);
+    assert_eq!(
+        local["value"],
+        fs::canonicalize(&model_dir)
+            .expect("canonical model directory")
+            .to_str()
+            .expect("utf-8 path")
+    );
+    env.command()
+        .arg("--json")
+        .args(["config", "set", "embedding_model", "./missing-model"])
+        .assert()
+        .code(4)
+        .stderr(contains("does not exist"));
+
+    let reset = env.run_json_success(["config", "set", "embedding_model", "default"]);
+    assert_eq!(reset["value"], "onnx-community/all-MiniLM-L6-v2-ONNX");
+    let settings = fs::read_to_string(env.documents_dir.join(".tentacle-settings.json"))
+        .expect("vault settings should still exist");
+    assert!(!settings.contains("embedding_model"));
+}
+
+#[test]
+fn search_languages_and_multilingual_preset_configure_the_vault() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let created = env.run_json_success_with_stdin(
+        ["create", "--title", "Relatório", "--folder", "notas"],
+        "As informações do projeto ficam nesta nota.",
+    );
+    let search_ids = |query: &str| {
+        env.run_json_success(["search", query])["results"]
+            .as_array()
+            .expect("results should be an array")
+            .iter()
+            .map(|result| result["docid"].clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        env.run_json_success(["config"])["search_languages"],
+        serde_json::json!(["en"])
+    );
+    assert!(!search_ids("informação").contains(&created["id"]));
+
+    let updated = env.run_json_success(["config", "set", "search_languages", "pt, english, pt"]);
+    assert_eq!(updated["value"], serde_json::json!(["pt", "en"]));
+    assert_eq!(
+        env.run_json_success(["config", "get", "search_languages"])["value"],
+        serde_json::json!(["pt", "en"])
+    );
+    assert!(search_ids("informação").contains(&created["id"]));
+
+    env.command()
+        .arg("--json")
+        .args(["config", "set", "search_languages", "pt,klingon"])
+        .assert()
+        .code(4)
+        .stderr(contains("unsupported search language"));
+
+    let preset = env.run_json_success(["config", "set", "embedding_model", "multilingual"]);
+    assert_eq!(preset["value"], "Xenova/multilingual-e5-small");
+    let settings = fs::read_to_string(env.documents_dir.join(".tentacle-settings.json"))
+        .expect("vault settings should be written to the documents folder");
+    assert!(settings.contains("\"embedding_model\": \"Xenova/multilingual-e5-small\""));
+    assert!(settings.contains("\"search_languages\": [\n    \"pt\",\n    \"en\"\n  ]"));
+}
+
+#[test]
+fn model_install_status_verify_and_remove_manage_offline_models() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let source = env.temp_dir.path().join("usb/multilingual-e5-small");
+    fs::create_dir_all(source.join("onnx")).expect("create model source");
+    fs::write(source.join("tokenizer.json"), b"{}").expect("write tokenizer");
+    fs::write(source.join("onnx/model.onnx"), b"graph").expect("write model");
+
+    let installed = env.run_json_success([
+        "model",
+        "install",
+        "--from",
+        source.to_str().expect("utf-8 path"),
+        "--model",
+        "multilingual",
+    ]);
+    assert_eq!(installed["status"], "installed");
+    assert_eq!(
+        installed["model"]["model_id"],
+        "Xenova/multilingual-e5-small"
+    );
+    assert_eq!(installed["model"]["total_bytes"], 7);
+    let model_path = installed["model_path"]
+        .as_str()
+        .expect("model path")
+        .to_owned();
+    assert!(model_path.ends_with("Xenova--multilingual-e5-small"));
+    assert_eq!(
+        env.run_json_success(["config", "get", "model_path"])["value"],
+        model_path.as_str()
+    );
+
+    env.run_json_success(["config", "set", "embedding_model", "multilingual"]);
+    let status = env.run_json_success(["model", "status"]);
+    assert_eq!(status["embedding_model"], "Xenova/multilingual-e5-small");
+    assert_eq!(status["loads_from"], "model_path");
+    assert_eq!(
+        status["installed"][0]["artifacts"][1]["path"],
+        "tokenizer.json"
+    );
+    assert_eq!(status["installed"][0]["artifacts"][1]["size_bytes"], 2);
+
+    let verified = env.run_json_success(["model", "verify"]);
+    assert_eq!(verified["verified"], true);
+    assert_eq!(verified["artifacts"][0]["status"], "ok");
+
+    fs::write(Path::new(&model_path).join("onnx/model.onnx"), b"GRAPH").expect("tamper model");
+    env.command()
+        .arg("--json")
+        .args(["model", "verify"])
+        .assert()
+        .code(1)
+        .stderr(contains("does not match its checksum"));
+
+    env.command()
+        .arg("--json")
+        .args([
+            "config",
+            "set",
+            "model_path",
+            source.parent().unwrap().to_str().unwrap(),
+        ])
+        .assert()
+        .code(4)
+        .stderr(contains("tokenizer.json was not found"));
+
+    let removed = env.run_json_success(["model", "remove"]);
+    assert_eq!(removed["model_id"], "Xenova/multilingual-e5-small");
+    assert_eq!(removed["model_path_cleared"], true);
+    assert!(!Path::new(&model_path).exists());
+    assert_eq!(
+        env.run_json_success(["config", "get", "model_path"])["value"],
+        ""
+    );
+    assert_eq!(
+        env.run_json_success(["model", "status"])["loads_from"],
+        "download"
+    );
+    env.command()
+        .arg("--json")
+        .args(["model", "remove"])
+        .assert()
+        .code(4)
+        .stderr(contains("model_path is not configured"));
+}
+
 #[test]
 fn delete_document_moves_to_trash() {
     let env = CliTestEnv::new();
@@ -749,3 +1509,488 @@ fn read_document_by_prefix_id() {
         .code(2)
         .stderr(contains("not found"));
 }
+
+#[test]
+fn search_reports_the_page_of_lexical_only_pdf_hits() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let created = env.run_json_success_with_stdin(
+        ["create", "--title", "Airship manual", "--folder", "manuals"],
+        "<!-- page 1 -->\nGeneral safety notes for the hangar crew.\n\n<!-- page 2 -->\nThe zeppelin envelope is inspected every spring.\n",
+    );
+
+    // Offline, the semantic leg is unavailable, so the page comes from the BM25 match.
+    let search = env.run_json_success(["search", "zeppelin"]);
+    assert_eq!(search["results"][0]["docid"], created["id"]);
+    assert_eq!(search["results"][0]["page"], 2);
+}
+
+#[test]
+fn search_snippets_locate_matched_passages_for_read() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+    env.run_json_success(["config", "set", "chunk_overlap_tokens", "0"]);
+    env.run_json_success(["config", "set", "chunk_tokens", "32"]);
+
+    env.write_markdown_fixture(
+        "guides/runbook.md",
+        "runbook",
+        "Runbook",
+        &["ops"],
+        "## Deploy\n\nBuild the release artifacts and upload them to the staging bucket.\n\n## Rollback\n\nRevert the release tag and redeploy the previous build.\n\n## Monitoring\n\nWatch the error dashboards for an hour after each deploy.",
+    );
+    env.run_json_success(["reindex"]);
+
+    let search_payload = env.run_json_success(["search", "rollback release tag", "--snippets"]);
+    let hit = search_payload["results"]
+        .as_array()
+        .and_then(|results| results.iter().find(|result| result["docid"] == "runbook"))
+        .expect("expected runbook result");
+    assert!(hit["context"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("Revert the release tag"));
+    assert_eq!(
+        hit["heading_path"]
+            .as_array()
+            .and_then(|path| path.last())
+            .and_then(Value::as_str),
+        Some("Rollback")
+    );
+    let passages = hit["passages"]
+        .as_array()
+        .expect("--snippets should include passages");
+    assert_eq!(passages[0]["locator"], hit["locator"]);
+    let snippet_text = hit["snippet"]["text"].as_str().unwrap_or_default();
+    let first_match = &hit["snippet"]["matches"][0];
+    let matched = snippet_text
+        .chars()
+        .skip(first_match["start"].as_u64().unwrap_or_default() as usize)
+        .take(
+            (first_match["end"].as_u64().unwrap_or_default()
+                - first_match["start"].as_u64().unwrap_or_default()) as usize,
+        )
+        .collect::<String>();
+    assert!(
+        ["rollback", "release", "tag"].contains(&matched.to_lowercase().as_str()),
+        "snippet match ranges should cover query terms, got {matched:?}"
+    );
+
+    let locator = hit["locator"].as_str().expect("hit should have a locator");
+    let (path, range) = locator
+        .split_once("#L")
+        .expect("locator should have a line range");
+    assert_eq!(path, "guides/runbook.md");
+    let (start, end) = range
+        .split_once("-L")
+        .map(|(start, end)| {
+            (
+                start.parse::<usize>().expect("numeric start line"),
+                end.parse::<usize>().expect("numeric end line"),
+            )
+        })
+        .expect("locator range should be L<start>-L<end>");
+
+    let read_payload = env.run_json_success([
+        "read",
+        "runbook",
+        "--from",
+        &start.to_string(),
+        "--length",
+        &(end - start + 1).to_string(),
+    ]);
+    assert_eq!(
+        read_payload["content"],
+        "## Rollback\n\nRevert the release tag and redeploy the previous build."
+    );
+}
+
+#[test]
+fn search_query_filters_and_reports_syntax_errors_with_column() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    env.write_markdown_fixture(
+        "runbooks/restart.md",
+        "restart-runbook",
+        "Restart Runbook",
+        &["infra"],
+        "Restart the kubernetes cluster when paged.",
+    );
+    env.write_markdown_fixture(
+        "runbooks/restart-draft.md",
+        "restart-draft",
+        "Restart Draft",
+        &["infra", "draft"],
+        "Restart the kubernetes cluster, maybe.",
+    );
+    env.write_markdown_fixture(
+        "notes/restart.md",
+        "restart-notes",
+        "Restart Notes",
+        &[],
+        "Restart k8s nodes one by one.",
+    );
+    env.run_json_success(["reindex"]);
+
+    let search_payload =
+        env.run_json_success(["search", "tag:infra folder:runbooks -tag:draft restart"]);
+    let ids = search_payload["results"]
+        .as_array()
+        .expect("search should return results")
+        .iter()
+        .filter_map(|result| result["docid"].as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["restart-runbook"]);
+
+    let search_payload = env.run_json_success(["search", "kubernetes OR k8s -maybe"]);
+    let mut ids = search_payload["results"]
+        .as_array()
+        .expect("search should return results")
+        .iter()
+        .filter_map(|result| result["docid"].as_str())
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    assert_eq!(ids, vec!["restart-notes", "restart-runbook"]);
+
+    let mut failing_command = env.command();
+    let failure_assert = failing_command
+        .arg("--json")
+        .args(["search", "restart \"on-call"])
+        .assert()
+        .code(4)
+        .stderr(contains("\"code\": \"invalid_arguments\""));
+
+    let failure_output = failure_assert.get_output().clone();
+    let error_payload = parse_json(
+        &failure_output.stderr,
+        "stderr",
+        &failure_output.stdout,
+        "stdout",
+        "expected error JSON payload",
+    );
+    assert_eq!(error_payload["error"]["column"], 9);
+    assert!(error_payload["error"]["message"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("unterminated quote"));
+}
+
+#[test]
+fn search_tag_filter_applies_before_the_limit() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    env.write_markdown_fixture(
+        "notes/deploy-log.md",
+        "deploy-log",
+        "Deploy Deploy",
+        &[],
+        "Deploy, deploy, deploy.",
+    );
+    env.write_markdown_fixture(
+        "notes/deploy-checklist.md",
+        "deploy-checklist",
+        "Deploy Checklist",
+        &["ops"],
+        "Before the release, confirm the rollback plan, notify the team, and then deploy once.",
+    );
+    env.run_json_success(["reindex"]);
+
+    let unfiltered = env.run_json_success(["search", "deploy", "--limit", "1"]);
+    assert_eq!(unfiltered["results"][0]["docid"], "deploy-log");
+
+    let filtered = env.run_json_success(["search", "deploy", "--tags", "OPS", "--limit", "1"]);
+    let ids = filtered["results"]
+        .as_array()
+        .expect("search should return results")
+        .iter()
+        .filter_map(|result| result["docid"].as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["deploy-checklist"]);
+}
+
+#[test]
+fn config_abbreviations_extend_search_preprocessing_in_the_vault() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    let defaults = env.run_json_success(["config"]);
+    assert_eq!(defaults["abbreviations"], serde_json::json!({}));
+
+    let updated = env.run_json_success([
+        "config",
+        "set",
+        "abbreviations",
+        "PR=pull request, sre=site  reliability",
+    ]);
+    assert_eq!(
+        updated["value"],
+        serde_json::json!({"pr": "pull request", "sre": "site reliability"})
+    );
+    let removed = env.run_json_success(["config", "set", "abbreviations", "sre="]);
+    assert_eq!(removed["value"], serde_json::json!({"pr": "pull request"}));
+
+    let settings = fs::read_to_string(env.documents_dir.join(".tentacle-settings.json"))
+        .expect("vault settings should be written to the documents folder");
+    assert!(settings.contains("\"pr\": \"pull request\""));
+
+    env.command()
+        .arg("--json")
+        .args(["config", "set", "abbreviations", "pull request"])
+        .assert()
+        .code(4)
+        .stderr(contains("abbr=expansion"));
+
+    env.write_markdown_fixture(
+        "notes/review.md",
+        "pr-review",
+        "PR Review",
+        &[],
+        "Review every PR before merging.",
+    );
+    env.run_json_success(["reindex"]);
+
+    // Lexical matching keeps the original term; only the semantic leg sees the expansion.
+    let payload = env.run_json_success(["search", "PR"]);
+    assert_eq!(payload["results"][0]["docid"], "pr-review");
+}
+
+#[test]
+fn search_rerank_keeps_fused_order_when_the_model_is_unavailable() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    env.write_markdown_fixture(
+        "notes/deploy-log.md",
+        "deploy-log",
+        "Deploy Deploy",
+        &[],
+        "Deploy, deploy, deploy.",
+    );
+    env.write_markdown_fixture(
+        "notes/deploy-checklist.md",
+        "deploy-checklist",
+        "Deploy Checklist",
+        &[],
+        "Before the release, confirm the rollback plan and deploy once.",
+    );
+    env.run_json_success(["reindex"]);
+
+    let updated = env.run_json_success(["config", "set", "rerank", "on"]);
+    assert_eq!(updated["value"], true);
+    let config = env.run_json_success(["config"]);
+    assert_eq!(config["rerank"], true);
+
+    // The hub is offline in tests, so the reranker cannot load and search falls back.
+    let payload = env.run_json_success(["search", "deploy", "--rerank", "--limit", "1"]);
+    assert_eq!(payload["total_results"], 1);
+    assert_eq!(payload["results"][0]["docid"], "deploy-log");
+    assert!(payload["search_time_ms"].is_u64());
+    assert!(payload.get("rerank_time_ms").is_none());
+}
+
+#[test]
+fn search_explain_reports_bm25_ranks_and_contributions() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    env.write_markdown_fixture(
+        "notes/deploy.md",
+        "deploy-log",
+        "Deploy Log",
+        &[],
+        "Deploy, deploy, deploy.",
+    );
+    env.write_markdown_fixture(
+        "notes/release-notes.md",
+        "release-notes",
+        "Release Notes",
+        &[],
+        "Confirm the rollback plan, then deploy once.",
+    );
+    env.run_json_success(["reindex"]);
+
+    let plain = env.run_json_success(["search", "deploy"]);
+    assert!(plain.get("explanation").is_none());
+    assert!(plain["results"][0].get("explanation").is_none());
+
+    let payload = env.run_json_success(["search", "deploy", "--explain"]);
+    assert_eq!(payload["results"][0]["docid"], "deploy-log");
+    assert_eq!(payload["results"][1]["docid"], "release-notes");
+
+    let top = &payload["results"][0]["explanation"];
+    assert_eq!(top["bm25_rank"], 1);
+    assert!(top["bm25_contribution"].as_f64() > Some(0.0));
+    assert!(top["title_boost"].as_f64() > Some(0.0));
+    let second = &payload["results"][1]["explanation"];
+    assert_eq!(second["bm25_rank"], 2);
+    assert_eq!(second["title_boost"].as_f64(), Some(0.0));
+
+    let explanation = &payload["explanation"];
+    assert_eq!(explanation["rrf_k"].as_f64(), Some(60.0));
+    assert_eq!(explanation["bm25_candidates"], 2);
+    assert_eq!(
+        explanation["dropped_by_semantic_floor"],
+        serde_json::json!([])
+    );
+}
+
+#[test]
+fn eval_scores_labelled_queries_and_compares_with_a_saved_baseline() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    env.write_markdown_fixture(
+        "runbooks/deploy.md",
+        "deploy-runbook",
+        "Deploy Runbook",
+        &[],
+        "Deploy the release to production.",
+    );
+    env.write_markdown_fixture(
+        "runbooks/rollback.md",
+        "rollback-plan",
+        "Rollback Plan",
+        &[],
+        "Revert the release when the rollback alarm fires.",
+    );
+    env.run_json_success(["reindex"]);
+
+    let qrels_path = env.temp_dir.path().join("qrels.jsonl");
+    fs::write(
+        &qrels_path,
+        "{\"id\": \"deploy\", \"query\": \"deploy production\", \"relevant\": [\"deploy-runbook\"]}\n\
+         {\"query\": \"rollback\", \"relevant\": [\"rollback-plan\", \"missing-doc\"]}\n",
+    )
+    .expect("failed to write qrels");
+    let qrels = qrels_path.to_string_lossy().into_owned();
+    let baseline = env
+        .temp_dir
+        .path()
+        .join("eval/baseline.json")
+        .to_string_lossy()
+        .into_owned();
+
+    let report = env.run_json_success([
+        "eval",
+        qrels.as_str(),
+        "--k",
+        "5",
+        "--save-baseline",
+        baseline.as_str(),
+    ]);
+    assert_eq!(report["k"], 5);
+    assert_eq!(report["query_count"], 2);
+    assert_eq!(report["queries"][0]["id"], "deploy");
+    assert_eq!(report["queries"][0]["first_relevant_rank"], 1);
+    assert_eq!(report["queries"][0]["metrics"]["mrr"].as_f64(), Some(1.0));
+    assert_eq!(report["queries"][1]["id"], "rollback");
+    assert_eq!(
+        report["queries"][1]["metrics"]["recall"].as_f64(),
+        Some(0.5)
+    );
+    assert_eq!(report["metrics"]["recall"].as_f64(), Some(0.75));
+    assert!(report.get("comparison").is_none());
+    assert!(fs::metadata(&baseline).is_ok());
+
+    let compared = env.run_json_success([
+        "eval",
+        qrels.as_str(),
+        "--k",
+        "5",
+        "--baseline",
+        baseline.as_str(),
+    ]);
+    let comparison = &compared["comparison"];
+    assert_eq!(comparison["queries"].as_array().map(Vec::len), Some(2));
+    assert_eq!(comparison["delta"]["ndcg"].as_f64(), Some(0.0));
+    assert_eq!(comparison["regressed"], serde_json::json!([]));
+    assert_eq!(comparison["added"], serde_json::json!([]));
+
+    env.command()
+        .arg("--json")
+        .args(["eval", qrels.as_str(), "--baseline", baseline.as_str()])
+        .assert()
+        .code(4)
+        .stderr(contains("baseline was measured at k=5"));
+
+    // A label that is not valid query syntax is searched as plain words, not a failed run.
+    fs::write(
+        &qrels_path,
+        "{\"id\": \"dangling-or\", \"query\": \"rollback OR\", \"relevant\": [\"rollback-plan\"]}\n",
+    )
+    .expect("failed to rewrite qrels");
+    let retried = env.run_json_success(["eval", qrels.as_str(), "--k", "5"]);
+    assert_eq!(retried["query_count"], 1);
+    assert_eq!(retried["queries"][0]["first_relevant_rank"], 1);
+    assert!(retried["queries"][0].get("error").is_none());
+
+    fs::write(&qrels_path, "{\"query\": \"deploy\", \"relevant\": []}\n")
+        .expect("failed to rewrite qrels");
+    env.command()
+        .arg("--json")
+        .args(["eval", qrels.as_str()])
+        .assert()
+        .code(4)
+        .stderr(contains("qrels line 1"));
+}
+
+#[test]
+fn related_ranks_documents_by_shared_tags_and_links() {
+    let env = CliTestEnv::new();
+    env.bootstrap();
+
+    env.write_markdown_fixture(
+        "runbooks/deploy.md",
+        "deploy-runbook",
+        "Deploy Runbook",
+        &["ops", "infra"],
+        "Ship the release.",
+    );
+    env.write_markdown_fixture(
+        "runbooks/rollback.md",
+        "rollback-runbook",
+        "Rollback Runbook",
+        &["ops"],
+        "Revert the release.",
+    );
+    env.write_markdown_fixture(
+        "notes/retro.md",
+        "release-retro",
+        "Release Retro",
+        &[],
+        "We followed the [deploy runbook](../runbooks/deploy.md).",
+    );
+    env.write_markdown_fixture(
+        "notes/groceries.md",
+        "groceries",
+        "Groceries",
+        &["home"],
+        "Milk and eggs.",
+    );
+    env.run_json_success(["reindex"]);
+
+    let payload = env.run_json_success(["related", "deploy-runbook", "--limit", "5"]);
+    assert_eq!(payload["docid"], "deploy-runbook");
+    assert_eq!(payload["path"], "runbooks/deploy-runbook.md");
+    let results = payload["results"]
+        .as_array()
+        .expect("related should return results");
+    let ids = results
+        .iter()
+        .filter_map(|result| result["docid"].as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["release-retro", "rollback-runbook"]);
+    assert_eq!(results[0]["linked"], true);
+    assert_eq!(json_string_array(&results[1], "shared_tags"), vec!["ops"]);
+
+    let mut missing_command = env.command();
+    missing_command
+        .arg("--json")
+        .args(["related", "missing-doc"])
+        .assert()
+        .code(2)
+        .stderr(contains("\"code\": \"document_not_found\""));
+}
@@ -0,0 +1,653 @@
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::document_store;
+
+/// Archive entry describing everything else in the backup.
+pub const BACKUP_MANIFEST_FILE_NAME: &str = "manifest.json";
+const BACKUP_FORMAT_VERSION: u32 = 1;
+const VAULT_ENTRY_PREFIX: &str = "vault/";
+const CONFIG_ENTRY: &str = "config/config.db";
+const CACHE_DB_FILE_NAME: &str = ".document-data.db";
+const DATABASE_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackupOptions {
+    /// `config.db` to store alongside the vault, when settings should be backed up.
+    pub config_database: Option<PathBuf>,
+    /// Keep a snapshot of the search cache in the archive. Without it, restore rebuilds the
+    /// cache.
+    pub include_cache: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreOptions {
+    /// Restore into a folder that already has files, overwriting the ones in the archive.
+    pub force: bool,
+    /// Where to write the archived `config.db`. Settings are only restored when this is set.
+    pub config_database: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupFileEntry {
+    /// Archive entry name.
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupManifestPayload {
+    pub format_version: u32,
+    pub created_at: String,
+    /// Vault the backup was taken from.
+    pub documents_folder: String,
+    pub includes_config: bool,
+    pub includes_cache: bool,
+    pub file_count: usize,
+    pub total_bytes: u64,
+    /// Vault-relative folders, so empty folders survive a restore.
+    #[serde(default)]
+    pub folders: Vec<String>,
+    pub files: Vec<BackupFileEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupRestorePayload {
+    pub manifest: BackupManifestPayload,
+    pub files_restored: usize,
+    pub config_restored: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("{0}")]
+    Validation(String),
+    #[error("backup verification failed: {0}")]
+    Verification(String),
+}
+
+/// Writes every file under `documents_folder` (markdown, attachments, and trash) into a new
+/// compressed archive at `archive_path`, plus `config.db` when requested. The manifest lists the
+/// size and SHA-256 of every entry so the archive can be verified before it is restored.
+pub fn create_backup(
+    documents_folder: &Path,
+    archive_path: &Path,
+    options: &BackupOptions,
+) -> Result<BackupManifestPayload, BackupError> {
+    if !documents_folder.is_dir() {
+        return Err(BackupError::Validation(format!(
+            "documents folder \"{}\" was not found",
+            documents_folder.display()
+        )));
+    }
+    if archive_path.exists() {
+        return Err(BackupError::Validation(format!(
+            "backup file \"{}\" already exists",
+            archive_path.display()
+        )));
+    }
+    if let Some(parent) = archive_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Collected before the archive is created, so a backup written into the vault never
+    // includes itself; earlier backups in the vault are skipped too.
+    let (folders, files) = collect_vault_entries(documents_folder)?;
+    let mut archive = ZipWriter::new(fs::File::create(archive_path)?);
+    let entry_options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    let mut entries = Vec::with_capacity(files.len() + 1);
+    for relative_path in &files {
+        let name = format!("{VAULT_ENTRY_PREFIX}{relative_path}");
+        archive.start_file(name.as_str(), entry_options)?;
+        let mut source = fs::File::open(documents_folder.join(relative_path))?;
+        let (size, sha256) = copy_and_hash(&mut source, &mut archive)?;
+        entries.push(BackupFileEntry {
+            path: name,
+            size,
+            sha256,
+        });
+    }
+    let cache_database = documents_folder.join(CACHE_DB_FILE_NAME);
+    if options.include_cache && cache_database.is_file() {
+        let name = format!("{VAULT_ENTRY_PREFIX}{CACHE_DB_FILE_NAME}");
+        archive.start_file(name.as_str(), entry_options)?;
+        let (size, sha256) = archive_database_snapshot(
+            &cache_database,
+            &archive_path.with_extension("cache-snapshot.db"),
+            &mut archive,
+        )?;
+        entries.push(BackupFileEntry {
+            path: name,
+            size,
+            sha256,
+        });
+    }
+    if let Some(config_database) = &options.config_database {
+        archive.start_file(CONFIG_ENTRY, entry_options)?;
+        let mut source = fs::File::open(config_database)?;
+        let (size, sha256) = copy_and_hash(&mut source, &mut archive)?;
+        entries.push(BackupFileEntry {
+            path: CONFIG_ENTRY.to_owned(),
+            size,
+            sha256,
+        });
+    }
+
+    let manifest = BackupManifestPayload {
+        format_version: BACKUP_FORMAT_VERSION,
+        created_at: document_store::now_iso_string_utc(),
+        documents_folder: documents_folder.to_string_lossy().into_owned(),
+        includes_config: options.config_database.is_some(),
+        includes_cache: options.include_cache && cache_database.is_file(),
+        file_count: entries.len(),
+        total_bytes: entries.iter().map(|entry| entry.size).sum(),
+        folders,
+        files: entries,
+    };
+    archive.start_file(BACKUP_MANIFEST_FILE_NAME, entry_options)?;
+    serde_json::to_writer_pretty(&mut archive, &manifest)?;
+    archive.write_all(b"\n")?;
+    archive.finish()?;
+
+    Ok(manifest)
+}
+
+/// Checks that every manifest entry is present with the recorded size and checksum, and that
+/// the archive holds nothing the manifest does not list.
+pub fn verify_backup(archive_path: &Path) -> Result<BackupManifestPayload, BackupError> {
+    let mut archive = open_archive(archive_path)?;
+    let manifest = read_manifest(&mut archive)?;
+
+    let mut expected = manifest
+        .files
+        .iter()
+        .map(|entry| (entry.path.as_str(), entry))
+        .collect::<BTreeMap<_, _>>();
+    let mut problems = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let name = file.name().to_owned();
+        if name == BACKUP_MANIFEST_FILE_NAME || file.is_dir() {
+            continue;
+        }
+        let Some(entry) = expected.remove(name.as_str()) else {
+            problems.push(format!("\"{name}\" is not listed in the manifest"));
+            continue;
+        };
+        let (size, sha256) = copy_and_hash(&mut file, &mut io::sink())?;
+        if size != entry.size || sha256 != entry.sha256 {
+            problems.push(format!("\"{name}\" does not match its checksum"));
+        }
+    }
+    problems.extend(
+        expected
+            .keys()
+            .map(|name| format!("\"{name}\" is missing from the archive")),
+    );
+
+    if problems.is_empty() {
+        Ok(manifest)
+    } else {
+        Err(BackupError::Verification(problems.join("; ")))
+    }
+}
+
+/// Verifies the archive, then extracts the vault into `target_folder`. The target must be
+/// missing or empty (a search cache alone does not count) unless `force` is set, in which case
+/// archived files overwrite existing ones and other files are left alone. Rebuilding the search
+/// cache is left to the caller.
+pub fn restore_backup(
+    archive_path: &Path,
+    target_folder: &Path,
+    options: &RestoreOptions,
+) -> Result<BackupRestorePayload, BackupError> {
+    let manifest = verify_backup(archive_path)?;
+    if options.config_database.is_some() && !manifest.includes_config {
+        return Err(BackupError::Validation(
+            "backup does not contain config.db settings".to_owned(),
+        ));
+    }
+    if target_folder.exists() {
+        if !target_folder.is_dir() {
+            return Err(BackupError::Validation(format!(
+                "restore target \"{}\" is not a directory",
+                target_folder.display()
+            )));
+        }
+        if !options.force && has_vault_content(target_folder)? {
+            return Err(BackupError::Validation(format!(
+                "restore target \"{}\" is not empty; pass force to overwrite it",
+                target_folder.display()
+            )));
+        }
+    }
+
+    fs::create_dir_all(target_folder)?;
+    for folder in &manifest.folders {
+        fs::create_dir_all(target_folder.join(safe_relative_path(folder)?))?;
+    }
+
+    let mut archive = open_archive(archive_path)?;
+    let mut files_restored = 0usize;
+    let mut config_restored = false;
+    for entry in &manifest.files {
+        let target = if let Some(relative_path) = entry.path.strip_prefix(VAULT_ENTRY_PREFIX) {
+            target_folder.join(safe_relative_path(relative_path)?)
+        } else if entry.path == CONFIG_ENTRY {
+            match &options.config_database {
+                Some(config_database) => {
+                    config_restored = true;
+                    config_database.clone()
+                }
+                None => continue,
+            }
+        } else {
+            return Err(BackupError::Validation(format!(
+                "backup entry \"{}\" has an unknown location",
+                entry.path
+            )));
+        };
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = archive.by_name(&entry.path)?;
+        io::copy(&mut file, &mut fs::File::create(&target)?)?;
+        if entry.path != CONFIG_ENTRY {
+            files_restored += 1;
+        }
+    }
+
+    Ok(BackupRestorePayload {
+        manifest,
+        files_restored,
+        config_restored,
+    })
+}
+
+fn collect_vault_entries(
+    documents_folder: &Path,
+) -> Result<(Vec<String>, Vec<String>), BackupError> {
+    let mut folders = BTreeSet::new();
+    let mut files = BTreeSet::new();
+    let mut pending = vec![String::new()];
+    while let Some(relative_directory) = pending.pop() {
+        for entry in fs::read_dir(documents_folder.join(&relative_directory))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative_path = if relative_directory.is_empty() {
+                name.clone()
+            } else {
+                format!("{relative_directory}/{name}")
+            };
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                folders.insert(relative_path.clone());
+                pending.push(relative_path);
+            } else if file_type.is_file() {
+                // The cache (and its journal) may be open; `create_backup` archives a snapshot.
+                if relative_directory.is_empty() && name.starts_with(CACHE_DB_FILE_NAME) {
+                    continue;
+                }
+                if is_backup_archive(&entry.path()) {
+                    continue;
+                }
+                files.insert(relative_path);
+            }
+        }
+    }
+    Ok((folders.into_iter().collect(), files.into_iter().collect()))
+}
+
+/// Whether `path` is a `.zip` with a readable tentacle backup manifest. Other files are never
+/// opened, so attachments are not parsed as archives.
+fn is_backup_archive(path: &Path) -> bool {
+    let is_zip = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+    is_zip
+        && open_archive(path)
+            .and_then(|mut archive| read_manifest(&mut archive))
+            .is_ok()
+}
+
+/// Writes a consistent copy of the SQLite database at `database_path` to `writer`. The copy is
+/// taken with `VACUUM INTO` at `snapshot_path`, so a connection writing to the database while
+/// the backup runs cannot leave a torn file or a stale journal in the archive.
+fn archive_database_snapshot(
+    database_path: &Path,
+    snapshot_path: &Path,
+    writer: &mut impl Write,
+) -> Result<(u64, String), BackupError> {
+    if snapshot_path.exists() {
+        fs::remove_file(snapshot_path)?;
+    }
+    let result = vacuum_into(database_path, snapshot_path).and_then(|()| {
+        let mut snapshot = fs::File::open(snapshot_path)?;
+        Ok(copy_and_hash(&mut snapshot, writer)?)
+    });
+    let _ = fs::remove_file(snapshot_path);
+    result
+}
+
+fn vacuum_into(database_path: &Path, snapshot_path: &Path) -> Result<(), BackupError> {
+    let connection = Connection::open_with_flags(
+        database_path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    connection.busy_timeout(DATABASE_BUSY_TIMEOUT)?;
+    connection.execute("VACUUM INTO ?1", [snapshot_path.to_string_lossy().as_ref()])?;
+    Ok(())
+}
+
+fn has_vault_content(folder: &Path) -> Result<bool, BackupError> {
+    for entry in fs::read_dir(folder)? {
+        if !entry?
+            .file_name()
+            .to_string_lossy()
+            .starts_with(CACHE_DB_FILE_NAME)
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn open_archive(archive_path: &Path) -> Result<ZipArchive<fs::File>, BackupError> {
+    if !archive_path.is_file() {
+        return Err(BackupError::Validation(format!(
+            "backup file \"{}\" was not found",
+            archive_path.display()
+        )));
+    }
+    Ok(ZipArchive::new(fs::File::open(archive_path)?)?)
+}
+
+fn read_manifest(archive: &mut ZipArchive<fs::File>) -> Result<BackupManifestPayload, BackupError> {
+    let mut contents = String::new();
+    match archive.by_name(BACKUP_MANIFEST_FILE_NAME) {
+        Ok(mut file) => file.read_to_string(&mut contents)?,
+        Err(zip::result::ZipError::FileNotFound) => {
+            return Err(BackupError::Validation(
+                "file is not a tentacle backup: manifest.json is missing".to_owned(),
+            ))
+        }
+        Err(error) => return Err(error.into()),
+    };
+    let manifest: BackupManifestPayload = serde_json::from_str(&contents)?;
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(BackupError::Validation(format!(
+            "backup format version {} is newer than this version of tentacle supports",
+            manifest.format_version
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Rejects absolute paths and `..` segments so archive entries cannot escape the target.
+fn safe_relative_path(path: &str) -> Result<PathBuf, BackupError> {
+    let relative = Path::new(path);
+    if path.is_empty()
+        || relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(BackupError::Validation(format!(
+            "backup entry \"{path}\" has an unsafe path"
+        )));
+    }
+    Ok(relative.to_path_buf())
+}
+
+fn copy_and_hash(reader: &mut impl Read, writer: &mut impl Write) -> io::Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+        size += read as u64;
+    }
+    let sha256 = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    Ok((size, sha256))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_cache::{CachedDocumentPayload, DocumentCacheStore};
+    use crate::document_store::CreateDocumentInput;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_path(prefix: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}-{timestamp}"))
+    }
+
+    #[test]
+    fn backup_round_trips_vault_folders_and_config() {
+        let root = unique_temp_path("tentacle-backup-round-trip");
+        let vault = root.join("vault");
+        let document = document_store::create_document(
+            &vault,
+            &CreateDocumentInput {
+                title: Some("Plan".to_owned()),
+                body: Some("Ship it.".to_owned()),
+                folder_path: Some("work".to_owned()),
+                ..CreateDocumentInput::default()
+            },
+        )
+        .expect("create document");
+        document_store::write_document_attachment(&vault, &document.id, "chart.png", b"png")
+            .expect("write attachment");
+        fs::create_dir_all(vault.join("empty")).expect("create empty folder");
+        fs::write(vault.join(CACHE_DB_FILE_NAME), b"cache").expect("write cache");
+        let config_database = root.join("app/config.db");
+        fs::create_dir_all(config_database.parent().unwrap()).expect("create app dir");
+        fs::write(&config_database, b"settings").expect("write config");
+        let archive_path = vault.join("backup.zip");
+
+        let manifest = create_backup(
+            &vault,
+            &archive_path,
+            &BackupOptions {
+                config_database: Some(config_database.clone()),
+                include_cache: false,
+            },
+        )
+        .expect("create backup");
+        let paths = manifest
+            .files
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                format!("vault/.attachments/{}/chart.png", document.id).as_str(),
+                "vault/work/Plan.md",
+                "config/config.db",
+            ]
+        );
+        assert!(manifest.folders.contains(&"empty".to_owned()));
+        assert_eq!(verify_backup(&archive_path).expect("verify"), manifest);
+
+        let second = create_backup(
+            &vault,
+            &vault.join("backup-2.zip"),
+            &BackupOptions::default(),
+        )
+        .expect("create second backup");
+        assert!(
+            second
+                .files
+                .iter()
+                .all(|entry| !entry.path.ends_with(".zip")),
+            "earlier backups in the vault are not archived again"
+        );
+        fs::remove_file(vault.join("backup-2.zip")).expect("remove second backup");
+
+        let restored_vault = root.join("restored");
+        let restored_config = root.join("restored-app/config.db");
+        let restored = restore_backup(
+            &archive_path,
+            &restored_vault,
+            &RestoreOptions {
+                force: false,
+                config_database: Some(restored_config.clone()),
+            },
+        )
+        .expect("restore backup");
+        assert_eq!(restored.files_restored, 2);
+        assert!(restored.config_restored);
+        assert_eq!(
+            document_store::read_document(&restored_vault, &document.id)
+                .expect("read restored document")
+                .body,
+            "Ship it."
+        );
+        assert!(restored_vault.join("empty").is_dir());
+        assert!(!restored_vault.join(CACHE_DB_FILE_NAME).exists());
+        assert_eq!(
+            fs::read(&restored_config).expect("read config"),
+            b"settings"
+        );
+
+        let error = restore_backup(&archive_path, &restored_vault, &RestoreOptions::default())
+            .expect_err("non-empty target is rejected");
+        assert!(matches!(error, BackupError::Validation(_)));
+        restore_backup(
+            &archive_path,
+            &restored_vault,
+            &RestoreOptions {
+                force: true,
+                config_database: None,
+            },
+        )
+        .expect("forced restore");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn backup_archives_a_snapshot_of_the_open_search_cache() {
+        let root = unique_temp_path("tentacle-backup-cache");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        fs::write(vault.join("note.md"), b"note").expect("write note");
+        let mut store = DocumentCacheStore::new(&vault).expect("open cache");
+        store
+            .upsert_document(&CachedDocumentPayload {
+                id: "note".to_owned(),
+                user_id: "user-1".to_owned(),
+                title: "Note".to_owned(),
+                body: "note".to_owned(),
+                folder_path: String::new(),
+                banner_image_url: None,
+                deleted_at: None,
+                created_at: "2026-02-13T00:00:00Z".to_owned(),
+                updated_at: "2026-02-13T00:00:00Z".to_owned(),
+                tags: Vec::new(),
+            })
+            .expect("cache document");
+        fs::write(vault.join(format!("{CACHE_DB_FILE_NAME}-journal")), b"torn")
+            .expect("write journal");
+        let archive_path = root.join("backup.zip");
+
+        let manifest = create_backup(
+            &vault,
+            &archive_path,
+            &BackupOptions {
+                config_database: None,
+                include_cache: true,
+            },
+        )
+        .expect("create backup while the cache is open");
+        drop(store);
+        let paths = manifest
+            .files
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["vault/note.md", "vault/.document-data.db"]);
+        assert!(manifest.includes_cache);
+        assert!(!root.join("backup.cache-snapshot.db").exists());
+
+        let restored_vault = root.join("restored");
+        restore_backup(&archive_path, &restored_vault, &RestoreOptions::default())
+            .expect("restore backup");
+        let restored = DocumentCacheStore::new(&restored_vault).expect("open restored cache");
+        let documents = restored.list_documents().expect("list cached documents");
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].id, "note");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn verify_rejects_tampered_entries() {
+        let root = unique_temp_path("tentacle-backup-tampered");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        fs::write(vault.join("note.md"), b"original").expect("write note");
+        let archive_path = root.join("backup.zip");
+        let manifest =
+            create_backup(&vault, &archive_path, &BackupOptions::default()).expect("create backup");
+
+        let tampered_path = root.join("tampered.zip");
+        let mut source = ZipArchive::new(fs::File::open(&archive_path).unwrap()).unwrap();
+        let mut tampered = ZipWriter::new(fs::File::create(&tampered_path).unwrap());
+        tampered
+            .start_file("vault/note.md", SimpleFileOptions::default())
+            .unwrap();
+        tampered.write_all(b"changed").unwrap();
+        tampered
+            .start_file(BACKUP_MANIFEST_FILE_NAME, SimpleFileOptions::default())
+            .unwrap();
+        io::copy(
+            &mut source.by_name(BACKUP_MANIFEST_FILE_NAME).unwrap(),
+            &mut tampered,
+        )
+        .unwrap();
+        tampered.finish().unwrap();
+
+        assert_eq!(manifest.file_count, 1);
+        let error = verify_backup(&tampered_path).expect_err("tampered archive fails");
+        assert!(
+            matches!(error, BackupError::Verification(message) if message.contains("vault/note.md"))
+        );
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -5,6 +5,9 @@ use rusqlite::{params, Connection, OptionalExtension};
 use thiserror::Error;
 
 pub const KEY_DOCUMENTS_FOLDER: &str = "documents_folder";
+/// Directory the embedding model is loaded from instead of hf-hub.
+pub const KEY_MODEL_PATH: &str = "model_path";
+pub const CONFIG_DATABASE_FILE_NAME: &str = "config.db";
 
 const CREATE_CONFIG_TABLE_SQL: &str =
     "CREATE TABLE IF NOT EXISTS config (key TEXT PRIMARY KEY, value TEXT NOT NULL)";
@@ -17,7 +20,7 @@ impl ConfigStore {
     pub fn new(app_data_dir: &Path) -> Result<Self, ConfigError> {
         std::fs::create_dir_all(app_data_dir)?;
 
-        let database_path = app_data_dir.join("config.db");
+        let database_path = app_data_dir.join(CONFIG_DATABASE_FILE_NAME);
         let connection = Connection::open(database_path)?;
         connection.execute(CREATE_CONFIG_TABLE_SQL, [])?;
 
@@ -54,6 +57,15 @@ impl ConfigStore {
         Ok(())
     }
 
+    /// The `model_path` directory, with a leading `~` expanded, or `None` when it is unset.
+    pub fn model_path(&self) -> Result<Option<PathBuf>, ConfigError> {
+        Ok(self
+            .get(KEY_MODEL_PATH)?
+            .map(|value| value.trim().to_owned())
+            .filter(|value| !value.is_empty())
+            .map(|value| expand_home_path(&value)))
+    }
+
     pub fn get_all(&self) -> Result<HashMap<String, String>, ConfigError> {
         let mut statement = self.connection.prepare("SELECT key, value FROM config")?;
         let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
@@ -68,6 +80,15 @@ impl ConfigStore {
     }
 }
 
+fn expand_home_path(raw: &str) -> PathBuf {
+    let home = dirs::home_dir();
+    match (raw, raw.strip_prefix("~/"), home) {
+        ("~", _, Some(home)) => home,
+        (_, Some(stripped), Some(home)) => home.join(stripped),
+        _ => PathBuf::from(raw),
+    }
+}
+
 pub fn default_data_dir() -> Option<PathBuf> {
     dirs::data_dir().map(|path| path.join("com.tentacle.desktop"))
 }
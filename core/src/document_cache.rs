@@ -1,14 +1,25 @@
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Once;
 use std::time::Instant;
 
-use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Transaction};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::document_store::document_file_name;
+use crate::importers::linked_document_paths;
+use crate::languages::{SearchLanguage, DEFAULT_SEARCH_LANGUAGES};
+use crate::query::{parse_search_query, DateField, QueryTerm, SearchQuery, TextClause};
+use crate::text_processing::{
+    chunk_markdown_document, estimate_token_count, tiptap_json_to_markdown,
+};
+use crate::vault_settings::load_vault_settings;
+
 const CACHE_DB_FILE_NAME: &str = ".document-data.db";
+/// Vector size of caches that have not recorded an embedding model (the default model's).
 pub const EMBEDDING_VECTOR_DIMENSIONS: usize = 384;
 
 static SQLITE_VEC_EXTENSION_INIT: Once = Once::new();
@@ -54,16 +65,6 @@ CREATE INDEX IF NOT EXISTS idx_document_embeddings_meta_updated_at ON document_e
 CREATE INDEX IF NOT EXISTS idx_document_embeddings_meta_content_hash ON document_embeddings_meta(content_hash);
 CREATE INDEX IF NOT EXISTS idx_document_embeddings_meta_document_id ON document_embeddings_meta(document_id);
 
-CREATE VIRTUAL TABLE IF NOT EXISTS document_embeddings_vec USING vec0(
-  embedding float[384]
-);
-
-CREATE TRIGGER IF NOT EXISTS trg_document_embeddings_meta_delete_vec
-AFTER DELETE ON document_embeddings_meta
-BEGIN
-  DELETE FROM document_embeddings_vec WHERE rowid = OLD.id;
-END;
-
 CREATE VIRTUAL TABLE IF NOT EXISTS documents_fts USING fts5(
   title, body,
   content='documents', content_rowid='rowid'
@@ -90,6 +91,12 @@ CREATE TABLE IF NOT EXISTS document_chunk_embeddings_meta (
   document_id TEXT NOT NULL,
   chunk_index INTEGER NOT NULL,
   chunk_text TEXT NOT NULL,
+  page INTEGER,
+  heading_path TEXT NOT NULL DEFAULT '[]',
+  char_start INTEGER NOT NULL DEFAULT 0,
+  char_end INTEGER NOT NULL DEFAULT 0,
+  line_start INTEGER NOT NULL DEFAULT 0,
+  line_end INTEGER NOT NULL DEFAULT 0,
   content_hash TEXT NOT NULL,
   model TEXT NOT NULL,
   updated_at TEXT NOT NULL,
@@ -99,7 +106,26 @@ CREATE TABLE IF NOT EXISTS document_chunk_embeddings_meta (
 
 CREATE INDEX IF NOT EXISTS idx_chunk_meta_doc_id ON document_chunk_embeddings_meta(document_id);
 
-CREATE VIRTUAL TABLE IF NOT EXISTS document_chunk_embeddings_vec USING vec0(embedding float[384]);
+CREATE TABLE IF NOT EXISTS cache_settings (
+  key TEXT PRIMARY KEY,
+  value TEXT NOT NULL
+);
+"#;
+
+/// Vector tables are sized to the embedding model, so they are created after the recorded
+/// dimension is read. `{dimensions}` is replaced before execution.
+const CREATE_VECTOR_TABLES_SQL: &str = r#"
+CREATE VIRTUAL TABLE IF NOT EXISTS document_embeddings_vec USING vec0(
+  embedding float[{dimensions}]
+);
+
+CREATE TRIGGER IF NOT EXISTS trg_document_embeddings_meta_delete_vec
+AFTER DELETE ON document_embeddings_meta
+BEGIN
+  DELETE FROM document_embeddings_vec WHERE rowid = OLD.id;
+END;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS document_chunk_embeddings_vec USING vec0(embedding float[{dimensions}]);
 
 CREATE TRIGGER IF NOT EXISTS trg_chunk_meta_delete_vec
 AFTER DELETE ON document_chunk_embeddings_meta
@@ -108,6 +134,18 @@ BEGIN
 END;
 "#;
 
+const DROP_VECTOR_TABLES_SQL: &str = r#"
+DROP TRIGGER IF EXISTS trg_document_embeddings_meta_delete_vec;
+DROP TRIGGER IF EXISTS trg_chunk_meta_delete_vec;
+DROP TABLE IF EXISTS document_embeddings_vec;
+DROP TABLE IF EXISTS document_chunk_embeddings_vec;
+DELETE FROM document_embeddings_meta;
+DELETE FROM document_chunk_embeddings_meta;
+"#;
+
+const CACHE_SETTING_EMBEDDING_MODEL: &str = "embedding_model";
+const CACHE_SETTING_EMBEDDING_DIMENSIONS: &str = "embedding_dimensions";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedDocumentPayload {
     pub id: String,
@@ -150,12 +188,142 @@ pub struct CachedDocumentEmbeddingMetadataPayload {
 pub struct SemanticSearchHitPayload {
     pub document_id: String,
     pub score: f32,
+    /// Page of the best-matching chunk, for paginated documents such as imported PDFs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HybridSearchHitPayload {
     pub document_id: String,
     pub score: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// Document title `title_matches` index into; set together with them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Query matches in the document title.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub title_matches: Vec<MatchRangePayload>,
+    /// Body excerpt around the query matches, from the full-text index. Only lexical hits
+    /// have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<HighlightedTextPayload>,
+    /// Best-matching chunks of the document, highest score first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub passages: Vec<SearchPassagePayload>,
+    /// How the score was built; only set by the explaining searches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<HybridHitExplanationPayload>,
+}
+
+/// Score breakdown of one hybrid hit. Ranks are 1-based positions in each leg's candidates.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HybridHitExplanationPayload {
+    #[serde(default)]
+    pub bm25_rank: Option<usize>,
+    #[serde(default)]
+    pub semantic_rank: Option<usize>,
+    /// Raw cosine similarity of the closest chunk (or whole-document vector).
+    #[serde(default)]
+    pub semantic_score: Option<f32>,
+    /// `bm25_weight / (RRF_K + rank)`; zero when the BM25 leg missed the document.
+    pub bm25_contribution: f32,
+    /// `semantic_weight / (RRF_K + rank)`; zero when the semantic leg missed the document.
+    pub semantic_contribution: f32,
+    /// Added when a query term appears in the title.
+    pub title_boost: f32,
+    /// Score after fusion and title boost, before any rerank.
+    pub fused_score: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>,
+    #[serde(default)]
+    pub best_chunk_index: Option<usize>,
+}
+
+/// Search-wide inputs and drops behind a hybrid ranking.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchExplanationPayload {
+    /// Leg weights actually used, after any fallback to BM25 only.
+    pub semantic_weight: f32,
+    pub bm25_weight: f32,
+    pub rrf_k: f32,
+    /// Candidates requested from each leg.
+    pub candidate_k: usize,
+    pub bm25_candidates: usize,
+    pub semantic_candidates: usize,
+    /// Minimum cosine a semantic-only candidate needs to be kept.
+    pub semantic_floor: f32,
+    /// Semantic-only candidates dropped for scoring under the floor.
+    pub dropped_by_semantic_floor: Vec<String>,
+}
+
+/// Hybrid hits with their explanations set, plus the search-wide explanation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplainedHybridSearchPayload {
+    pub hits: Vec<HybridSearchHitPayload>,
+    pub explanation: SearchExplanationPayload,
+}
+
+/// Character range of a query match in a text, end exclusive. Offsets count Unicode scalar
+/// values (Rust `char`s), not bytes or UTF-16 code units, so JavaScript callers must convert
+/// them before slicing.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MatchRangePayload {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Text with the character ranges that matched a query.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HighlightedTextPayload {
+    pub text: String,
+    pub matches: Vec<MatchRangePayload>,
+}
+
+/// A matched chunk of a search hit. Offsets point into the document body the chunk was
+/// taken from: characters end exclusive, lines 1-based and inclusive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchPassagePayload {
+    pub chunk_index: usize,
+    /// Chunk text without the document title prefix.
+    pub text: String,
+    #[serde(default)]
+    pub heading_path: Vec<String>,
+    pub char_start: usize,
+    pub char_end: usize,
+    pub line_start: usize,
+    pub line_end: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    pub score: f32,
+}
+
+/// The closest pair of chunks between a document and one related to it; both scores are
+/// the pair's embedding similarity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedChunkPairPayload {
+    pub source: SearchPassagePayload,
+    pub related: SearchPassagePayload,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedDocumentHitPayload {
+    pub document_id: String,
+    /// `similarity` plus the shared-tag and link bonuses.
+    pub score: f32,
+    /// Best chunk-pair similarity, or whole-document similarity while no chunks are
+    /// stored. `None` when either document has no stored vectors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+    /// Lowercased tags both documents have.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_tags: Vec<String>,
+    /// Whether either document links to the other.
+    #[serde(default)]
+    pub linked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_pair: Option<RelatedChunkPairPayload>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -163,6 +331,18 @@ pub struct CachedDocumentChunkEmbeddingPayload {
     pub document_id: String,
     pub chunk_index: usize,
     pub chunk_text: String,
+    #[serde(default)]
+    pub page: Option<u32>,
+    #[serde(default)]
+    pub heading_path: Vec<String>,
+    #[serde(default)]
+    pub char_start: usize,
+    #[serde(default)]
+    pub char_end: usize,
+    #[serde(default)]
+    pub line_start: usize,
+    #[serde(default)]
+    pub line_end: usize,
     pub content_hash: String,
     pub model: String,
     pub vector: Vec<f32>,
@@ -178,6 +358,10 @@ pub struct CachedDocumentEmbeddingSyncBatchPayload {
 
 pub struct DocumentCacheStore {
     connection: Connection,
+    documents_folder: PathBuf,
+    embedding_dimensions: usize,
+    /// Stopwords and stemming of BM25 query terms, from the vault settings.
+    search_languages: Vec<SearchLanguage>,
 }
 
 fn initialize_sqlite_vec_extension() {
@@ -189,52 +373,17 @@ fn initialize_sqlite_vec_extension() {
     });
 }
 
-fn is_fts_stopword(token: &str) -> bool {
-    matches!(
-        token,
-        "a" | "an"
-            | "and"
-            | "are"
-            | "as"
-            | "at"
-            | "be"
-            | "but"
-            | "by"
-            | "for"
-            | "from"
-            | "how"
-            | "if"
-            | "in"
-            | "into"
-            | "is"
-            | "it"
-            | "no"
-            | "not"
-            | "of"
-            | "on"
-            | "or"
-            | "that"
-            | "the"
-            | "their"
-            | "then"
-            | "there"
-            | "these"
-            | "they"
-            | "this"
-            | "to"
-            | "was"
-            | "will"
-            | "with"
-    )
+fn is_fts_stopword(token: &str, languages: &[SearchLanguage]) -> bool {
+    languages.iter().any(|language| language.is_stopword(token))
 }
 
-fn tokenize_query_terms(raw: &str) -> Vec<String> {
+fn tokenize_query_terms(raw: &str, languages: &[SearchLanguage]) -> Vec<String> {
     let mut tokens: Vec<String> = Vec::new();
     let mut seen: HashSet<String> = HashSet::new();
 
     for token in raw.split(|c: char| !c.is_alphanumeric() && c != '-') {
         let normalized = token.trim().to_lowercase();
-        if normalized.len() < 2 || is_fts_stopword(&normalized) {
+        if normalized.len() < 2 || is_fts_stopword(&normalized, languages) {
             continue;
         }
 
@@ -246,50 +395,33 @@ fn tokenize_query_terms(raw: &str) -> Vec<String> {
     tokens
 }
 
-fn stemmed_token_prefix(token: &str) -> Option<String> {
-    let lower = token.to_lowercase();
-    let mut stem = lower.clone();
-
-    if stem.len() > 6 && stem.ends_with("ing") {
-        let new_len = stem.len().saturating_sub(3);
-        stem.truncate(new_len);
-    } else if stem.len() > 5 && stem.ends_with("edly") {
-        let new_len = stem.len().saturating_sub(4);
-        stem.truncate(new_len);
-    } else if stem.len() > 4 && stem.ends_with("ed") {
-        let new_len = stem.len().saturating_sub(2);
-        stem.truncate(new_len);
-    } else if stem.len() > 4 && stem.ends_with("ies") {
-        let new_len = stem.len().saturating_sub(3);
-        stem.truncate(new_len);
-        stem.push('y');
-    } else if stem.len() > 4 && stem.ends_with("es") {
-        let new_len = stem.len().saturating_sub(2);
-        stem.truncate(new_len);
-    } else if stem.len() > 4 && stem.ends_with("s") {
-        let new_len = stem.len().saturating_sub(1);
-        stem.truncate(new_len);
-    } else if stem.len() > 5 && stem.ends_with("al") {
-        let new_len = stem.len().saturating_sub(2);
-        stem.truncate(new_len);
-    } else if stem.len() > 5 && stem.ends_with("ly") {
-        let new_len = stem.len().saturating_sub(2);
-        stem.truncate(new_len);
-    }
-
-    if stem == lower || stem.len() < 3 {
-        return None;
+/// Stems of `token` in each of `languages`, without stems another one already covers as a
+/// prefix.
+fn stemmed_token_prefixes(token: &str, languages: &[SearchLanguage]) -> Vec<String> {
+    let mut stems = languages
+        .iter()
+        .filter_map(|language| language.stem(token))
+        .collect::<Vec<_>>();
+    stems.sort_by_key(|stem| stem.len());
+    let mut prefixes: Vec<String> = Vec::new();
+    for stem in stems {
+        if !prefixes
+            .iter()
+            .any(|prefix| stem.starts_with(prefix.as_str()))
+        {
+            prefixes.push(stem);
+        }
     }
 
-    Some(stem)
+    prefixes
 }
 
 /// Sanitize a raw user query for use in FTS5 MATCH.
 /// Splits on non-alphanumeric chars (except hyphen), removes trivial stopwords,
 /// and combines remaining tokens with AND for precision.
 /// Returns None if no valid tokens (fall back to semantic-only).
-fn sanitize_fts5_query(raw: &str) -> Option<String> {
-    let tokens = tokenize_query_terms(raw);
+fn sanitize_fts5_query(raw: &str, languages: &[SearchLanguage]) -> Option<String> {
+    let tokens = tokenize_query_terms(raw, languages);
 
     if tokens.is_empty() {
         None
@@ -298,10 +430,15 @@ fn sanitize_fts5_query(raw: &str) -> Option<String> {
             tokens
                 .iter()
                 .map(|token| {
-                    if let Some(stem) = stemmed_token_prefix(token) {
-                        format!("(\"{}\" OR \"{}\"*)", token, stem)
-                    } else {
+                    let stems = stemmed_token_prefixes(token, languages);
+                    if stems.is_empty() {
                         format!("\"{}\"", token)
+                    } else {
+                        let prefixes = stems
+                            .iter()
+                            .map(|stem| format!(" OR \"{stem}\"*"))
+                            .collect::<String>();
+                        format!("(\"{token}\"{prefixes})")
                     }
                 })
                 .collect::<Vec<_>>()
@@ -310,8 +447,203 @@ fn sanitize_fts5_query(raw: &str) -> Option<String> {
     }
 }
 
+/// FTS5 expression for one query term. Words expand like [`sanitize_fts5_query`]; phrases
+/// match their tokens in order.
+fn fts5_term_expression(term: &QueryTerm, languages: &[SearchLanguage]) -> Option<String> {
+    match term {
+        QueryTerm::Word(word) => {
+            sanitize_fts5_query(word, languages).map(|expression| format!("({expression})"))
+        }
+        QueryTerm::Phrase(phrase) => Some(format!("\"{}\"", phrase.replace('"', "\"\""))),
+    }
+}
+
+/// FTS5 expression matching any alternative of `clause`, restricted to the title column
+/// for `title:` clauses. `None` when every alternative is made of stopwords.
+fn fts5_clause_expression(clause: &TextClause, languages: &[SearchLanguage]) -> Option<String> {
+    let alternatives = clause
+        .alternatives
+        .iter()
+        .filter_map(|term| fts5_term_expression(term, languages))
+        .collect::<Vec<_>>();
+    if alternatives.is_empty() {
+        return None;
+    }
+
+    let expression = alternatives.join(" OR ");
+    if clause.title_only {
+        Some(format!("title : ({expression})"))
+    } else {
+        Some(format!("({expression})"))
+    }
+}
+
+/// FTS5 expression requiring every positive clause of `query`.
+fn fts5_query_expression(query: &SearchQuery, languages: &[SearchLanguage]) -> Option<String> {
+    let clauses = query
+        .positive_clauses()
+        .filter_map(|clause| fts5_clause_expression(clause, languages))
+        .collect::<Vec<_>>();
+    (!clauses.is_empty()).then(|| clauses.join(" AND "))
+}
+
+/// SQL predicate over `documents d` for the filters and negated clauses of `query`, with
+/// the values of its `?` placeholders in order.
+fn search_filter_sql(query: &SearchQuery, languages: &[SearchLanguage]) -> (String, Vec<Value>) {
+    let mut predicates = Vec::new();
+    let mut values = Vec::new();
+
+    for document_id in &query.excluded_document_ids {
+        predicates.push("d.id <> ?".to_owned());
+        values.push(Value::Text(document_id.clone()));
+    }
+    for (tags, operator) in [
+        (&query.tags, "EXISTS"),
+        (&query.excluded_tags, "NOT EXISTS"),
+    ] {
+        for tag in tags {
+            predicates.push(format!(
+                "{operator} (SELECT 1 FROM document_tags t WHERE t.document_id = d.id AND lower(t.tag) = ?)"
+            ));
+            values.push(Value::Text(tag.clone()));
+        }
+    }
+    // A folder filter matches the folder itself and everything below it.
+    let folder_predicate =
+        "(d.folder_path = ? OR substr(d.folder_path, 1, length(?) + 1) = ? || '/')";
+    if !query.folders.is_empty() {
+        predicates.push(format!(
+            "({})",
+            vec![folder_predicate; query.folders.len()].join(" OR ")
+        ));
+        for folder in &query.folders {
+            values.extend(std::iter::repeat_n(Value::Text(folder.clone()), 3));
+        }
+    }
+    if let Some(folder) = &query.within_folder {
+        predicates.push(folder_predicate.to_owned());
+        values.extend(std::iter::repeat_n(Value::Text(folder.clone()), 3));
+    }
+    for folder in &query.excluded_folders {
+        predicates.push(format!("NOT {folder_predicate}"));
+        values.extend(std::iter::repeat_n(Value::Text(folder.clone()), 3));
+    }
+    for filter in &query.dates {
+        let column = match filter.field {
+            DateField::Created => "d.created_at",
+            DateField::Updated => "d.updated_at",
+        };
+        predicates.push(format!(
+            "substr({column}, 1, 10) {} ?",
+            filter.comparison.sql_operator()
+        ));
+        values.push(Value::Text(filter.date.clone()));
+    }
+    for clause in query.clauses.iter().filter(|clause| clause.negated) {
+        if let Some(expression) = fts5_clause_expression(clause, languages) {
+            predicates.push(
+                "d.rowid NOT IN (SELECT rowid FROM documents_fts WHERE documents_fts MATCH ?)"
+                    .to_owned(),
+            );
+            values.push(Value::Text(expression));
+        }
+    }
+
+    if predicates.is_empty() {
+        ("1".to_owned(), values)
+    } else {
+        (predicates.join(" AND "), values)
+    }
+}
+
+/// Chunks of a document that are attached to each search hit.
+const SEARCH_PASSAGES_PER_DOCUMENT: usize = 3;
+/// Rank offset in reciprocal rank fusion; larger values flatten the rank differences.
+const RRF_K: f32 = 60.0;
+/// Added to the fused score of documents whose title contains a query term.
+const TITLE_BOOST: f32 = 0.015;
+/// Lowest cosine a semantic-only candidate can have, whatever the caller's `min_score`.
+const MIN_SEMANTIC_FLOOR: f32 = 0.15;
+/// Weight of query-term coverage (0..1) added to a passage's embedding similarity.
+const PASSAGE_TERM_COVERAGE_WEIGHT: f32 = 0.25;
+/// Share of the best passage's score another passage of the same hit needs to be kept.
+const PASSAGE_MIN_RELATIVE_SCORE: f32 = 0.5;
+
+/// Share of `terms` found in `text`, matching stemmed prefixes like the BM25 query does.
+fn query_term_coverage(text: &str, terms: &[String], languages: &[SearchLanguage]) -> f32 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+
+    let text = text.to_lowercase();
+    let matched = terms
+        .iter()
+        .filter(|term| {
+            text.contains(term.as_str())
+                || stemmed_token_prefixes(term, languages)
+                    .iter()
+                    .any(|stem| text.contains(stem.as_str()))
+        })
+        .count();
+    matched as f32 / terms.len() as f32
+}
+
+/// Chunk text without the document title every chunk is embedded with.
+fn strip_chunk_title(chunk_text: &str, title: &str) -> String {
+    let title = title.trim();
+    let text = if title.is_empty() {
+        chunk_text
+    } else {
+        chunk_text
+            .strip_prefix(title)
+            .map_or(chunk_text, |rest| rest.trim_start_matches('\n'))
+    };
+    text.trim().to_owned()
+}
+
+/// Weight of the share of the source document's tags a related document also has.
+const RELATED_SHARED_TAG_WEIGHT: f32 = 0.1;
+/// Added when a related document links to, or is linked from, the source document.
+const RELATED_LINK_WEIGHT: f32 = 0.1;
+
+/// Markers FTS5 `highlight()` and `snippet()` wrap matches in. Private-use characters, so
+/// they cannot collide with document text; they are stripped into [`MatchRangePayload`]s.
+const FTS_MATCH_OPEN: char = '\u{E000}';
+const FTS_MATCH_CLOSE: char = '\u{E001}';
+/// Tokens in a body snippet around its best-matching terms.
+const FTS_SNIPPET_TOKENS: i64 = 24;
+
 struct Bm25Hit {
     document_id: String,
+    title: HighlightedTextPayload,
+    snippet: HighlightedTextPayload,
+}
+
+/// Strips the FTS match markers from `marked`, recording where each match was.
+fn parse_fts_highlight(marked: &str) -> HighlightedTextPayload {
+    let mut text = String::with_capacity(marked.len());
+    let mut matches = Vec::new();
+    let mut position = 0;
+    let mut match_start = None;
+    for character in marked.chars() {
+        match character {
+            FTS_MATCH_OPEN => match_start = Some(position),
+            FTS_MATCH_CLOSE => {
+                if let Some(start) = match_start.take().filter(|start| *start < position) {
+                    matches.push(MatchRangePayload {
+                        start,
+                        end: position,
+                    });
+                }
+            }
+            _ => {
+                text.push(character);
+                position += 1;
+            }
+        }
+    }
+
+    HighlightedTextPayload { text, matches }
 }
 
 impl DocumentCacheStore {
@@ -322,13 +654,93 @@ impl DocumentCacheStore {
         let database_path = documents_folder.join(CACHE_DB_FILE_NAME);
         let connection = Connection::open(database_path)?;
         connection.execute_batch(CREATE_SCHEMA_SQL)?;
-
-        let store = Self { connection };
+        let embedding_dimensions =
+            read_cache_setting(&connection, CACHE_SETTING_EMBEDDING_DIMENSIONS)?
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|dimensions| *dimensions > 0)
+                .unwrap_or(EMBEDDING_VECTOR_DIMENSIONS);
+        connection.execute_batch(&vector_tables_sql(embedding_dimensions))?;
+        let search_languages = load_vault_settings(documents_folder)
+            .map(|settings| settings.search_languages())
+            .unwrap_or_else(|_| DEFAULT_SEARCH_LANGUAGES.to_vec());
+
+        let store = Self {
+            connection,
+            documents_folder: documents_folder.to_path_buf(),
+            embedding_dimensions,
+            search_languages,
+        };
         store.ensure_documents_folder_path_column()?;
+        store.ensure_chunk_location_columns()?;
         store.rebuild_fts_index_if_empty()?;
         Ok(store)
     }
 
+    /// Documents folder the cache belongs to.
+    pub fn documents_folder(&self) -> &Path {
+        &self.documents_folder
+    }
+
+    /// Length every stored and query vector must have.
+    pub fn embedding_dimensions(&self) -> usize {
+        self.embedding_dimensions
+    }
+
+    /// Model the stored embeddings were built with, or `None` for caches that predate the
+    /// record (built with the default model).
+    pub fn embedding_model(&self) -> Result<Option<String>, DocumentCacheError> {
+        read_cache_setting(&self.connection, CACHE_SETTING_EMBEDDING_MODEL)
+    }
+
+    /// Deletes every stored embedding and recreates the vector tables with `dimensions`, for
+    /// a switch to another embedding model.
+    pub fn reset_embeddings(
+        &mut self,
+        model: &str,
+        dimensions: usize,
+    ) -> Result<(), DocumentCacheError> {
+        if dimensions == 0 {
+            return Err(DocumentCacheError::Validation(
+                "embedding dimensions must be greater than zero".to_owned(),
+            ));
+        }
+
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch(DROP_VECTOR_TABLES_SQL)?;
+        transaction.execute_batch(&vector_tables_sql(dimensions))?;
+        for (key, value) in [
+            (CACHE_SETTING_EMBEDDING_MODEL, model.to_owned()),
+            (CACHE_SETTING_EMBEDDING_DIMENSIONS, dimensions.to_string()),
+        ] {
+            transaction.execute(
+                "INSERT INTO cache_settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                params![key, value],
+            )?;
+        }
+        transaction.commit()?;
+
+        self.embedding_dimensions = dimensions;
+        Ok(())
+    }
+
+    /// Documents with a whole-document or chunk embedding built by a model other than `model`.
+    pub fn count_documents_with_stale_embeddings(
+        &self,
+        model: &str,
+    ) -> Result<usize, DocumentCacheError> {
+        let count: i64 = self.connection.query_row(
+            "SELECT COUNT(DISTINCT document_id) FROM (
+               SELECT document_id, model FROM document_embeddings_meta
+               UNION ALL
+               SELECT document_id, model FROM document_chunk_embeddings_meta
+             ) WHERE model != ?1",
+            params![model],
+            |row| row.get(0),
+        )?;
+        Ok(usize::try_from(count).unwrap_or(0))
+    }
+
     fn ensure_documents_folder_path_column(&self) -> Result<(), DocumentCacheError> {
         let has_folder_path_column = self
             .connection
@@ -350,6 +762,41 @@ impl DocumentCacheStore {
         Ok(())
     }
 
+    /// Adds the chunk page and passage location columns to caches created before them.
+    fn ensure_chunk_location_columns(&self) -> Result<(), DocumentCacheError> {
+        const COLUMNS: [(&str, &str); 6] = [
+            ("page", "INTEGER"),
+            ("heading_path", "TEXT NOT NULL DEFAULT '[]'"),
+            ("char_start", "INTEGER NOT NULL DEFAULT 0"),
+            ("char_end", "INTEGER NOT NULL DEFAULT 0"),
+            ("line_start", "INTEGER NOT NULL DEFAULT 0"),
+            ("line_end", "INTEGER NOT NULL DEFAULT 0"),
+        ];
+
+        for (name, definition) in COLUMNS {
+            let has_column = self
+                .connection
+                .query_row(
+                    "SELECT 1 FROM pragma_table_info('document_chunk_embeddings_meta') WHERE name = ?1 LIMIT 1",
+                    params![name],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()?
+                .is_some();
+
+            if !has_column {
+                self.connection.execute(
+                    &format!(
+                        "ALTER TABLE document_chunk_embeddings_meta ADD COLUMN {name} {definition}"
+                    ),
+                    [],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Ensure the FTS5 index is consistent with the documents table.
     ///
     /// On first run after migration (the FTS table was just created from schema), the
@@ -554,7 +1001,7 @@ impl DocumentCacheStore {
         &mut self,
         embedding: &CachedDocumentEmbeddingPayload,
     ) -> Result<(), DocumentCacheError> {
-        validate_embedding_vector(&embedding.vector)?;
+        validate_embedding_vector(&embedding.vector, self.embedding_dimensions)?;
 
         let transaction = self.connection.transaction()?;
         Self::upsert_document_embedding_in_transaction(&transaction, embedding)?;
@@ -575,7 +1022,7 @@ impl DocumentCacheStore {
         embeddings: &[CachedDocumentEmbeddingPayload],
     ) -> Result<(), DocumentCacheError> {
         for embedding in embeddings {
-            validate_embedding_vector(&embedding.vector)?;
+            validate_embedding_vector(&embedding.vector, self.embedding_dimensions)?;
         }
 
         let transaction = self.connection.transaction()?;
@@ -595,7 +1042,7 @@ impl DocumentCacheStore {
         chunks: &[CachedDocumentChunkEmbeddingPayload],
     ) -> Result<(), DocumentCacheError> {
         for chunk in chunks {
-            validate_embedding_vector(&chunk.vector)?;
+            validate_embedding_vector(&chunk.vector, self.embedding_dimensions)?;
         }
 
         let transaction = self.connection.transaction()?;
@@ -608,6 +1055,7 @@ impl DocumentCacheStore {
         &mut self,
         payloads: &[CachedDocumentEmbeddingSyncBatchPayload],
     ) -> Result<(), DocumentCacheError> {
+        let dimensions = self.embedding_dimensions;
         let transaction = self.connection.transaction()?;
 
         for payload in payloads {
@@ -619,7 +1067,7 @@ impl DocumentCacheStore {
                     )));
                 }
 
-                validate_embedding_vector(&document_embedding.vector)?;
+                validate_embedding_vector(&document_embedding.vector, dimensions)?;
                 Self::upsert_document_embedding_in_transaction(&transaction, document_embedding)?;
             }
 
@@ -632,7 +1080,7 @@ impl DocumentCacheStore {
                         )));
                     }
 
-                    validate_embedding_vector(&chunk.vector)?;
+                    validate_embedding_vector(&chunk.vector, dimensions)?;
                 }
 
                 Self::replace_document_chunk_embeddings_in_transaction(
@@ -708,7 +1156,7 @@ impl DocumentCacheStore {
             return Ok(Vec::new());
         }
 
-        validate_embedding_vector(&query_vector)?;
+        validate_embedding_vector(&query_vector, self.embedding_dimensions)?;
 
         let k = limit.saturating_mul(4).max(1);
         let k = i64::try_from(k).map_err(|_| {
@@ -741,7 +1189,11 @@ impl DocumentCacheStore {
             }
 
             let score = 1.0 - ((distance * distance) / 2.0);
-            hits.push(SemanticSearchHitPayload { document_id, score });
+            hits.push(SemanticSearchHitPayload {
+                document_id,
+                score,
+                page: None,
+            });
         }
 
         hits.sort_by(|left, right| {
@@ -756,15 +1208,16 @@ impl DocumentCacheStore {
         Ok(hits)
     }
 
-    /// BM25 search via FTS5. Returns up to `limit` hits ordered by FTS5 rank (best first).
+    /// BM25 search via FTS5 over the positive clauses of `query`, restricted by its filters.
+    /// Returns up to `limit` hits ordered by FTS5 rank (best first).
     fn bm25_search_documents(
         &self,
-        query_text: &str,
+        query: &SearchQuery,
         limit: usize,
-        exclude_document_id: Option<&str>,
     ) -> Result<Vec<Bm25Hit>, DocumentCacheError> {
         let started = Instant::now();
-        let fts_query = match sanitize_fts5_query(query_text) {
+        let query_text = query.lexical_text();
+        let fts_query = match fts5_query_expression(query, &self.search_languages) {
             Some(q) => q,
             None => {
                 log::info!(
@@ -776,25 +1229,46 @@ impl DocumentCacheStore {
         };
 
         let k = i64::try_from(limit).unwrap_or(i64::MAX);
+        let (filter_sql, filter_values) = search_filter_sql(query, &self.search_languages);
 
-        let mut statement = self.connection.prepare(
-            "SELECT d.id
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT d.id,
+                    highlight(documents_fts, 0, ?, ?),
+                    snippet(documents_fts, 1, ?, ?, '…', ?)
              FROM documents_fts
              JOIN documents d ON d.rowid = documents_fts.rowid
-             WHERE documents_fts MATCH ?1
-               AND (?2 IS NULL OR d.id != ?2)
+             WHERE documents_fts MATCH ?
+               AND {filter_sql}
              ORDER BY rank
-             LIMIT ?3",
-        )?;
-
-        let rows = statement.query_map(params![&fts_query, exclude_document_id, k], |row| {
-            row.get::<_, String>(0)
+             LIMIT ?"
+        ))?;
+
+        let mut values = vec![
+            Value::Text(FTS_MATCH_OPEN.to_string()),
+            Value::Text(FTS_MATCH_CLOSE.to_string()),
+            Value::Text(FTS_MATCH_OPEN.to_string()),
+            Value::Text(FTS_MATCH_CLOSE.to_string()),
+            Value::Integer(FTS_SNIPPET_TOKENS),
+            Value::Text(fts_query.clone()),
+        ];
+        values.extend(filter_values);
+        values.push(Value::Integer(k));
+        let rows = statement.query_map(params_from_iter(values), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
         })?;
 
         let mut hits = Vec::new();
         for row in rows {
-            let document_id = row?;
-            hits.push(Bm25Hit { document_id });
+            let (document_id, title, snippet) = row?;
+            hits.push(Bm25Hit {
+                document_id,
+                title: parse_fts_highlight(&title),
+                snippet: parse_fts_highlight(&snippet),
+            });
         }
 
         let top = hits
@@ -807,7 +1281,7 @@ impl DocumentCacheStore {
             query_text,
             fts_query,
             limit,
-            exclude_document_id,
+            query.excluded_document_ids,
             hits.len(),
             top,
             started.elapsed().as_millis()
@@ -823,7 +1297,6 @@ impl DocumentCacheStore {
         query_vector: &[f32],
         limit: usize,
         min_score: f32,
-        exclude_document_id: Option<&str>,
     ) -> Result<Vec<SemanticSearchHitPayload>, DocumentCacheError> {
         let started = Instant::now();
         // Check if chunk table has any rows.
@@ -835,12 +1308,8 @@ impl DocumentCacheStore {
 
         if chunk_count == 0 {
             // Fall back to whole-doc KNN.
-            let hits = self.semantic_search_documents(
-                query_vector.to_vec(),
-                limit,
-                min_score,
-                exclude_document_id.map(str::to_owned),
-            )?;
+            let hits =
+                self.semantic_search_documents(query_vector.to_vec(), limit, min_score, None)?;
             let top = hits
                 .iter()
                 .take(5)
@@ -863,27 +1332,35 @@ impl DocumentCacheStore {
         let bounded_min_score = min_score.clamp(-1.0, 1.0);
 
         let mut statement = self.connection.prepare(
-            "SELECT m.document_id, MAX(1.0 - (v.distance * v.distance / 2.0)) as score
+            "SELECT m.document_id, MAX(1.0 - (v.distance * v.distance / 2.0)) as score, m.page
              FROM document_chunk_embeddings_vec v
              JOIN document_chunk_embeddings_meta m ON m.id = v.rowid
              WHERE v.embedding MATCH vec_f32(?1) AND k = ?2
-               AND (?3 IS NULL OR m.document_id != ?3)
              GROUP BY m.document_id
              ORDER BY score DESC
-             LIMIT ?4",
+             LIMIT ?3",
         )?;
 
         let limit_i64 = i64::try_from(limit).unwrap_or(i64::MAX);
-        let rows = statement.query_map(
-            params![query_vector_bytes, k, exclude_document_id, limit_i64],
-            |row| Ok((row.get::<_, String>(0)?, row.get::<_, f32>(1)?)),
-        )?;
+        let rows = statement.query_map(params![query_vector_bytes, k, limit_i64], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, f32>(1)?,
+                row.get::<_, Option<u32>>(2)?,
+            ))
+        })?;
 
+        // SQLite returns the bare `m.page` column from the row that produced MAX(score),
+        // so the page belongs to the best-matching chunk.
         let mut hits = Vec::new();
         for row in rows {
-            let (document_id, score) = row?;
+            let (document_id, score, page) = row?;
             if score >= bounded_min_score {
-                hits.push(SemanticSearchHitPayload { document_id, score });
+                hits.push(SemanticSearchHitPayload {
+                    document_id,
+                    score,
+                    page,
+                });
             }
         }
 
@@ -893,12 +1370,11 @@ impl DocumentCacheStore {
             .map(|hit| format!("{}:{:.3}", hit.document_id, hit.score))
             .collect::<Vec<_>>();
         log::info!(
-            "[search-debug][semantic] chunk_count={} k={} limit={} min_score={} exclude={:?} hits={} top={:?} elapsed_ms={}",
+            "[search-debug][semantic] chunk_count={} k={} limit={} min_score={} hits={} top={:?} elapsed_ms={}",
             chunk_count,
             k,
             limit,
             bounded_min_score,
-            exclude_document_id,
             hits.len(),
             top,
             started.elapsed().as_millis()
@@ -907,6 +1383,83 @@ impl DocumentCacheStore {
         Ok(hits)
     }
 
+    /// Exact KNN over the embeddings of documents passing the filters of `query`, so
+    /// filters apply before ranking and `limit` hits come back whenever that many
+    /// documents match. Chunk scores are max-pooled per document; whole-document
+    /// embeddings are used while no chunks are stored.
+    fn filtered_knn_search(
+        &self,
+        query_vector: &[f32],
+        query: &SearchQuery,
+        limit: usize,
+        min_score: f32,
+    ) -> Result<Vec<SemanticSearchHitPayload>, DocumentCacheError> {
+        let started = Instant::now();
+        let chunk_count: i64 = self.connection.query_row(
+            "SELECT COUNT(*) FROM document_chunk_embeddings_meta LIMIT 1",
+            [],
+            |row| row.get(0),
+        )?;
+        let (meta_table, vec_table, page_column) = if chunk_count == 0 {
+            (
+                "document_embeddings_meta",
+                "document_embeddings_vec",
+                "NULL",
+            )
+        } else {
+            (
+                "document_chunk_embeddings_meta",
+                "document_chunk_embeddings_vec",
+                "m.page",
+            )
+        };
+
+        let (filter_sql, filter_values) = search_filter_sql(query, &self.search_languages);
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT document_id, MAX(1.0 - (distance * distance / 2.0)) AS score, page
+             FROM (
+               SELECT m.document_id,
+                      {page_column} AS page,
+                      vec_distance_l2(v.embedding, vec_f32(?)) AS distance
+               FROM documents d
+               JOIN {meta_table} m ON m.document_id = d.id
+               JOIN {vec_table} v ON v.rowid = m.id
+               WHERE {filter_sql}
+             )
+             GROUP BY document_id
+             HAVING score >= ?
+             ORDER BY score DESC, document_id
+             LIMIT ?"
+        ))?;
+
+        let mut values = vec![Value::Blob(f32_vector_to_le_bytes(query_vector))];
+        values.extend(filter_values);
+        values.push(Value::Real(f64::from(min_score.clamp(-1.0, 1.0))));
+        values.push(Value::Integer(i64::try_from(limit).unwrap_or(i64::MAX)));
+        let rows = statement.query_map(params_from_iter(values), |row| {
+            Ok(SemanticSearchHitPayload {
+                document_id: row.get(0)?,
+                score: row.get(1)?,
+                page: row.get(2)?,
+            })
+        })?;
+
+        let mut hits = Vec::new();
+        for row in rows {
+            hits.push(row?);
+        }
+
+        log::info!(
+            "[search-debug][semantic] filtered=true chunk_count={} limit={} min_score={} hits={} elapsed_ms={}",
+            chunk_count,
+            limit,
+            min_score,
+            hits.len(),
+            started.elapsed().as_millis()
+        );
+        Ok(hits)
+    }
+
     /// Hybrid search: BM25 + chunk KNN, combined with Reciprocal Rank Fusion.
     pub fn hybrid_search_documents(
         &self,
@@ -918,34 +1471,125 @@ impl DocumentCacheStore {
         semantic_weight: f32,
         bm25_weight: f32,
     ) -> Result<Vec<HybridSearchHitPayload>, DocumentCacheError> {
+        // Callers without a way to report syntax errors search malformed queries as plain words.
+        let mut query =
+            parse_search_query(query_text).unwrap_or_else(|_| SearchQuery::plain_text(query_text));
+        query.excluded_document_ids.extend(exclude_document_id);
+        self.hybrid_search_documents_for_query(
+            query_vector,
+            &query,
+            limit,
+            min_score,
+            semantic_weight,
+            bm25_weight,
+        )
+    }
+
+    /// Hybrid search for a parsed query. Positive clauses feed the BM25 leg, `query_vector`
+    /// (the embedded [`SearchQuery::semantic_text`]) the KNN leg, and filters restrict both.
+    /// A query with filters but no positive text lists the matching documents, most
+    /// recently updated first.
+    pub fn hybrid_search_documents_for_query(
+        &self,
+        query_vector: Vec<f32>,
+        query: &SearchQuery,
+        limit: usize,
+        min_score: f32,
+        semantic_weight: f32,
+        bm25_weight: f32,
+    ) -> Result<Vec<HybridSearchHitPayload>, DocumentCacheError> {
+        let mut hits = self
+            .explain_hybrid_search_for_query(
+                query_vector,
+                query,
+                limit,
+                min_score,
+                semantic_weight,
+                bm25_weight,
+            )?
+            .hits;
+        for hit in &mut hits {
+            hit.explanation = None;
+        }
+        Ok(hits)
+    }
+
+    /// [`Self::hybrid_search_documents_for_query`], keeping each hit's score breakdown and
+    /// the search-wide explanation (candidate counts, semantic floor drops).
+    pub fn explain_hybrid_search_for_query(
+        &self,
+        query_vector: Vec<f32>,
+        query: &SearchQuery,
+        limit: usize,
+        min_score: f32,
+        semantic_weight: f32,
+        bm25_weight: f32,
+    ) -> Result<ExplainedHybridSearchPayload, DocumentCacheError> {
         let started = Instant::now();
+        let candidate_k = limit.saturating_mul(2).max(1);
+        let bounded_min_score = min_score.clamp(-1.0, 1.0);
+        let semantic_floor = bounded_min_score.max(MIN_SEMANTIC_FLOOR);
+        let mut explanation = SearchExplanationPayload {
+            semantic_weight,
+            bm25_weight,
+            rrf_k: RRF_K,
+            candidate_k,
+            bm25_candidates: 0,
+            semantic_candidates: 0,
+            semantic_floor,
+            dropped_by_semantic_floor: Vec::new(),
+        };
         if limit == 0 {
-            return Ok(Vec::new());
+            return Ok(ExplainedHybridSearchPayload {
+                hits: Vec::new(),
+                explanation,
+            });
         }
 
-        validate_embedding_vector(&query_vector)?;
-
-        let candidate_k = limit.saturating_mul(2).max(1);
+        validate_embedding_vector(&query_vector, self.embedding_dimensions)?;
+
+        let query_text = query.lexical_text();
+        if query.positive_clauses().next().is_none() {
+            let hits = if query.has_filters() {
+                self.filtered_document_ids(query, limit)?
+                    .into_iter()
+                    .map(|document_id| HybridSearchHitPayload {
+                        document_id,
+                        score: 0.0,
+                        page: None,
+                        title: None,
+                        title_matches: Vec::new(),
+                        snippet: None,
+                        passages: Vec::new(),
+                        explanation: Some(HybridHitExplanationPayload::default()),
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            return Ok(ExplainedHybridSearchPayload { hits, explanation });
+        }
 
         // BM25 leg
         let bm25_hits = if bm25_weight > 0.0 {
-            self.bm25_search_documents(query_text, candidate_k, exclude_document_id.as_deref())?
+            self.bm25_search_documents(query, candidate_k)?
         } else {
             Vec::new()
         };
 
         // Semantic/chunk KNN leg
-        let semantic_hits = if semantic_weight > 0.0 {
-            self.chunk_knn_search(
-                &query_vector,
-                candidate_k,
-                min_score,
-                exclude_document_id.as_deref(),
-            )?
-        } else {
+        // Filtered searches rank only the matching documents' vectors.
+        let semantic_hits = if semantic_weight <= 0.0 {
             Vec::new()
+        } else if query.has_filters() || !query.excluded_document_ids.is_empty() {
+            self.filtered_knn_search(&query_vector, query, candidate_k, min_score)?
+        } else {
+            self.chunk_knn_search(&query_vector, candidate_k, min_score)?
         };
 
+        explanation.bm25_candidates = bm25_hits.len();
+        explanation.semantic_candidates = semantic_hits.len();
+
         // Collect all candidate document IDs
         let mut all_doc_ids: Vec<String> = Vec::new();
         let mut seen_ids: HashSet<String> = HashSet::new();
@@ -973,7 +1617,10 @@ impl DocumentCacheStore {
                 semantic_hits.len(),
                 started.elapsed().as_millis()
             );
-            return Ok(Vec::new());
+            return Ok(ExplainedHybridSearchPayload {
+                hits: Vec::new(),
+                explanation,
+            });
         }
 
         // Fetch titles for all candidate docs in one query (for title boosting)
@@ -992,17 +1639,20 @@ impl DocumentCacheStore {
             .collect();
 
         // Query tokens for title boost (token-exact; excludes stopwords).
-        let query_tokens: HashSet<String> = tokenize_query_terms(query_text).into_iter().collect();
+        let query_tokens: HashSet<String> =
+            tokenize_query_terms(&query_text, &self.search_languages)
+                .into_iter()
+                .collect();
         let semantic_score_map: HashMap<&str, f32> = semantic_hits
             .iter()
             .map(|hit| (hit.document_id.as_str(), hit.score))
             .collect();
-        let bounded_min_score = min_score.clamp(-1.0, 1.0);
-        let semantic_floor = bounded_min_score.max(0.15);
+        let semantic_page_map: HashMap<&str, u32> = semantic_hits
+            .iter()
+            .filter_map(|hit| hit.page.map(|page| (hit.document_id.as_str(), page)))
+            .collect();
 
         // RRF scoring
-        const RRF_K: f32 = 60.0;
-        let mut dropped_semantic_only = 0_usize;
         let mut results: Vec<HybridSearchHitPayload> = Vec::with_capacity(all_doc_ids.len());
         for doc_id in &all_doc_ids {
             let has_bm25 = bm25_rank.contains_key(doc_id.as_str());
@@ -1013,7 +1663,7 @@ impl DocumentCacheStore {
 
             // Avoid flooding results with low-confidence semantic-only hits.
             if semantic_weight > 0.0 && !has_bm25 && !semantic_passes_floor {
-                dropped_semantic_only += 1;
+                explanation.dropped_by_semantic_floor.push(doc_id.clone());
                 continue;
             }
 
@@ -1028,25 +1678,43 @@ impl DocumentCacheStore {
                 .unwrap_or(0.0);
 
             let mut score = sem_score + bm25_score;
+            let mut title_boost = 0.0;
 
             // Title boost: only for exact term overlap, with a small additive value.
             if !query_tokens.is_empty() {
                 let has_title_overlap = title_map
                     .get(doc_id.as_str())
                     .map(|title| {
-                        tokenize_query_terms(title)
+                        tokenize_query_terms(title, &self.search_languages)
                             .into_iter()
                             .any(|title_token| query_tokens.contains(&title_token))
                     })
                     .unwrap_or(false);
                 if has_title_overlap {
-                    score += 0.015;
+                    title_boost = TITLE_BOOST;
+                    score += title_boost;
                 }
             }
 
             results.push(HybridSearchHitPayload {
                 document_id: doc_id.clone(),
                 score,
+                page: semantic_page_map.get(doc_id.as_str()).copied(),
+                title: None,
+                title_matches: Vec::new(),
+                snippet: None,
+                passages: Vec::new(),
+                explanation: Some(HybridHitExplanationPayload {
+                    bm25_rank: bm25_rank.get(doc_id.as_str()).map(|rank| rank + 1),
+                    semantic_rank: semantic_rank.get(doc_id.as_str()).map(|rank| rank + 1),
+                    semantic_score: semantic_raw_score,
+                    bm25_contribution: bm25_score,
+                    semantic_contribution: sem_score,
+                    title_boost,
+                    fused_score: score,
+                    rerank_score: None,
+                    best_chunk_index: None,
+                }),
             });
         }
 
@@ -1058,6 +1726,34 @@ impl DocumentCacheStore {
         });
         results.truncate(limit);
 
+        // Highlights come from the BM25 leg, so semantic-only hits have none.
+        let bm25_highlights: HashMap<&str, &Bm25Hit> = bm25_hits
+            .iter()
+            .map(|hit| (hit.document_id.as_str(), hit))
+            .collect();
+        let passage_query_vector = (semantic_weight > 0.0).then_some(query_vector.as_slice());
+        for result in &mut results {
+            if let Some(hit) = bm25_highlights.get(result.document_id.as_str()) {
+                result.title = Some(hit.title.text.clone());
+                result.title_matches = hit.title.matches.clone();
+                result.snippet = Some(hit.snippet.clone());
+            }
+            result.passages = self.best_matching_passages(
+                &result.document_id,
+                passage_query_vector,
+                &query_text,
+                SEARCH_PASSAGES_PER_DOCUMENT,
+            )?;
+            if let Some(explanation) = result.explanation.as_mut() {
+                explanation.best_chunk_index =
+                    result.passages.first().map(|passage| passage.chunk_index);
+            }
+            // Lexical-only hits have no chunk page from the vector leg; use the best passage's.
+            if result.page.is_none() {
+                result.page = result.passages.first().and_then(|passage| passage.page);
+            }
+        }
+
         let bm25_top = bm25_hits
             .iter()
             .take(5)
@@ -1084,7 +1780,7 @@ impl DocumentCacheStore {
             bm25_hits.len(),
             semantic_hits.len(),
             all_doc_ids.len(),
-            dropped_semantic_only,
+            explanation.dropped_by_semantic_floor.len(),
             results.len(),
             bm25_top,
             semantic_top,
@@ -1092,17 +1788,458 @@ impl DocumentCacheStore {
             started.elapsed().as_millis()
         );
 
-        Ok(results)
+        Ok(ExplainedHybridSearchPayload {
+            hits: results,
+            explanation,
+        })
     }
 
-    /// Fetch title strings for a list of document IDs in one query.
-    fn fetch_titles_for_ids(
+    /// Scores the chunks of one document against a query and returns the best `limit`,
+    /// highest score first. Chunks score by embedding similarity when `query_vector` is
+    /// given, plus a bonus for the share of query terms they contain. Documents whose chunks
+    /// were not embedded with locations (or not embedded at all) are chunked from the cached
+    /// body and scored on query terms alone.
+    pub fn best_matching_passages(
         &self,
-        ids: &[String],
-    ) -> Result<HashMap<String, String>, DocumentCacheError> {
-        if ids.is_empty() {
-            return Ok(HashMap::new());
-        }
+        document_id: &str,
+        query_vector: Option<&[f32]>,
+        query_text: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchPassagePayload>, DocumentCacheError> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        if let Some(vector) = query_vector {
+            validate_embedding_vector(vector, self.embedding_dimensions)?;
+        }
+
+        let Some((title, body)) = self.document_title_and_body(document_id)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut candidates = self.stored_passage_candidates(document_id, &title, query_vector)?;
+        if candidates.is_empty()
+            || candidates
+                .iter()
+                .any(|(passage, _)| passage.line_start == 0)
+        {
+            candidates = self.chunk_passage_candidates(&title, &body);
+        }
+
+        let terms = tokenize_query_terms(query_text, &self.search_languages);
+        let mut passages = candidates
+            .into_iter()
+            .map(|(mut passage, similarity)| {
+                let coverage = query_term_coverage(&passage.text, &terms, &self.search_languages);
+                passage.score = match similarity {
+                    Some(similarity) => similarity + PASSAGE_TERM_COVERAGE_WEIGHT * coverage,
+                    None => coverage,
+                };
+                passage
+            })
+            .collect::<Vec<_>>();
+        passages.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.chunk_index.cmp(&b.chunk_index))
+        });
+
+        // The best chunk is kept even when nothing matched (e.g. a title-only hit) so every
+        // hit has some context; the others must score close to it.
+        let best_score = passages.first().map_or(0.0, |passage| passage.score);
+        let min_score = best_score * PASSAGE_MIN_RELATIVE_SCORE;
+        let mut position = 0;
+        passages.retain(|passage| {
+            position += 1;
+            position == 1 || (passage.score > 0.0 && passage.score >= min_score)
+        });
+        passages.truncate(limit);
+        Ok(passages)
+    }
+
+    /// Stored chunks of a document with their similarity to `query_vector`, when given.
+    fn stored_passage_candidates(
+        &self,
+        document_id: &str,
+        title: &str,
+        query_vector: Option<&[f32]>,
+    ) -> Result<Vec<(SearchPassagePayload, Option<f32>)>, DocumentCacheError> {
+        let query_vector_bytes = query_vector.map(f32_vector_to_le_bytes);
+        let mut statement = self.connection.prepare(
+            "SELECT m.chunk_index, m.chunk_text, m.page, m.heading_path, m.char_start,
+                    m.char_end, m.line_start, m.line_end,
+                    CASE WHEN ?2 IS NULL THEN NULL
+                         ELSE vec_distance_l2(v.embedding, vec_f32(?2)) END
+             FROM document_chunk_embeddings_meta m
+             LEFT JOIN document_chunk_embeddings_vec v ON v.rowid = m.id
+             WHERE m.document_id = ?1
+             ORDER BY m.chunk_index ASC",
+        )?;
+
+        let rows = statement.query_map(params![document_id, query_vector_bytes], |row| {
+            let heading_path = row.get::<_, String>(3)?;
+            let distance = row.get::<_, Option<f32>>(8)?;
+            Ok((
+                SearchPassagePayload {
+                    chunk_index: row.get(0)?,
+                    text: strip_chunk_title(&row.get::<_, String>(1)?, title),
+                    heading_path: serde_json::from_str(&heading_path).unwrap_or_default(),
+                    char_start: row.get(4)?,
+                    char_end: row.get(5)?,
+                    line_start: row.get(6)?,
+                    line_end: row.get(7)?,
+                    page: row.get(2)?,
+                    score: 0.0,
+                },
+                distance.map(|distance| 1.0 - (distance * distance / 2.0)),
+            ))
+        })?;
+
+        let mut candidates = Vec::new();
+        for row in rows {
+            candidates.push(row?);
+        }
+        Ok(candidates)
+    }
+
+    /// Chunks a cached body the way embedding sync does, with estimated token counts.
+    fn chunk_passage_candidates(
+        &self,
+        title: &str,
+        body: &str,
+    ) -> Vec<(SearchPassagePayload, Option<f32>)> {
+        let chunking = load_vault_settings(&self.documents_folder)
+            .map(|settings| settings.chunking)
+            .unwrap_or_default();
+        let markdown_body = tiptap_json_to_markdown(body).unwrap_or_else(|| body.to_owned());
+
+        chunk_markdown_document(title, &markdown_body, &chunking, &estimate_token_count)
+            .into_iter()
+            .map(|chunk| {
+                let passage = SearchPassagePayload {
+                    chunk_index: chunk.index,
+                    text: strip_chunk_title(&chunk.text, title),
+                    heading_path: chunk.heading_path,
+                    char_start: chunk.char_start,
+                    char_end: chunk.char_end,
+                    line_start: chunk.line_start,
+                    line_end: chunk.line_end,
+                    page: chunk.page,
+                    score: 0.0,
+                };
+                (passage, None)
+            })
+            .collect()
+    }
+
+    /// Documents most similar to `document_id`, compared through its stored vectors so
+    /// nothing is re-embedded. Each candidate scores by its closest chunk pair with the
+    /// source (whole-document vectors while no chunks are stored), plus bonuses for shared
+    /// tags and links between the two. Documents with no similarity, shared tag, or link
+    /// are left out.
+    pub fn related_documents(
+        &self,
+        document_id: &str,
+        limit: usize,
+    ) -> Result<Vec<RelatedDocumentHitPayload>, DocumentCacheError> {
+        let Some((source_title, source_body)) = self.document_title_and_body(document_id)? else {
+            return Err(DocumentCacheError::Validation(format!(
+                "document '{document_id}' is not in the search cache"
+            )));
+        };
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let closest_chunks = self.closest_chunk_pairs(document_id)?;
+        let similarities = if closest_chunks.is_empty() {
+            self.whole_document_similarities(document_id)?
+        } else {
+            closest_chunks
+                .iter()
+                .map(|(related_id, (similarity, _, _))| (related_id.clone(), *similarity))
+                .collect()
+        };
+
+        let mut tags_by_document: HashMap<String, Vec<String>> = HashMap::new();
+        let mut statement = self.connection.prepare(
+            "SELECT document_id, lower(tag) FROM document_tags ORDER BY document_id, lower(tag)",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (tagged_id, tag) = row?;
+            tags_by_document.entry(tagged_id).or_default().push(tag);
+        }
+        let source_tags = tags_by_document.remove(document_id).unwrap_or_default();
+
+        let mut path_by_id: HashMap<String, String> = HashMap::new();
+        let mut linking_bodies: Vec<(String, String)> = Vec::new();
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, title, folder_path, body FROM documents")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (cached_id, title, folder_path, body) = row?;
+            let file_name = document_file_name(&title);
+            let path = if folder_path.is_empty() {
+                file_name
+            } else {
+                format!("{folder_path}/{file_name}")
+            };
+            path_by_id.insert(cached_id.clone(), path);
+            if cached_id != document_id {
+                linking_bodies.push((cached_id, body));
+            }
+        }
+        let id_by_path: HashMap<&str, &str> = path_by_id
+            .iter()
+            .map(|(cached_id, path)| (path.as_str(), cached_id.as_str()))
+            .collect();
+        let source_path = path_by_id.get(document_id).cloned().unwrap_or_default();
+
+        let mut linked_ids: HashSet<String> = linked_document_paths(&source_path, &source_body)
+            .iter()
+            .filter_map(|path| id_by_path.get(path.as_str()))
+            .map(|linked_id| (*linked_id).to_owned())
+            .collect();
+        for (linking_id, body) in &linking_bodies {
+            if linked_document_paths(&path_by_id[linking_id], body).contains(&source_path) {
+                linked_ids.insert(linking_id.clone());
+            }
+        }
+        linked_ids.remove(document_id);
+
+        let mut candidate_ids: HashSet<String> = similarities.keys().cloned().collect();
+        candidate_ids.extend(linked_ids.iter().cloned());
+        candidate_ids.extend(
+            tags_by_document
+                .iter()
+                .filter(|(_, tags)| tags.iter().any(|tag| source_tags.contains(tag)))
+                .map(|(tagged_id, _)| tagged_id.clone()),
+        );
+        let titles = self.fetch_titles_for_ids(&candidate_ids.into_iter().collect::<Vec<_>>())?;
+
+        let mut hits = titles
+            .keys()
+            .cloned()
+            .map(|related_id| {
+                let similarity = similarities.get(&related_id).copied();
+                let shared_tags = tags_by_document
+                    .get(&related_id)
+                    .map(|tags| {
+                        tags.iter()
+                            .filter(|tag| source_tags.contains(tag))
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                let linked = linked_ids.contains(&related_id);
+
+                let mut score = similarity.unwrap_or(0.0);
+                if !source_tags.is_empty() {
+                    score += RELATED_SHARED_TAG_WEIGHT * shared_tags.len() as f32
+                        / source_tags.len() as f32;
+                }
+                if linked {
+                    score += RELATED_LINK_WEIGHT;
+                }
+
+                RelatedDocumentHitPayload {
+                    document_id: related_id,
+                    score,
+                    similarity,
+                    shared_tags,
+                    linked,
+                    chunk_pair: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        hits.sort_by(|left, right| {
+            right
+                .score
+                .partial_cmp(&left.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| left.document_id.cmp(&right.document_id))
+        });
+        hits.truncate(limit);
+
+        for hit in &mut hits {
+            let Some((similarity, source_chunk_id, related_chunk_id)) =
+                closest_chunks.get(&hit.document_id)
+            else {
+                continue;
+            };
+            let related_title = titles.get(&hit.document_id).map_or("", String::as_str);
+            hit.chunk_pair = Some(RelatedChunkPairPayload {
+                source: self.stored_chunk_passage(*source_chunk_id, &source_title, *similarity)?,
+                related: self.stored_chunk_passage(
+                    *related_chunk_id,
+                    related_title,
+                    *similarity,
+                )?,
+            });
+        }
+
+        Ok(hits)
+    }
+
+    /// For every other document with stored chunks, the similarity of its chunk closest to
+    /// any chunk of `document_id`, with the meta ids of that source and related chunk.
+    fn closest_chunk_pairs(
+        &self,
+        document_id: &str,
+    ) -> Result<HashMap<String, (f32, i64, i64)>, DocumentCacheError> {
+        // SQLite takes the bare columns from the row that produced MIN(distance).
+        let mut statement = self.connection.prepare(
+            "SELECT related.document_id,
+                    MIN(vec_distance_l2(source_vec.embedding, related_vec.embedding)) AS distance,
+                    source.id,
+                    related.id
+             FROM document_chunk_embeddings_meta source
+             JOIN document_chunk_embeddings_vec source_vec ON source_vec.rowid = source.id
+             JOIN document_chunk_embeddings_meta related
+               ON related.document_id <> source.document_id
+             JOIN document_chunk_embeddings_vec related_vec ON related_vec.rowid = related.id
+             WHERE source.document_id = ?1
+             GROUP BY related.document_id",
+        )?;
+        let rows = statement.query_map(params![document_id], |row| {
+            let distance = row.get::<_, f32>(1)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                (
+                    1.0 - (distance * distance / 2.0),
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                ),
+            ))
+        })?;
+
+        let mut pairs = HashMap::new();
+        for row in rows {
+            let (related_id, pair) = row?;
+            pairs.insert(related_id, pair);
+        }
+        Ok(pairs)
+    }
+
+    /// Similarity of the whole-document vector of `document_id` to every other one.
+    fn whole_document_similarities(
+        &self,
+        document_id: &str,
+    ) -> Result<HashMap<String, f32>, DocumentCacheError> {
+        let mut statement = self.connection.prepare(
+            "SELECT related.document_id,
+                    vec_distance_l2(source_vec.embedding, related_vec.embedding)
+             FROM document_embeddings_meta source
+             JOIN document_embeddings_vec source_vec ON source_vec.rowid = source.id
+             JOIN document_embeddings_meta related
+               ON related.document_id <> source.document_id
+             JOIN document_embeddings_vec related_vec ON related_vec.rowid = related.id
+             WHERE source.document_id = ?1",
+        )?;
+        let rows = statement.query_map(params![document_id], |row| {
+            let distance = row.get::<_, f32>(1)?;
+            Ok((row.get::<_, String>(0)?, 1.0 - (distance * distance / 2.0)))
+        })?;
+
+        let mut similarities = HashMap::new();
+        for row in rows {
+            let (related_id, similarity) = row?;
+            similarities.insert(related_id, similarity);
+        }
+        Ok(similarities)
+    }
+
+    fn document_title_and_body(
+        &self,
+        document_id: &str,
+    ) -> Result<Option<(String, String)>, DocumentCacheError> {
+        let document = self
+            .connection
+            .query_row(
+                "SELECT title, body FROM documents WHERE id = ?1",
+                params![document_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+        Ok(document)
+    }
+
+    /// A stored chunk, by meta row id, as a passage scored `score`.
+    fn stored_chunk_passage(
+        &self,
+        chunk_id: i64,
+        title: &str,
+        score: f32,
+    ) -> Result<SearchPassagePayload, DocumentCacheError> {
+        let passage = self.connection.query_row(
+            "SELECT chunk_index, chunk_text, page, heading_path, char_start, char_end,
+                    line_start, line_end
+             FROM document_chunk_embeddings_meta
+             WHERE id = ?1",
+            params![chunk_id],
+            |row| {
+                let heading_path = row.get::<_, String>(3)?;
+                Ok(SearchPassagePayload {
+                    chunk_index: row.get(0)?,
+                    text: strip_chunk_title(&row.get::<_, String>(1)?, title),
+                    heading_path: serde_json::from_str(&heading_path).unwrap_or_default(),
+                    char_start: row.get(4)?,
+                    char_end: row.get(5)?,
+                    line_start: row.get(6)?,
+                    line_end: row.get(7)?,
+                    page: row.get(2)?,
+                    score,
+                })
+            },
+        )?;
+        Ok(passage)
+    }
+
+    /// Ids of documents passing the filters of `query`, most recently updated first.
+    fn filtered_document_ids(
+        &self,
+        query: &SearchQuery,
+        limit: usize,
+    ) -> Result<Vec<String>, DocumentCacheError> {
+        let (filter_sql, mut values) = search_filter_sql(query, &self.search_languages);
+        values.push(Value::Integer(i64::try_from(limit).unwrap_or(i64::MAX)));
+
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT d.id FROM documents d
+             WHERE {filter_sql}
+             ORDER BY d.updated_at DESC, d.id
+             LIMIT ?"
+        ))?;
+        let rows = statement.query_map(params_from_iter(values), |row| row.get::<_, String>(0))?;
+
+        let mut ids = Vec::new();
+        for row in rows {
+            ids.push(row?);
+        }
+        Ok(ids)
+    }
+
+    /// Fetch title strings for a list of document IDs in one query.
+    fn fetch_titles_for_ids(
+        &self,
+        ids: &[String],
+    ) -> Result<HashMap<String, String>, DocumentCacheError> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
 
         // Build a parameterized IN clause.
         let placeholders: String = ids
@@ -1274,14 +2411,24 @@ impl DocumentCacheStore {
         for chunk in chunks {
             let chunk_index = i64::try_from(chunk.chunk_index)
                 .map_err(|_| DocumentCacheError::Validation("chunk_index is too large".into()))?;
+            let heading_path = serde_json::to_string(&chunk.heading_path).map_err(|error| {
+                DocumentCacheError::Validation(format!("invalid chunk heading path: {error}"))
+            })?;
             transaction.execute(
                 "INSERT INTO document_chunk_embeddings_meta
-                   (document_id, chunk_index, chunk_text, content_hash, model, updated_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                   (document_id, chunk_index, chunk_text, page, heading_path, char_start,
+                    char_end, line_start, line_end, content_hash, model, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
                 params![
                     &chunk.document_id,
                     chunk_index,
                     &chunk.chunk_text,
+                    chunk.page,
+                    heading_path,
+                    chunk.char_start,
+                    chunk.char_end,
+                    chunk.line_start,
+                    chunk.line_end,
                     &chunk.content_hash,
                     &chunk.model,
                     &chunk.updated_at,
@@ -1319,10 +2466,10 @@ fn dedupe_non_empty_tags(tags: &[String]) -> Vec<String> {
     deduped
 }
 
-fn validate_embedding_vector(vector: &[f32]) -> Result<(), DocumentCacheError> {
-    if vector.len() != EMBEDDING_VECTOR_DIMENSIONS {
+fn validate_embedding_vector(vector: &[f32], dimensions: usize) -> Result<(), DocumentCacheError> {
+    if vector.len() != dimensions {
         return Err(DocumentCacheError::Validation(format!(
-            "embedding vector must contain exactly {EMBEDDING_VECTOR_DIMENSIONS} dimensions (got {})",
+            "embedding vector must contain exactly {dimensions} dimensions (got {})",
             vector.len()
         )));
     }
@@ -1330,6 +2477,24 @@ fn validate_embedding_vector(vector: &[f32]) -> Result<(), DocumentCacheError> {
     Ok(())
 }
 
+fn vector_tables_sql(dimensions: usize) -> String {
+    CREATE_VECTOR_TABLES_SQL.replace("{dimensions}", &dimensions.to_string())
+}
+
+fn read_cache_setting(
+    connection: &Connection,
+    key: &str,
+) -> Result<Option<String>, DocumentCacheError> {
+    connection
+        .query_row(
+            "SELECT value FROM cache_settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(DocumentCacheError::from)
+}
+
 fn f32_vector_to_le_bytes(vector: &[f32]) -> Vec<u8> {
     let mut bytes = Vec::with_capacity(vector.len() * std::mem::size_of::<f32>());
     for value in vector {
@@ -1429,6 +2594,96 @@ mod tests {
         let _ = std::fs::remove_dir_all(temp_dir);
     }
 
+    #[test]
+    fn reset_embeddings_resizes_vector_tables_and_records_the_model() {
+        let temp_dir = unique_temp_path();
+
+        {
+            let mut store =
+                DocumentCacheStore::new(&temp_dir).expect("cache store should initialize");
+            assert_eq!(store.embedding_dimensions(), EMBEDDING_VECTOR_DIMENSIONS);
+            assert_eq!(store.embedding_model().expect("model read"), None);
+
+            store
+                .upsert_document(&CachedDocumentPayload {
+                    id: "doc-1".to_string(),
+                    user_id: "user-1".to_string(),
+                    title: "Test".to_string(),
+                    body: "Body".to_string(),
+                    folder_path: "".to_string(),
+                    banner_image_url: None,
+                    deleted_at: None,
+                    created_at: "2026-02-13T00:00:00Z".to_string(),
+                    updated_at: "2026-02-13T00:00:00Z".to_string(),
+                    tags: vec![],
+                })
+                .expect("document upsert should succeed");
+            store
+                .upsert_document_embedding(&CachedDocumentEmbeddingPayload {
+                    document_id: "doc-1".to_string(),
+                    model: "old-model".to_string(),
+                    content_hash: "hash".to_string(),
+                    vector: vec![1.0; EMBEDDING_VECTOR_DIMENSIONS],
+                    updated_at: "2026-02-13T00:00:00Z".to_string(),
+                })
+                .expect("embedding upsert should succeed");
+            assert_eq!(
+                store
+                    .count_documents_with_stale_embeddings("new-model")
+                    .expect("stale count"),
+                1
+            );
+            assert_eq!(
+                store
+                    .count_documents_with_stale_embeddings("old-model")
+                    .expect("stale count"),
+                0
+            );
+
+            store
+                .reset_embeddings("new-model", 8)
+                .expect("reset should succeed");
+            assert_eq!(store.embedding_dimensions(), 8);
+            assert!(store
+                .list_document_embedding_metadata()
+                .expect("metadata read")
+                .is_empty());
+            assert!(store
+                .upsert_document_embedding(&CachedDocumentEmbeddingPayload {
+                    document_id: "doc-1".to_string(),
+                    model: "new-model".to_string(),
+                    content_hash: "hash".to_string(),
+                    vector: vec![1.0; EMBEDDING_VECTOR_DIMENSIONS],
+                    updated_at: "2026-02-13T00:00:01Z".to_string(),
+                })
+                .is_err());
+            store
+                .upsert_document_embedding(&CachedDocumentEmbeddingPayload {
+                    document_id: "doc-1".to_string(),
+                    model: "new-model".to_string(),
+                    content_hash: "hash".to_string(),
+                    vector: vec![1.0; 8],
+                    updated_at: "2026-02-13T00:00:01Z".to_string(),
+                })
+                .expect("embedding at the new dimension should succeed");
+            assert!(store.reset_embeddings("new-model", 0).is_err());
+        }
+
+        let reopened = DocumentCacheStore::new(&temp_dir).expect("cache store should reopen");
+        assert_eq!(reopened.embedding_dimensions(), 8);
+        assert_eq!(
+            reopened.embedding_model().expect("model read").as_deref(),
+            Some("new-model")
+        );
+        let hits = reopened
+            .semantic_search_documents(vec![1.0; 8], 1, 0.0, None)
+            .expect("semantic search should succeed");
+        assert_eq!(hits.len(), 1);
+        drop(reopened);
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
     #[test]
     fn hybrid_search_returns_results() {
         let temp_dir = unique_temp_path();
@@ -1483,6 +2738,89 @@ mod tests {
         let _ = std::fs::remove_dir_all(temp_dir);
     }
 
+    #[test]
+    fn best_matching_passages_rank_stored_chunks_and_chunk_unembedded_bodies() {
+        let temp_dir = unique_temp_path();
+
+        {
+            let mut store =
+                DocumentCacheStore::new(&temp_dir).expect("cache store should initialize");
+
+            let document = CachedDocumentPayload {
+                id: "doc-passages".to_string(),
+                user_id: "user-1".to_string(),
+                title: "Deploy Notes".to_string(),
+                body: "# Setup\n\nInstall the CLI.\n\n# Rollback\n\nRevert the release tag."
+                    .to_string(),
+                folder_path: "".to_string(),
+                banner_image_url: None,
+                deleted_at: None,
+                created_at: "2026-02-13T00:00:00Z".to_string(),
+                updated_at: "2026-02-13T00:00:00Z".to_string(),
+                tags: vec![],
+            };
+            store
+                .upsert_document(&document)
+                .expect("upsert should succeed");
+
+            let unembedded = store
+                .best_matching_passages(&document.id, None, "release", 3)
+                .expect("passages should be computed from the body");
+            assert_eq!(unembedded.len(), 1);
+            assert_eq!(unembedded[0].text, document.body);
+            assert_eq!((unembedded[0].line_start, unembedded[0].line_end), (1, 7));
+
+            let unit_vector = |axis: usize| {
+                let mut vector = vec![0.0; EMBEDDING_VECTOR_DIMENSIONS];
+                vector[axis] = 1.0;
+                vector
+            };
+            let chunk = |index: usize, text: &str, heading: &str, lines: (usize, usize)| {
+                CachedDocumentChunkEmbeddingPayload {
+                    document_id: document.id.clone(),
+                    chunk_index: index,
+                    chunk_text: format!("{}\n\n{text}", document.title),
+                    page: None,
+                    heading_path: vec![heading.to_string()],
+                    char_start: 0,
+                    char_end: text.chars().count(),
+                    line_start: lines.0,
+                    line_end: lines.1,
+                    content_hash: "chunks".to_string(),
+                    model: "test-model".to_string(),
+                    vector: unit_vector(index),
+                    updated_at: "2026-02-13T00:00:00Z".to_string(),
+                }
+            };
+            store
+                .replace_document_chunk_embeddings(
+                    &document.id,
+                    &[
+                        chunk(0, "# Setup\n\nInstall the CLI.", "Setup", (1, 3)),
+                        chunk(
+                            1,
+                            "# Rollback\n\nRevert the release tag.",
+                            "Rollback",
+                            (5, 7),
+                        ),
+                    ],
+                )
+                .expect("chunk write should succeed");
+
+            let passages = store
+                .best_matching_passages(&document.id, Some(&unit_vector(1)), "release", 3)
+                .expect("passages should be scored");
+            assert_eq!(passages.len(), 1, "unrelated chunks should be dropped");
+            assert_eq!(passages[0].chunk_index, 1);
+            assert_eq!(passages[0].text, "# Rollback\n\nRevert the release tag.");
+            assert_eq!(passages[0].heading_path, vec!["Rollback".to_string()]);
+            assert_eq!((passages[0].line_start, passages[0].line_end), (5, 7));
+            assert!(passages[0].score > 1.0);
+        }
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
     #[test]
     fn hybrid_search_supports_bm25_only_mode_when_semantic_weight_is_zero() {
         let temp_dir = unique_temp_path();
@@ -1528,6 +2866,456 @@ mod tests {
         let _ = std::fs::remove_dir_all(temp_dir);
     }
 
+    #[test]
+    fn hybrid_search_returns_highlighted_title_and_snippet() {
+        let temp_dir = unique_temp_path();
+
+        {
+            let mut store =
+                DocumentCacheStore::new(&temp_dir).expect("cache store should initialize");
+
+            let document = CachedDocumentPayload {
+                id: "doc-highlight".to_string(),
+                user_id: "user-1".to_string(),
+                title: "Café Authentication Notes".to_string(),
+                body: "Déjà vu: token authentication expires after an hour.".to_string(),
+                folder_path: "".to_string(),
+                banner_image_url: None,
+                deleted_at: None,
+                created_at: "2026-02-13T00:00:00Z".to_string(),
+                updated_at: "2026-02-13T00:00:00Z".to_string(),
+                tags: vec![],
+            };
+            store
+                .upsert_document(&document)
+                .expect("upsert should succeed");
+
+            let hits = store
+                .hybrid_search_documents(
+                    vec![0.0; EMBEDDING_VECTOR_DIMENSIONS],
+                    "authentication",
+                    5,
+                    0.0,
+                    None,
+                    0.0,
+                    1.0,
+                )
+                .expect("hybrid search should succeed");
+
+            assert_eq!(hits.len(), 1);
+            assert_eq!(
+                hits[0].title_matches,
+                vec![MatchRangePayload { start: 5, end: 19 }]
+            );
+            let snippet = hits[0].snippet.as_ref().expect("lexical hit has a snippet");
+            assert_eq!(snippet.text, document.body);
+            assert_eq!(
+                snippet.matches,
+                vec![MatchRangePayload { start: 15, end: 29 }]
+            );
+            let matched = snippet
+                .text
+                .chars()
+                .skip(snippet.matches[0].start)
+                .take(snippet.matches[0].end - snippet.matches[0].start)
+                .collect::<String>();
+            assert_eq!(matched, "authentication");
+        }
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
+    #[test]
+    fn hybrid_search_for_query_applies_filters_phrases_and_negation() {
+        let temp_dir = unique_temp_path();
+
+        {
+            let mut store =
+                DocumentCacheStore::new(&temp_dir).expect("cache store should initialize");
+
+            let documents = [
+                (
+                    "doc-oncall",
+                    "On-call handbook",
+                    "Restart the kubernetes cluster when paged.",
+                    "runbooks/infra",
+                    "2026-03-02T09:00:00Z",
+                    vec!["infra"],
+                ),
+                (
+                    "doc-draft",
+                    "On-call draft",
+                    "Restart the kubernetes cluster, maybe.",
+                    "runbooks",
+                    "2026-03-03T09:00:00Z",
+                    vec!["infra", "draft"],
+                ),
+                (
+                    "doc-old",
+                    "Cluster notes",
+                    "Restart k8s nodes one by one.",
+                    "runbooks-archive",
+                    "2025-11-20T09:00:00Z",
+                    vec!["Infra"],
+                ),
+            ];
+            for (id, title, body, folder_path, timestamp, tags) in documents {
+                store
+                    .upsert_document(&CachedDocumentPayload {
+                        id: id.to_string(),
+                        user_id: "user-1".to_string(),
+                        title: title.to_string(),
+                        body: body.to_string(),
+                        folder_path: folder_path.to_string(),
+                        banner_image_url: None,
+                        deleted_at: None,
+                        created_at: timestamp.to_string(),
+                        updated_at: timestamp.to_string(),
+                        tags: tags.into_iter().map(str::to_string).collect(),
+                    })
+                    .expect("upsert should succeed");
+            }
+
+            let search = |query: &str| {
+                let query = parse_search_query(query).expect("query should parse");
+                let mut ids = store
+                    .hybrid_search_documents_for_query(
+                        vec![0.0; EMBEDDING_VECTOR_DIMENSIONS],
+                        &query,
+                        10,
+                        0.0,
+                        0.0,
+                        1.0,
+                    )
+                    .expect("hybrid search should succeed")
+                    .into_iter()
+                    .map(|hit| hit.document_id)
+                    .collect::<Vec<_>>();
+                ids.sort();
+                ids
+            };
+
+            assert_eq!(
+                search("restart kubernetes OR k8s"),
+                vec!["doc-draft", "doc-old", "doc-oncall"]
+            );
+            assert_eq!(
+                search("tag:infra folder:runbooks -tag:draft restart"),
+                vec!["doc-oncall"]
+            );
+            assert_eq!(search(r#"title:"on-call" -maybe"#), vec!["doc-oncall"]);
+            assert_eq!(search(r#""nodes one by one""#), vec!["doc-old"]);
+            assert_eq!(search("restart created:<2026-01-01"), vec!["doc-old"]);
+            assert_eq!(
+                search("-folder:runbooks/infra updated:>=2026-03-01"),
+                vec!["doc-draft"]
+            );
+        }
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
+    #[test]
+    fn explain_hybrid_search_reports_ranks_contributions_and_floor_drops() {
+        let temp_dir = unique_temp_path();
+
+        {
+            let mut store =
+                DocumentCacheStore::new(&temp_dir).expect("cache store should initialize");
+
+            let unit_vector = |axis: usize, weight: f32| {
+                let mut vector = vec![0.0; EMBEDDING_VECTOR_DIMENSIONS];
+                vector[0] = weight;
+                vector[axis] = (1.0 - weight * weight).sqrt();
+                vector
+            };
+            let documents = [
+                ("doc-lexical", "Deploy Guide", "Deploy steps.", 0.3, 1),
+                ("doc-semantic", "Release Notes", "Shipping notes.", 0.9, 2),
+                ("doc-weak", "Misc", "Other things.", 0.05, 3),
+            ];
+            for (id, title, body, weight, axis) in documents {
+                store
+                    .upsert_document(&CachedDocumentPayload {
+                        id: id.to_string(),
+                        user_id: "user-1".to_string(),
+                        title: title.to_string(),
+                        body: body.to_string(),
+                        folder_path: "".to_string(),
+                        banner_image_url: None,
+                        deleted_at: None,
+                        created_at: "2026-02-13T00:00:00Z".to_string(),
+                        updated_at: "2026-02-13T00:00:00Z".to_string(),
+                        tags: Vec::new(),
+                    })
+                    .expect("upsert should succeed");
+                store
+                    .replace_document_chunk_embeddings(
+                        id,
+                        &[CachedDocumentChunkEmbeddingPayload {
+                            document_id: id.to_string(),
+                            chunk_index: 0,
+                            chunk_text: body.to_string(),
+                            page: None,
+                            heading_path: Vec::new(),
+                            char_start: 0,
+                            char_end: body.chars().count(),
+                            line_start: 1,
+                            line_end: 1,
+                            content_hash: "chunks".to_string(),
+                            model: "test-model".to_string(),
+                            vector: unit_vector(axis, weight),
+                            updated_at: "2026-02-13T00:00:00Z".to_string(),
+                        }],
+                    )
+                    .expect("chunk write should succeed");
+            }
+
+            let mut query_vector = vec![0.0; EMBEDDING_VECTOR_DIMENSIONS];
+            query_vector[0] = 1.0;
+            let query = SearchQuery::plain_text("deploy");
+            let explained = store
+                .explain_hybrid_search_for_query(query_vector.clone(), &query, 5, 0.0, 1.0, 1.0)
+                .expect("explained search should succeed");
+
+            let explanation = &explained.explanation;
+            assert_eq!(explanation.rrf_k, RRF_K);
+            assert_eq!(explanation.candidate_k, 10);
+            assert_eq!(explanation.bm25_candidates, 1);
+            assert_eq!(explanation.semantic_candidates, 3);
+            assert_eq!(explanation.semantic_floor, MIN_SEMANTIC_FLOOR);
+            assert_eq!(explanation.dropped_by_semantic_floor, vec!["doc-weak"]);
+
+            let ids = explained
+                .hits
+                .iter()
+                .map(|hit| hit.document_id.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(ids, vec!["doc-lexical", "doc-semantic"]);
+
+            let lexical = explained.hits[0]
+                .explanation
+                .as_ref()
+                .expect("lexical hit should be explained");
+            assert_eq!(lexical.bm25_rank, Some(1));
+            assert_eq!(lexical.semantic_rank, Some(2));
+            assert!((lexical.semantic_score.expect("cosine") - 0.3).abs() < 1e-4);
+            assert!((lexical.bm25_contribution - 1.0 / RRF_K).abs() < 1e-6);
+            assert!((lexical.semantic_contribution - 1.0 / (RRF_K + 1.0)).abs() < 1e-6);
+            assert_eq!(lexical.title_boost, TITLE_BOOST);
+            assert_eq!(lexical.fused_score, explained.hits[0].score);
+            assert_eq!(lexical.best_chunk_index, Some(0));
+
+            let semantic = explained.hits[1]
+                .explanation
+                .as_ref()
+                .expect("semantic hit should be explained");
+            assert_eq!(semantic.bm25_rank, None);
+            assert_eq!(semantic.semantic_rank, Some(1));
+            assert_eq!(semantic.bm25_contribution, 0.0);
+            assert_eq!(semantic.title_boost, 0.0);
+
+            let plain = store
+                .hybrid_search_documents_for_query(query_vector, &query, 5, 0.0, 1.0, 1.0)
+                .expect("hybrid search should succeed");
+            assert_eq!(plain.len(), 2);
+            assert!(plain.iter().all(|hit| hit.explanation.is_none()));
+        }
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
+    #[test]
+    fn filtered_semantic_search_ranks_only_matching_documents() {
+        let temp_dir = unique_temp_path();
+
+        {
+            let mut store =
+                DocumentCacheStore::new(&temp_dir).expect("cache store should initialize");
+
+            let unit_vector = |axis: usize, weight: f32| {
+                let mut vector = vec![0.0; EMBEDDING_VECTOR_DIMENSIONS];
+                vector[0] = weight;
+                vector[axis] = (1.0 - weight * weight).sqrt();
+                vector
+            };
+            // Untagged documents sit closest to the query vector, so an unfiltered top-k
+            // would not contain the tagged ones.
+            let documents = [
+                ("doc-near-1", vec![], 0.99, 1),
+                ("doc-near-2", vec![], 0.98, 2),
+                ("doc-near-3", vec![], 0.97, 3),
+                ("doc-tagged-1", vec!["infra"], 0.6, 4),
+                ("doc-tagged-2", vec!["infra"], 0.5, 5),
+            ];
+            for (id, tags, weight, axis) in documents {
+                store
+                    .upsert_document(&CachedDocumentPayload {
+                        id: id.to_string(),
+                        user_id: "user-1".to_string(),
+                        title: id.to_string(),
+                        body: "Body".to_string(),
+                        folder_path: "".to_string(),
+                        banner_image_url: None,
+                        deleted_at: None,
+                        created_at: "2026-02-13T00:00:00Z".to_string(),
+                        updated_at: "2026-02-13T00:00:00Z".to_string(),
+                        tags: tags.into_iter().map(str::to_string).collect(),
+                    })
+                    .expect("upsert should succeed");
+                store
+                    .replace_document_chunk_embeddings(
+                        id,
+                        &[CachedDocumentChunkEmbeddingPayload {
+                            document_id: id.to_string(),
+                            chunk_index: 0,
+                            chunk_text: "Body".to_string(),
+                            page: None,
+                            heading_path: Vec::new(),
+                            char_start: 0,
+                            char_end: 4,
+                            line_start: 1,
+                            line_end: 1,
+                            content_hash: "chunks".to_string(),
+                            model: "test-model".to_string(),
+                            vector: unit_vector(axis, weight),
+                            updated_at: "2026-02-13T00:00:00Z".to_string(),
+                        }],
+                    )
+                    .expect("chunk write should succeed");
+            }
+
+            let search = |query: &SearchQuery, limit: usize| {
+                store
+                    .hybrid_search_documents_for_query(
+                        unit_vector(0, 1.0),
+                        query,
+                        limit,
+                        0.0,
+                        1.0,
+                        0.0,
+                    )
+                    .expect("hybrid search should succeed")
+                    .into_iter()
+                    .map(|hit| hit.document_id)
+                    .collect::<Vec<_>>()
+            };
+
+            let tagged = SearchQuery {
+                tags: vec!["infra".to_string()],
+                ..SearchQuery::plain_text("body")
+            };
+            assert_eq!(search(&tagged, 2), vec!["doc-tagged-1", "doc-tagged-2"]);
+            assert_eq!(search(&tagged, 1), vec!["doc-tagged-1"]);
+
+            let excluded = SearchQuery {
+                excluded_document_ids: vec!["doc-near-1".to_string()],
+                ..SearchQuery::plain_text("body")
+            };
+            assert_eq!(search(&excluded, 2), vec!["doc-near-2", "doc-near-3"]);
+        }
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
+    #[test]
+    fn related_documents_rank_by_closest_chunk_pair_tags_and_links() {
+        let temp_dir = unique_temp_path();
+
+        {
+            let mut store =
+                DocumentCacheStore::new(&temp_dir).expect("cache store should initialize");
+
+            let unit_vector = |axis: usize| {
+                let mut vector = vec![0.0; EMBEDDING_VECTOR_DIMENSIONS];
+                vector[axis] = 1.0;
+                vector
+            };
+            let documents = [
+                (
+                    "doc-source",
+                    "# Setup\n\nInstall.\n\n# Deploy\n\nShip it.",
+                    vec!["Infra", "ops"],
+                    vec![0, 1],
+                ),
+                ("doc-close", "Shipping notes.", vec![], vec![1]),
+                ("doc-tagged", "Unrelated.", vec!["ops"], vec![5]),
+                (
+                    "doc-linking",
+                    "See [source](../doc-source/Title.md).",
+                    vec![],
+                    vec![6],
+                ),
+                ("doc-far", "Nothing in common.", vec![], vec![7]),
+            ];
+            for (id, body, tags, axes) in documents {
+                store
+                    .upsert_document(&CachedDocumentPayload {
+                        id: id.to_string(),
+                        user_id: "user-1".to_string(),
+                        title: "Title".to_string(),
+                        body: body.to_string(),
+                        folder_path: id.to_string(),
+                        banner_image_url: None,
+                        deleted_at: None,
+                        created_at: "2026-02-13T00:00:00Z".to_string(),
+                        updated_at: "2026-02-13T00:00:00Z".to_string(),
+                        tags: tags.into_iter().map(str::to_string).collect(),
+                    })
+                    .expect("upsert should succeed");
+                let chunks = axes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, axis)| CachedDocumentChunkEmbeddingPayload {
+                        document_id: id.to_string(),
+                        chunk_index: index,
+                        chunk_text: format!("Title\n\nchunk {index} of {id}"),
+                        page: None,
+                        heading_path: vec![format!("Heading {index}")],
+                        char_start: 0,
+                        char_end: 1,
+                        line_start: index * 4 + 1,
+                        line_end: index * 4 + 3,
+                        content_hash: "chunks".to_string(),
+                        model: "test-model".to_string(),
+                        vector: unit_vector(axis),
+                        updated_at: "2026-02-13T00:00:00Z".to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                store
+                    .replace_document_chunk_embeddings(id, &chunks)
+                    .expect("chunk write should succeed");
+            }
+
+            let hits = store
+                .related_documents("doc-source", 3)
+                .expect("related documents should be found");
+            let ids = hits
+                .iter()
+                .map(|hit| hit.document_id.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(ids, vec!["doc-close", "doc-linking", "doc-tagged"]);
+
+            let pair = hits[0].chunk_pair.as_ref().expect("chunks are stored");
+            assert_eq!(pair.source.chunk_index, 1);
+            assert_eq!(pair.source.text, "chunk 1 of doc-source");
+            assert_eq!((pair.source.line_start, pair.source.line_end), (5, 7));
+            assert_eq!(pair.related.text, "chunk 0 of doc-close");
+            assert!((pair.related.score - 1.0).abs() < 1e-5);
+
+            assert!(hits[1].linked);
+            assert_eq!(hits[2].shared_tags, vec!["ops".to_string()]);
+            assert!((hits[2].score - 0.05).abs() < 1e-5);
+
+            let error = store
+                .related_documents("doc-missing", 3)
+                .expect_err("unknown documents should fail");
+            assert!(matches!(error, DocumentCacheError::Validation(_)));
+        }
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
     #[test]
     fn hybrid_search_handles_hyphenated_terms_without_sql_errors() {
         let temp_dir = unique_temp_path();
@@ -1624,6 +3412,12 @@ mod tests {
                         document_id: document_one.id.clone(),
                         chunk_index: 0,
                         chunk_text: "chunk 1".to_string(),
+                        page: None,
+                        heading_path: Vec::new(),
+                        char_start: 0,
+                        char_end: 0,
+                        line_start: 0,
+                        line_end: 0,
                         content_hash: "chunk-1-hash".to_string(),
                         model: "test-model".to_string(),
                         vector: vec![0.5; EMBEDDING_VECTOR_DIMENSIONS],
@@ -1724,6 +3518,12 @@ mod tests {
                         document_id: document_one.id.clone(),
                         chunk_index: 0,
                         chunk_text: "seed chunk".to_string(),
+                        page: None,
+                        heading_path: Vec::new(),
+                        char_start: 0,
+                        char_end: 0,
+                        line_start: 0,
+                        line_end: 0,
                         content_hash: initial_chunk_hash.to_string(),
                         model: "test-model".to_string(),
                         vector: vec![0.4; EMBEDDING_VECTOR_DIMENSIONS],
@@ -1746,6 +3546,12 @@ mod tests {
                         document_id: document_one.id.clone(),
                         chunk_index: 0,
                         chunk_text: "replacement chunk".to_string(),
+                        page: None,
+                        heading_path: Vec::new(),
+                        char_start: 0,
+                        char_end: 0,
+                        line_start: 0,
+                        line_end: 0,
                         content_hash: "new-chunk-hash".to_string(),
                         model: "test-model".to_string(),
                         vector: vec![0.6; EMBEDDING_VECTOR_DIMENSIONS],
@@ -1858,6 +3664,12 @@ mod tests {
                             document_id: documents[0].id.clone(),
                             chunk_index: 0,
                             chunk_text: "chunk old".to_string(),
+                            page: None,
+                            heading_path: Vec::new(),
+                            char_start: 0,
+                            char_end: 0,
+                            line_start: 0,
+                            line_end: 0,
                             content_hash: "hash-old".to_string(),
                             model: "model-a".to_string(),
                             vector: vec![0.1; EMBEDDING_VECTOR_DIMENSIONS],
@@ -1867,6 +3679,12 @@ mod tests {
                             document_id: documents[0].id.clone(),
                             chunk_index: 1,
                             chunk_text: "chunk latest".to_string(),
+                            page: None,
+                            heading_path: Vec::new(),
+                            char_start: 0,
+                            char_end: 0,
+                            line_start: 0,
+                            line_end: 0,
                             content_hash: "hash-latest".to_string(),
                             model: "model-a".to_string(),
                             vector: vec![0.2; EMBEDDING_VECTOR_DIMENSIONS],
@@ -1883,6 +3701,12 @@ mod tests {
                         document_id: documents[1].id.clone(),
                         chunk_index: 0,
                         chunk_text: "chunk".to_string(),
+                        page: None,
+                        heading_path: Vec::new(),
+                        char_start: 0,
+                        char_end: 0,
+                        line_start: 0,
+                        line_end: 0,
                         content_hash: "hash-doc-2".to_string(),
                         model: "model-a".to_string(),
                         vector: vec![0.3; EMBEDDING_VECTOR_DIMENSIONS],
@@ -1898,6 +3722,12 @@ mod tests {
                         document_id: documents[2].id.clone(),
                         chunk_index: 0,
                         chunk_text: "chunk".to_string(),
+                        page: None,
+                        heading_path: Vec::new(),
+                        char_start: 0,
+                        char_end: 0,
+                        line_start: 0,
+                        line_end: 0,
                         content_hash: "hash-doc-3".to_string(),
                         model: "model-b".to_string(),
                         vector: vec![0.4; EMBEDDING_VECTOR_DIMENSIONS],
@@ -1935,25 +3765,42 @@ mod tests {
     #[test]
     fn sanitize_fts5_query_basic() {
         assert_eq!(
-            sanitize_fts5_query("OAuth API"),
+            sanitize_fts5_query("OAuth API", &DEFAULT_SEARCH_LANGUAGES),
             Some("\"oauth\" AND \"api\"".to_string())
         );
-        assert_eq!(sanitize_fts5_query("a"), None); // single char token filtered
+        assert_eq!(sanitize_fts5_query("a", &DEFAULT_SEARCH_LANGUAGES), None); // single char token filtered
         assert_eq!(
-            sanitize_fts5_query("machine learning"),
+            sanitize_fts5_query("machine learning", &DEFAULT_SEARCH_LANGUAGES),
             Some("\"machine\" AND (\"learning\" OR \"learn\"*)".to_string())
         );
         assert_eq!(
-            sanitize_fts5_query("how to use the api"),
+            sanitize_fts5_query("how to use the api", &DEFAULT_SEARCH_LANGUAGES),
             Some("\"use\" AND \"api\"".to_string())
         );
         assert_eq!(
-            sanitize_fts5_query("Herbal"),
+            sanitize_fts5_query("Herbal", &DEFAULT_SEARCH_LANGUAGES),
             Some("(\"herbal\" OR \"herb\"*)".to_string())
         );
         assert_eq!(
-            sanitize_fts5_query("hands-only"),
+            sanitize_fts5_query("hands-only", &DEFAULT_SEARCH_LANGUAGES),
             Some("(\"hands-only\" OR \"hands-on\"*)".to_string())
         );
     }
+
+    #[test]
+    fn sanitize_fts5_query_applies_every_configured_language() {
+        let languages = [SearchLanguage::English, SearchLanguage::Spanish];
+        assert_eq!(
+            sanitize_fts5_query("canciones para los viajes", &languages),
+            Some("(\"canciones\" OR \"canci\"*) AND (\"viajes\" OR \"viaj\"*)".to_string())
+        );
+        assert_eq!(
+            sanitize_fts5_query("para", &DEFAULT_SEARCH_LANGUAGES),
+            Some("\"para\"".to_string())
+        );
+        assert_eq!(
+            sanitize_fts5_query("informações", &[SearchLanguage::Portuguese]),
+            Some("(\"informações\" OR \"informaç\"*)".to_string())
+        );
+    }
 }
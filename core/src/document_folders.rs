@@ -6,6 +6,7 @@ use thiserror::Error;
 
 const MARKDOWN_EXTENSION: &str = ".md";
 const RESERVED_TRASH_FOLDER: &str = ".trash";
+const RESERVED_ATTACHMENTS_FOLDER: &str = ".attachments";
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct DocumentFolderPayload {
@@ -284,7 +285,7 @@ fn collect_folders(
         let name = entry.file_name().to_string_lossy().into_owned();
 
         if file_type.is_dir() {
-            if name == RESERVED_TRASH_FOLDER {
+            if name == RESERVED_TRASH_FOLDER || name == RESERVED_ATTACHMENTS_FOLDER {
                 continue;
             }
             let child_path = join_relative_path(relative_path, &name);
@@ -337,7 +338,7 @@ fn build_folder_payload(
         let name = entry.file_name().to_string_lossy().into_owned();
 
         if file_type.is_dir() {
-            if name != RESERVED_TRASH_FOLDER {
+            if name != RESERVED_TRASH_FOLDER && name != RESERVED_ATTACHMENTS_FOLDER {
                 subfolder_count += 1;
             }
             continue;
@@ -403,7 +404,7 @@ fn collect_markdown_files(
         let name = entry.file_name().to_string_lossy().into_owned();
 
         if file_type.is_dir() {
-            if name == RESERVED_TRASH_FOLDER {
+            if name == RESERVED_TRASH_FOLDER || name == RESERVED_ATTACHMENTS_FOLDER {
                 continue;
             }
             let child_path = join_relative_path(relative_path, &name);
@@ -511,9 +512,9 @@ fn normalize_folder_name(raw_name: &str) -> Result<String, DocumentFoldersError>
             "folder name must not contain path separators".to_owned(),
         ));
     }
-    if trimmed == RESERVED_TRASH_FOLDER {
+    if trimmed == RESERVED_TRASH_FOLDER || trimmed == RESERVED_ATTACHMENTS_FOLDER {
         return Err(DocumentFoldersError::Validation(format!(
-            "folder name \"{RESERVED_TRASH_FOLDER}\" is reserved"
+            "folder name \"{trimmed}\" is reserved"
         )));
     }
 
@@ -521,12 +522,11 @@ fn normalize_folder_name(raw_name: &str) -> Result<String, DocumentFoldersError>
 }
 
 fn reject_reserved_folder_path(path: &str) -> Result<(), DocumentFoldersError> {
-    if path
-        .split('/')
-        .any(|segment| !segment.is_empty() && segment == RESERVED_TRASH_FOLDER)
-    {
+    if let Some(segment) = path.split('/').find(|segment| {
+        *segment == RESERVED_TRASH_FOLDER || *segment == RESERVED_ATTACHMENTS_FOLDER
+    }) {
         return Err(DocumentFoldersError::Validation(format!(
-            "folder path segment \"{RESERVED_TRASH_FOLDER}\" is reserved"
+            "folder path segment \"{segment}\" is reserved"
         )));
     }
     Ok(())
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs::{self, OpenOptions};
 use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
@@ -10,6 +10,11 @@ use thiserror::Error;
 const MARKDOWN_EXTENSION: &str = ".md";
 const RESERVED_TRASH_FOLDER: &str = ".trash";
 const DEFAULT_TITLE: &str = "Untitled";
+const RESERVED_FRONTMATTER_KEYS: [&str; 5] =
+    ["id", "created_at", "updated_at", "tags", "tags_locked"];
+
+/// Vault-relative folder holding files attached to documents, one subfolder per document id.
+pub const ATTACHMENTS_FOLDER: &str = ".attachments";
 
 static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
 
@@ -23,6 +28,8 @@ pub struct StoredDocument {
     pub tags_locked: bool,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -34,6 +41,8 @@ pub struct StoredDocumentListItem {
     pub tags_locked: bool,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
@@ -44,6 +53,23 @@ pub struct CreateDocumentInput {
     pub tags: Vec<String>,
     pub tags_locked: Option<bool>,
     pub id: Option<String>,
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+    /// Original creation time for imported documents. Defaults to now.
+    #[serde(default)]
+    pub created_at: Option<String>,
+    /// Original modification time for imported documents. Defaults to `created_at`.
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+/// Fields to overwrite on an existing document. `None` leaves the field unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct UpdateDocumentInput {
+    pub body: Option<String>,
+    pub properties: Option<BTreeMap<String, String>>,
+    /// Defaults to now.
+    pub updated_at: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -54,6 +80,14 @@ pub enum TagUpdateMode {
     Remove,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentSort {
+    Created,
+    Modified,
+    Title,
+}
+
 #[derive(Debug, Error)]
 pub enum DocumentStoreError {
     #[error("io error: {0}")]
@@ -73,27 +107,28 @@ struct MarkdownFrontmatter {
     updated_at: String,
     tags: Vec<String>,
     tags_locked: bool,
+    properties: BTreeMap<String, String>,
+    verbatim_properties: BTreeMap<String, VerbatimProperty>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 struct ParsedFrontmatterMetadata {
     id: Option<String>,
     created_at: Option<String>,
     updated_at: Option<String>,
     tags: Vec<String>,
     tags_locked: Option<bool>,
+    properties: BTreeMap<String, String>,
+    verbatim_properties: BTreeMap<String, VerbatimProperty>,
 }
 
-impl Default for ParsedFrontmatterMetadata {
-    fn default() -> Self {
-        Self {
-            id: None,
-            created_at: None,
-            updated_at: None,
-            tags: Vec::new(),
-            tags_locked: None,
-        }
-    }
+/// A frontmatter value the line parser can't represent, such as a block list or a flow
+/// sequence. `raw` is everything after `key:`, continuation lines included, and is written back
+/// unchanged as long as the property still holds `value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VerbatimProperty {
+    raw: String,
+    value: String,
 }
 
 #[derive(Debug, Clone)]
@@ -178,6 +213,40 @@ pub fn list_documents(
     Ok(documents)
 }
 
+/// Orders list items for `list`-style output. Without an explicit sort the newest modified
+/// documents come first; an explicit sort is ascending unless `desc` is set. Ties break on id.
+pub fn sort_documents(
+    documents: &mut [StoredDocumentListItem],
+    sort: Option<DocumentSort>,
+    desc: bool,
+) {
+    let sort_mode = sort.unwrap_or(DocumentSort::Modified);
+    let descending = desc || sort.is_none();
+
+    documents.sort_by(|left, right| {
+        let order = match sort_mode {
+            DocumentSort::Created => left
+                .created_at
+                .cmp(&right.created_at)
+                .then_with(|| left.id.cmp(&right.id)),
+            DocumentSort::Modified => left
+                .updated_at
+                .cmp(&right.updated_at)
+                .then_with(|| left.id.cmp(&right.id)),
+            DocumentSort::Title => left
+                .title
+                .cmp(&right.title)
+                .then_with(|| left.id.cmp(&right.id)),
+        };
+
+        if descending {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+}
+
 pub fn read_document(
     documents_folder: &Path,
     document_id: &str,
@@ -218,7 +287,10 @@ pub fn create_document(
     ensure_within_documents_folder(documents_folder, &folder_absolute_path)?;
     fs::create_dir_all(&folder_absolute_path)?;
 
-    let now = now_iso_string_utc();
+    let created_at = resolve_input_timestamp(input.created_at.as_deref(), "created_at")?
+        .unwrap_or_else(now_iso_string_utc);
+    let updated_at = resolve_input_timestamp(input.updated_at.as_deref(), "updated_at")?
+        .unwrap_or_else(|| created_at.clone());
     let mut document_id = if let Some(requested) = input.id.as_deref() {
         let normalized = normalize_document_id(requested)
             .ok_or_else(|| DocumentStoreError::Validation("id must not be empty".to_owned()))?;
@@ -245,10 +317,12 @@ pub fn create_document(
         let record = StoredDocumentRecord {
             metadata: MarkdownFrontmatter {
                 id: document_id.clone(),
-                created_at: now.clone(),
-                updated_at: now.clone(),
+                created_at: created_at.clone(),
+                updated_at: updated_at.clone(),
                 tags: normalize_tags(input.tags.iter().map(|tag| tag.as_str())),
                 tags_locked: input.tags_locked.unwrap_or(false),
+                properties: normalize_properties(&input.properties),
+                verbatim_properties: BTreeMap::new(),
             },
             title: next_title.clone(),
             body: input.body.clone().unwrap_or_default(),
@@ -317,6 +391,39 @@ pub fn update_document_tags(
     ))
 }
 
+pub fn update_document(
+    documents_folder: &Path,
+    document_id: &str,
+    input: &UpdateDocumentInput,
+) -> Result<StoredDocument, DocumentStoreError> {
+    let normalized_id = normalize_document_id(document_id).ok_or_else(|| {
+        DocumentStoreError::Validation("document_id must not be empty".to_owned())
+    })?;
+    let updated_at = resolve_input_timestamp(input.updated_at.as_deref(), "updated_at")?
+        .unwrap_or_else(now_iso_string_utc);
+
+    let file =
+        find_stored_markdown_file_by_id(documents_folder, &normalized_id)?.ok_or_else(|| {
+            DocumentStoreError::NotFound(format!("document \"{normalized_id}\" was not found"))
+        })?;
+
+    let mut read_result =
+        read_stored_document_from_file(documents_folder, &file, Some(&normalized_id))?;
+    if let Some(body) = input.body.as_ref() {
+        read_result.record.body = body.clone();
+    }
+    if let Some(properties) = input.properties.as_ref() {
+        read_result.record.metadata.properties = normalize_properties(properties);
+    }
+    read_result.record.metadata.updated_at = updated_at;
+
+    write_stored_document_to_file(&file.absolute_path, &read_result.record)?;
+    Ok(map_stored_record_to_document(
+        &read_result.record,
+        &file.relative_folder_path,
+    ))
+}
+
 pub fn find_document_by_id(
     documents_folder: &Path,
     document_id: &str,
@@ -375,7 +482,8 @@ pub fn delete_document(
         let mut counter: usize = 1;
         loop {
             let file_stem = file.name.trim_end_matches(MARKDOWN_EXTENSION);
-            let candidate = trash_absolute_path.join(format!("{file_stem} ({}){MARKDOWN_EXTENSION}", counter));
+            let candidate =
+                trash_absolute_path.join(format!("{file_stem} ({}){MARKDOWN_EXTENSION}", counter));
             if !candidate.exists() {
                 break candidate;
             }
@@ -390,6 +498,117 @@ pub fn delete_document(
     Ok(document)
 }
 
+/// Copies `bytes` into the document's attachment folder and returns the vault-relative path.
+pub fn write_document_attachment(
+    documents_folder: &Path,
+    document_id: &str,
+    file_name: &str,
+    bytes: &[u8],
+) -> Result<String, DocumentStoreError> {
+    let normalized_id = normalize_document_id(document_id).ok_or_else(|| {
+        DocumentStoreError::Validation("document_id must not be empty".to_owned())
+    })?;
+    let id_segment = sanitize_title_for_file_name(Some(&normalized_id));
+    let attachment_folder = documents_folder.join(ATTACHMENTS_FOLDER).join(&id_segment);
+    ensure_within_documents_folder(documents_folder, &attachment_folder)?;
+    fs::create_dir_all(&attachment_folder)?;
+
+    let sanitized_name = sanitize_title_for_file_name(Some(file_name));
+    let (stem, extension) = match sanitized_name.rfind('.') {
+        Some(index) if index > 0 => (&sanitized_name[..index], &sanitized_name[index..]),
+        _ => (sanitized_name.as_str(), ""),
+    };
+
+    let mut candidate = sanitized_name.clone();
+    let mut counter: usize = 2;
+    loop {
+        let path = attachment_folder.join(&candidate);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(bytes)?;
+                return Ok(format!("{ATTACHMENTS_FOLDER}/{id_segment}/{candidate}"));
+            }
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                candidate = format!("{stem} ({counter}){extension}");
+                counter += 1;
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+/// Removes the document's attachment folder and everything in it. A document without
+/// attachments is left as is.
+pub fn delete_document_attachments(
+    documents_folder: &Path,
+    document_id: &str,
+) -> Result<(), DocumentStoreError> {
+    let normalized_id = normalize_document_id(document_id).ok_or_else(|| {
+        DocumentStoreError::Validation("document_id must not be empty".to_owned())
+    })?;
+    let attachment_folder = documents_folder
+        .join(ATTACHMENTS_FOLDER)
+        .join(sanitize_title_for_file_name(Some(&normalized_id)));
+    ensure_within_documents_folder(documents_folder, &attachment_folder)?;
+    match fs::remove_dir_all(&attachment_folder) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// File name the store uses on disk for a document with this title.
+pub fn document_file_name(title: &str) -> String {
+    format!(
+        "{}{MARKDOWN_EXTENSION}",
+        sanitize_title_for_file_name(Some(title))
+    )
+}
+
+/// Renders a document exactly as the store writes it to disk. With `include_metadata` off, the
+/// tentacle-specific keys (`id`, `created_at`, `updated_at`, `tags_locked`) are left out and the
+/// frontmatter only keeps tags and properties, or is omitted when both are empty.
+pub fn render_document_markdown(document: &StoredDocument, include_metadata: bool) -> String {
+    let record = StoredDocumentRecord {
+        metadata: MarkdownFrontmatter {
+            id: document.id.clone(),
+            created_at: document.created_at.clone(),
+            updated_at: document.updated_at.clone(),
+            tags: document.tags.clone(),
+            tags_locked: document.tags_locked,
+            properties: document.properties.clone(),
+            verbatim_properties: BTreeMap::new(),
+        },
+        title: document.title.clone(),
+        body: document.body.clone(),
+    };
+    let markdown = build_markdown_file(&record);
+    if include_metadata {
+        return markdown;
+    }
+
+    let body = markdown
+        .strip_prefix(&serialize_frontmatter(&record.metadata))
+        .unwrap_or(&markdown);
+    let tags = normalize_tags(document.tags.iter().map(|tag| tag.as_str()));
+    let properties = normalize_properties(&document.properties);
+    if tags.is_empty() && properties.is_empty() {
+        return body.to_owned();
+    }
+
+    let mut lines = vec!["---".to_owned()];
+    if !tags.is_empty() {
+        let tags = serde_json::to_string(&tags).unwrap_or_else(|_| "[]".to_owned());
+        lines.push(format!("tags: {tags}"));
+    }
+    for (key, value) in properties {
+        lines.push(format!("{key}: {}", format_yaml_property_value(&value)));
+    }
+    lines.push("---".to_owned());
+    lines.push(String::new());
+    format!("{}{body}", lines.join("\n"))
+}
+
 fn ensure_documents_folder_exists(documents_folder: &Path) -> Result<(), DocumentStoreError> {
     fs::create_dir_all(documents_folder)?;
     if !documents_folder.is_dir() {
@@ -413,6 +632,7 @@ fn map_stored_record_to_document(
         tags_locked: record.metadata.tags_locked,
         created_at: record.metadata.created_at.clone(),
         updated_at: record.metadata.updated_at.clone(),
+        properties: record.metadata.properties.clone(),
     }
 }
 
@@ -428,6 +648,7 @@ fn map_stored_record_to_list_item(
         tags_locked: record.metadata.tags_locked,
         created_at: record.metadata.created_at.clone(),
         updated_at: record.metadata.updated_at.clone(),
+        properties: record.metadata.properties.clone(),
     }
 }
 
@@ -462,7 +683,7 @@ fn collect_markdown_files(
         let name = entry.file_name().to_string_lossy().into_owned();
 
         if file_type.is_dir() {
-            if name == RESERVED_TRASH_FOLDER {
+            if name == RESERVED_TRASH_FOLDER || name == ATTACHMENTS_FOLDER {
                 continue;
             }
 
@@ -599,6 +820,8 @@ fn read_stored_document_from_file(
         updated_at,
         tags: normalize_tags(parsed_metadata.tags.iter().map(|tag| tag.as_str())),
         tags_locked: parsed_metadata.tags_locked.unwrap_or(false),
+        properties: normalize_properties(&parsed_metadata.properties),
+        verbatim_properties: parsed_metadata.verbatim_properties,
     };
 
     let record = StoredDocumentRecord {
@@ -613,6 +836,24 @@ fn read_stored_document_from_file(
     })
 }
 
+fn resolve_input_timestamp(
+    value: Option<&str>,
+    field: &str,
+) -> Result<Option<String>, DocumentStoreError> {
+    let Some(raw) = value else {
+        return Ok(None);
+    };
+
+    let normalized = raw.trim();
+    if !is_valid_iso8601_like_timestamp(normalized) {
+        return Err(DocumentStoreError::Validation(format!(
+            "{field} must be an ISO 8601 timestamp, got \"{normalized}\""
+        )));
+    }
+
+    Ok(Some(normalized.to_owned()))
+}
+
 fn resolve_timestamp_value(value: Option<&str>, fallback: &str) -> (String, bool) {
     let Some(raw) = value else {
         return (fallback.to_owned(), true);
@@ -645,13 +886,22 @@ fn parse_frontmatter(content: &str) -> (ParsedFrontmatterMetadata, String) {
     let markdown = normalized[end_index + 5..].to_owned();
 
     let mut metadata = ParsedFrontmatterMetadata::default();
-    for line in raw_frontmatter.lines() {
+    let lines: Vec<&str> = raw_frontmatter.lines().collect();
+    let mut index = 0usize;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
         let Some(separator_index) = line.find(':') else {
             continue;
         };
 
         let key = line[..separator_index].trim();
         let raw_value = line[separator_index + 1..].trim();
+        let continuation_start = index;
+        while index < lines.len() && is_frontmatter_continuation_line(lines[index]) {
+            index += 1;
+        }
+        let continuation = &lines[continuation_start..index];
         let unquoted_value = if raw_value == "null" {
             None
         } else {
@@ -680,13 +930,76 @@ fn parse_frontmatter(content: &str) -> (ParsedFrontmatterMetadata, String) {
             "tags_locked" => {
                 metadata.tags_locked = Some(matches!(unquoted_value.as_deref(), Some("true")));
             }
-            _ => {}
+            _ if !continuation.is_empty() || needs_verbatim_round_trip(raw_value) => {
+                let mut raw = line[separator_index + 1..].to_owned();
+                for continuation_line in continuation {
+                    raw.push('\n');
+                    raw.push_str(continuation_line);
+                }
+                let value = verbatim_property_value(raw_value, continuation);
+                metadata.properties.insert(key.to_owned(), value.clone());
+                metadata
+                    .verbatim_properties
+                    .insert(key.to_owned(), VerbatimProperty { raw, value });
+            }
+            _ => {
+                if let Some(value) = unquoted_value {
+                    metadata.properties.insert(key.to_owned(), value);
+                }
+            }
         }
     }
 
     (metadata, markdown)
 }
 
+/// Indented lines and block list items belong to the key above them.
+fn is_frontmatter_continuation_line(line: &str) -> bool {
+    line.starts_with([' ', '\t']) || line == "-" || line.starts_with("- ")
+}
+
+/// Flow collections, single-quoted strings, block scalars, anchors, aliases and tags would lose
+/// their meaning if rewritten as a double-quoted string.
+fn needs_verbatim_round_trip(raw_value: &str) -> bool {
+    raw_value.starts_with(['[', '{', '\'', '|', '>', '&', '*', '!'])
+}
+
+/// Flat string form of a verbatim value: block list items joined with `, `, anything else with
+/// its whitespace collapsed.
+fn verbatim_property_value(raw_value: &str, continuation: &[&str]) -> String {
+    if continuation.is_empty() && raw_value.len() >= 2 {
+        if let Some(quoted) = raw_value
+            .strip_prefix('\'')
+            .and_then(|rest| rest.strip_suffix('\''))
+        {
+            return quoted.replace("''", "'");
+        }
+    }
+
+    let items: Option<Vec<&str>> = continuation
+        .iter()
+        .map(|line| {
+            line.trim_start()
+                .strip_prefix('-')
+                .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                .map(str::trim)
+        })
+        .collect();
+    match items {
+        Some(items) if raw_value.is_empty() => items
+            .into_iter()
+            .map(unquote_yaml_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => collapse_whitespace(
+            &std::iter::once(raw_value)
+                .chain(continuation.iter().copied())
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+    }
+}
+
 fn parse_tags_frontmatter_value(raw_value: &str) -> Vec<String> {
     normalize_tags(parse_inline_array(raw_value).iter().map(|tag| tag.as_str()))
 }
@@ -813,7 +1126,7 @@ fn serialize_frontmatter(metadata: &MarkdownFrontmatter) -> String {
     ))
     .unwrap_or_else(|_| "[]".to_owned());
 
-    [
+    let mut lines = vec![
         "---".to_owned(),
         format!("id: \"{}\"", escape_yaml_string(&metadata.id)),
         format!(
@@ -833,10 +1146,45 @@ fn serialize_frontmatter(metadata: &MarkdownFrontmatter) -> String {
                 "false"
             }
         ),
-        "---".to_owned(),
-        String::new(),
-    ]
-    .join("\n")
+    ];
+    for (key, value) in normalize_properties(&metadata.properties) {
+        match metadata.verbatim_properties.get(&key) {
+            Some(verbatim) if verbatim.value == value => {
+                lines.push(format!("{key}:{}", verbatim.raw));
+            }
+            _ => lines.push(format!("{key}: {}", format_yaml_property_value(&value))),
+        }
+    }
+    lines.push("---".to_owned());
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Writes numbers and booleans as plain YAML scalars so they keep their type when read by
+/// other tools; everything else is a quoted string.
+fn format_yaml_property_value(value: &str) -> String {
+    if is_yaml_number_or_bool(value) {
+        value.to_owned()
+    } else {
+        format!("\"{}\"", escape_yaml_string(value))
+    }
+}
+
+/// Only canonical forms count, so values such as `007` or `1.` stay strings.
+fn is_yaml_number_or_bool(value: &str) -> bool {
+    if matches!(value, "true" | "false") {
+        return true;
+    }
+
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit());
+    is_digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(is_digits)
 }
 
 fn escape_yaml_string(value: &str) -> String {
@@ -1004,6 +1352,33 @@ fn sanitize_tag(raw_tag: &str) -> String {
         .to_lowercase()
 }
 
+/// Keeps keys as written apart from surrounding whitespace; `:` and control characters, which
+/// would break the frontmatter line, become `_`.
+fn normalize_properties(properties: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut normalized = BTreeMap::new();
+    for (raw_key, raw_value) in properties {
+        let key = raw_key.trim().replace(
+            |character: char| character == ':' || character.is_control(),
+            "_",
+        );
+        if key.is_empty() {
+            continue;
+        }
+        let key = if RESERVED_FRONTMATTER_KEYS.contains(&key.as_str()) {
+            format!("property_{key}")
+        } else {
+            key
+        };
+
+        let value = collapse_whitespace(&normalize_line_endings(raw_value).replace('\n', " "));
+        if value.is_empty() {
+            continue;
+        }
+        normalized.entry(key).or_insert(value);
+    }
+    normalized
+}
+
 fn collapse_whitespace(value: &str) -> String {
     value.split_whitespace().collect::<Vec<_>>().join(" ")
 }
@@ -1030,7 +1405,7 @@ fn generate_unique_document_id(used_ids: &HashSet<String>) -> String {
     }
 }
 
-fn generate_document_id() -> String {
+pub fn generate_document_id() -> String {
     let duration = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default();
@@ -1038,7 +1413,7 @@ fn generate_document_id() -> String {
     format!("{:x}{:x}", duration.as_nanos(), counter)
 }
 
-fn now_iso_string_utc() -> String {
+pub(crate) fn now_iso_string_utc() -> String {
     let duration = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default();
@@ -1240,12 +1615,12 @@ fn normalize_relative_path(raw_path: &str) -> Result<String, DocumentStoreError>
 }
 
 fn reject_reserved_folder_path(path: &str) -> Result<(), DocumentStoreError> {
-    if path
+    if let Some(segment) = path
         .split('/')
-        .any(|segment| !segment.is_empty() && segment == RESERVED_TRASH_FOLDER)
+        .find(|segment| *segment == RESERVED_TRASH_FOLDER || *segment == ATTACHMENTS_FOLDER)
     {
         return Err(DocumentStoreError::Validation(format!(
-            "folder path segment \"{RESERVED_TRASH_FOLDER}\" is reserved"
+            "folder path segment \"{segment}\" is reserved"
         )));
     }
     Ok(())
@@ -1414,6 +1789,7 @@ mod tests {
                 ],
                 tags_locked: Some(false),
                 id: Some("tag-doc".to_owned()),
+                ..CreateDocumentInput::default()
             },
         )
         .expect("create document");
@@ -1516,6 +1892,176 @@ mod tests {
         let _ = fs::remove_dir_all(temp_dir);
     }
 
+    #[test]
+    fn round_trips_properties_and_preserves_unknown_frontmatter_keys() {
+        let temp_dir = unique_temp_path("tentacle-document-store-properties");
+        fs::create_dir_all(&temp_dir).expect("create temp directory");
+
+        let created = create_document(
+            &temp_dir,
+            &CreateDocumentInput {
+                title: Some("With Properties".to_owned()),
+                body: Some("Body".to_owned()),
+                id: Some("props-doc".to_owned()),
+                properties: BTreeMap::from([
+                    ("dueDate".to_owned(), "2026-03-01".to_owned()),
+                    (" Review by ".to_owned(), "me".to_owned()),
+                    ("tags".to_owned(), "shadowed".to_owned()),
+                    ("Note".to_owned(), "line one\nline \"two\"".to_owned()),
+                ]),
+                ..CreateDocumentInput::default()
+            },
+        )
+        .expect("create document");
+        assert_eq!(
+            created.properties,
+            BTreeMap::from([
+                ("Note".to_owned(), "line one line \"two\"".to_owned()),
+                ("Review by".to_owned(), "me".to_owned()),
+                ("dueDate".to_owned(), "2026-03-01".to_owned()),
+                ("property_tags".to_owned(), "shadowed".to_owned()),
+            ])
+        );
+        assert!(created.tags.is_empty());
+
+        let file_path = temp_dir.join("With Properties.md");
+        let raw = fs::read_to_string(&file_path).expect("read created file");
+        assert!(raw.contains("dueDate: \"2026-03-01\"\n"));
+
+        let edited = raw.replace(
+            "tags_locked: false\n",
+            "tags_locked: false\nsource: https://example.com/a\npriority: 3\ndraft: true\nratio: -0.5\nzip: \"007\"\n",
+        );
+        fs::write(&file_path, edited).expect("write hand-edited file");
+        let read = read_document(&temp_dir, "props-doc").expect("read document");
+        assert_eq!(
+            read.properties.get("source").map(String::as_str),
+            Some("https://example.com/a")
+        );
+
+        update_document_tags(
+            &temp_dir,
+            "props-doc",
+            &["kept".to_owned()],
+            TagUpdateMode::Add,
+        )
+        .expect("update tags");
+        let rewritten = fs::read_to_string(&file_path).expect("read rewritten file");
+        assert!(rewritten.contains("source: \"https://example.com/a\""));
+        assert!(rewritten.contains("priority: 3\n"));
+        assert!(rewritten.contains("draft: true\n"));
+        assert!(rewritten.contains("ratio: -0.5\n"));
+        assert!(rewritten.contains("zip: \"007\"\n"));
+
+        let _ = fs::remove_dir_all(temp_dir);
+    }
+
+    #[test]
+    fn keeps_frontmatter_values_the_parser_cannot_represent() {
+        let temp_dir = unique_temp_path("tentacle-document-store-verbatim");
+        fs::create_dir_all(&temp_dir).expect("create temp directory");
+        let file_path = temp_dir.join("Aliased.md");
+        fs::write(
+            &file_path,
+            "---\nid: \"aliased\"\ncreated_at: \"2026-02-13T00:00:00.000Z\"\nupdated_at: \"2026-02-13T00:00:00.000Z\"\ntags: []\ntags_locked: false\naliases:\n  - First name\n  - \"Second\"\nmeta:\n  author: Ana\nrelated: [a, b]\nquote: 'it''s'\n---\n\n# Aliased\n\nBody\n",
+        )
+        .expect("write document");
+
+        let read = read_document(&temp_dir, "aliased").expect("read document");
+        assert_eq!(
+            read.properties.get("aliases").map(String::as_str),
+            Some("First name, Second")
+        );
+        assert_eq!(
+            read.properties.get("meta").map(String::as_str),
+            Some("author: Ana")
+        );
+        assert_eq!(read.properties.get("author"), None);
+        assert_eq!(
+            read.properties.get("quote").map(String::as_str),
+            Some("it's")
+        );
+
+        update_document_tags(
+            &temp_dir,
+            "aliased",
+            &["kept".to_owned()],
+            TagUpdateMode::Add,
+        )
+        .expect("update tags");
+        let rewritten = fs::read_to_string(&file_path).expect("read rewritten file");
+        assert!(rewritten.contains("aliases:\n  - First name\n  - \"Second\"\n"));
+        assert!(rewritten.contains("meta:\n  author: Ana\n"));
+        assert!(rewritten.contains("related: [a, b]\n"));
+        assert!(rewritten.contains("quote: 'it''s'\n"));
+
+        let mut properties = read.properties.clone();
+        properties.insert("related".to_owned(), "c".to_owned());
+        update_document(
+            &temp_dir,
+            "aliased",
+            &UpdateDocumentInput {
+                properties: Some(properties),
+                ..UpdateDocumentInput::default()
+            },
+        )
+        .expect("update properties");
+        let rewritten = fs::read_to_string(&file_path).expect("read rewritten file");
+        assert!(rewritten.contains("aliases:\n  - First name\n"));
+        assert!(rewritten.contains("related: \"c\"\n"));
+
+        let _ = fs::remove_dir_all(temp_dir);
+    }
+
+    #[test]
+    fn update_document_replaces_body_properties_and_timestamp() {
+        let temp_dir = unique_temp_path("tentacle-document-store-update");
+        create_document(
+            &temp_dir,
+            &CreateDocumentInput {
+                title: Some("Transcript".to_owned()),
+                body: Some("old body".to_owned()),
+                tags: vec!["keep".to_owned()],
+                id: Some("update-doc".to_owned()),
+                ..CreateDocumentInput::default()
+            },
+        )
+        .expect("create document");
+
+        let updated = update_document(
+            &temp_dir,
+            "update-doc",
+            &UpdateDocumentInput {
+                body: Some("new body".to_owned()),
+                properties: Some(BTreeMap::from([(
+                    "content_hash".to_owned(),
+                    "abc".to_owned(),
+                )])),
+                updated_at: Some("2026-03-02T10:00:00Z".to_owned()),
+            },
+        )
+        .expect("update document");
+        assert_eq!(updated.body, "new body");
+        assert_eq!(updated.tags, vec!["keep".to_owned()]);
+        assert_eq!(updated.updated_at, "2026-03-02T10:00:00Z");
+        assert_eq!(
+            updated.properties.get("content_hash").map(String::as_str),
+            Some("abc")
+        );
+
+        let invalid = update_document(
+            &temp_dir,
+            "update-doc",
+            &UpdateDocumentInput {
+                updated_at: Some("yesterday".to_owned()),
+                ..UpdateDocumentInput::default()
+            },
+        );
+        assert!(matches!(invalid, Err(DocumentStoreError::Validation(_))));
+
+        let _ = fs::remove_dir_all(temp_dir);
+    }
+
     #[test]
     fn finds_document_by_prefix_match() {
         let temp_dir = unique_temp_path("tentacle-document-store-prefix");
@@ -1531,6 +2077,7 @@ mod tests {
                 tags: vec![],
                 tags_locked: Some(false),
                 id: Some("abc123456".to_owned()),
+                ..CreateDocumentInput::default()
             },
         )
         .expect("create first document");
@@ -1544,6 +2091,7 @@ mod tests {
                 tags: vec![],
                 tags_locked: Some(false),
                 id: Some("abc789def".to_owned()),
+                ..CreateDocumentInput::default()
             },
         )
         .expect("create second document");
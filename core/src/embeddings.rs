@@ -1,6 +1,6 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
 
 use hf_hub::api::sync::{Api as HfApi, ApiError as HfApiError, ApiRepo as HfApiRepo};
@@ -18,16 +18,99 @@ use tokenizers::{Tokenizer, TruncationDirection};
 use crate::document_cache::{
     CachedDocumentChunkEmbeddingPayload, CachedDocumentEmbeddingMetadataPayload,
     CachedDocumentEmbeddingPayload, CachedDocumentEmbeddingSyncBatchPayload, DocumentCacheError,
-    DocumentCacheStore, HybridSearchHitPayload, EMBEDDING_VECTOR_DIMENSIONS,
+    DocumentCacheStore, ExplainedHybridSearchPayload, HybridSearchHitPayload,
+    EMBEDDING_VECTOR_DIMENSIONS,
 };
+use crate::models::{installed_model_directory, read_model_manifest};
+use crate::query::{parse_search_query, SearchQuery};
 use crate::text_processing::{
-    build_document_embedding_source_text, chunk_document_text, format_query_for_embedding,
-    DocumentChunk,
+    build_document_embedding_source_text, chunk_markdown_document, estimate_token_count,
+    format_passage_for_embedding, format_query_for_embedding, tiptap_json_to_markdown,
+    ChunkingOptions, DocumentChunk,
 };
+use crate::vault_settings::{load_vault_settings, VaultSettingsError, MIN_CHUNK_TOKENS};
 
 const HF_EMBEDDING_REPO_ID: &str = "onnx-community/all-MiniLM-L6-v2-ONNX";
+/// Embedding model of vaults that do not configure `VaultSettings.embedding_model`.
 pub const LOCAL_EMBEDDING_MODEL_ID: &str = HF_EMBEDDING_REPO_ID;
 pub const LOCAL_EMBEDDING_DIMENSIONS: usize = EMBEDDING_VECTOR_DIMENSIONS;
+/// ONNX files tried first, in order, in a model repository or directory.
+const PREFERRED_MODEL_FILES: [&str; 4] = [
+    "onnx/model_quantized.onnx",
+    "onnx/model.onnx",
+    "model_quantized.onnx",
+    "model.onnx",
+];
+const TOKENIZER_FILES: [&str; 2] = ["tokenizer.json", "onnx/tokenizer.json"];
+const DIMENSION_PROBE_TEXT: &str = "dimension probe";
+/// ONNX export of `intfloat/multilingual-e5-small`: 384 dimensions, trained on about 100
+/// languages.
+pub const MULTILINGUAL_EMBEDDING_MODEL_ID: &str = "Xenova/multilingual-e5-small";
+/// Short names accepted in place of a model id for `VaultSettings.embedding_model`.
+pub const EMBEDDING_MODEL_PRESETS: [(&str, &str); 2] = [
+    ("default", LOCAL_EMBEDDING_MODEL_ID),
+    ("multilingual", MULTILINGUAL_EMBEDDING_MODEL_ID),
+];
+
+/// Model id of a preset name such as `multilingual`.
+pub fn resolve_embedding_model_preset(name: &str) -> Option<&'static str> {
+    EMBEDDING_MODEL_PRESETS
+        .iter()
+        .find(|(preset, _)| preset.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, model_id)| *model_id)
+}
+
+/// How token states are reduced to one vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingPooling {
+    LastToken,
+    /// Average over the attended tokens, as most sentence-transformers models are trained.
+    Mean,
+    /// State of the first (`[CLS]`) token, as bge models are trained.
+    Cls,
+}
+
+/// Input instructions and pooling a model was trained with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddingModelProfile {
+    pub query_prefix: &'static str,
+    pub passage_prefix: &'static str,
+    pub pooling: EmbeddingPooling,
+}
+
+impl EmbeddingModelProfile {
+    const PLAIN: Self = Self {
+        query_prefix: "",
+        passage_prefix: "",
+        pooling: EmbeddingPooling::Mean,
+    };
+    const E5: Self = Self {
+        query_prefix: "query: ",
+        passage_prefix: "passage: ",
+        pooling: EmbeddingPooling::Mean,
+    };
+
+    /// Profile of `model_id`, recognized by the repository or directory name: e5 models
+    /// (`multilingual-e5-small`, `e5-base-v2`, ...) take `query: ` / `passage: ` prefixes
+    /// and mean pooling; other models embed text as-is with mean pooling. A model's
+    /// `1_Pooling/config.json` overrides the pooling when it ships one.
+    pub fn for_model(model_id: &str) -> Self {
+        let name = model_id
+            .trim_end_matches(['/', '\\'])
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(model_id)
+            .to_ascii_lowercase();
+        if name
+            .split(|character: char| !character.is_ascii_alphanumeric())
+            .any(|part| part == "e5")
+        {
+            Self::E5
+        } else {
+            Self::PLAIN
+        }
+    }
+}
 const MAX_SEQUENCE_LENGTH: usize = 512;
 const INPUT_IDS_NAME: &str = "input_ids";
 const ATTENTION_MASK_NAME: &str = "attention_mask";
@@ -38,9 +121,17 @@ const USE_CACHE_BRANCH_NAME: &str = "use_cache_branch";
 const CACHE_POSITION_NAME: &str = "cache_position";
 const EMBEDDING_SYNC_WRITE_BATCH_SIZE: usize = 75;
 const EMBEDDING_INFERENCE_MICRO_BATCH_SIZE: usize = 8;
+/// Part of the chunk content hash. Bump it when stored chunk metadata (heading paths,
+/// offsets) changes without the chunk texts changing, so existing chunks are re-embedded.
+const CHUNK_PLAN_VERSION: u32 = 2;
+/// Part of both content hashes. Bump it when the vectors a model produces change without
+/// its id changing (e.g. its pooling), so stored embeddings are recomputed.
+const EMBEDDING_VECTOR_VERSION: u32 = 2;
+/// sentence-transformers pooling settings, shipped next to the ONNX file by many repos.
+const POOLING_CONFIG_FILE: &str = "1_Pooling/config.json";
 
 #[inline]
-const fn embeddings_runtime_enabled() -> bool {
+pub(crate) const fn embeddings_runtime_enabled() -> bool {
     !cfg!(all(target_os = "macos", target_arch = "x86_64"))
 }
 
@@ -139,6 +230,8 @@ pub struct EmbeddingBatchSyncResultPayload {
 pub enum EmbeddingError {
     #[error("document cache error: {0}")]
     DocumentCache(#[from] DocumentCacheError),
+    #[error("vault settings error: {0}")]
+    VaultSettings(#[from] VaultSettingsError),
     #[error("onnx runtime error: {0}")]
     OnnxRuntime(#[from] ort::Error),
     #[error("embedding input must not be empty")]
@@ -155,15 +248,14 @@ pub enum EmbeddingError {
     MissingModelOutput,
     #[error("embedding output has invalid shape: {0}")]
     InvalidOutputShape(String),
-    #[error(
-        "embedding vector length {0} does not match expected length {LOCAL_EMBEDDING_DIMENSIONS}"
-    )]
-    InvalidEmbeddingLength(usize),
+    #[error("embedding vector length {actual} does not match the model's {expected} dimensions")]
+    InvalidEmbeddingLength { actual: usize, expected: usize },
 }
 
-struct EmbeddingArtifacts {
-    tokenizer_path: PathBuf,
-    model_path: PathBuf,
+pub(crate) struct EmbeddingArtifacts {
+    pub(crate) tokenizer_path: PathBuf,
+    pub(crate) model_path: PathBuf,
+    pub(crate) pooling_config_path: Option<PathBuf>,
 }
 
 struct EmbeddingEngine {
@@ -171,9 +263,32 @@ struct EmbeddingEngine {
     session: Session,
     input_specs: Vec<ModelInputSpec>,
     output_name: String,
+    pooling: EmbeddingPooling,
+    /// Length of the model's pooled output, probed once at load.
+    dimensions: usize,
 }
 
-static EMBEDDING_ENGINE: OnceCell<Mutex<EmbeddingEngine>> = OnceCell::new();
+/// Loaded engines by model id, since vaults opened by one process may use different models.
+static EMBEDDING_ENGINES: OnceCell<Mutex<HashMap<String, Arc<Mutex<EmbeddingEngine>>>>> =
+    OnceCell::new();
+
+/// Tokenizer used to size chunks, loaded without the ONNX session because chunk plans are
+/// computed before deciding whether a document needs embedding at all.
+struct ChunkTokenizer {
+    tokenizer: Tokenizer,
+    /// Tokens the model sees per input, special tokens included.
+    sequence_window: usize,
+}
+
+static CHUNK_TOKENIZERS: OnceCell<Mutex<HashMap<String, Option<Arc<ChunkTokenizer>>>>> =
+    OnceCell::new();
+
+/// Model directory from the per-machine `model_path` setting, read from disk instead of
+/// resolving the model through hf-hub.
+static EMBEDDING_MODEL_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+/// Folder `tentacle model install` copies models into, searched for models `model_path` is
+/// not for.
+static INSTALLED_MODELS_DIRECTORY: RwLock<Option<PathBuf>> = RwLock::new(None);
 
 #[derive(Debug, Clone)]
 struct ModelInputSpec {
@@ -185,7 +300,7 @@ fn l2_normalize(mut values: Vec<f32>) -> Vec<f32> {
     let magnitude = values.iter().map(|value| value * value).sum::<f32>().sqrt();
 
     if magnitude <= f32::EPSILON {
-        return vec![0.0; LOCAL_EMBEDDING_DIMENSIONS];
+        return vec![0.0; values.len()];
     }
 
     for value in &mut values {
@@ -196,11 +311,10 @@ fn l2_normalize(mut values: Vec<f32>) -> Vec<f32> {
 }
 
 impl EmbeddingEngine {
-    fn initialize() -> Result<Self, EmbeddingError> {
-        Self::initialize_with_progress(|_| {})
-    }
-
-    fn initialize_with_progress<F>(mut report_progress: F) -> Result<Self, EmbeddingError>
+    fn initialize_with_progress<F>(
+        model_id: &str,
+        mut report_progress: F,
+    ) -> Result<Self, EmbeddingError>
     where
         F: FnMut(EmbeddingModelLoadStatePayload),
     {
@@ -209,16 +323,12 @@ impl EmbeddingEngine {
             0.05,
             "Starting embedding model initialization...",
         ));
-        let api = HfApi::new().map_err(|error| {
-            EmbeddingError::ModelArtifacts(format!("failed to create hf-hub client: {error}"))
-        })?;
-        let repo = api.model(HF_EMBEDDING_REPO_ID.to_owned());
         report_progress(EmbeddingModelLoadStatePayload::loading(
             EmbeddingModelLoadStage::ResolvingArtifacts,
             0.3,
             "Resolving model artifacts...",
         ));
-        let artifacts = resolve_artifacts(&repo)?;
+        let artifacts = resolve_model_artifacts(model_id)?;
 
         report_progress(EmbeddingModelLoadStatePayload::loading(
             EmbeddingModelLoadStage::LoadingTokenizer,
@@ -257,22 +367,61 @@ impl EmbeddingEngine {
         }
 
         let output_name = select_model_output_name(&session)?;
-        log::info!(
-            "[embeddings] Initialized ONNX model {} ({})",
-            HF_EMBEDDING_REPO_ID,
-            artifacts.model_path.display()
-        );
-
-        Ok(Self {
+        let mut engine = Self {
             tokenizer,
             session,
             input_specs,
             output_name,
-        })
+            pooling: artifacts
+                .pooling_config_path
+                .as_deref()
+                .and_then(pooling_from_config)
+                .unwrap_or(EmbeddingModelProfile::for_model(model_id).pooling),
+            dimensions: 0,
+        };
+        // Vector size is whatever the model produces, so the cache can be sized before
+        // anything is stored.
+        engine.dimensions = engine
+            .embed_texts(&[DIMENSION_PROBE_TEXT])?
+            .first()
+            .map(Vec::len)
+            .filter(|dimensions| *dimensions > 0)
+            .ok_or(EmbeddingError::MissingModelOutput)?;
+        log::info!(
+            "[embeddings] Initialized ONNX model {} ({}, {} dimensions)",
+            model_id,
+            artifacts.model_path.display(),
+            engine.dimensions
+        );
+
+        Ok(engine)
     }
 
-    fn engine() -> Result<&'static Mutex<Self>, EmbeddingError> {
-        EMBEDDING_ENGINE.get_or_try_init(|| Self::initialize().map(Mutex::new))
+    fn engine(model_id: &str) -> Result<Arc<Mutex<Self>>, EmbeddingError> {
+        Self::engine_with_progress(model_id, |_| {}).map(|(engine, _)| engine)
+    }
+
+    /// Engine for `model_id`, loaded on first use; the flag is `true` when it was already
+    /// loaded.
+    fn engine_with_progress<F>(
+        model_id: &str,
+        report_progress: F,
+    ) -> Result<(Arc<Mutex<Self>>, bool), EmbeddingError>
+    where
+        F: FnMut(EmbeddingModelLoadStatePayload),
+    {
+        let engines = EMBEDDING_ENGINES.get_or_init(|| Mutex::new(HashMap::new()));
+        let mut engines = engines.lock().map_err(|_| EmbeddingError::EnginePoisoned)?;
+        if let Some(engine) = engines.get(model_id) {
+            return Ok((Arc::clone(engine), true));
+        }
+
+        let engine = Arc::new(Mutex::new(Self::initialize_with_progress(
+            model_id,
+            report_progress,
+        )?));
+        engines.insert(model_id.to_owned(), Arc::clone(&engine));
+        Ok((engine, false))
     }
 
     fn embed_texts(&mut self, texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
@@ -408,12 +557,15 @@ impl EmbeddingEngine {
             return Err(EmbeddingError::MissingModelOutput);
         };
         let (shape, data) = output_tensor.try_extract_tensor::<f32>()?;
-        let pooled = pool_embeddings(data, shape, &attention_masks)?;
+        let pooled = pool_embeddings(data, shape, &attention_masks, self.pooling)?;
 
         let mut normalized_embeddings = Vec::with_capacity(pooled.len());
         for embedding in pooled {
-            if embedding.len() != LOCAL_EMBEDDING_DIMENSIONS {
-                return Err(EmbeddingError::InvalidEmbeddingLength(embedding.len()));
+            if self.dimensions > 0 && embedding.len() != self.dimensions {
+                return Err(EmbeddingError::InvalidEmbeddingLength {
+                    actual: embedding.len(),
+                    expected: self.dimensions,
+                });
             }
 
             normalized_embeddings.push(l2_normalize(embedding));
@@ -537,7 +689,102 @@ fn select_model_output_name(session: &Session) -> Result<String, EmbeddingError>
     Ok(outputs[0].name().to_owned())
 }
 
-fn resolve_artifacts(repo: &HfApiRepo) -> Result<EmbeddingArtifacts, EmbeddingError> {
+/// Artifacts of `model_id`: a local directory holding `tokenizer.json` and an ONNX file, or
+/// a Hugging Face repository resolved through hf-hub.
+fn resolve_model_artifacts(model_id: &str) -> Result<EmbeddingArtifacts, EmbeddingError> {
+    if let Some(directory) = embedding_model_directory(model_id) {
+        return resolve_local_artifacts(&directory);
+    }
+
+    resolve_artifacts(&hf_model_repo(model_id)?)
+}
+
+fn hf_model_repo(model_id: &str) -> Result<HfApiRepo, EmbeddingError> {
+    let api = HfApi::new().map_err(|error| {
+        EmbeddingError::ModelArtifacts(format!("failed to create hf-hub client: {error}"))
+    })?;
+    Ok(api.model(model_id.to_owned()))
+}
+
+pub(crate) fn resolve_local_artifacts(
+    directory: &Path,
+) -> Result<EmbeddingArtifacts, EmbeddingError> {
+    let tokenizer_path = local_tokenizer_path(directory)?;
+    let model_path = local_model_path(directory).ok_or_else(|| {
+        EmbeddingError::ModelArtifacts(format!("no .onnx file found in {}", directory.display()))
+    })?;
+
+    Ok(EmbeddingArtifacts {
+        tokenizer_path,
+        model_path,
+        pooling_config_path: Some(directory.join(POOLING_CONFIG_FILE))
+            .filter(|path| path.is_file()),
+    })
+}
+
+/// Pooling named by a sentence-transformers `1_Pooling/config.json`, or `None` when the file
+/// can't be read or asks for a mode the engine doesn't implement.
+fn pooling_from_config(path: &Path) -> Option<EmbeddingPooling> {
+    let config: serde_json::Value = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+    let enabled = |key: &str| config.get(key).and_then(serde_json::Value::as_bool) == Some(true);
+    if enabled("pooling_mode_cls_token") {
+        Some(EmbeddingPooling::Cls)
+    } else if enabled("pooling_mode_lasttoken") {
+        Some(EmbeddingPooling::LastToken)
+    } else if enabled("pooling_mode_mean_tokens") {
+        Some(EmbeddingPooling::Mean)
+    } else {
+        log::warn!(
+            "[embeddings] Unsupported pooling in {}; using the model profile",
+            path.display()
+        );
+        None
+    }
+}
+
+pub(crate) fn local_tokenizer_path(directory: &Path) -> Result<PathBuf, EmbeddingError> {
+    TOKENIZER_FILES
+        .iter()
+        .map(|file| directory.join(file))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            EmbeddingError::ModelArtifacts(format!(
+                "tokenizer.json was not found in {}",
+                directory.display()
+            ))
+        })
+}
+
+pub(crate) fn local_model_path(directory: &Path) -> Option<PathBuf> {
+    if let Some(path) = PREFERRED_MODEL_FILES
+        .iter()
+        .map(|file| directory.join(file))
+        .find(|path| path.is_file())
+    {
+        return Some(path);
+    }
+
+    [directory.to_path_buf(), directory.join("onnx")]
+        .iter()
+        .filter_map(|folder| std::fs::read_dir(folder).ok())
+        .flat_map(|entries| {
+            let mut files = entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_file()
+                        && path
+                            .extension()
+                            .is_some_and(|extension| extension == "onnx")
+                })
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        })
+        .next()
+}
+
+pub(crate) fn resolve_artifacts(repo: &HfApiRepo) -> Result<EmbeddingArtifacts, EmbeddingError> {
     let repo_info = repo.info().ok();
     let siblings = repo_info.as_ref().map(|info| info.siblings.as_slice());
     let tokenizer_path = resolve_tokenizer_path(repo, siblings)?;
@@ -572,9 +819,29 @@ fn resolve_artifacts(repo: &HfApiRepo) -> Result<EmbeddingArtifacts, EmbeddingEr
         }
     }
 
+    let lists_pooling_config = siblings.is_none_or(|siblings| {
+        siblings
+            .iter()
+            .any(|sibling| sibling.rfilename == POOLING_CONFIG_FILE)
+    });
+    let pooling_config_path = if lists_pooling_config {
+        repo.get(POOLING_CONFIG_FILE)
+            .map_err(|error| {
+                log::debug!(
+                    "[embeddings] Optional pooling config not found ({}): {}",
+                    POOLING_CONFIG_FILE,
+                    error
+                );
+            })
+            .ok()
+    } else {
+        None
+    };
+
     Ok(EmbeddingArtifacts {
         tokenizer_path,
         model_path,
+        pooling_config_path,
     })
 }
 
@@ -590,9 +857,8 @@ fn resolve_tokenizer_path(
         }
     }
 
-    let tokenizer_candidates = ["tokenizer.json", "onnx/tokenizer.json"];
     let mut last_error = None;
-    for candidate in tokenizer_candidates {
+    for candidate in TOKENIZER_FILES {
         match repo.get(candidate) {
             Ok(path) => return Ok(path),
             Err(error) => last_error = Some(model_artifact_error("tokenizer", candidate, error)),
@@ -607,12 +873,7 @@ fn resolve_tokenizer_path(
 }
 
 fn resolve_model_file_name(siblings: Option<&[Siblings]>) -> Result<String, EmbeddingError> {
-    let preferred = [
-        "onnx/model_quantized.onnx",
-        "onnx/model.onnx",
-        "model_quantized.onnx",
-        "model.onnx",
-    ];
+    let preferred = PREFERRED_MODEL_FILES;
 
     if let Some(siblings) = siblings {
         let files = siblings
@@ -676,7 +937,11 @@ fn model_artifact_error(kind: &str, file: &str, error: HfApiError) -> EmbeddingE
     EmbeddingError::ModelArtifacts(format!("failed to resolve {kind} artifact {file}: {error}"))
 }
 
-fn positive_dim(shape: &[i64], index: usize, label: &str) -> Result<usize, EmbeddingError> {
+pub(crate) fn positive_dim(
+    shape: &[i64],
+    index: usize,
+    label: &str,
+) -> Result<usize, EmbeddingError> {
     let value = *shape.get(index).ok_or_else(|| {
         EmbeddingError::InvalidOutputShape(format!("missing {label} dimension in shape {shape:?}"))
     })?;
@@ -705,10 +970,49 @@ fn last_active_token_index(attention_mask: &[i64], sequence_length: usize) -> us
     sequence_length.saturating_sub(1)
 }
 
+/// Reduces one row of token states (sequence × `hidden_size`) to a single vector.
+fn pool_token_states(
+    token_states: &[f32],
+    attention_mask: &[i64],
+    hidden_size: usize,
+    pooling: EmbeddingPooling,
+) -> Vec<f32> {
+    let sequence_length = token_states.len() / hidden_size;
+    match pooling {
+        EmbeddingPooling::LastToken => {
+            let start = last_active_token_index(attention_mask, sequence_length) * hidden_size;
+            token_states[start..start + hidden_size].to_vec()
+        }
+        EmbeddingPooling::Cls => token_states[..hidden_size].to_vec(),
+        EmbeddingPooling::Mean => {
+            let mut sum = vec![0.0_f32; hidden_size];
+            let mut attended = 0_usize;
+            for (token_index, state) in token_states.chunks_exact(hidden_size).enumerate() {
+                if attention_mask
+                    .get(token_index)
+                    .is_some_and(|mask| *mask > 0)
+                {
+                    for (total, value) in sum.iter_mut().zip(state) {
+                        *total += value;
+                    }
+                    attended += 1;
+                }
+            }
+            if attended > 0 {
+                for total in &mut sum {
+                    *total /= attended as f32;
+                }
+            }
+            sum
+        }
+    }
+}
+
 fn pool_embeddings(
     output_data: &[f32],
     output_shape: &[i64],
     attention_masks: &[Vec<i64>],
+    pooling: EmbeddingPooling,
 ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
     let batch_size = attention_masks.len();
     if batch_size == 0 {
@@ -743,14 +1047,12 @@ fn pool_embeddings(
                 }
                 Ok(pooled)
             } else if batch_size == 1 {
-                let token_index = last_active_token_index(&attention_masks[0], first_dim);
-                let start = token_index.checked_mul(hidden_size).ok_or_else(|| {
-                    EmbeddingError::InvalidOutputShape(format!(
-                        "shape is too large: {output_shape:?}"
-                    ))
-                })?;
-                let end = start + hidden_size;
-                Ok(vec![output_data[start..end].to_vec()])
+                Ok(vec![pool_token_states(
+                    output_data,
+                    &attention_masks[0],
+                    hidden_size,
+                    pooling,
+                )])
             } else {
                 Err(EmbeddingError::InvalidOutputShape(format!(
                     "rank-2 output first dimension {first_dim} does not match batch size {batch_size}"
@@ -788,26 +1090,15 @@ fn pool_embeddings(
                 EmbeddingError::InvalidOutputShape(format!("shape is too large: {output_shape:?}"))
             })?;
             let mut pooled = Vec::with_capacity(batch_size);
-            for (batch_index, attention_mask_row) in attention_masks.iter().enumerate() {
-                let token_index = last_active_token_index(attention_mask_row, sequence_length);
-                let row_offset = batch_index.checked_mul(row_width).ok_or_else(|| {
-                    EmbeddingError::InvalidOutputShape(format!(
-                        "shape is too large: {output_shape:?}"
-                    ))
-                })?;
-                let start = row_offset
-                    .checked_add(token_index.checked_mul(hidden_size).ok_or_else(|| {
-                        EmbeddingError::InvalidOutputShape(format!(
-                            "shape is too large: {output_shape:?}"
-                        ))
-                    })?)
-                    .ok_or_else(|| {
-                        EmbeddingError::InvalidOutputShape(format!(
-                            "shape is too large: {output_shape:?}"
-                        ))
-                    })?;
-                let end = start + hidden_size;
-                pooled.push(output_data[start..end].to_vec());
+            for (token_states, attention_mask_row) in
+                output_data.chunks_exact(row_width).zip(attention_masks)
+            {
+                pooled.push(pool_token_states(
+                    token_states,
+                    attention_mask_row,
+                    hidden_size,
+                    pooling,
+                ));
             }
 
             Ok(pooled)
@@ -824,37 +1115,132 @@ fn compute_sha256_hex(value: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
-fn compute_document_content_hash(source_text: &str) -> String {
-    compute_sha256_hex(&format!("{source_text}\0{LOCAL_EMBEDDING_MODEL_ID}"))
+fn compute_document_content_hash(source_text: &str, model_id: &str) -> String {
+    compute_sha256_hex(&format!(
+        "{source_text}\0{model_id}\0{EMBEDDING_VECTOR_VERSION}"
+    ))
 }
 
-fn compute_chunk_content_hash(chunk_texts: &[String]) -> String {
+fn compute_chunk_content_hash(chunk_texts: &[String], model_id: &str) -> String {
     let joined = chunk_texts.join("\0");
-    compute_sha256_hex(&format!("chunks\0{joined}\0{LOCAL_EMBEDDING_MODEL_ID}"))
+    compute_sha256_hex(&format!(
+        "chunks\0{CHUNK_PLAN_VERSION}\0{EMBEDDING_VECTOR_VERSION}\0{joined}\0{model_id}"
+    ))
+}
+
+/// Returns the chunk tokenizer of `model_id`, or `None` when it cannot be resolved (e.g.
+/// offline before the model was ever downloaded); chunk sizes are then estimated from
+/// character counts.
+fn chunk_tokenizer(model_id: &str) -> Option<Arc<ChunkTokenizer>> {
+    if !embeddings_runtime_enabled() {
+        return None;
+    }
+
+    let tokenizers = CHUNK_TOKENIZERS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut tokenizers = tokenizers.lock().ok()?;
+    tokenizers
+        .entry(model_id.to_owned())
+        .or_insert_with(|| {
+            load_chunk_tokenizer(model_id)
+                .inspect_err(|error| {
+                    log::warn!("[embeddings] Estimating chunk token counts: {error}");
+                })
+                .ok()
+                .map(Arc::new)
+        })
+        .clone()
 }
 
-fn compute_chunk_plan(title: &str, body: &str) -> (Vec<DocumentChunk>, String) {
-    let plain_body = crate::text_processing::extract_plain_text_from_tiptap_or_raw(body);
-    let chunks = chunk_document_text(title, &plain_body);
+fn load_chunk_tokenizer(model_id: &str) -> Result<ChunkTokenizer, EmbeddingError> {
+    let tokenizer_path = if let Some(directory) = embedding_model_directory(model_id) {
+        local_tokenizer_path(&directory)?
+    } else {
+        resolve_tokenizer_path(&hf_model_repo(model_id)?, None)?
+    };
+    let mut tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|error| {
+        EmbeddingError::ModelArtifacts(format!(
+            "failed to load tokenizer from {}: {error}",
+            tokenizer_path.display()
+        ))
+    })?;
+
+    // A tokenizer that truncates on its own caps the model input below MAX_SEQUENCE_LENGTH,
+    // and would also cap the counts below.
+    let sequence_window = tokenizer
+        .get_truncation()
+        .map(|truncation| truncation.max_length.min(MAX_SEQUENCE_LENGTH))
+        .unwrap_or(MAX_SEQUENCE_LENGTH);
+    tokenizer
+        .with_truncation(None)
+        .map_err(|error| EmbeddingError::Tokenization(error.to_string()))?;
+    tokenizer.with_padding(None);
+
+    Ok(ChunkTokenizer {
+        tokenizer,
+        sequence_window,
+    })
+}
+
+fn count_chunk_tokens(tokenizer: Option<&ChunkTokenizer>, text: &str) -> usize {
+    tokenizer
+        .and_then(|tokenizer| tokenizer.tokenizer.encode(text, false).ok())
+        .map(|encoding| encoding.len())
+        .unwrap_or_else(|| estimate_token_count(text))
+}
+
+fn compute_chunk_plan(
+    title: &str,
+    body: &str,
+    settings: &EmbeddingSyncSettings,
+) -> (Vec<DocumentChunk>, String) {
+    let markdown_body = tiptap_json_to_markdown(body).unwrap_or_else(|| body.to_owned());
+    let tokenizer = chunk_tokenizer(&settings.model_id);
+    let count_tokens = |text: &str| count_chunk_tokens(tokenizer.as_deref(), text);
+    let chunking = &settings.chunking;
+
+    // Every chunk is embedded as "prefix + title + body" with two special tokens, so cap the
+    // body budget to what still fits the model input instead of letting inference truncate it.
+    let sequence_window = tokenizer
+        .as_ref()
+        .map(|tokenizer| tokenizer.sequence_window)
+        .unwrap_or(MAX_SEQUENCE_LENGTH);
+    let passage_prefix = EmbeddingModelProfile::for_model(&settings.model_id).passage_prefix;
+    let available_tokens = sequence_window
+        .saturating_sub(
+            count_tokens(&format_passage_for_embedding(title.trim(), passage_prefix)) + 2,
+        )
+        .max(MIN_CHUNK_TOKENS);
+    let options = ChunkingOptions {
+        chunk_tokens: chunking.chunk_tokens.min(available_tokens),
+        overlap_tokens: chunking.overlap_tokens,
+    };
+
+    let chunks = chunk_markdown_document(title, &markdown_body, &options, &count_tokens);
     let chunk_texts = chunks
         .iter()
         .map(|chunk| chunk.text.clone())
         .collect::<Vec<_>>();
-    let chunk_content_hash = compute_chunk_content_hash(&chunk_texts);
+    let chunk_content_hash = compute_chunk_content_hash(&chunk_texts, &settings.model_id);
     (chunks, chunk_content_hash)
 }
 
 fn build_metadata_lookup(
     metadata: Vec<CachedDocumentEmbeddingMetadataPayload>,
+    model_id: &str,
 ) -> HashMap<String, CachedDocumentEmbeddingMetadataPayload> {
     metadata
         .into_iter()
-        .filter(|item| item.model == LOCAL_EMBEDDING_MODEL_ID)
+        .filter(|item| item.model == model_id)
         .map(|item| (item.document_id.clone(), item))
         .collect()
 }
 
+/// Embeds `texts` with the default model ([`LOCAL_EMBEDDING_MODEL_ID`]).
 pub fn embed_texts_batch(texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+    embed_texts_with_model(LOCAL_EMBEDDING_MODEL_ID, texts)
+}
+
+fn embed_texts_with_model(model_id: &str, texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
     if texts.is_empty() {
         return Ok(Vec::new());
     }
@@ -863,11 +1249,143 @@ pub fn embed_texts_batch(texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbeddingError
         return Err(EmbeddingError::EmptyInput);
     }
 
-    let engine = EmbeddingEngine::engine()?;
+    let engine = EmbeddingEngine::engine(model_id)?;
     let mut engine = engine.lock().map_err(|_| EmbeddingError::EnginePoisoned)?;
     embed_texts_batch_internal(&mut engine, texts)
 }
 
+/// Vector length of `model_id`'s output, loading the model if needed.
+pub fn embedding_model_dimensions(model_id: &str) -> Result<usize, EmbeddingError> {
+    let engine = EmbeddingEngine::engine(model_id)?;
+    let engine = engine.lock().map_err(|_| EmbeddingError::EnginePoisoned)?;
+    Ok(engine.dimensions)
+}
+
+/// Sets the directory from the per-machine `model_path` setting, or clears it with `None`.
+/// Engines already loaded in this process keep the artifacts they were loaded from.
+pub fn set_embedding_model_path(directory: Option<PathBuf>) {
+    if let Ok(mut model_path) = EMBEDDING_MODEL_PATH.write() {
+        *model_path = directory;
+    }
+}
+
+/// Sets the folder installed models are looked up in (`models::models_directory`), or clears
+/// it with `None`.
+pub fn set_installed_models_directory(directory: Option<PathBuf>) {
+    if let Ok(mut models_directory) = INSTALLED_MODELS_DIRECTORY.write() {
+        *models_directory = directory;
+    }
+}
+
+/// Whether `model_id` names a local directory rather than a Hugging Face repository: an
+/// absolute path or one starting with `./` or `../`. Ids are never checked against the
+/// working directory, so a repository id stays one whatever folder the process runs in.
+pub fn is_local_model_path(model_id: &str) -> bool {
+    Path::new(model_id).is_absolute() || model_id.starts_with("./") || model_id.starts_with("../")
+}
+
+/// Local directory `model_id` is loaded from: the id itself when it is a local path, else the
+/// `model_path` directory when its install manifest is for `model_id` or it has no manifest,
+/// else the model's install folder.
+pub fn embedding_model_directory(model_id: &str) -> Option<PathBuf> {
+    if is_local_model_path(model_id) {
+        return Some(PathBuf::from(model_id));
+    }
+
+    if let Some(model_path) = EMBEDDING_MODEL_PATH.read().ok()?.clone() {
+        match read_model_manifest(&model_path) {
+            Ok(Some(manifest)) if manifest.model_id != model_id => {}
+            Ok(_) => return Some(model_path),
+            Err(error) => {
+                log::warn!(
+                    "[embeddings] Ignoring model_path {}: {}",
+                    model_path.display(),
+                    error
+                );
+            }
+        }
+    }
+    installed_model_directory_for(model_id)
+}
+
+/// Installed copy of `model_id`: the `model_path` directory when its manifest is for
+/// `model_id`, else the model's folder in the installed models directory when it has one. A
+/// `model_path` without a manifest is never returned, so models other than the embedder (the
+/// reranker) don't load the embedder's hand-placed files.
+pub(crate) fn installed_model_directory_for(model_id: &str) -> Option<PathBuf> {
+    let model_path = EMBEDDING_MODEL_PATH.read().ok()?.clone();
+    let installed_copy = INSTALLED_MODELS_DIRECTORY
+        .read()
+        .ok()?
+        .as_deref()
+        .and_then(|models_directory| installed_model_directory(models_directory, model_id).ok());
+    model_path
+        .into_iter()
+        .chain(installed_copy)
+        .find(|directory| {
+            matches!(
+                read_model_manifest(directory),
+                Ok(Some(manifest)) if manifest.model_id == model_id
+            )
+        })
+}
+
+/// Embedding model the vault is configured to use.
+pub fn configured_embedding_model(documents_folder: &Path) -> Result<String, EmbeddingError> {
+    Ok(load_vault_settings(documents_folder)?
+        .embedding_model
+        .unwrap_or_else(|| LOCAL_EMBEDDING_MODEL_ID.to_owned()))
+}
+
+/// Inputs of an embedding sync that come from the vault settings.
+#[derive(Debug, Clone)]
+struct EmbeddingSyncSettings {
+    model_id: String,
+    chunking: ChunkingOptions,
+}
+
+impl EmbeddingSyncSettings {
+    fn load(documents_folder: &Path) -> Result<Self, EmbeddingError> {
+        let settings = load_vault_settings(documents_folder)?;
+        Ok(Self {
+            model_id: settings
+                .embedding_model
+                .unwrap_or_else(|| LOCAL_EMBEDDING_MODEL_ID.to_owned()),
+            chunking: settings.chunking,
+        })
+    }
+}
+
+/// Switches the cache to `model_id` when its embeddings were built with another model: the
+/// vector tables are recreated at the new model's dimension, so every document re-embeds.
+/// A model that fails to load leaves the cache untouched, and its documents fail to embed.
+fn ensure_cache_embedding_model(
+    store: &mut DocumentCacheStore,
+    model_id: &str,
+) -> Result<(), EmbeddingError> {
+    let cached_model = store
+        .embedding_model()?
+        .unwrap_or_else(|| LOCAL_EMBEDDING_MODEL_ID.to_owned());
+    if cached_model == model_id {
+        return Ok(());
+    }
+
+    let dimensions = match embedding_model_dimensions(model_id) {
+        Ok(dimensions) => dimensions,
+        Err(error) => {
+            log::warn!(
+                "[embedding-sync] Keeping embeddings from {cached_model}; {model_id} failed to load: {error}"
+            );
+            return Ok(());
+        }
+    };
+    store.reset_embeddings(model_id, dimensions)?;
+    log::info!(
+        "[embedding-sync] Switched cache from {cached_model} to {model_id} ({dimensions} dimensions); re-embedding all documents"
+    );
+    Ok(())
+}
+
 fn embed_texts_batch_internal(
     engine: &mut EmbeddingEngine,
     texts: &[&str],
@@ -881,21 +1399,37 @@ fn embed_texts_batch_internal(
     Ok(embeddings)
 }
 
-fn embed_query_text(query: &str) -> Result<Vec<f32>, EmbeddingError> {
-    let formatted = format_query_for_embedding(query);
-    embed_texts_batch(&[formatted.as_str()])?
+fn embed_query_text(model_id: &str, query: &str) -> Result<Vec<f32>, EmbeddingError> {
+    let profile = EmbeddingModelProfile::for_model(model_id);
+    let formatted = format_query_for_embedding(query, profile.query_prefix);
+    embed_texts_with_model(model_id, &[formatted.as_str()])?
         .into_iter()
         .next()
         .ok_or(EmbeddingError::EmptyInput)
 }
 
-fn embed_document_text(text: &str) -> Result<Vec<f32>, EmbeddingError> {
-    embed_texts_batch(&[text])?
+fn embed_document_text(model_id: &str, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+    embed_passages(model_id, &[text])?
         .into_iter()
         .next()
         .ok_or(EmbeddingError::EmptyInput)
 }
 
+/// Embeds document or chunk texts after the model's passage prefix.
+fn embed_passages(model_id: &str, texts: &[&str]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+    let prefix = EmbeddingModelProfile::for_model(model_id).passage_prefix;
+    if prefix.is_empty() {
+        return embed_texts_with_model(model_id, texts);
+    }
+
+    let formatted = texts
+        .iter()
+        .map(|text| format_passage_for_embedding(text, prefix))
+        .collect::<Vec<_>>();
+    let formatted_refs = formatted.iter().map(String::as_str).collect::<Vec<_>>();
+    embed_texts_with_model(model_id, &formatted_refs)
+}
+
 #[derive(Debug)]
 struct PreparedDocumentEmbeddingWrite {
     document_id: String,
@@ -924,9 +1458,11 @@ fn plan_document_embedding_sync_for_document(
     document: &EmbeddingSyncDocumentPayload,
     metadata_lookup: Option<&HashMap<String, CachedDocumentEmbeddingMetadataPayload>>,
     chunk_hash_lookup: Option<&HashMap<String, String>>,
+    settings: &EmbeddingSyncSettings,
 ) -> Result<DocumentEmbeddingSyncPlan, EmbeddingError> {
+    let model_id = settings.model_id.as_str();
     let source_text = build_document_embedding_source_text(&document.title, &document.body);
-    let document_content_hash = compute_document_content_hash(&source_text);
+    let document_content_hash = compute_document_content_hash(&source_text, model_id);
 
     let should_update_document_embedding = if let Some(lookup) = metadata_lookup {
         lookup
@@ -937,7 +1473,7 @@ fn plan_document_embedding_sync_for_document(
         let metadata = store
             .list_document_embedding_metadata()?
             .into_iter()
-            .find(|item| item.document_id == document.id && item.model == LOCAL_EMBEDDING_MODEL_ID);
+            .find(|item| item.document_id == document.id && item.model == model_id);
 
         metadata.as_ref().map(|item| item.content_hash.as_str())
             != Some(document_content_hash.as_str())
@@ -946,7 +1482,7 @@ fn plan_document_embedding_sync_for_document(
     let existing_chunk_hash = if let Some(lookup) = chunk_hash_lookup {
         lookup.get(&document.id).cloned()
     } else {
-        store.get_document_chunk_embedding_content_hash(&document.id, LOCAL_EMBEDDING_MODEL_ID)?
+        store.get_document_chunk_embedding_content_hash(&document.id, model_id)?
     };
 
     // Fast path: when document hash is unchanged and chunk embeddings exist for the same model,
@@ -955,7 +1491,8 @@ fn plan_document_embedding_sync_for_document(
         if !should_update_document_embedding && existing_chunk_hash.is_some() {
             (Vec::new(), String::new(), false)
         } else {
-            let (chunks, chunk_content_hash) = compute_chunk_plan(&document.title, &document.body);
+            let (chunks, chunk_content_hash) =
+                compute_chunk_plan(&document.title, &document.body, settings);
             let should_update_chunk_embeddings =
                 existing_chunk_hash.as_deref() != Some(chunk_content_hash.as_str());
             (chunks, chunk_content_hash, should_update_chunk_embeddings)
@@ -974,12 +1511,13 @@ fn plan_document_embedding_sync_for_document(
 fn prepare_document_embedding_write_from_plan(
     document: &EmbeddingSyncDocumentPayload,
     plan: DocumentEmbeddingSyncPlan,
+    model_id: &str,
 ) -> Result<PreparedDocumentEmbeddingWrite, EmbeddingError> {
     let document_embedding = if plan.should_update_document_embedding {
-        let vector = embed_document_text(&plan.source_text)?;
+        let vector = embed_document_text(model_id, &plan.source_text)?;
         Some(CachedDocumentEmbeddingPayload {
             document_id: document.id.clone(),
-            model: LOCAL_EMBEDDING_MODEL_ID.to_owned(),
+            model: model_id.to_owned(),
             content_hash: plan.document_content_hash.clone(),
             vector,
             updated_at: document.updated_at.clone(),
@@ -998,7 +1536,7 @@ fn prepare_document_embedding_write_from_plan(
             .iter()
             .map(|chunk| chunk.text.as_str())
             .collect::<Vec<_>>();
-        let vectors = embed_texts_batch(&chunk_text_refs)?;
+        let vectors = embed_passages(model_id, &chunk_text_refs)?;
         if vectors.len() != plan.chunks.len() {
             return Err(EmbeddingError::InvalidOutputShape(format!(
                 "expected {} chunk embeddings, got {}",
@@ -1015,8 +1553,14 @@ fn prepare_document_embedding_write_from_plan(
                 document_id: document.id.clone(),
                 chunk_index: chunk.index,
                 chunk_text: chunk.text,
+                page: chunk.page,
+                heading_path: chunk.heading_path,
+                char_start: chunk.char_start,
+                char_end: chunk.char_end,
+                line_start: chunk.line_start,
+                line_end: chunk.line_end,
                 content_hash: plan.chunk_content_hash.clone(),
-                model: LOCAL_EMBEDDING_MODEL_ID.to_owned(),
+                model: model_id.to_owned(),
                 vector,
                 updated_at: document.updated_at.clone(),
             })
@@ -1052,14 +1596,16 @@ fn prepare_document_embedding_write_for_document(
     document: &EmbeddingSyncDocumentPayload,
     metadata_lookup: Option<&HashMap<String, CachedDocumentEmbeddingMetadataPayload>>,
     chunk_hash_lookup: Option<&HashMap<String, String>>,
+    settings: &EmbeddingSyncSettings,
 ) -> Result<PreparedDocumentEmbeddingWrite, EmbeddingError> {
     let plan = plan_document_embedding_sync_for_document(
         store,
         document,
         metadata_lookup,
         chunk_hash_lookup,
+        settings,
     )?;
-    prepare_document_embedding_write_from_plan(document, plan)
+    prepare_document_embedding_write_from_plan(document, plan, &settings.model_id)
 }
 
 pub fn preload_embedding_model<F>(mut on_state: F) -> Result<(), EmbeddingError>
@@ -1079,42 +1625,27 @@ where
     }
 
     let started = Instant::now();
-    if EMBEDDING_ENGINE.get().is_some() {
-        let ready = EmbeddingModelLoadStatePayload::ready();
+    let result = EmbeddingEngine::engine_with_progress(LOCAL_EMBEDDING_MODEL_ID, |state| {
         log::info!(
-            "[embeddings][startup] status={:?} stage={:?} progress={} elapsed_ms={} cached=true",
-            ready.status,
-            ready.stage,
-            ready.progress,
-            started.elapsed().as_millis()
+            "[embeddings][startup] status={:?} stage={:?} progress={} message=\"{}\"",
+            state.status,
+            state.stage,
+            state.progress,
+            state.message
         );
-        on_state(ready);
-        return Ok(());
-    }
-
-    let result = EMBEDDING_ENGINE.get_or_try_init(|| {
-        EmbeddingEngine::initialize_with_progress(|state| {
-            log::info!(
-                "[embeddings][startup] status={:?} stage={:?} progress={} message=\"{}\"",
-                state.status,
-                state.stage,
-                state.progress,
-                state.message
-            );
-            on_state(state);
-        })
-        .map(Mutex::new)
+        on_state(state);
     });
 
     match result {
-        Ok(_) => {
+        Ok((_, cached)) => {
             let ready = EmbeddingModelLoadStatePayload::ready();
             log::info!(
-                "[embeddings][startup] status={:?} stage={:?} progress={} elapsed_ms={} cached=false",
+                "[embeddings][startup] status={:?} stage={:?} progress={} elapsed_ms={} cached={}",
                 ready.status,
                 ready.stage,
                 ready.progress,
-                started.elapsed().as_millis()
+                started.elapsed().as_millis(),
+                cached
             );
             on_state(ready);
             Ok(())
@@ -1144,8 +1675,15 @@ pub fn sync_document_embeddings(
         return Ok(());
     }
 
-    let prepared_write =
-        prepare_document_embedding_write_for_document(store, document, metadata_lookup, None)?;
+    let settings = EmbeddingSyncSettings::load(store.documents_folder())?;
+    ensure_cache_embedding_model(store, &settings.model_id)?;
+    let prepared_write = prepare_document_embedding_write_for_document(
+        store,
+        document,
+        metadata_lookup,
+        None,
+        &settings,
+    )?;
 
     if let Some(document_embedding) = prepared_write.document_embedding.as_ref() {
         store.upsert_document_embedding(document_embedding)?;
@@ -1193,9 +1731,14 @@ pub fn sync_documents_embeddings_batch_with_progress(
     }
 
     let total_documents = documents.len();
-    let metadata_lookup = build_metadata_lookup(store.list_document_embedding_metadata()?);
+    let settings = EmbeddingSyncSettings::load(store.documents_folder())?;
+    ensure_cache_embedding_model(store, &settings.model_id)?;
+    let metadata_lookup = build_metadata_lookup(
+        store.list_document_embedding_metadata()?,
+        &settings.model_id,
+    );
     let chunk_hash_lookup =
-        store.list_document_chunk_embedding_hashes_by_model(LOCAL_EMBEDDING_MODEL_ID)?;
+        store.list_document_chunk_embedding_hashes_by_model(&settings.model_id)?;
 
     if let Some(callback) = progress_callback.as_mut() {
         callback(crate::knowledge_base::ProgressEvent::Phase2Start { total_documents });
@@ -1208,6 +1751,7 @@ pub fn sync_documents_embeddings_batch_with_progress(
             document,
             Some(&metadata_lookup),
             Some(&chunk_hash_lookup),
+            &settings,
         )?;
         if plan.should_update_document_embedding || plan.should_update_chunk_embeddings {
             changed_documents.push(ChangedDocumentEmbeddingSyncCandidate { document, plan });
@@ -1245,7 +1789,11 @@ pub fn sync_documents_embeddings_batch_with_progress(
         let mut batch_document_ids: Vec<String> = Vec::with_capacity(batch.len());
 
         for candidate in batch {
-            match prepare_document_embedding_write_from_plan(candidate.document, candidate.plan) {
+            match prepare_document_embedding_write_from_plan(
+                candidate.document,
+                candidate.plan,
+                &settings.model_id,
+            ) {
                 Ok(prepared_write) => {
                     batch_document_ids.push(candidate.document.id.clone());
                     batch_payloads.push(prepared_write_to_batch_payload(prepared_write));
@@ -1358,19 +1906,80 @@ pub fn hybrid_search_documents_by_query(
     bm25_weight: f32,
 ) -> Result<Vec<HybridSearchHitPayload>, EmbeddingError> {
     let normalized_query = query_text.trim();
-    if normalized_query.is_empty() || limit == 0 {
-        return Ok(Vec::new());
+    // Callers without a way to report syntax errors search malformed queries as plain words.
+    let mut query = parse_search_query(normalized_query)
+        .unwrap_or_else(|_| SearchQuery::plain_text(normalized_query));
+    query.excluded_document_ids.extend(exclude_document_id);
+    hybrid_search_documents_by_search_query(
+        store,
+        &query,
+        semantic_query_text,
+        limit,
+        min_score,
+        semantic_weight,
+        bm25_weight,
+    )
+}
+
+/// Hybrid search for a parsed query. The semantic leg embeds `semantic_query_text`, or the
+/// query's own [`SearchQuery::semantic_text`] when none is given.
+pub fn hybrid_search_documents_by_search_query(
+    store: &DocumentCacheStore,
+    query: &SearchQuery,
+    semantic_query_text: Option<&str>,
+    limit: usize,
+    min_score: f32,
+    semantic_weight: f32,
+    bm25_weight: f32,
+) -> Result<Vec<HybridSearchHitPayload>, EmbeddingError> {
+    let mut hits = explain_hybrid_search_by_search_query(
+        store,
+        query,
+        semantic_query_text,
+        limit,
+        min_score,
+        semantic_weight,
+        bm25_weight,
+    )?
+    .hits;
+    for hit in &mut hits {
+        hit.explanation = None;
+    }
+    Ok(hits)
+}
+
+/// [`hybrid_search_documents_by_search_query`] with the score breakdown of every hit and
+/// the weights actually used, which show a fallback to BM25 only.
+pub fn explain_hybrid_search_by_search_query(
+    store: &DocumentCacheStore,
+    query: &SearchQuery,
+    semantic_query_text: Option<&str>,
+    limit: usize,
+    min_score: f32,
+    semantic_weight: f32,
+    bm25_weight: f32,
+) -> Result<ExplainedHybridSearchPayload, EmbeddingError> {
+    if (query.clauses.is_empty() && !query.has_filters()) || limit == 0 {
+        return store
+            .explain_hybrid_search_for_query(
+                vec![0.0_f32; store.embedding_dimensions()],
+                &SearchQuery::default(),
+                0,
+                min_score,
+                semantic_weight,
+                bm25_weight,
+            )
+            .map_err(EmbeddingError::from);
     }
 
     if !embeddings_runtime_enabled() {
         let effective_bm25_weight = if bm25_weight > 0.0 { bm25_weight } else { 1.0 };
         return store
-            .hybrid_search_documents(
-                vec![0.0_f32; LOCAL_EMBEDDING_DIMENSIONS],
-                query_text,
+            .explain_hybrid_search_for_query(
+                vec![0.0_f32; store.embedding_dimensions()],
+                query,
                 limit,
                 min_score,
-                exclude_document_id,
                 0.0,
                 effective_bm25_weight,
             )
@@ -1378,77 +1987,99 @@ pub fn hybrid_search_documents_by_query(
     }
 
     let started = Instant::now();
+    let model_id = configured_embedding_model(store.documents_folder())?;
+    let cached_model = store
+        .embedding_model()?
+        .unwrap_or_else(|| LOCAL_EMBEDDING_MODEL_ID.to_owned());
+    // Vectors from another model are not comparable with this model's query vector.
+    let cache_matches_model = cached_model == model_id;
+    if !cache_matches_model {
+        log::warn!(
+            "[semantic-search] Cached embeddings were built with {cached_model}, not {model_id}; reindex to search semantically"
+        );
+    }
 
-    let normalized_semantic_query = semantic_query_text.unwrap_or(query_text).trim();
+    let query_text = query.lexical_text();
+    let default_semantic_query = query.semantic_text();
+    let normalized_semantic_query = semantic_query_text
+        .unwrap_or(&default_semantic_query)
+        .trim();
     let has_semantic_query = !normalized_semantic_query.is_empty();
     log::info!(
         "[search-debug][query] query_text=\"{}\" semantic_query_text=\"{}\" limit={} min_score={} semantic_weight={} bm25_weight={} exclude={:?}",
-        normalized_query,
+        query_text,
         normalized_semantic_query,
         limit,
         min_score,
         semantic_weight,
         bm25_weight,
-        exclude_document_id
+        query.excluded_document_ids
     );
 
-    let (query_vector, semantic_weight, bm25_weight) =
-        if semantic_weight > 0.0 && has_semantic_query {
-            let embed_started = Instant::now();
-            match embed_query_text(normalized_semantic_query) {
-                Ok(vector) => {
-                    log::info!(
-                        "[search-debug][query] embedding=ok dim={} elapsed_ms={}",
-                        vector.len(),
-                        embed_started.elapsed().as_millis()
-                    );
-                    (vector, semantic_weight, bm25_weight)
-                }
-                Err(error) => {
-                    log::warn!(
+    let (query_vector, semantic_weight, bm25_weight) = if semantic_weight > 0.0
+        && has_semantic_query
+        && cache_matches_model
+    {
+        let embed_started = Instant::now();
+        match embed_query_text(&model_id, normalized_semantic_query) {
+            Ok(vector) => {
+                log::info!(
+                    "[search-debug][query] embedding=ok dim={} elapsed_ms={}",
+                    vector.len(),
+                    embed_started.elapsed().as_millis()
+                );
+                (vector, semantic_weight, bm25_weight)
+            }
+            Err(error) => {
+                log::warn!(
                     "[semantic-search] Query embedding failed, falling back to BM25-only mode: {}",
                     error
                 );
-                    (vec![0.0_f32; LOCAL_EMBEDDING_DIMENSIONS], 0.0_f32, 1.0_f32)
-                }
+                (
+                    vec![0.0_f32; store.embedding_dimensions()],
+                    0.0_f32,
+                    1.0_f32,
+                )
             }
-        } else {
-            log::info!(
-                "[search-debug][query] embedding=skipped semantic_weight={} has_semantic_query={}",
+        }
+    } else {
+        log::info!(
+                "[search-debug][query] embedding=skipped semantic_weight={} has_semantic_query={} cache_matches_model={}",
                 semantic_weight,
-                has_semantic_query
+                has_semantic_query,
+                cache_matches_model
             );
-            let effective_bm25_weight = if bm25_weight > 0.0 { bm25_weight } else { 1.0 };
-            (
-                vec![0.0_f32; LOCAL_EMBEDDING_DIMENSIONS],
-                0.0_f32,
-                effective_bm25_weight,
-            )
-        };
+        let effective_bm25_weight = if bm25_weight > 0.0 { bm25_weight } else { 1.0 };
+        (
+            vec![0.0_f32; store.embedding_dimensions()],
+            0.0_f32,
+            effective_bm25_weight,
+        )
+    };
 
-    let hits = store
-        .hybrid_search_documents(
+    let explained = store
+        .explain_hybrid_search_for_query(
             query_vector,
-            query_text,
+            query,
             limit,
             min_score,
-            exclude_document_id,
             semantic_weight,
             bm25_weight,
         )
         .map_err(EmbeddingError::from)?;
-    let top = hits
+    let top = explained
+        .hits
         .iter()
         .take(5)
         .map(|hit| format!("{}:{:.4}", hit.document_id, hit.score))
         .collect::<Vec<_>>();
     log::info!(
         "[search-debug][query] final_hits={} top={:?} elapsed_ms={}",
-        hits.len(),
+        explained.hits.len(),
         top,
         started.elapsed().as_millis()
     );
-    Ok(hits)
+    Ok(explained)
 }
 
 #[cfg(test)]
@@ -1463,19 +2094,22 @@ mod tests {
         CachedDocumentChunkEmbeddingPayload, CachedDocumentEmbeddingPayload, CachedDocumentPayload,
         DocumentCacheStore,
     };
+    use crate::models::{install_model, models_directory};
     use crate::text_processing::{
         build_document_embedding_source_text, chunk_document_text,
-        extract_plain_text_from_tiptap_or_raw,
+        extract_plain_text_from_tiptap_or_raw, ChunkingOptions,
     };
 
     use super::{
         build_attention_mask_with_past, build_metadata_lookup, compute_chunk_content_hash,
         compute_document_content_hash, embed_texts_batch, infer_past_kv_shape,
-        infer_past_sequence_length, l2_normalize, plan_document_embedding_sync_for_document,
-        pool_embeddings, sync_documents_embeddings_batch,
-        sync_documents_embeddings_batch_with_progress, EmbeddingError,
-        EmbeddingSyncDocumentPayload, ModelInputSpec, LOCAL_EMBEDDING_DIMENSIONS,
-        LOCAL_EMBEDDING_MODEL_ID, PAST_KEY_VALUES_NAME,
+        infer_past_sequence_length, installed_model_directory_for, is_local_model_path,
+        l2_normalize, plan_document_embedding_sync_for_document, pool_embeddings,
+        pooling_from_config, resolve_embedding_model_preset, set_installed_models_directory,
+        sync_documents_embeddings_batch, sync_documents_embeddings_batch_with_progress,
+        EmbeddingError, EmbeddingModelProfile, EmbeddingPooling, EmbeddingSyncDocumentPayload,
+        EmbeddingSyncSettings, ModelInputSpec, LOCAL_EMBEDDING_DIMENSIONS,
+        LOCAL_EMBEDDING_MODEL_ID, MULTILINGUAL_EMBEDDING_MODEL_ID, PAST_KEY_VALUES_NAME,
     };
 
     fn unique_temp_path() -> std::path::PathBuf {
@@ -1513,10 +2147,15 @@ mod tests {
         let output = vec![1.0_f32, 2.0_f32, 3.0_f32, 4.0_f32, 5.0_f32, 6.0_f32];
         let shape = vec![1_i64, 3_i64, 2_i64];
         let attention_mask = vec![1_i64, 1_i64, 0_i64];
-        let pooled = pool_embeddings(&output, &shape, &[attention_mask])
-            .expect("pooling should work")
-            .pop()
-            .expect("single-row pool should contain one embedding");
+        let pooled = pool_embeddings(
+            &output,
+            &shape,
+            &[attention_mask],
+            EmbeddingPooling::LastToken,
+        )
+        .expect("pooling should work")
+        .pop()
+        .expect("single-row pool should contain one embedding");
         assert_eq!(pooled, vec![3.0_f32, 4.0_f32]);
     }
 
@@ -1529,11 +2168,137 @@ mod tests {
         let shape = vec![2_i64, 3_i64, 2_i64];
         let attention_masks = vec![vec![1_i64, 1_i64, 0_i64], vec![1_i64, 0_i64, 0_i64]];
 
-        let pooled = pool_embeddings(&output, &shape, &attention_masks)
-            .expect("batched pooling should work");
+        let pooled = pool_embeddings(
+            &output,
+            &shape,
+            &attention_masks,
+            EmbeddingPooling::LastToken,
+        )
+        .expect("batched pooling should work");
         assert_eq!(pooled, vec![vec![3.0_f32, 4.0_f32], vec![7.0_f32, 8.0_f32]]);
     }
 
+    #[test]
+    fn pool_embeddings_mean_averages_attended_tokens_only() {
+        let output = vec![
+            1.0_f32, 2.0_f32, 3.0_f32, 4.0_f32, 5.0_f32, 6.0_f32, 7.0_f32, 8.0_f32, 9.0_f32,
+            10.0_f32, 11.0_f32, 12.0_f32,
+        ];
+        let shape = vec![2_i64, 3_i64, 2_i64];
+        let attention_masks = vec![vec![1_i64, 1_i64, 0_i64], vec![1_i64, 0_i64, 0_i64]];
+
+        let pooled = pool_embeddings(&output, &shape, &attention_masks, EmbeddingPooling::Mean)
+            .expect("mean pooling should work");
+        assert_eq!(pooled, vec![vec![2.0_f32, 3.0_f32], vec![7.0_f32, 8.0_f32]]);
+    }
+
+    #[test]
+    fn model_profiles_prefix_e5_models_and_resolve_presets() {
+        for model_id in [
+            MULTILINGUAL_EMBEDDING_MODEL_ID,
+            "intfloat/e5-base-v2",
+            "/models/multilingual-e5-small/",
+        ] {
+            let profile = EmbeddingModelProfile::for_model(model_id);
+            assert_eq!(profile.query_prefix, "query: ", "{model_id}");
+            assert_eq!(profile.passage_prefix, "passage: ", "{model_id}");
+            assert_eq!(profile.pooling, EmbeddingPooling::Mean, "{model_id}");
+        }
+
+        let default_profile = EmbeddingModelProfile::for_model(LOCAL_EMBEDDING_MODEL_ID);
+        assert_eq!(default_profile.query_prefix, "");
+        assert_eq!(default_profile.passage_prefix, "");
+        assert_eq!(default_profile.pooling, EmbeddingPooling::Mean);
+        assert_eq!(
+            EmbeddingModelProfile::for_model("sentence-transformers/all-mpnet-base-v2").pooling,
+            EmbeddingPooling::Mean
+        );
+        assert_eq!(
+            EmbeddingModelProfile::for_model("acme/bge5-small").query_prefix,
+            ""
+        );
+
+        assert_eq!(
+            resolve_embedding_model_preset("Multilingual"),
+            Some(MULTILINGUAL_EMBEDDING_MODEL_ID)
+        );
+        assert_eq!(
+            resolve_embedding_model_preset("default"),
+            Some(LOCAL_EMBEDDING_MODEL_ID)
+        );
+        assert_eq!(resolve_embedding_model_preset("intfloat/e5-base-v2"), None);
+    }
+
+    #[test]
+    fn pooling_config_selects_cls_last_token_or_mean_pooling() {
+        let directory = unique_temp_path();
+        std::fs::create_dir_all(&directory).expect("create temp directory");
+        let config_path = directory.join("config.json");
+        for (config, expected) in [
+            (
+                r#"{"pooling_mode_cls_token": true, "pooling_mode_mean_tokens": false}"#,
+                Some(EmbeddingPooling::Cls),
+            ),
+            (
+                r#"{"pooling_mode_mean_tokens": true}"#,
+                Some(EmbeddingPooling::Mean),
+            ),
+            (
+                r#"{"pooling_mode_lasttoken": true}"#,
+                Some(EmbeddingPooling::LastToken),
+            ),
+            (r#"{"pooling_mode_max_tokens": true}"#, None),
+        ] {
+            std::fs::write(&config_path, config).expect("write pooling config");
+            assert_eq!(pooling_from_config(&config_path), expected, "{config}");
+        }
+
+        let output = vec![1.0_f32, 2.0_f32, 3.0_f32, 4.0_f32];
+        let pooled = pool_embeddings(&output, &[1, 2, 2], &[vec![1, 1]], EmbeddingPooling::Cls)
+            .expect("cls pooling should work");
+        assert_eq!(pooled, vec![vec![1.0_f32, 2.0_f32]]);
+
+        let _ = std::fs::remove_dir_all(directory);
+    }
+
+    #[test]
+    fn only_absolute_and_dot_prefixed_model_ids_are_local_paths() {
+        assert!(is_local_model_path("./models/minilm"));
+        assert!(is_local_model_path("../minilm"));
+        assert!(is_local_model_path(
+            &std::env::temp_dir().join("minilm").to_string_lossy()
+        ));
+        assert!(!is_local_model_path("Xenova/multilingual-e5-small"));
+        assert!(!is_local_model_path("src"));
+        assert!(!is_local_model_path(".hidden/model"));
+    }
+
+    #[test]
+    fn installed_models_are_found_by_id_in_the_models_directory() {
+        let root = unique_temp_path();
+        let source = root.join("download");
+        std::fs::create_dir_all(source.join("onnx")).expect("create model folder");
+        std::fs::write(source.join("tokenizer.json"), b"{}").expect("write tokenizer");
+        std::fs::write(source.join("onnx/model.onnx"), b"graph").expect("write model");
+        let models_dir = models_directory(&root.join("app"));
+        let model_id = "Xenova/test-installed-reranker";
+        let installed = install_model(&source, &models_dir, model_id).expect("install model");
+
+        set_installed_models_directory(Some(models_dir));
+        assert_eq!(
+            installed_model_directory_for(model_id),
+            Some(std::path::PathBuf::from(&installed.directory))
+        );
+        assert_eq!(
+            installed_model_directory_for("Xenova/test-missing-reranker"),
+            None
+        );
+        set_installed_models_directory(None);
+        assert_eq!(installed_model_directory_for(model_id), None);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
     #[test]
     fn infer_past_kv_shape_uses_zero_past_sequence_length() {
         let spec = ModelInputSpec {
@@ -1620,7 +2385,8 @@ mod tests {
                 .expect("document upsert should succeed");
 
             let source_text = build_document_embedding_source_text(title, body);
-            let document_hash = compute_document_content_hash(&source_text);
+            let document_hash =
+                compute_document_content_hash(&source_text, LOCAL_EMBEDDING_MODEL_ID);
             let plain_body = extract_plain_text_from_tiptap_or_raw(body);
             let chunks = chunk_document_text(title, &plain_body);
             let chunk_hash = compute_chunk_content_hash(
@@ -1628,6 +2394,7 @@ mod tests {
                     .iter()
                     .map(|chunk| chunk.text.clone())
                     .collect::<Vec<_>>(),
+                LOCAL_EMBEDDING_MODEL_ID,
             );
 
             store
@@ -1646,6 +2413,12 @@ mod tests {
                     document_id: document_id.clone(),
                     chunk_index: chunk.index,
                     chunk_text: chunk.text.clone(),
+                    page: chunk.page,
+                    heading_path: chunk.heading_path.clone(),
+                    char_start: chunk.char_start,
+                    char_end: chunk.char_end,
+                    line_start: chunk.line_start,
+                    line_end: chunk.line_end,
                     content_hash: chunk_hash.clone(),
                     model: LOCAL_EMBEDDING_MODEL_ID.to_string(),
                     vector: vec![0.5; LOCAL_EMBEDDING_DIMENSIONS],
@@ -1721,7 +2494,8 @@ mod tests {
                 .expect("document upsert should succeed");
 
             let source_text = build_document_embedding_source_text(title, body);
-            let document_hash = compute_document_content_hash(&source_text);
+            let document_hash =
+                compute_document_content_hash(&source_text, LOCAL_EMBEDDING_MODEL_ID);
 
             store
                 .upsert_document_embedding(&CachedDocumentEmbeddingPayload {
@@ -1751,6 +2525,7 @@ mod tests {
                 store
                     .list_document_embedding_metadata()
                     .expect("metadata read should succeed"),
+                LOCAL_EMBEDDING_MODEL_ID,
             );
             let chunk_hash_lookup = store
                 .list_document_chunk_embedding_hashes_by_model(LOCAL_EMBEDDING_MODEL_ID)
@@ -1761,6 +2536,10 @@ mod tests {
                 &document,
                 Some(&metadata_lookup),
                 Some(&chunk_hash_lookup),
+                &EmbeddingSyncSettings {
+                    model_id: LOCAL_EMBEDDING_MODEL_ID.to_owned(),
+                    chunking: ChunkingOptions::default(),
+                },
             )
             .expect("planning should succeed");
 
@@ -1836,7 +2615,8 @@ mod tests {
                 .expect("document upsert should succeed");
 
             let source_text = build_document_embedding_source_text(title, body);
-            let document_hash = compute_document_content_hash(&source_text);
+            let document_hash =
+                compute_document_content_hash(&source_text, LOCAL_EMBEDDING_MODEL_ID);
 
             store
                 .upsert_document_embedding(&CachedDocumentEmbeddingPayload {
@@ -1907,7 +2687,8 @@ mod tests {
                 .expect("document upsert should succeed");
 
             let source_text = build_document_embedding_source_text(title, body);
-            let document_hash = compute_document_content_hash(&source_text);
+            let document_hash =
+                compute_document_content_hash(&source_text, LOCAL_EMBEDDING_MODEL_ID);
             let plain_body = extract_plain_text_from_tiptap_or_raw(body);
             let chunks = chunk_document_text(title, &plain_body);
             let chunk_hash = compute_chunk_content_hash(
@@ -1915,6 +2696,7 @@ mod tests {
                     .iter()
                     .map(|chunk| chunk.text.clone())
                     .collect::<Vec<_>>(),
+                LOCAL_EMBEDDING_MODEL_ID,
             );
 
             store
@@ -1933,6 +2715,12 @@ mod tests {
                     document_id: document_id.clone(),
                     chunk_index: chunk.index,
                     chunk_text: chunk.text.clone(),
+                    page: chunk.page,
+                    heading_path: chunk.heading_path.clone(),
+                    char_start: chunk.char_start,
+                    char_end: chunk.char_end,
+                    line_start: chunk.line_start,
+                    line_end: chunk.line_end,
                     content_hash: chunk_hash.clone(),
                     model: LOCAL_EMBEDDING_MODEL_ID.to_string(),
                     vector: vec![0.5; LOCAL_EMBEDDING_DIMENSIONS],
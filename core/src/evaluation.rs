@@ -0,0 +1,510 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::knowledge_base::{KnowledgeBaseError, KnowledgeBaseService, SearchOptions};
+
+/// Ranking depth metrics are cut at when the caller does not choose one.
+pub const DEFAULT_EVALUATION_CUTOFF: usize = 10;
+
+/// One labelled query: the documents a good ranking returns for it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QrelPayload {
+    /// Stable name used to match the query against a baseline; defaults to the query text.
+    #[serde(default)]
+    pub id: Option<String>,
+    pub query: String,
+    pub relevant: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvaluationOptions {
+    pub k: usize,
+    pub rerank: bool,
+}
+
+impl Default for EvaluationOptions {
+    fn default() -> Self {
+        Self {
+            k: DEFAULT_EVALUATION_CUTOFF,
+            rerank: false,
+        }
+    }
+}
+
+/// MRR, recall, and nDCG at the report's `k`. For a single query `mrr` is its reciprocal rank.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct EvaluationMetricsPayload {
+    pub mrr: f64,
+    pub recall: f64,
+    pub ndcg: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueryEvaluationPayload {
+    pub id: String,
+    pub query: String,
+    pub relevant: Vec<String>,
+    /// Top `k` document ids, best first.
+    pub retrieved: Vec<String>,
+    /// 1-based rank of the first relevant document within the top `k`.
+    pub first_relevant_rank: Option<usize>,
+    pub metrics: EvaluationMetricsPayload,
+    /// Why the search failed; the query then scores zero instead of ending the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Evaluation run; also the format of a saved baseline.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EvaluationReportPayload {
+    pub k: usize,
+    pub rerank: bool,
+    pub query_count: usize,
+    /// Means over all queries.
+    pub metrics: EvaluationMetricsPayload,
+    pub queries: Vec<QueryEvaluationPayload>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueryComparisonPayload {
+    pub id: String,
+    pub baseline: EvaluationMetricsPayload,
+    /// Current minus baseline.
+    pub delta: EvaluationMetricsPayload,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EvaluationComparisonPayload {
+    /// Baseline means over the queries both runs share.
+    pub baseline: EvaluationMetricsPayload,
+    /// Current minus baseline over the shared queries.
+    pub delta: EvaluationMetricsPayload,
+    pub queries: Vec<QueryComparisonPayload>,
+    /// Shared queries whose nDCG dropped.
+    pub regressed: Vec<String>,
+    /// Queries only in the current run.
+    pub added: Vec<String>,
+    /// Queries only in the baseline.
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum EvaluationError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("qrels line {line}: {message}")]
+    Qrels { line: usize, message: String },
+    #[error("{0}")]
+    Validation(String),
+    #[error(transparent)]
+    KnowledgeBase(#[from] KnowledgeBaseError),
+}
+
+impl QrelPayload {
+    pub fn query_id(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.query)
+    }
+}
+
+/// Parses JSON lines of `{"id"?, "query", "relevant": [document ids]}`. Blank lines are skipped.
+pub fn parse_qrels(text: &str) -> Result<Vec<QrelPayload>, EvaluationError> {
+    let mut qrels = Vec::new();
+    let mut seen_ids = HashSet::new();
+    for (index, raw_line) in text.lines().enumerate() {
+        let line = index + 1;
+        if raw_line.trim().is_empty() {
+            continue;
+        }
+
+        let mut qrel = serde_json::from_str::<QrelPayload>(raw_line).map_err(|error| {
+            EvaluationError::Qrels {
+                line,
+                message: error.to_string(),
+            }
+        })?;
+        qrel.query = qrel.query.trim().to_owned();
+        qrel.id = qrel
+            .id
+            .map(|id| id.trim().to_owned())
+            .filter(|id| !id.is_empty());
+        let mut unique = BTreeSet::new();
+        qrel.relevant = qrel
+            .relevant
+            .into_iter()
+            .map(|id| id.trim().to_owned())
+            .filter(|id| !id.is_empty() && unique.insert(id.clone()))
+            .collect();
+
+        if qrel.query.is_empty() {
+            return Err(EvaluationError::Qrels {
+                line,
+                message: "query is empty".to_owned(),
+            });
+        }
+        if qrel.relevant.is_empty() {
+            return Err(EvaluationError::Qrels {
+                line,
+                message: "relevant must list at least one document id".to_owned(),
+            });
+        }
+        if !seen_ids.insert(qrel.query_id().to_owned()) {
+            return Err(EvaluationError::Qrels {
+                line,
+                message: format!("duplicate query id \"{}\"", qrel.query_id()),
+            });
+        }
+        qrels.push(qrel);
+    }
+
+    if qrels.is_empty() {
+        return Err(EvaluationError::Validation(
+            "qrels file has no queries".to_owned(),
+        ));
+    }
+    Ok(qrels)
+}
+
+pub fn load_qrels(path: &Path) -> Result<Vec<QrelPayload>, EvaluationError> {
+    parse_qrels(&fs::read_to_string(path)?)
+}
+
+/// Scores the top `k` of `retrieved` against binary relevance labels.
+pub fn score_ranking(
+    relevant: &[String],
+    retrieved: &[String],
+    k: usize,
+) -> EvaluationMetricsPayload {
+    let relevant = relevant.iter().map(String::as_str).collect::<HashSet<_>>();
+    if relevant.is_empty() || k == 0 {
+        return EvaluationMetricsPayload::default();
+    }
+
+    let mut reciprocal_rank = 0.0;
+    let mut hits = 0_usize;
+    let mut dcg = 0.0;
+    for (index, document_id) in retrieved.iter().take(k).enumerate() {
+        if !relevant.contains(document_id.as_str()) {
+            continue;
+        }
+        if hits == 0 {
+            reciprocal_rank = 1.0 / (index + 1) as f64;
+        }
+        hits += 1;
+        dcg += discount(index);
+    }
+    let ideal_dcg = (0..relevant.len().min(k)).map(discount).sum::<f64>();
+
+    EvaluationMetricsPayload {
+        mrr: reciprocal_rank,
+        recall: hits as f64 / relevant.len() as f64,
+        ndcg: dcg / ideal_dcg,
+    }
+}
+
+fn discount(index: usize) -> f64 {
+    1.0 / ((index + 2) as f64).log2()
+}
+
+fn mean_metrics<'a>(
+    metrics: impl Iterator<Item = &'a EvaluationMetricsPayload>,
+) -> EvaluationMetricsPayload {
+    let mut total = EvaluationMetricsPayload::default();
+    let mut count = 0_usize;
+    for item in metrics {
+        total.mrr += item.mrr;
+        total.recall += item.recall;
+        total.ndcg += item.ndcg;
+        count += 1;
+    }
+    if count == 0 {
+        return total;
+    }
+    EvaluationMetricsPayload {
+        mrr: total.mrr / count as f64,
+        recall: total.recall / count as f64,
+        ndcg: total.ndcg / count as f64,
+    }
+}
+
+fn metrics_delta(
+    current: &EvaluationMetricsPayload,
+    baseline: &EvaluationMetricsPayload,
+) -> EvaluationMetricsPayload {
+    EvaluationMetricsPayload {
+        mrr: current.mrr - baseline.mrr,
+        recall: current.recall - baseline.recall,
+        ndcg: current.ndcg - baseline.ndcg,
+    }
+}
+
+/// Runs every labelled query through `KnowledgeBaseService::search` (with the CLI's query
+/// preprocessing) against the current index and scores the top `k` results.
+pub fn evaluate_qrels(
+    documents_folder: &Path,
+    qrels: &[QrelPayload],
+    options: EvaluationOptions,
+) -> Result<EvaluationReportPayload, EvaluationError> {
+    if options.k == 0 {
+        return Err(EvaluationError::Validation(
+            "k must be at least 1".to_owned(),
+        ));
+    }
+
+    let mut queries = Vec::with_capacity(qrels.len());
+    for qrel in qrels {
+        let (retrieved, error) = match search_qrel(documents_folder, &qrel.query, options) {
+            Ok(retrieved) => (retrieved, None),
+            Err(error) => {
+                log::warn!(
+                    "[eval] Query {:?} failed, scoring it as zero: {}",
+                    qrel.query_id(),
+                    error
+                );
+                (Vec::new(), Some(error.to_string()))
+            }
+        };
+        let metrics = score_ranking(&qrel.relevant, &retrieved, options.k);
+        let first_relevant_rank = retrieved
+            .iter()
+            .position(|document_id| qrel.relevant.contains(document_id))
+            .map(|index| index + 1);
+        queries.push(QueryEvaluationPayload {
+            id: qrel.query_id().to_owned(),
+            query: qrel.query.clone(),
+            relevant: qrel.relevant.clone(),
+            retrieved,
+            first_relevant_rank,
+            metrics,
+            error,
+        });
+    }
+
+    Ok(EvaluationReportPayload {
+        k: options.k,
+        rerank: options.rerank,
+        query_count: queries.len(),
+        metrics: mean_metrics(queries.iter().map(|query| &query.metrics)),
+        queries,
+    })
+}
+
+/// Top `k` document ids for one labelled query. A query that is not valid query syntax (an
+/// unbalanced quote, a bare operator) is retried as plain words, as a user would retype it.
+fn search_qrel(
+    documents_folder: &Path,
+    query: &str,
+    options: EvaluationOptions,
+) -> Result<Vec<String>, KnowledgeBaseError> {
+    let search_options = SearchOptions {
+        limit: options.k,
+        preprocess: true,
+        rerank: options.rerank,
+        ..SearchOptions::default()
+    };
+    let response =
+        match KnowledgeBaseService::search(documents_folder, query, search_options.clone()) {
+            Err(KnowledgeBaseError::InvalidQuery(_)) => KnowledgeBaseService::search(
+                documents_folder,
+                query,
+                SearchOptions {
+                    plain_text: true,
+                    ..search_options
+                },
+            )?,
+            result => result?,
+        };
+    Ok(response
+        .results
+        .into_iter()
+        .map(|result| result.id)
+        .collect())
+}
+
+/// Compares two runs query by query. Both must be cut at the same `k`.
+pub fn compare_reports(
+    current: &EvaluationReportPayload,
+    baseline: &EvaluationReportPayload,
+) -> Result<EvaluationComparisonPayload, EvaluationError> {
+    if current.k != baseline.k {
+        return Err(EvaluationError::Validation(format!(
+            "baseline was measured at k={}, not k={}",
+            baseline.k, current.k
+        )));
+    }
+
+    let baseline_by_id = baseline
+        .queries
+        .iter()
+        .map(|query| (query.id.as_str(), &query.metrics))
+        .collect::<HashMap<_, _>>();
+    let current_ids = current
+        .queries
+        .iter()
+        .map(|query| query.id.as_str())
+        .collect::<HashSet<_>>();
+
+    let mut queries = Vec::new();
+    let mut added = Vec::new();
+    for query in &current.queries {
+        match baseline_by_id.get(query.id.as_str()) {
+            Some(baseline_metrics) => queries.push(QueryComparisonPayload {
+                id: query.id.clone(),
+                baseline: **baseline_metrics,
+                delta: metrics_delta(&query.metrics, baseline_metrics),
+            }),
+            None => added.push(query.id.clone()),
+        }
+    }
+    let removed = baseline
+        .queries
+        .iter()
+        .filter(|query| !current_ids.contains(query.id.as_str()))
+        .map(|query| query.id.clone())
+        .collect();
+    let regressed = queries
+        .iter()
+        .filter(|query| query.delta.ndcg < -f64::EPSILON)
+        .map(|query| query.id.clone())
+        .collect();
+
+    let shared_current = current
+        .queries
+        .iter()
+        .filter(|query| baseline_by_id.contains_key(query.id.as_str()))
+        .map(|query| &query.metrics);
+    let current_mean = mean_metrics(shared_current);
+    let baseline_mean = mean_metrics(queries.iter().map(|query| &query.baseline));
+
+    Ok(EvaluationComparisonPayload {
+        baseline: baseline_mean,
+        delta: metrics_delta(&current_mean, &baseline_mean),
+        queries,
+        regressed,
+        added,
+        removed,
+    })
+}
+
+pub fn read_report(path: &Path) -> Result<EvaluationReportPayload, EvaluationError> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+pub fn write_report(path: &Path, report: &EvaluationReportPayload) -> Result<(), EvaluationError> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(report)? + "\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compare_reports, parse_qrels, score_ranking, EvaluationError, EvaluationMetricsPayload,
+        EvaluationReportPayload, QueryEvaluationPayload,
+    };
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| (*value).to_owned()).collect()
+    }
+
+    fn report(k: usize, queries: &[(&str, f64)]) -> EvaluationReportPayload {
+        let queries = queries
+            .iter()
+            .map(|(id, ndcg)| QueryEvaluationPayload {
+                id: (*id).to_owned(),
+                query: (*id).to_owned(),
+                relevant: ids(&["a"]),
+                retrieved: Vec::new(),
+                first_relevant_rank: None,
+                metrics: EvaluationMetricsPayload {
+                    mrr: *ndcg,
+                    recall: 1.0,
+                    ndcg: *ndcg,
+                },
+                error: None,
+            })
+            .collect::<Vec<_>>();
+        EvaluationReportPayload {
+            k,
+            rerank: false,
+            query_count: queries.len(),
+            metrics: EvaluationMetricsPayload::default(),
+            queries,
+        }
+    }
+
+    #[test]
+    fn score_ranking_computes_reciprocal_rank_recall_and_ndcg() {
+        let metrics = score_ranking(&ids(&["b", "d"]), &ids(&["a", "b", "c", "d"]), 3);
+        assert_eq!(metrics.mrr, 0.5);
+        assert_eq!(metrics.recall, 0.5);
+        let expected_ndcg = (1.0 / 3_f64.log2()) / (1.0 + 1.0 / 3_f64.log2());
+        assert!((metrics.ndcg - expected_ndcg).abs() < 1e-12);
+
+        let perfect = score_ranking(&ids(&["a"]), &ids(&["a", "b"]), 10);
+        assert_eq!(
+            perfect,
+            EvaluationMetricsPayload {
+                mrr: 1.0,
+                recall: 1.0,
+                ndcg: 1.0
+            }
+        );
+        assert_eq!(
+            score_ranking(&ids(&["z"]), &ids(&["a"]), 10),
+            EvaluationMetricsPayload::default()
+        );
+    }
+
+    #[test]
+    fn parse_qrels_reports_line_numbers_and_duplicate_ids() {
+        let qrels = parse_qrels(
+            "{\"query\": \"deploy\", \"relevant\": [\"a\", \"a\", \" b \"]}\n\n{\"id\": \"q2\", \"query\": \"deploy\", \"relevant\": [\"c\"]}\n",
+        )
+        .expect("valid qrels");
+        assert_eq!(qrels.len(), 2);
+        assert_eq!(qrels[0].query_id(), "deploy");
+        assert_eq!(qrels[0].relevant, ids(&["a", "b"]));
+        assert_eq!(qrels[1].query_id(), "q2");
+
+        let error = parse_qrels(
+            "{\"query\": \"deploy\", \"relevant\": [\"a\"]}\n{\"query\": \"deploy\", \"relevant\": [\"b\"]}",
+        )
+        .expect_err("duplicate id");
+        assert!(matches!(error, EvaluationError::Qrels { line: 2, .. }));
+        assert!(matches!(
+            parse_qrels("{\"query\": \"deploy\", \"relevant\": []}"),
+            Err(EvaluationError::Qrels { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn compare_reports_matches_queries_by_id_and_flags_regressions() {
+        let baseline = report(10, &[("q1", 1.0), ("q2", 0.5), ("old", 1.0)]);
+        let current = report(10, &[("q1", 0.5), ("q2", 1.0), ("new", 0.0)]);
+
+        let comparison = compare_reports(&current, &baseline).expect("same k");
+        assert_eq!(comparison.queries.len(), 2);
+        assert_eq!(comparison.queries[0].delta.ndcg, -0.5);
+        assert_eq!(comparison.regressed, ids(&["q1"]));
+        assert_eq!(comparison.added, ids(&["new"]));
+        assert_eq!(comparison.removed, ids(&["old"]));
+        assert_eq!(comparison.baseline.ndcg, 0.75);
+        assert_eq!(comparison.delta.ndcg, 0.0);
+
+        assert!(matches!(
+            compare_reports(&report(5, &[]), &baseline),
+            Err(EvaluationError::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,363 @@
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag, TagEnd};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::{
+    document_path, escape_html, markdown_options, ExportError, ExportOptions, ExportScope,
+    ExportedDocumentPayload,
+};
+use crate::document_store::{self, StoredDocument, ATTACHMENTS_FOLDER};
+use crate::importers::{linked_document_path, percent_decode, resolve_relative_path};
+
+const CONTENT_FOLDER: &str = "OEBPS";
+const DEFAULT_BOOK_TITLE: &str = "Tentacle";
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLE_CSS: &str = r#"body { font-family: serif; line-height: 1.5; }
+h1, h2, h3 { font-family: sans-serif; }
+img { max-width: 100%; }
+pre { white-space: pre-wrap; font-size: 0.85em; }
+table { border-collapse: collapse; }
+th, td { padding: 0.2em 0.5em; border: 1px solid #999; }
+"#;
+
+struct EmbeddedImage {
+    /// Path inside `OEBPS/`.
+    href: String,
+    media_type: &'static str,
+    bytes: Vec<u8>,
+}
+
+/// Writes `documents` as an EPUB 3 book at `destination`, one XHTML chapter per document in
+/// the given order. Images that point into the vault's attachments are embedded; links between
+/// exported documents become chapter links, and other vault links are reduced to their text.
+pub(super) fn write_epub(
+    documents_folder: &Path,
+    scope: &ExportScope,
+    documents: &[StoredDocument],
+    destination: &Path,
+    options: &ExportOptions,
+) -> Result<Vec<ExportedDocumentPayload>, ExportError> {
+    if documents.is_empty() {
+        return Err(ExportError::Validation(
+            "there are no documents to put in the book".to_owned(),
+        ));
+    }
+    if destination.exists() {
+        return Err(ExportError::Validation(format!(
+            "export destination \"{}\" already exists",
+            destination.display()
+        )));
+    }
+
+    let chapters = documents
+        .iter()
+        .enumerate()
+        .map(|(index, document)| (document.id.as_str(), chapter_file_name(index)))
+        .collect::<HashMap<_, _>>();
+    let chapters_by_path = documents
+        .iter()
+        .map(|document| {
+            (
+                document_path(document),
+                chapters[document.id.as_str()].clone(),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let mut images: Vec<EmbeddedImage> = Vec::new();
+    let mut image_hrefs: HashMap<String, String> = HashMap::new();
+    let mut chapter_files = Vec::with_capacity(documents.len());
+    let mut exported = Vec::with_capacity(documents.len());
+
+    for document in documents {
+        let first_image = images.len();
+        let body = render_chapter_body(document, &chapters_by_path, |vault_path| {
+            if let Some(href) = image_hrefs.get(vault_path) {
+                return Some(href.clone());
+            }
+            let media_type = image_media_type(vault_path)?;
+            let bytes = fs::read(documents_folder.join(vault_path)).ok()?;
+            let extension = vault_path.rsplit('.').next().unwrap_or_default();
+            let href = format!(
+                "images/image-{:03}.{}",
+                images.len() + 1,
+                extension.to_lowercase()
+            );
+            images.push(EmbeddedImage {
+                href: href.clone(),
+                media_type,
+                bytes,
+            });
+            image_hrefs.insert(vault_path.to_owned(), href.clone());
+            Some(href)
+        });
+        let file_name = chapters[document.id.as_str()].clone();
+        chapter_files.push((file_name.clone(), chapter_xhtml(&document.title, &body)));
+        exported.push(ExportedDocumentPayload {
+            id: document.id.clone(),
+            title: document.title.clone(),
+            folder_path: document.folder_path.clone(),
+            path: format!("{CONTENT_FOLDER}/{file_name}"),
+            attachments: images[first_image..]
+                .iter()
+                .map(|image| format!("{CONTENT_FOLDER}/{}", image.href))
+                .collect(),
+        });
+    }
+
+    let title = book_title(scope, documents);
+    let modified = document_store::now_iso_string_utc();
+    let package = package_opf(
+        &title,
+        options.author.as_deref(),
+        &modified,
+        &book_identifier(documents),
+        &chapter_files,
+        &images,
+    );
+    let navigation = navigation_xhtml(&title, documents, &chapters);
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut archive = ZipWriter::new(fs::File::create(destination)?);
+    // The mimetype entry must come first and be stored uncompressed.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    archive.start_file("mimetype", stored)?;
+    archive.write_all(b"application/epub+zip")?;
+    archive.start_file("META-INF/container.xml", deflated)?;
+    archive.write_all(CONTAINER_XML.as_bytes())?;
+    archive.start_file(format!("{CONTENT_FOLDER}/content.opf"), deflated)?;
+    archive.write_all(package.as_bytes())?;
+    archive.start_file(format!("{CONTENT_FOLDER}/nav.xhtml"), deflated)?;
+    archive.write_all(navigation.as_bytes())?;
+    archive.start_file(format!("{CONTENT_FOLDER}/style.css"), deflated)?;
+    archive.write_all(STYLE_CSS.as_bytes())?;
+    for (file_name, xhtml) in &chapter_files {
+        archive.start_file(format!("{CONTENT_FOLDER}/{file_name}"), deflated)?;
+        archive.write_all(xhtml.as_bytes())?;
+    }
+    for image in &images {
+        archive.start_file(format!("{CONTENT_FOLDER}/{}", image.href), stored)?;
+        archive.write_all(&image.bytes)?;
+    }
+    archive.finish()?;
+
+    Ok(exported)
+}
+
+/// Renders a document body as XHTML. Raw HTML is kept as visible text (it is rarely valid
+/// XHTML) and HTML comments such as page markers are dropped.
+fn render_chapter_body(
+    document: &StoredDocument,
+    chapters_by_path: &HashMap<String, String>,
+    mut embed_image: impl FnMut(&str) -> Option<String>,
+) -> String {
+    let document_path = document_path(document);
+    // Whether each open link or image was kept, so its end tag can be dropped to match.
+    let mut open_targets: Vec<bool> = Vec::new();
+    let events =
+        Parser::new_ext(&document.body, markdown_options()).filter_map(|event| match event {
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                let dest_url = match linked_document_path(&document_path, &dest_url) {
+                    Some(path) => chapters_by_path
+                        .get(&path)
+                        .map(|file| CowStr::from(file.clone())),
+                    None if is_external_url(&dest_url) || dest_url.starts_with('#') => {
+                        Some(dest_url)
+                    }
+                    None => None,
+                };
+                open_targets.push(dest_url.is_some());
+                dest_url.map(|dest_url| {
+                    Event::Start(Tag::Link {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    })
+                })
+            }
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                let embedded = resolve_relative_path(&document_path, &percent_decode(&dest_url))
+                    .filter(|path| path.starts_with(&format!("{ATTACHMENTS_FOLDER}/")))
+                    .and_then(|path| embed_image(&path));
+                open_targets.push(embedded.is_some());
+                embedded.map(|href| {
+                    Event::Start(Tag::Image {
+                        link_type,
+                        dest_url: CowStr::from(href),
+                        title,
+                        id,
+                    })
+                })
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                open_targets.pop().unwrap_or(false).then_some(event)
+            }
+            Event::Html(raw) | Event::InlineHtml(raw) => {
+                (!raw.trim_start().starts_with("<!--")).then_some(Event::Text(raw))
+            }
+            other => Some(other),
+        });
+
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    output
+}
+
+fn chapter_xhtml(title: &str, body: &str) -> String {
+    let title = escape_html(title);
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n\
+<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n\
+<head>\n<meta charset=\"utf-8\"/>\n<title>{title}</title>\n\
+<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n\
+<body>\n<section epub:type=\"chapter\">\n<h1>{title}</h1>\n{body}</section>\n</body>\n</html>\n"
+    )
+}
+
+fn navigation_xhtml(
+    title: &str,
+    documents: &[StoredDocument],
+    chapters: &HashMap<&str, String>,
+) -> String {
+    let mut entries = String::new();
+    for document in documents {
+        entries.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            chapters[document.id.as_str()],
+            escape_html(&document.title)
+        ));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n\
+<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n\
+<head>\n<meta charset=\"utf-8\"/>\n<title>{title}</title>\n</head>\n<body>\n\
+<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n<ol>\n{entries}</ol>\n</nav>\n\
+</body>\n</html>\n",
+        title = escape_html(title),
+    )
+}
+
+fn package_opf(
+    title: &str,
+    author: Option<&str>,
+    modified: &str,
+    identifier: &str,
+    chapter_files: &[(String, String)],
+    images: &[EmbeddedImage],
+) -> String {
+    let creator = author
+        .map(str::trim)
+        .filter(|author| !author.is_empty())
+        .map(|author| format!("    <dc:creator>{}</dc:creator>\n", escape_html(author)))
+        .unwrap_or_default();
+    let mut items = String::from(
+        "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+    <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+    for (file_name, _) in chapter_files {
+        let id = file_name.trim_end_matches(".xhtml");
+        items.push_str(&format!(
+            "    <item id=\"{id}\" href=\"{file_name}\" media-type=\"application/xhtml+xml\"/>\n"
+        ));
+        spine.push_str(&format!("    <itemref idref=\"{id}\"/>\n"));
+    }
+    for (index, image) in images.iter().enumerate() {
+        items.push_str(&format!(
+            "    <item id=\"image-{:03}\" href=\"{}\" media-type=\"{}\"/>\n",
+            index + 1,
+            image.href,
+            image.media_type
+        ));
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"en\">\n\
+  <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+    <dc:identifier id=\"book-id\">{identifier}</dc:identifier>\n\
+    <dc:title>{title}</dc:title>\n{creator}\
+    <dc:language>en</dc:language>\n\
+    <dc:date>{modified}</dc:date>\n\
+    <meta property=\"dcterms:modified\">{modified}</meta>\n\
+  </metadata>\n  <manifest>\n{items}  </manifest>\n  <spine>\n{spine}  </spine>\n</package>\n",
+        title = escape_html(title),
+    )
+}
+
+fn book_title(scope: &ExportScope, documents: &[StoredDocument]) -> String {
+    match scope {
+        ExportScope::Document(_) => documents[0].title.clone(),
+        ExportScope::Folder(folder) => folder
+            .trim_matches('/')
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or(DEFAULT_BOOK_TITLE)
+            .to_owned(),
+        ExportScope::Tag(tag) => format!("#{}", tag.trim().to_lowercase()),
+    }
+}
+
+/// Stable for the same set of documents, so re-exports replace the book on a reader.
+fn book_identifier(documents: &[StoredDocument]) -> String {
+    let mut hasher = Sha256::new();
+    for document in documents {
+        hasher.update(document.id.as_bytes());
+        hasher.update([0]);
+    }
+    let digest = hasher.finalize();
+    let hex = digest[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!("urn:tentacle:{hex}")
+}
+
+fn chapter_file_name(index: usize) -> String {
+    format!("chapter-{:03}.xhtml", index + 1)
+}
+
+fn image_media_type(path: &str) -> Option<&'static str> {
+    let extension = path.rsplit_once('.')?.1.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+fn is_external_url(url: &str) -> bool {
+    ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+}
@@ -0,0 +1,659 @@
+use pulldown_cmark::Options;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::document_cache::DocumentCacheError;
+use crate::document_store::{
+    self, DocumentSort, DocumentStoreError, StoredDocument, ATTACHMENTS_FOLDER,
+};
+
+mod epub;
+mod site;
+
+/// File written at the root of every export describing what it contains.
+pub const EXPORT_MANIFEST_FILE_NAME: &str = "manifest.json";
+const JSON_EXPORT_FILE_NAME: &str = "documents.json";
+const JSONL_EXPORT_FILE_NAME: &str = "documents.jsonl";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One markdown file per document, laid out like the vault.
+    #[default]
+    Markdown,
+    /// A single JSON array of full `StoredDocument` records.
+    Json,
+    /// One `StoredDocument` JSON record per line.
+    Jsonl,
+    /// A static site browsable from `file://`, with navigation, tag pages, and search.
+    Html,
+    /// A single EPUB 3 book with one chapter per document.
+    Epub,
+}
+
+impl ExportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+            Self::Html => "html",
+            Self::Epub => "epub",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportScope {
+    Document(String),
+    /// A vault folder and everything below it. Empty means the whole vault.
+    Folder(String),
+    /// Every document carrying the tag.
+    Tag(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Leave tentacle-specific frontmatter keys out of markdown exports.
+    pub strip_frontmatter: bool,
+    /// Document order, as in `document_store::sort_documents`. Without a sort or `desc`,
+    /// documents are exported in folder and title order.
+    pub sort: Option<DocumentSort>,
+    pub desc: bool,
+    /// Author recorded in EPUB metadata.
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportedDocumentPayload {
+    pub id: String,
+    pub title: String,
+    pub folder_path: String,
+    /// Destination-relative file holding the document.
+    pub path: String,
+    /// Destination-relative attachment files copied for the document.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportManifestPayload {
+    pub format: ExportFormat,
+    pub exported_at: String,
+    /// Vault folder the export was scoped to, when a folder was exported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    /// Tag the export was scoped to, when a tag was exported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    pub strip_frontmatter: bool,
+    pub document_count: usize,
+    pub attachment_count: usize,
+    pub documents: Vec<ExportedDocumentPayload>,
+}
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    DocumentStore(#[from] DocumentStoreError),
+    #[error(transparent)]
+    DocumentCache(#[from] DocumentCacheError),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("{0}")]
+    FolderNotFound(String),
+    #[error("{0}")]
+    Validation(String),
+}
+
+/// Exports a document, a folder, or a tag into `destination`, which must not exist or be empty.
+///
+/// Markdown exports mirror the vault layout so relative attachment links keep working; JSON
+/// and JSONL exports write full `StoredDocument` records to `documents.json(l)`. HTML exports
+/// render the same layout as a static site (see `site::write_site`). Attachments are copied
+/// to `.attachments/<id>/` in every format, and `manifest.json` lists everything that was
+/// written. EPUB exports instead write a single book file at `destination` (see
+/// `epub::write_epub`) and return the manifest without writing it.
+pub fn export_documents(
+    documents_folder: &Path,
+    scope: &ExportScope,
+    destination: &Path,
+    options: &ExportOptions,
+) -> Result<ExportManifestPayload, ExportError> {
+    if options.strip_frontmatter && options.format != ExportFormat::Markdown {
+        return Err(ExportError::Validation(
+            "frontmatter can only be stripped from markdown exports".to_owned(),
+        ));
+    }
+    let documents = collect_documents(documents_folder, scope, options)?;
+    if options.format == ExportFormat::Epub {
+        let exported = epub::write_epub(documents_folder, scope, &documents, destination, options)?;
+        return Ok(build_manifest(scope, options, exported));
+    }
+    prepare_destination(destination)?;
+
+    let mut exported = Vec::with_capacity(documents.len());
+    for document in &documents {
+        let path = match options.format {
+            ExportFormat::Markdown => {
+                let relative_path = document_path(document);
+                let target = destination.join(&relative_path);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(
+                    &target,
+                    document_store::render_document_markdown(document, !options.strip_frontmatter),
+                )?;
+                relative_path
+            }
+            ExportFormat::Json => JSON_EXPORT_FILE_NAME.to_owned(),
+            ExportFormat::Jsonl => JSONL_EXPORT_FILE_NAME.to_owned(),
+            ExportFormat::Html => site::page_path(document),
+            ExportFormat::Epub => unreachable!("epub exports are written by epub::write_epub"),
+        };
+
+        exported.push(ExportedDocumentPayload {
+            id: document.id.clone(),
+            title: document.title.clone(),
+            folder_path: document.folder_path.clone(),
+            path,
+            attachments: copy_attachments(documents_folder, &document.id, destination)?,
+        });
+    }
+
+    match options.format {
+        ExportFormat::Markdown | ExportFormat::Epub => {}
+        ExportFormat::Html => site::write_site(documents_folder, &documents, destination)?,
+        ExportFormat::Json => {
+            let json = serde_json::to_string_pretty(&documents)?;
+            fs::write(destination.join(JSON_EXPORT_FILE_NAME), format!("{json}\n"))?;
+        }
+        ExportFormat::Jsonl => {
+            let mut file = fs::File::create(destination.join(JSONL_EXPORT_FILE_NAME))?;
+            for document in &documents {
+                serde_json::to_writer(&mut file, document)?;
+                file.write_all(b"\n")?;
+            }
+        }
+    }
+
+    let manifest = build_manifest(scope, options, exported);
+    let manifest_json = serde_json::to_string_pretty(&manifest)?;
+    fs::write(
+        destination.join(EXPORT_MANIFEST_FILE_NAME),
+        format!("{manifest_json}\n"),
+    )?;
+
+    Ok(manifest)
+}
+
+fn build_manifest(
+    scope: &ExportScope,
+    options: &ExportOptions,
+    exported: Vec<ExportedDocumentPayload>,
+) -> ExportManifestPayload {
+    ExportManifestPayload {
+        format: options.format,
+        exported_at: document_store::now_iso_string_utc(),
+        folder: match scope {
+            ExportScope::Folder(folder) => Some(folder.clone()),
+            ExportScope::Document(_) | ExportScope::Tag(_) => None,
+        },
+        tag: match scope {
+            ExportScope::Tag(tag) => Some(tag.clone()),
+            ExportScope::Document(_) | ExportScope::Folder(_) => None,
+        },
+        strip_frontmatter: options.strip_frontmatter,
+        document_count: exported.len(),
+        attachment_count: exported
+            .iter()
+            .map(|document| document.attachments.len())
+            .sum(),
+        documents: exported,
+    }
+}
+
+fn collect_documents(
+    documents_folder: &Path,
+    scope: &ExportScope,
+    options: &ExportOptions,
+) -> Result<Vec<StoredDocument>, ExportError> {
+    if let ExportScope::Document(document_id) = scope {
+        return Ok(vec![document_store::read_document(
+            documents_folder,
+            document_id,
+        )?]);
+    }
+
+    let mut items = document_store::list_documents(documents_folder)?;
+    match scope {
+        ExportScope::Document(_) => {}
+        ExportScope::Folder(folder) => {
+            let folder = folder.trim_matches('/');
+            if !folder.is_empty() && !documents_folder.join(folder).is_dir() {
+                return Err(ExportError::FolderNotFound(format!(
+                    "folder \"{folder}\" was not found"
+                )));
+            }
+            items.retain(|document| {
+                folder.is_empty()
+                    || document.folder_path == folder
+                    || document.folder_path.starts_with(&format!("{folder}/"))
+            });
+        }
+        ExportScope::Tag(tag) => {
+            let tag = tag.trim().to_lowercase();
+            items.retain(|document| document.tags.contains(&tag));
+            if items.is_empty() {
+                return Err(ExportError::Validation(format!(
+                    "no documents are tagged \"{tag}\""
+                )));
+            }
+        }
+    }
+
+    if options.sort.is_some() || options.desc {
+        document_store::sort_documents(&mut items, options.sort, options.desc);
+    } else {
+        items.sort_by(|left, right| {
+            left.folder_path
+                .cmp(&right.folder_path)
+                .then_with(|| left.title.cmp(&right.title))
+        });
+    }
+
+    items
+        .iter()
+        .map(|item| {
+            document_store::read_document(documents_folder, &item.id).map_err(ExportError::from)
+        })
+        .collect()
+}
+
+fn prepare_destination(destination: &Path) -> Result<(), ExportError> {
+    if destination.exists() {
+        if !destination.is_dir() {
+            return Err(ExportError::Validation(format!(
+                "export destination \"{}\" is not a directory",
+                destination.display()
+            )));
+        }
+        if fs::read_dir(destination)?.next().is_some() {
+            return Err(ExportError::Validation(format!(
+                "export destination \"{}\" is not empty",
+                destination.display()
+            )));
+        }
+    }
+    fs::create_dir_all(destination)?;
+    Ok(())
+}
+
+/// Copies `.attachments/<id>/` into the export and returns the copied files relative to
+/// `destination`, so links written relative to the vault root keep resolving.
+fn copy_attachments(
+    documents_folder: &Path,
+    document_id: &str,
+    destination: &Path,
+) -> Result<Vec<String>, ExportError> {
+    let relative_folder = format!("{ATTACHMENTS_FOLDER}/{document_id}");
+    let source_folder = documents_folder.join(&relative_folder);
+    if !source_folder.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut copied = Vec::new();
+    let mut pending = vec![String::new()];
+    while let Some(relative_directory) = pending.pop() {
+        let source_directory = source_folder.join(&relative_directory);
+        let target_directory = destination.join(&relative_folder).join(&relative_directory);
+        fs::create_dir_all(&target_directory)?;
+        for entry in fs::read_dir(&source_directory)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative_path = join_relative(&relative_directory, &name);
+            if entry.file_type()?.is_dir() {
+                pending.push(relative_path);
+            } else {
+                fs::copy(entry.path(), target_directory.join(&name))?;
+                copied.push(format!("{relative_folder}/{relative_path}"));
+            }
+        }
+    }
+    copied.sort();
+    Ok(copied)
+}
+
+/// Vault-relative markdown file of `document`, which links from other documents point at.
+fn document_path(document: &StoredDocument) -> String {
+    join_relative(
+        &document.folder_path,
+        &document_store::document_file_name(&document.title),
+    )
+}
+
+fn markdown_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+fn join_relative(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{prefix}/{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_store::CreateDocumentInput;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_path(prefix: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}-{timestamp}"))
+    }
+
+    fn create_fixture_vault(vault: &Path) -> (StoredDocument, StoredDocument) {
+        let spec = document_store::create_document(
+            vault,
+            &CreateDocumentInput {
+                title: Some("Spec".to_owned()),
+                body: Some("See the diagram.".to_owned()),
+                folder_path: Some("projects/alpha".to_owned()),
+                tags: vec!["design".to_owned()],
+                properties: BTreeMap::from([("owner".to_owned(), "Ana".to_owned())]),
+                ..CreateDocumentInput::default()
+            },
+        )
+        .expect("create spec");
+        document_store::write_document_attachment(vault, &spec.id, "diagram.png", b"png")
+            .expect("write attachment");
+        let inbox = document_store::create_document(
+            vault,
+            &CreateDocumentInput {
+                title: Some("Inbox note".to_owned()),
+                body: Some("Loose thought.".to_owned()),
+                ..CreateDocumentInput::default()
+            },
+        )
+        .expect("create inbox note");
+        (spec, inbox)
+    }
+
+    #[test]
+    fn exports_folder_as_markdown_with_attachments_and_manifest() {
+        let root = unique_temp_path("tentacle-export-markdown");
+        let vault = root.join("vault");
+        let destination = root.join("out");
+        let (spec, _) = create_fixture_vault(&vault);
+
+        let manifest = export_documents(
+            &vault,
+            &ExportScope::Folder("projects".to_owned()),
+            &destination,
+            &ExportOptions {
+                format: ExportFormat::Markdown,
+                strip_frontmatter: true,
+                ..ExportOptions::default()
+            },
+        )
+        .expect("export folder");
+
+        assert_eq!(manifest.document_count, 1);
+        assert_eq!(manifest.documents[0].path, "projects/alpha/Spec.md");
+        assert_eq!(
+            manifest.documents[0].attachments,
+            vec![format!(".attachments/{}/diagram.png", spec.id)]
+        );
+        assert_eq!(
+            fs::read_to_string(destination.join("projects/alpha/Spec.md")).expect("read export"),
+            "---\ntags: [\"design\"]\nowner: \"Ana\"\n---\n# Spec\n\nSee the diagram."
+        );
+        assert!(destination
+            .join(format!(".attachments/{}/diagram.png", spec.id))
+            .is_file());
+        assert!(destination.join(EXPORT_MANIFEST_FILE_NAME).is_file());
+
+        let error = export_documents(
+            &vault,
+            &ExportScope::Folder(String::new()),
+            &destination,
+            &ExportOptions::default(),
+        )
+        .expect_err("non-empty destination is rejected");
+        assert!(matches!(error, ExportError::Validation(_)));
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn exports_whole_vault_as_jsonl_records() {
+        let root = unique_temp_path("tentacle-export-jsonl");
+        let vault = root.join("vault");
+        let destination = root.join("out");
+        let (spec, inbox) = create_fixture_vault(&vault);
+
+        let manifest = export_documents(
+            &vault,
+            &ExportScope::Folder(String::new()),
+            &destination,
+            &ExportOptions {
+                format: ExportFormat::Jsonl,
+                strip_frontmatter: false,
+                ..ExportOptions::default()
+            },
+        )
+        .expect("export vault");
+        assert_eq!(manifest.document_count, 2);
+        assert_eq!(manifest.attachment_count, 1);
+
+        let records = fs::read_to_string(destination.join(JSONL_EXPORT_FILE_NAME))
+            .expect("read jsonl")
+            .lines()
+            .map(|line| serde_json::from_str::<StoredDocument>(line).expect("parse record"))
+            .collect::<Vec<_>>();
+        assert_eq!(records, vec![inbox, spec]);
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn exports_folder_as_static_site_with_tags_backlinks_and_search_index() {
+        let root = unique_temp_path("tentacle-export-html");
+        let vault = root.join("vault");
+        let destination = root.join("out");
+        let (spec, _) = create_fixture_vault(&vault);
+        let overview = document_store::create_document(
+            &vault,
+            &CreateDocumentInput {
+                title: Some("Overview".to_owned()),
+                body: Some(format!(
+                    "Read the [spec](alpha/Spec.md) first.\n\n![diagram](../.attachments/{}/diagram.png)",
+                    spec.id
+                )),
+                folder_path: Some("projects".to_owned()),
+                tags: vec!["design".to_owned(), "a-b".to_owned(), "a/b".to_owned()],
+                ..CreateDocumentInput::default()
+            },
+        )
+        .expect("create overview");
+
+        let manifest = export_documents(
+            &vault,
+            &ExportScope::Folder("projects".to_owned()),
+            &destination,
+            &ExportOptions {
+                format: ExportFormat::Html,
+                strip_frontmatter: false,
+                ..ExportOptions::default()
+            },
+        )
+        .expect("export site");
+
+        assert_eq!(manifest.document_count, 2);
+        assert_eq!(manifest.documents[0].path, "projects/Overview.html");
+        assert_eq!(manifest.documents[1].path, "projects/alpha/Spec.html");
+
+        let overview_page =
+            fs::read_to_string(destination.join("projects/Overview.html")).expect("read page");
+        assert!(overview_page.contains("<a href=\"../projects/alpha/Spec.html\">spec</a>"));
+        assert!(overview_page.contains(&format!(
+            "<img src=\"../.attachments/{}/diagram.png\"",
+            spec.id
+        )));
+        assert!(overview_page.contains("<script src=\"../search-index.js\"></script>"));
+        assert!(overview_page.contains("<a href=\"../tags/a-b.html\">#a-b</a>"));
+        assert!(overview_page.contains("<a href=\"../tags/a%252Fb.html\">#a/b</a>"));
+        assert!(destination.join("tags/a-b.html").is_file());
+        assert!(destination.join("tags/a%2Fb.html").is_file());
+
+        let spec_page = fs::read_to_string(destination.join("projects/alpha/Spec.html"))
+            .expect("read spec page");
+        assert!(spec_page.contains("<h2>Linked from</h2>"));
+        assert!(spec_page.contains("<a href=\"../../projects/Overview.html\">Overview</a>"));
+        assert!(spec_page.contains("<a href=\"../../tags/design.html\">#design</a>"));
+
+        let tag_page =
+            fs::read_to_string(destination.join("tags/design.html")).expect("read tag page");
+        assert!(tag_page.contains("../projects/Overview.html"));
+        assert!(tag_page.contains("../projects/alpha/Spec.html"));
+        assert!(destination.join("index.html").is_file());
+        assert!(destination.join("assets/search.js").is_file());
+        assert!(destination
+            .join(format!(".attachments/{}/diagram.png", spec.id))
+            .is_file());
+
+        let index: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(destination.join("search-index.json")).expect("read index"),
+        )
+        .expect("parse index");
+        let entries = index.as_array().expect("index array");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["id"], overview.id);
+        assert_eq!(entries[1]["url"], "projects/alpha/Spec.html");
+        assert_eq!(entries[1]["text"], "See the diagram.");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn exports_tag_as_epub_in_requested_order_with_embedded_images() {
+        use std::io::Read;
+
+        let root = unique_temp_path("tentacle-export-epub");
+        let vault = root.join("vault");
+        let destination = root.join("out/design.epub");
+        let (spec, _) = create_fixture_vault(&vault);
+        let overview = document_store::create_document(
+            &vault,
+            &CreateDocumentInput {
+                title: Some("Architecture overview".to_owned()),
+                body: Some(format!(
+                    "Start with the [spec](projects/alpha/Spec.md).\n\n<!-- page 1 -->\n\n![diagram](.attachments/{id}/diagram.png)",
+                    id = spec.id
+                )),
+                tags: vec!["design".to_owned()],
+                ..CreateDocumentInput::default()
+            },
+        )
+        .expect("create overview");
+
+        let manifest = export_documents(
+            &vault,
+            &ExportScope::Tag("Design".to_owned()),
+            &destination,
+            &ExportOptions {
+                format: ExportFormat::Epub,
+                sort: Some(DocumentSort::Title),
+                author: Some("Ana".to_owned()),
+                ..ExportOptions::default()
+            },
+        )
+        .expect("export epub");
+
+        assert_eq!(manifest.tag.as_deref(), Some("Design"));
+        assert_eq!(
+            manifest
+                .documents
+                .iter()
+                .map(|document| (document.id.as_str(), document.path.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (overview.id.as_str(), "OEBPS/chapter-001.xhtml"),
+                (spec.id.as_str(), "OEBPS/chapter-002.xhtml"),
+            ]
+        );
+        assert_eq!(
+            manifest.documents[0].attachments,
+            vec!["OEBPS/images/image-001.png"]
+        );
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(&destination).expect("open epub"))
+            .expect("read epub");
+        let read_entry = |archive: &mut zip::ZipArchive<fs::File>, name: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(name)
+                .expect("entry exists")
+                .read_to_string(&mut content)
+                .expect("read entry");
+            content
+        };
+        {
+            let mimetype = archive.by_index(0).expect("first entry");
+            assert_eq!(mimetype.name(), "mimetype");
+            assert_eq!(mimetype.compression(), zip::CompressionMethod::Stored);
+        }
+        let package = read_entry(&mut archive, "OEBPS/content.opf");
+        assert!(package.contains("<dc:title>#design</dc:title>"));
+        assert!(package.contains("<dc:creator>Ana</dc:creator>"));
+        assert!(package.contains("href=\"images/image-001.png\" media-type=\"image/png\""));
+        let navigation = read_entry(&mut archive, "OEBPS/nav.xhtml");
+        assert!(
+            navigation
+                .find("Architecture overview")
+                .expect("overview entry")
+                < navigation.find("Spec").expect("spec entry")
+        );
+        let chapter = read_entry(&mut archive, "OEBPS/chapter-001.xhtml");
+        assert!(chapter.contains("<a href=\"chapter-002.xhtml\">spec</a>"));
+        assert!(chapter.contains("<img src=\"images/image-001.png\" alt=\"diagram\" />"));
+        assert!(!chapter.contains("<!--"));
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,417 @@
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use super::{document_path, escape_html, join_relative, markdown_options, ExportError};
+use crate::document_cache::DocumentCacheStore;
+use crate::document_store::{self, StoredDocument};
+use crate::importers::{linked_document_path, linked_document_paths};
+use crate::text_processing::extract_plain_text_from_tiptap_or_raw;
+
+const CACHE_DB_FILE_NAME: &str = ".document-data.db";
+const INDEX_PAGE: &str = "index.html";
+const TAGS_FOLDER: &str = "tags";
+const ASSETS_FOLDER: &str = "assets";
+/// Loaded through a `<script>` tag because browsers refuse `fetch` on `file://` URLs.
+const SEARCH_INDEX_SCRIPT: &str = "search-index.js";
+const SEARCH_INDEX_JSON: &str = "search-index.json";
+
+const STYLE_CSS: &str = r#"* { box-sizing: border-box; }
+body { margin: 0; display: flex; min-height: 100vh; font: 16px/1.6 -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; color: #1f2328; }
+aside { width: 280px; flex-shrink: 0; padding: 1.25rem; border-right: 1px solid #d0d7de; background: #f6f8fa; overflow-y: auto; }
+aside ul { list-style: none; margin: 0; padding-left: 0.9rem; }
+aside > nav > ul { padding-left: 0; }
+aside a { color: inherit; text-decoration: none; }
+aside a:hover, aside a.current { text-decoration: underline; }
+aside .folder { font-weight: 600; margin-top: 0.5rem; }
+main { flex: 1; max-width: 860px; padding: 2rem 3rem; }
+main img { max-width: 100%; }
+main pre { padding: 1rem; overflow-x: auto; background: #f6f8fa; border-radius: 6px; }
+main table { border-collapse: collapse; }
+main th, main td { padding: 0.3rem 0.7rem; border: 1px solid #d0d7de; }
+.home { display: block; margin-bottom: 1rem; font-weight: 700; }
+.tags a { display: inline-block; margin-right: 0.4rem; padding: 0 0.5rem; border-radius: 999px; background: #ddf4ff; color: #0969da; text-decoration: none; font-size: 0.85rem; }
+.backlinks { margin-top: 3rem; padding-top: 1rem; border-top: 1px solid #d0d7de; }
+#search { width: 100%; margin-bottom: 1rem; padding: 0.4rem 0.6rem; border: 1px solid #d0d7de; border-radius: 6px; }
+#search-results { list-style: none; padding: 0; }
+#search-results li { margin-bottom: 0.75rem; }
+#search-results small { display: block; color: #59636e; }
+"#;
+
+const SEARCH_JS: &str = r#"(function () {
+  var input = document.getElementById("search");
+  var results = document.getElementById("search-results");
+  var content = document.getElementById("content");
+  var entries = window.TENTACLE_SEARCH_INDEX || [];
+  var root = document.body.getAttribute("data-root") || "";
+  if (!input || !results || !content) {
+    return;
+  }
+
+  function snippet(text, term) {
+    var at = text.toLowerCase().indexOf(term);
+    var start = Math.max(0, at - 60);
+    return (start > 0 ? "…" : "") + text.slice(start, start + 160) + (start + 160 < text.length ? "…" : "");
+  }
+
+  input.addEventListener("input", function () {
+    var terms = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+    results.innerHTML = "";
+    content.hidden = terms.length > 0;
+    results.hidden = terms.length === 0;
+    if (terms.length === 0) {
+      return;
+    }
+    var matches = entries.filter(function (entry) {
+      var haystack = (entry.title + " " + entry.tags.join(" ") + " " + entry.text).toLowerCase();
+      return terms.every(function (term) { return haystack.indexOf(term) !== -1; });
+    });
+    matches.sort(function (left, right) {
+      var leftTitle = terms.some(function (term) { return left.title.toLowerCase().indexOf(term) !== -1; });
+      var rightTitle = terms.some(function (term) { return right.title.toLowerCase().indexOf(term) !== -1; });
+      return (rightTitle ? 1 : 0) - (leftTitle ? 1 : 0);
+    });
+    if (matches.length === 0) {
+      results.innerHTML = "<li>No matching pages.</li>";
+      return;
+    }
+    matches.slice(0, 50).forEach(function (entry) {
+      var item = document.createElement("li");
+      var link = document.createElement("a");
+      link.href = root + entry.url;
+      link.textContent = entry.title;
+      var detail = document.createElement("small");
+      detail.textContent = snippet(entry.text, terms[0]);
+      item.appendChild(link);
+      item.appendChild(detail);
+      results.appendChild(item);
+    });
+  });
+})();
+"#;
+
+#[derive(Debug, Serialize)]
+struct SearchIndexEntry<'a> {
+    id: &'a str,
+    title: &'a str,
+    folder_path: &'a str,
+    tags: &'a [String],
+    /// URL-encoded page path relative to the site root.
+    url: String,
+    text: String,
+}
+
+#[derive(Default)]
+struct FolderNode<'a> {
+    folders: BTreeMap<&'a str, FolderNode<'a>>,
+    pages: Vec<(&'a str, &'a str)>,
+}
+
+/// Destination-relative page written for `document`, mirroring its vault folder.
+pub(super) fn page_path(document: &StoredDocument) -> String {
+    let file_name = document_store::document_file_name(&document.title);
+    let stem = file_name.strip_suffix(".md").unwrap_or(&file_name);
+    join_relative(&document.folder_path, &format!("{stem}.html"))
+}
+
+/// Writes a self-contained static site for `documents`: one page per document, tag index
+/// pages, an index page, and a client-side search index. Every link is relative so the
+/// site can be opened straight from disk.
+pub(super) fn write_site(
+    documents_folder: &Path,
+    documents: &[StoredDocument],
+    destination: &Path,
+) -> Result<(), ExportError> {
+    let pages = documents
+        .iter()
+        .map(|document| (document.id.as_str(), page_path(document)))
+        .collect::<HashMap<_, _>>();
+    let ids_by_path = documents
+        .iter()
+        .map(|document| (document_path(document), document.id.as_str()))
+        .collect::<HashMap<_, _>>();
+    let titles = documents
+        .iter()
+        .map(|document| (document.id.as_str(), document.title.as_str()))
+        .collect::<HashMap<_, _>>();
+
+    let mut tree = FolderNode::default();
+    for document in documents {
+        let mut node = &mut tree;
+        for segment in document
+            .folder_path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+        {
+            node = node.folders.entry(segment).or_default();
+        }
+        node.pages
+            .push((&document.title, &pages[document.id.as_str()]));
+    }
+
+    let mut backlinks: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for document in documents {
+        let targets = linked_document_paths(&document_path(document), &document.body);
+        for target_id in targets
+            .iter()
+            .filter_map(|path| ids_by_path.get(path).copied())
+        {
+            if target_id != document.id {
+                backlinks
+                    .entry(target_id)
+                    .or_default()
+                    .insert(document.id.as_str());
+            }
+        }
+    }
+
+    let mut tags: BTreeMap<&str, Vec<&StoredDocument>> = BTreeMap::new();
+    for document in documents {
+        for tag in &document.tags {
+            tags.entry(tag.as_str()).or_default().push(document);
+        }
+    }
+
+    for document in documents {
+        let path = &pages[document.id.as_str()];
+        let root = root_prefix(path);
+        let mut content = format!("<h1>{}</h1>\n", escape_html(&document.title));
+        if !document.tags.is_empty() {
+            content.push_str("<p class=\"tags\">");
+            for tag in &document.tags {
+                content.push_str(&format!(
+                    "<a href=\"{root}{}\">#{}</a>",
+                    escape_html(&encode_path(&tag_page_path(tag))),
+                    escape_html(tag)
+                ));
+            }
+            content.push_str("</p>\n");
+        }
+        content.push_str(&render_markdown(document, &root, &pages, &ids_by_path));
+        if let Some(sources) = backlinks.get(document.id.as_str()) {
+            content.push_str("<section class=\"backlinks\">\n<h2>Linked from</h2>\n<ul>\n");
+            for source in sources {
+                content.push_str(&format!(
+                    "<li><a href=\"{root}{}\">{}</a></li>\n",
+                    escape_html(&encode_path(&pages[source])),
+                    escape_html(titles[source])
+                ));
+            }
+            content.push_str("</ul>\n</section>\n");
+        }
+        write_page(destination, path, &document.title, &tree, &content)?;
+    }
+
+    let mut index = String::from("<h1>Documents</h1>\n<ul>\n");
+    for document in documents {
+        index.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            escape_html(&encode_path(&pages[document.id.as_str()])),
+            escape_html(&document.title)
+        ));
+    }
+    index.push_str("</ul>\n");
+    if !tags.is_empty() {
+        index.push_str("<h2>Tags</h2>\n<p class=\"tags\">");
+        for tag in tags.keys() {
+            index.push_str(&format!(
+                "<a href=\"{}\">#{}</a>",
+                escape_html(&encode_path(&tag_page_path(tag))),
+                escape_html(tag)
+            ));
+        }
+        index.push_str("</p>\n");
+    }
+    write_page(destination, INDEX_PAGE, "Documents", &tree, &index)?;
+
+    for (tag, tagged) in &tags {
+        let path = tag_page_path(tag);
+        let root = root_prefix(&path);
+        let mut content = format!("<h1>#{}</h1>\n<ul>\n", escape_html(tag));
+        for document in tagged {
+            content.push_str(&format!(
+                "<li><a href=\"{root}{}\">{}</a></li>\n",
+                escape_html(&encode_path(&pages[document.id.as_str()])),
+                escape_html(&document.title)
+            ));
+        }
+        content.push_str("</ul>\n");
+        write_page(destination, &path, &format!("#{tag}"), &tree, &content)?;
+    }
+
+    write_search_index(documents_folder, documents, &pages, destination)?;
+    fs::create_dir_all(destination.join(ASSETS_FOLDER))?;
+    fs::write(destination.join(ASSETS_FOLDER).join("style.css"), STYLE_CSS)?;
+    fs::write(destination.join(ASSETS_FOLDER).join("search.js"), SEARCH_JS)?;
+    Ok(())
+}
+
+/// Builds the search index from the cached FTS bodies, falling back to the stored body
+/// for documents the cache has not indexed yet. The cache is only read when it already
+/// exists so exporting never creates one.
+fn write_search_index(
+    documents_folder: &Path,
+    documents: &[StoredDocument],
+    pages: &HashMap<&str, String>,
+    destination: &Path,
+) -> Result<(), ExportError> {
+    let mut indexed_bodies = HashMap::new();
+    if documents_folder.join(CACHE_DB_FILE_NAME).is_file() {
+        for cached in DocumentCacheStore::new(documents_folder)?.list_documents()? {
+            indexed_bodies.insert(cached.id, cached.body);
+        }
+    }
+
+    let entries = documents
+        .iter()
+        .map(|document| {
+            let body = indexed_bodies.get(&document.id).unwrap_or(&document.body);
+            SearchIndexEntry {
+                id: &document.id,
+                title: &document.title,
+                folder_path: &document.folder_path,
+                tags: &document.tags,
+                url: encode_path(&pages[document.id.as_str()]),
+                text: extract_plain_text_from_tiptap_or_raw(body),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let json = serde_json::to_string(&entries)?;
+    fs::write(destination.join(SEARCH_INDEX_JSON), format!("{json}\n"))?;
+    fs::write(
+        destination.join(SEARCH_INDEX_SCRIPT),
+        format!("window.TENTACLE_SEARCH_INDEX = {json};\n"),
+    )?;
+    Ok(())
+}
+
+fn write_page(
+    destination: &Path,
+    path: &str,
+    title: &str,
+    tree: &FolderNode<'_>,
+    content: &str,
+) -> Result<(), ExportError> {
+    let root = root_prefix(path);
+    let mut navigation = String::new();
+    render_navigation(tree, &root, path, &mut navigation);
+    let html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+<title>{title}</title>\n<link rel=\"stylesheet\" href=\"{root}{ASSETS_FOLDER}/style.css\">\n\
+</head>\n<body data-root=\"{root}\">\n<aside>\n<a class=\"home\" href=\"{root}{INDEX_PAGE}\">Home</a>\n\
+<input id=\"search\" type=\"search\" placeholder=\"Search\" autocomplete=\"off\">\n\
+<nav>\n{navigation}</nav>\n</aside>\n<main>\n<ul id=\"search-results\" hidden></ul>\n\
+<article id=\"content\">\n{content}</article>\n</main>\n\
+<script src=\"{root}{SEARCH_INDEX_SCRIPT}\"></script>\n\
+<script src=\"{root}{ASSETS_FOLDER}/search.js\"></script>\n</body>\n</html>\n",
+        title = escape_html(title),
+    );
+
+    let target = destination.join(path);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(target, html)?;
+    Ok(())
+}
+
+fn render_navigation(node: &FolderNode<'_>, root: &str, current: &str, output: &mut String) {
+    output.push_str("<ul>\n");
+    for (name, child) in &node.folders {
+        output.push_str(&format!(
+            "<li><div class=\"folder\">{}</div>\n",
+            escape_html(name)
+        ));
+        render_navigation(child, root, current, output);
+        output.push_str("</li>\n");
+    }
+    for (title, path) in &node.pages {
+        let class = if *path == current {
+            " class=\"current\""
+        } else {
+            ""
+        };
+        output.push_str(&format!(
+            "<li><a{class} href=\"{root}{}\">{}</a></li>\n",
+            escape_html(&encode_path(path)),
+            escape_html(title)
+        ));
+    }
+    output.push_str("</ul>\n");
+}
+
+/// Renders a document body, pointing links to other exported documents at their pages.
+fn render_markdown(
+    document: &StoredDocument,
+    root: &str,
+    pages: &HashMap<&str, String>,
+    ids_by_path: &HashMap<String, &str>,
+) -> String {
+    let document_path = document_path(document);
+    let events = Parser::new_ext(&document.body, markdown_options()).map(|event| match event {
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = linked_document_path(&document_path, &dest_url)
+                .and_then(|path| ids_by_path.get(&path))
+                .map(|target_id| encode_path(&pages[target_id]))
+                .map(|page| CowStr::from(format!("{root}{page}")))
+                .unwrap_or(dest_url);
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        other => other,
+    });
+
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    output
+}
+
+/// Tag page file, with every character other than letters, digits, `-` and `_` percent-encoded
+/// so distinct tags such as `a-b` and `a/b` never share a page.
+fn tag_page_path(tag: &str) -> String {
+    let mut name = String::with_capacity(tag.len());
+    for character in tag.chars() {
+        if character.is_alphanumeric() || character == '-' || character == '_' {
+            name.push(character);
+        } else {
+            let mut buffer = [0u8; 4];
+            for byte in character.encode_utf8(&mut buffer).bytes() {
+                name.push_str(&format!("%{byte:02X}"));
+            }
+        }
+    }
+    format!("{TAGS_FOLDER}/{name}.html")
+}
+
+/// Percent-encodes the characters that would otherwise end or break a relative URL path.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for character in path.chars() {
+        match character {
+            '%' => encoded.push_str("%25"),
+            ' ' => encoded.push_str("%20"),
+            '#' => encoded.push_str("%23"),
+            '?' => encoded.push_str("%3F"),
+            _ => encoded.push(character),
+        }
+    }
+    encoded
+}
+
+/// `../` repeated once per folder above `path`, so links resolve from any page depth.
+fn root_prefix(path: &str) -> String {
+    "../".repeat(path.matches('/').count())
+}
@@ -0,0 +1,466 @@
+use scraper::{ElementRef, Html, Node};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::document_store::{self, CreateDocumentInput};
+use crate::text_processing::markdown_link_destination;
+
+use super::{
+    ensure_folder_path, format_unix_seconds_utc, join_folder_path, normalize_base_folder,
+    sanitize_folder_segment, ImportError, ImportOptions, ImportResultPayload, ImportSkippedPayload,
+    ImportedDocumentPayload,
+};
+
+/// Doctype marker every browser writes at the top of a bookmark export.
+pub const NETSCAPE_BOOKMARK_MARKER: &str = "NETSCAPE-Bookmark-file";
+
+const SUPPORTED_URL_SCHEMES: [&str; 4] = ["http://", "https://", "ftp://", "file://"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bookmark {
+    folder_path: String,
+    title: String,
+    url: String,
+    add_date: Option<String>,
+    last_modified: Option<String>,
+    tags: Vec<String>,
+    description: Option<String>,
+}
+
+/// Returns true when `bytes` look like a Netscape bookmark export rather than a saved page.
+pub fn is_netscape_bookmark_file(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(1024)];
+    String::from_utf8_lossy(head).contains(NETSCAPE_BOOKMARK_MARKER)
+}
+
+/// Imports a Netscape bookmark export (the HTML file every browser exports).
+///
+/// Each bookmark becomes a note linking to its URL, with the URL stored as the `source`
+/// property and `ADD_DATE` as `added_at`/`created_at`. Bookmark folders become folders under
+/// `options.folder_path`, Firefox `TAGS` become tags, and bookmarks whose URL already appears
+/// as the `source` of an existing note are reported as skipped.
+pub fn import_bookmarks(
+    documents_folder: &Path,
+    source: &Path,
+    options: &ImportOptions,
+) -> Result<ImportResultPayload, ImportError> {
+    if !source.is_file() {
+        return Err(ImportError::Validation(format!(
+            "bookmark import expects an exported bookmarks .html file, got \"{}\"",
+            source.display()
+        )));
+    }
+    let bytes = fs::read(source)?;
+    if !is_netscape_bookmark_file(&bytes) {
+        return Err(ImportError::Validation(format!(
+            "\"{}\" is not a Netscape bookmark export",
+            source.display()
+        )));
+    }
+
+    let base_folder = normalize_base_folder(options.folder_path.as_deref());
+    let bookmarks = parse_bookmarks(&String::from_utf8_lossy(&bytes), &base_folder);
+
+    let mut known_urls = document_store::list_documents(documents_folder)?
+        .into_iter()
+        .filter_map(|document| {
+            document
+                .properties
+                .get("source")
+                .map(|url| normalize_url(url))
+        })
+        .collect::<HashSet<_>>();
+
+    let mut result = ImportResultPayload::default();
+    ensure_folder_path(documents_folder, &base_folder, &mut result)?;
+    for bookmark in bookmarks {
+        let source_path = if bookmark.folder_path.is_empty() {
+            bookmark.title.clone()
+        } else {
+            format!("{}/{}", bookmark.folder_path, bookmark.title)
+        };
+        let url = bookmark.url.to_ascii_lowercase();
+        if !SUPPORTED_URL_SCHEMES
+            .iter()
+            .any(|scheme| url.starts_with(scheme))
+        {
+            result.skipped.push(ImportSkippedPayload {
+                source_path,
+                reason: "unsupported URL scheme".to_owned(),
+            });
+            continue;
+        }
+        if !known_urls.insert(normalize_url(&bookmark.url)) {
+            result.skipped.push(ImportSkippedPayload {
+                source_path,
+                reason: "a note with this URL already exists".to_owned(),
+            });
+            continue;
+        }
+
+        ensure_folder_path(documents_folder, &bookmark.folder_path, &mut result)?;
+
+        let mut body = format!(
+            "[{}]({})",
+            escape_link_text(&bookmark.title),
+            markdown_link_destination(&bookmark.url)
+        );
+        if let Some(description) = bookmark.description.as_ref() {
+            body.push_str("\n\n");
+            body.push_str(description);
+        }
+
+        let mut properties = BTreeMap::new();
+        properties.insert("source".to_owned(), bookmark.url.clone());
+        if let Some(add_date) = bookmark.add_date.as_ref() {
+            properties.insert("added_at".to_owned(), add_date.clone());
+        }
+
+        let mut tags = options.tags.clone();
+        tags.extend(bookmark.tags.iter().cloned());
+
+        let created = document_store::create_document(
+            documents_folder,
+            &CreateDocumentInput {
+                title: Some(bookmark.title.clone()),
+                body: Some(body),
+                folder_path: Some(bookmark.folder_path.clone()),
+                tags,
+                tags_locked: Some(false),
+                properties,
+                created_at: bookmark.add_date.clone(),
+                updated_at: bookmark.last_modified.clone().or(bookmark.add_date.clone()),
+                ..CreateDocumentInput::default()
+            },
+        )?;
+
+        result.documents.push(ImportedDocumentPayload {
+            id: created.id,
+            title: created.title,
+            folder_path: created.folder_path,
+            source_path,
+        });
+    }
+
+    Ok(result)
+}
+
+/// Walks the export in document order. Browsers nest `<DL>` lists inside `<DT>`/`<DD>` items
+/// inconsistently, so a folder is tracked as "the last `<H3>` before a `<DL>`" rather than by
+/// tree position.
+fn parse_bookmarks(html: &str, base_folder: &str) -> Vec<Bookmark> {
+    struct Walker {
+        bookmarks: Vec<Bookmark>,
+        pending_folder: Option<String>,
+        last_was_bookmark: bool,
+    }
+
+    impl Walker {
+        fn walk(&mut self, element: ElementRef<'_>, folder_path: &str) {
+            for child in element.children() {
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                match child.value().name() {
+                    "h3" => {
+                        self.pending_folder =
+                            Some(collapse_whitespace(&child.text().collect::<String>()));
+                        self.last_was_bookmark = false;
+                    }
+                    "dl" => {
+                        let folder = match self.pending_folder.take() {
+                            Some(name) if !name.is_empty() => {
+                                join_folder_path(folder_path, &sanitize_folder_segment(&name))
+                            }
+                            _ => folder_path.to_owned(),
+                        };
+                        self.last_was_bookmark = false;
+                        self.walk(child, &folder);
+                    }
+                    "a" => {
+                        if let Some(bookmark) = parse_bookmark_link(child, folder_path) {
+                            self.bookmarks.push(bookmark);
+                            self.last_was_bookmark = true;
+                        }
+                    }
+                    "dd" => {
+                        let description = collapse_whitespace(
+                            &child
+                                .children()
+                                .filter_map(|node| match node.value() {
+                                    Node::Text(text) => Some(&**text),
+                                    _ => None,
+                                })
+                                .collect::<String>(),
+                        );
+                        if self.last_was_bookmark && !description.is_empty() {
+                            if let Some(bookmark) = self.bookmarks.last_mut() {
+                                bookmark.description = Some(description);
+                            }
+                        }
+                        self.last_was_bookmark = false;
+                        self.walk(child, folder_path);
+                    }
+                    _ => self.walk(child, folder_path),
+                }
+            }
+        }
+    }
+
+    let document = Html::parse_document(html);
+    let mut walker = Walker {
+        bookmarks: Vec::new(),
+        pending_folder: None,
+        last_was_bookmark: false,
+    };
+    walker.walk(document.root_element(), base_folder);
+    walker.bookmarks
+}
+
+fn parse_bookmark_link(link: ElementRef<'_>, folder_path: &str) -> Option<Bookmark> {
+    let attributes = link.value();
+    let url = attributes.attr("href")?.trim().to_owned();
+    if url.is_empty() {
+        return None;
+    }
+    let title = collapse_whitespace(&link.text().collect::<String>());
+    let tags = attributes
+        .attr("tags")
+        .map(|tags| {
+            tags.split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(Bookmark {
+        folder_path: folder_path.to_owned(),
+        title: if title.is_empty() { url.clone() } else { title },
+        add_date: attributes
+            .attr("add_date")
+            .and_then(parse_bookmark_timestamp),
+        last_modified: attributes
+            .attr("last_modified")
+            .and_then(parse_bookmark_timestamp),
+        url,
+        tags,
+        description: None,
+    })
+}
+
+/// `ADD_DATE` is seconds since the unix epoch; some exporters write milliseconds or
+/// microseconds instead, which are scaled down.
+fn parse_bookmark_timestamp(value: &str) -> Option<String> {
+    let mut seconds = value
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|value| *value > 0)?;
+    while seconds > 100_000_000_000 {
+        seconds /= 1_000;
+    }
+    Some(format_unix_seconds_utc(seconds))
+}
+
+/// Compares URLs ignoring surrounding whitespace, a trailing slash, and scheme/host case.
+fn normalize_url(url: &str) -> String {
+    let trimmed = url.trim().trim_end_matches('/');
+    match trimmed.split_once("://") {
+        Some((scheme, rest)) => {
+            let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+            let mut normalized = format!(
+                "{}://{}",
+                scheme.to_ascii_lowercase(),
+                host.to_ascii_lowercase()
+            );
+            if !path.is_empty() {
+                normalized.push('/');
+                normalized.push_str(path);
+            }
+            normalized
+        }
+        None => trimmed.to_owned(),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape_link_text(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_path(prefix: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}-{timestamp}"))
+    }
+
+    const BOOKMARKS_HTML: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+<DL><p>
+    <DT><A HREF="https://www.rust-lang.org/" ADD_DATE="1700000000" TAGS="rust,lang">Rust</A>
+    <DD>The Rust homepage
+    <DT><H3 ADD_DATE="1700000000">Reading</H3>
+    <DL><p>
+        <DT><H3>Databases</H3>
+        <DL><p>
+            <DT><A HREF="https://sqlite.org/fts5.html" ADD_DATE="1700000000000000" LAST_MODIFIED="1700000600">SQLite FTS5</A>
+        </DL><p>
+        <DT><A HREF="https://example.com/post">A [bracketed] post</A>
+    </DL><p>
+    <DT><A HREF="place:sort=8&maxResults=10">Recent Tags</A>
+</DL>
+"#;
+
+    #[test]
+    fn parses_nested_folders_tags_dates_and_descriptions() {
+        let bookmarks = parse_bookmarks(BOOKMARKS_HTML, "bookmarks");
+        assert_eq!(bookmarks.len(), 4);
+
+        assert_eq!(bookmarks[0].folder_path, "bookmarks");
+        assert_eq!(
+            bookmarks[0].tags,
+            vec!["rust".to_owned(), "lang".to_owned()]
+        );
+        assert_eq!(
+            bookmarks[0].add_date.as_deref(),
+            Some("2023-11-14T22:13:20Z")
+        );
+        assert_eq!(
+            bookmarks[0].description.as_deref(),
+            Some("The Rust homepage")
+        );
+
+        assert_eq!(bookmarks[1].folder_path, "bookmarks/Reading/Databases");
+        assert_eq!(
+            bookmarks[1].add_date.as_deref(),
+            Some("2023-11-14T22:13:20Z")
+        );
+        assert_eq!(
+            bookmarks[1].last_modified.as_deref(),
+            Some("2023-11-14T22:23:20Z")
+        );
+
+        assert_eq!(bookmarks[2].folder_path, "bookmarks/Reading");
+        assert_eq!(bookmarks[2].description, None);
+        assert_eq!(bookmarks[3].folder_path, "bookmarks");
+    }
+
+    #[test]
+    fn imports_bookmarks_and_skips_known_urls() {
+        let root = unique_temp_path("tentacle-bookmarks-import");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        let export = root.join("bookmarks.html");
+        fs::write(&export, BOOKMARKS_HTML).expect("write bookmarks");
+
+        document_store::create_document(
+            &vault,
+            &CreateDocumentInput {
+                title: Some("FTS5 notes".to_owned()),
+                properties: BTreeMap::from([(
+                    "source".to_owned(),
+                    "https://SQLite.org/fts5.html".to_owned(),
+                )]),
+                ..CreateDocumentInput::default()
+            },
+        )
+        .expect("create existing note");
+
+        let options = ImportOptions {
+            folder_path: Some("bookmarks".to_owned()),
+            ..ImportOptions::default()
+        };
+        let result = import_bookmarks(&vault, &export, &options).expect("import bookmarks");
+        assert_eq!(result.documents.len(), 2);
+        assert_eq!(
+            result.folders_created,
+            vec!["bookmarks".to_owned(), "bookmarks/Reading".to_owned()]
+        );
+        let reasons = result
+            .skipped
+            .iter()
+            .map(|skipped| skipped.reason.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            vec![
+                "a note with this URL already exists",
+                "unsupported URL scheme"
+            ]
+        );
+
+        let rust = document_store::read_document(&vault, &result.documents[0].id)
+            .expect("read rust bookmark");
+        assert_eq!(rust.title, "Rust");
+        assert_eq!(rust.tags, vec!["rust".to_owned(), "lang".to_owned()]);
+        assert_eq!(rust.created_at, "2023-11-14T22:13:20Z");
+        assert_eq!(
+            rust.properties.get("source").map(String::as_str),
+            Some("https://www.rust-lang.org/")
+        );
+        assert_eq!(
+            rust.body,
+            "[Rust](https://www.rust-lang.org/)\n\nThe Rust homepage"
+        );
+
+        let again = import_bookmarks(&vault, &export, &options).expect("reimport bookmarks");
+        assert!(again.documents.is_empty());
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn wraps_urls_with_spaces_or_parentheses_in_angle_brackets() {
+        let root = unique_temp_path("tentacle-bookmarks-url");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        let export = root.join("bookmarks.html");
+        fs::write(
+            &export,
+            r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><A HREF="https://en.wikipedia.org/wiki/Rust_(programming_language)">Rust (language)</A>
+    <DT><A HREF="file:///home/me/My Notes/todo.txt">Todo</A>
+</DL>
+"#,
+        )
+        .expect("write bookmarks");
+
+        let result =
+            import_bookmarks(&vault, &export, &ImportOptions::default()).expect("import bookmarks");
+        let bodies = result
+            .documents
+            .iter()
+            .map(|document| {
+                document_store::read_document(&vault, &document.id)
+                    .expect("read bookmark")
+                    .body
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bodies,
+            vec![
+                "[Rust (language)](<https://en.wikipedia.org/wiki/Rust_(programming_language)>)",
+                "[Todo](<file:///home/me/My Notes/todo.txt>)",
+            ]
+        );
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
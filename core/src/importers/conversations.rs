@@ -0,0 +1,620 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::document_store::{self, CreateDocumentInput, UpdateDocumentInput};
+
+use super::{
+    ensure_folder_path, format_unix_seconds_utc, normalize_base_folder, ImportError, ImportOptions,
+    ImportResultPayload, ImportSkippedPayload, ImportedDocumentPayload,
+};
+
+/// Tag added to every document created from a chat export.
+pub const CHAT_IMPORT_TAG: &str = "chat-import";
+
+const CONVERSATIONS_FILE_NAME: &str = "conversations.json";
+const CONVERSATION_ID_PROPERTY: &str = "conversation_id";
+const CONVERSATION_HASH_PROPERTY: &str = "conversation_hash";
+const CONVERSATION_SOURCE_PROPERTY: &str = "conversation_source";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConversationImportOptions {
+    /// Only import conversations last updated on or after this date (`YYYY-MM-DD` or ISO 8601).
+    pub since: Option<String>,
+    /// Only import conversations last updated on or before this date (`YYYY-MM-DD` or ISO 8601).
+    pub until: Option<String>,
+    /// Only import conversations whose title or messages contain any of these (case-insensitive).
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Conversation {
+    id: String,
+    source: &'static str,
+    title: String,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    turns: Vec<ConversationTurn>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ConversationTurn {
+    speaker: &'static str,
+    text: String,
+}
+
+/// Imports a ChatGPT or Claude `conversations.json` export, one document per conversation.
+///
+/// Re-importing the same export only touches conversations whose rendered content changed:
+/// each document records the conversation id and a content hash, unchanged conversations are
+/// reported as skipped, and changed ones are rewritten in place.
+pub fn import_conversations(
+    documents_folder: &Path,
+    source: &Path,
+    options: &ImportOptions,
+    filters: &ConversationImportOptions,
+) -> Result<ImportResultPayload, ImportError> {
+    let since = filters
+        .since
+        .as_deref()
+        .map(|value| validate_date_filter("since", value))
+        .transpose()?;
+    let until = filters
+        .until
+        .as_deref()
+        .map(|value| validate_date_filter("until", value))
+        .transpose()?;
+    let keywords = filters
+        .keywords
+        .iter()
+        .map(|keyword| keyword.trim().to_lowercase())
+        .filter(|keyword| !keyword.is_empty())
+        .collect::<Vec<_>>();
+
+    let export_path = if source.is_dir() {
+        source.join(CONVERSATIONS_FILE_NAME)
+    } else {
+        source.to_path_buf()
+    };
+    if !export_path.is_file() {
+        return Err(ImportError::Validation(format!(
+            "import source \"{}\" was not found",
+            export_path.display()
+        )));
+    }
+
+    let export: Value = serde_json::from_slice(&fs::read(&export_path)?).map_err(|error| {
+        ImportError::Validation(format!("conversations export is not valid JSON: {error}"))
+    })?;
+    let entries = match &export {
+        Value::Array(entries) => entries.as_slice(),
+        Value::Object(map) => map
+            .get("conversations")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default(),
+        _ => &[],
+    };
+
+    let base_folder = normalize_base_folder(options.folder_path.as_deref());
+    let mut existing = existing_conversation_documents(documents_folder)?;
+    let mut result = ImportResultPayload::default();
+    ensure_folder_path(documents_folder, &base_folder, &mut result)?;
+
+    for (index, entry) in entries.iter().enumerate() {
+        let Some(conversation) = parse_conversation(entry) else {
+            result.skipped.push(ImportSkippedPayload {
+                source_path: format!("{CONVERSATIONS_FILE_NAME}#{}", index + 1),
+                reason: "unrecognized conversation format".to_owned(),
+            });
+            continue;
+        };
+        let source_path = format!("{CONVERSATIONS_FILE_NAME}#{}", conversation.id);
+        if conversation.turns.is_empty() {
+            result.skipped.push(ImportSkippedPayload {
+                source_path,
+                reason: "conversation has no messages".to_owned(),
+            });
+            continue;
+        }
+        if !matches_date_range(&conversation, since.as_deref(), until.as_deref())
+            || !matches_keywords(&conversation, &keywords)
+        {
+            continue;
+        }
+
+        let body = render_conversation(&conversation);
+        let content_hash = compute_content_hash(&conversation.title, &body);
+        let mut properties = BTreeMap::from([
+            (CONVERSATION_ID_PROPERTY.to_owned(), conversation.id.clone()),
+            (
+                CONVERSATION_SOURCE_PROPERTY.to_owned(),
+                conversation.source.to_owned(),
+            ),
+            (CONVERSATION_HASH_PROPERTY.to_owned(), content_hash.clone()),
+        ]);
+
+        let stored = if let Some(previous) = existing.get(&conversation.id) {
+            if previous.content_hash == content_hash {
+                result.skipped.push(ImportSkippedPayload {
+                    source_path,
+                    reason: "unchanged since last import".to_owned(),
+                });
+                continue;
+            }
+            for (key, value) in &previous.properties {
+                properties
+                    .entry(key.clone())
+                    .or_insert_with(|| value.clone());
+            }
+            document_store::update_document(
+                documents_folder,
+                &previous.document_id,
+                &UpdateDocumentInput {
+                    body: Some(body),
+                    properties: Some(properties),
+                    updated_at: conversation.updated_at.clone(),
+                },
+            )?
+        } else {
+            let mut tags = vec![CHAT_IMPORT_TAG.to_owned()];
+            tags.extend(options.tags.iter().cloned());
+            document_store::create_document(
+                documents_folder,
+                &CreateDocumentInput {
+                    title: Some(conversation.title.clone()),
+                    body: Some(body),
+                    folder_path: Some(base_folder.clone()),
+                    tags,
+                    tags_locked: Some(false),
+                    properties,
+                    created_at: conversation.created_at.clone(),
+                    updated_at: conversation.updated_at.clone(),
+                    ..CreateDocumentInput::default()
+                },
+            )?
+        };
+
+        existing.insert(
+            conversation.id.clone(),
+            ExistingConversation {
+                document_id: stored.id.clone(),
+                content_hash,
+                properties: stored.properties.clone(),
+            },
+        );
+        result.documents.push(ImportedDocumentPayload {
+            id: stored.id,
+            title: stored.title,
+            folder_path: stored.folder_path,
+            source_path,
+        });
+    }
+
+    Ok(result)
+}
+
+#[derive(Debug)]
+struct ExistingConversation {
+    document_id: String,
+    content_hash: String,
+    properties: BTreeMap<String, String>,
+}
+
+fn existing_conversation_documents(
+    documents_folder: &Path,
+) -> Result<HashMap<String, ExistingConversation>, ImportError> {
+    let mut existing = HashMap::new();
+    for document in document_store::list_documents(documents_folder)? {
+        let Some(conversation_id) = document.properties.get(CONVERSATION_ID_PROPERTY) else {
+            continue;
+        };
+        existing.insert(
+            conversation_id.clone(),
+            ExistingConversation {
+                document_id: document.id.clone(),
+                content_hash: document
+                    .properties
+                    .get(CONVERSATION_HASH_PROPERTY)
+                    .cloned()
+                    .unwrap_or_default(),
+                properties: document.properties.clone(),
+            },
+        );
+    }
+    Ok(existing)
+}
+
+fn parse_conversation(entry: &Value) -> Option<Conversation> {
+    if entry.get("chat_messages").is_some() {
+        parse_claude_conversation(entry)
+    } else if entry.get("mapping").is_some() {
+        parse_chatgpt_conversation(entry)
+    } else {
+        None
+    }
+}
+
+fn parse_claude_conversation(entry: &Value) -> Option<Conversation> {
+    let id = string_field(entry, "uuid")?;
+    let turns = entry
+        .get("chat_messages")?
+        .as_array()?
+        .iter()
+        .filter_map(|message| {
+            let speaker = match message.get("sender").and_then(Value::as_str)? {
+                "human" => "User",
+                "assistant" => "Assistant",
+                _ => return None,
+            };
+            let mut text = string_field(message, "text").unwrap_or_default();
+            if text.trim().is_empty() {
+                text = message
+                    .get("content")
+                    .and_then(Value::as_array)
+                    .map(|blocks| {
+                        blocks
+                            .iter()
+                            .filter(|block| {
+                                block.get("type").and_then(Value::as_str) == Some("text")
+                            })
+                            .filter_map(|block| string_field(block, "text"))
+                            .collect::<Vec<_>>()
+                            .join("\n\n")
+                    })
+                    .unwrap_or_default();
+            }
+            non_empty_turn(speaker, &text)
+        })
+        .collect();
+
+    Some(Conversation {
+        title: conversation_title(string_field(entry, "name")),
+        created_at: string_field(entry, "created_at")
+            .and_then(|value| normalize_utc_timestamp(&value)),
+        updated_at: string_field(entry, "updated_at")
+            .and_then(|value| normalize_utc_timestamp(&value)),
+        id,
+        source: "claude",
+        turns,
+    })
+}
+
+fn parse_chatgpt_conversation(entry: &Value) -> Option<Conversation> {
+    let id = string_field(entry, "conversation_id").or_else(|| string_field(entry, "id"))?;
+    let mapping = entry.get("mapping")?.as_object()?;
+
+    // Follow the active branch from the current node back to the root; regenerated replies
+    // live on sibling branches and are left out.
+    let mut node_ids = Vec::new();
+    let mut cursor = string_field(entry, "current_node");
+    while let Some(node_id) = cursor {
+        if node_ids.contains(&node_id) {
+            break;
+        }
+        let node = mapping.get(&node_id);
+        cursor = node.and_then(|node| string_field(node, "parent"));
+        node_ids.push(node_id);
+    }
+    if node_ids.is_empty() {
+        let mut ordered = mapping.iter().collect::<Vec<_>>();
+        ordered.sort_by(|left, right| {
+            message_create_time(left.1)
+                .partial_cmp(&message_create_time(right.1))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        node_ids = ordered
+            .into_iter()
+            .rev()
+            .map(|(id, _)| id.clone())
+            .collect();
+    }
+    node_ids.reverse();
+
+    let turns = node_ids
+        .iter()
+        .filter_map(|node_id| {
+            let message = mapping.get(node_id)?.get("message")?;
+            let speaker = match message.get("author")?.get("role")?.as_str()? {
+                "user" => "User",
+                "assistant" => "Assistant",
+                _ => return None,
+            };
+            let hidden = message
+                .get("metadata")
+                .and_then(|metadata| metadata.get("is_visually_hidden_from_conversation"))
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            if hidden {
+                return None;
+            }
+            let content = message.get("content")?;
+            let text = match content.get("parts").and_then(Value::as_array) {
+                Some(parts) => parts
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+                None => string_field(content, "text").unwrap_or_default(),
+            };
+            non_empty_turn(speaker, &text)
+        })
+        .collect();
+
+    Some(Conversation {
+        title: conversation_title(string_field(entry, "title")),
+        created_at: entry
+            .get("create_time")
+            .and_then(Value::as_f64)
+            .map(|seconds| format_unix_seconds_utc(seconds.floor() as i64)),
+        updated_at: entry
+            .get("update_time")
+            .and_then(Value::as_f64)
+            .map(|seconds| format_unix_seconds_utc(seconds.floor() as i64)),
+        id,
+        source: "chatgpt",
+        turns,
+    })
+}
+
+fn message_create_time(node: &Value) -> f64 {
+    node.get("message")
+        .and_then(|message| message.get("create_time"))
+        .and_then(Value::as_f64)
+        .unwrap_or(0.0)
+}
+
+fn non_empty_turn(speaker: &'static str, text: &str) -> Option<ConversationTurn> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some(ConversationTurn {
+        speaker,
+        text: text.to_owned(),
+    })
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_owned)
+}
+
+fn conversation_title(title: Option<String>) -> String {
+    title.unwrap_or_else(|| "Untitled conversation".to_owned())
+}
+
+fn render_conversation(conversation: &Conversation) -> String {
+    conversation
+        .turns
+        .iter()
+        .map(|turn| format!("## {}\n\n{}", turn.speaker, turn.text))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn compute_content_hash(title: &str, body: &str) -> String {
+    let digest = Sha256::digest(format!("{title}\0{body}").as_bytes());
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn matches_date_range(
+    conversation: &Conversation,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> bool {
+    let Some(timestamp) = conversation
+        .updated_at
+        .as_deref()
+        .or(conversation.created_at.as_deref())
+    else {
+        return since.is_none() && until.is_none();
+    };
+
+    let after_since = since.is_none_or(|since| timestamp >= since);
+    let before_until = until.is_none_or(|until| {
+        let comparable = &timestamp[..until.len().min(timestamp.len())];
+        comparable <= until
+    });
+    after_since && before_until
+}
+
+fn matches_keywords(conversation: &Conversation, keywords: &[String]) -> bool {
+    if keywords.is_empty() {
+        return true;
+    }
+    let title = conversation.title.to_lowercase();
+    keywords.iter().any(|keyword| {
+        title.contains(keyword)
+            || conversation
+                .turns
+                .iter()
+                .any(|turn| turn.text.to_lowercase().contains(keyword))
+    })
+}
+
+/// Accepts `YYYY-MM-DD` or an ISO 8601 timestamp and returns it in a form that compares
+/// lexicographically against normalized conversation timestamps.
+fn validate_date_filter(name: &str, value: &str) -> Result<String, ImportError> {
+    let value = value.trim();
+    let bytes = value.as_bytes();
+    let is_date = bytes.len() >= 10
+        && bytes[..4].iter().all(u8::is_ascii_digit)
+        && bytes[4] == b'-'
+        && bytes[5..7].iter().all(u8::is_ascii_digit)
+        && bytes[7] == b'-'
+        && bytes[8..10].iter().all(u8::is_ascii_digit);
+    if !is_date {
+        return Err(ImportError::Validation(format!(
+            "{name} must be a date like 2026-01-31, got \"{value}\""
+        )));
+    }
+    if bytes.len() == 10 {
+        return Ok(value.to_owned());
+    }
+    normalize_utc_timestamp(value).ok_or_else(|| {
+        ImportError::Validation(format!(
+            "{name} must be a date like 2026-01-31, got \"{value}\""
+        ))
+    })
+}
+
+/// Normalizes `2024-05-01T12:00:00.123456+00:00`-style UTC timestamps to `2024-05-01T12:00:00Z`.
+fn normalize_utc_timestamp(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.len() < 19 || !value.is_char_boundary(19) {
+        return None;
+    }
+    let (prefix, rest) = value.split_at(19);
+    let rest = rest
+        .strip_prefix('.')
+        .map(|fraction| fraction.trim_start_matches(|character: char| character.is_ascii_digit()))
+        .unwrap_or(rest);
+    if !matches!(rest, "" | "Z" | "z" | "+00:00" | "+0000") {
+        return None;
+    }
+    Some(format!("{}Z", prefix.replacen(' ', "T", 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_path(prefix: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}-{timestamp}"))
+    }
+
+    fn chatgpt_conversation(id: &str, title: &str, update_time: f64, reply: &str) -> Value {
+        serde_json::json!({
+            "id": id,
+            "title": title,
+            "create_time": 1_714_564_800.5,
+            "update_time": update_time,
+            "current_node": "n3",
+            "mapping": {
+                "n0": { "id": "n0", "message": null, "parent": null, "children": ["n1"] },
+                "n1": { "id": "n1", "parent": "n0", "message": {
+                    "author": { "role": "system" },
+                    "content": { "content_type": "text", "parts": ["You are helpful."] }
+                }},
+                "n2": { "id": "n2", "parent": "n1", "message": {
+                    "author": { "role": "user" },
+                    "content": { "content_type": "text", "parts": ["How do I rotate keys?"] }
+                }},
+                "n2b": { "id": "n2b", "parent": "n2", "message": {
+                    "author": { "role": "assistant" },
+                    "content": { "content_type": "text", "parts": ["Discarded draft"] }
+                }},
+                "n3": { "id": "n3", "parent": "n2", "message": {
+                    "author": { "role": "assistant" },
+                    "content": { "content_type": "text", "parts": [reply] }
+                }}
+            }
+        })
+    }
+
+    #[test]
+    fn parses_claude_and_chatgpt_conversations() {
+        let claude = serde_json::json!({
+            "uuid": "c-1",
+            "name": "Vector search",
+            "created_at": "2024-05-01T12:00:00.123456+00:00",
+            "updated_at": "2024-05-02T08:30:00Z",
+            "chat_messages": [
+                { "sender": "human", "text": "What is RRF?", "created_at": "2024-05-01T12:00:00Z" },
+                { "sender": "assistant", "text": "", "content": [{ "type": "text", "text": "Reciprocal rank fusion." }] }
+            ]
+        });
+        let parsed = parse_conversation(&claude).expect("claude conversation");
+        assert_eq!(parsed.source, "claude");
+        assert_eq!(parsed.created_at.as_deref(), Some("2024-05-01T12:00:00Z"));
+        assert_eq!(
+            render_conversation(&parsed),
+            "## User\n\nWhat is RRF?\n\n## Assistant\n\nReciprocal rank fusion."
+        );
+
+        let chatgpt =
+            chatgpt_conversation("g-1", "Keys", 1_714_651_200.0, "Use the rotate command.");
+        let parsed = parse_conversation(&chatgpt).expect("chatgpt conversation");
+        assert_eq!(parsed.source, "chatgpt");
+        assert_eq!(parsed.created_at.as_deref(), Some("2024-05-01T12:00:00Z"));
+        assert_eq!(parsed.updated_at.as_deref(), Some("2024-05-02T12:00:00Z"));
+        assert_eq!(
+            render_conversation(&parsed),
+            "## User\n\nHow do I rotate keys?\n\n## Assistant\n\nUse the rotate command."
+        );
+    }
+
+    #[test]
+    fn reimport_skips_unchanged_and_updates_changed_conversations() {
+        let root = unique_temp_path("tentacle-conversations-import");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        let export_path = root.join(CONVERSATIONS_FILE_NAME);
+        let write_export = |reply: &str| {
+            let export = serde_json::json!([
+                chatgpt_conversation("g-1", "Keys", 1_714_651_200.0, reply),
+                chatgpt_conversation("g-2", "Old chat", 1_600_000_000.0, "Ancient history."),
+            ]);
+            fs::write(&export_path, export.to_string()).expect("write export");
+        };
+        let filters = ConversationImportOptions {
+            since: Some("2024-01-01".to_owned()),
+            ..ConversationImportOptions::default()
+        };
+
+        write_export("Use the rotate command.");
+        let first = import_conversations(&vault, &root, &ImportOptions::default(), &filters)
+            .expect("first import");
+        assert_eq!(first.documents.len(), 1);
+        let document_id = first.documents[0].id.clone();
+        let document = document_store::read_document(&vault, &document_id).expect("read");
+        assert_eq!(document.tags, vec![CHAT_IMPORT_TAG.to_owned()]);
+        assert_eq!(document.created_at, "2024-05-01T12:00:00Z");
+
+        let second = import_conversations(&vault, &root, &ImportOptions::default(), &filters)
+            .expect("second import");
+        assert!(second.documents.is_empty());
+        assert_eq!(second.skipped[0].reason, "unchanged since last import");
+
+        write_export("Use the rotate command, then revoke old keys.");
+        let third = import_conversations(&vault, &root, &ImportOptions::default(), &filters)
+            .expect("third import");
+        assert_eq!(third.documents.len(), 1);
+        assert_eq!(third.documents[0].id, document_id);
+        let updated = document_store::read_document(&vault, &document_id).expect("read");
+        assert!(updated.body.contains("revoke old keys"));
+        assert_eq!(
+            document_store::list_documents(&vault).expect("list").len(),
+            1
+        );
+
+        let keyword_filtered = import_conversations(
+            &vault,
+            &root,
+            &ImportOptions::default(),
+            &ConversationImportOptions {
+                keywords: vec!["ANCIENT".to_owned()],
+                ..ConversationImportOptions::default()
+            },
+        )
+        .expect("keyword import");
+        assert_eq!(keyword_filtered.documents.len(), 1);
+        assert_eq!(keyword_filtered.documents[0].title, "Old chat");
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
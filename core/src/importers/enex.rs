@@ -0,0 +1,593 @@
+use base64::Engine;
+use md5::{Digest, Md5};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use scraper::Html;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::document_store::{self, CreateDocumentInput};
+
+use super::html_to_markdown::element_to_markdown;
+use super::{
+    discard_attachments, ensure_folder_path, join_folder_path, normalize_base_folder,
+    relative_prefix_to_vault_root, sanitize_folder_segment, ImportError, ImportOptions,
+    ImportResultPayload, ImportSkippedPayload, ImportedDocumentPayload,
+};
+
+const ENEX_EXTENSION: &str = "enex";
+const SELF_CLOSING_ENML_ELEMENTS: [&str; 2] = ["en-media", "en-todo"];
+
+#[derive(Debug, Default)]
+struct EnexNote {
+    title: String,
+    content: String,
+    created: Option<String>,
+    updated: Option<String>,
+    tags: Vec<String>,
+    source_url: Option<String>,
+    author: Option<String>,
+    resources: Vec<EnexResource>,
+}
+
+#[derive(Debug, Default)]
+struct EnexResource {
+    encoded_data: String,
+    mime: String,
+    file_name: Option<String>,
+}
+
+#[derive(Debug)]
+struct WrittenResource {
+    attachment_path: String,
+    file_name: String,
+    is_image: bool,
+}
+
+/// Imports an Evernote `.enex` export file, or every `.enex` file in a directory.
+///
+/// Each export file is treated as one notebook and becomes a folder. Notes are streamed one
+/// at a time, so memory use is bounded by the largest single note rather than the file size.
+pub fn import_enex(
+    documents_folder: &Path,
+    source: &Path,
+    options: &ImportOptions,
+) -> Result<ImportResultPayload, ImportError> {
+    let files = collect_enex_files(source)?;
+    if files.is_empty() {
+        return Err(ImportError::Validation(format!(
+            "no .enex files were found in \"{}\"",
+            source.display()
+        )));
+    }
+
+    let base_folder = normalize_base_folder(options.folder_path.as_deref());
+    let mut result = ImportResultPayload::default();
+    for file in files {
+        let notebook = file
+            .file_stem()
+            .map(|stem| sanitize_folder_segment(&stem.to_string_lossy()))
+            .unwrap_or_else(|| "Evernote".to_owned());
+        let folder_path = join_folder_path(&base_folder, &notebook);
+        let source_name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut note_index = 0usize;
+        stream_enex_notes(&file, |note| {
+            note_index += 1;
+            let source_path = format!("{source_name}#{note_index}");
+            import_note(
+                documents_folder,
+                &folder_path,
+                &source_path,
+                note,
+                options,
+                &mut result,
+            )
+        })?;
+    }
+
+    Ok(result)
+}
+
+fn collect_enex_files(source: &Path) -> Result<Vec<PathBuf>, ImportError> {
+    if source.is_file() {
+        return Ok(vec![source.to_path_buf()]);
+    }
+    if !source.is_dir() {
+        return Err(ImportError::Validation(format!(
+            "import source \"{}\" was not found",
+            source.display()
+        )));
+    }
+
+    let mut files = Vec::new();
+    let mut pending = vec![source.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case(ENEX_EXTENSION))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Reads `path` with a streaming XML reader and calls `on_note` once per complete `<note>`.
+fn stream_enex_notes(
+    path: &Path,
+    mut on_note: impl FnMut(EnexNote) -> Result<(), ImportError>,
+) -> Result<(), ImportError> {
+    let mut reader = Reader::from_reader(BufReader::new(File::open(path)?));
+    let mut buffer = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut note: Option<EnexNote> = None;
+
+    loop {
+        match reader.read_event_into(&mut buffer)? {
+            Event::Start(start) => {
+                let name = String::from_utf8_lossy(start.local_name().as_ref()).into_owned();
+                match name.as_str() {
+                    "note" => note = Some(EnexNote::default()),
+                    "resource" => {
+                        if let Some(note) = note.as_mut() {
+                            note.resources.push(EnexResource::default());
+                        }
+                    }
+                    _ => {}
+                }
+                stack.push(name);
+                text.clear();
+            }
+            Event::End(_) => {
+                let name = stack.pop().unwrap_or_default();
+                let parent = stack.last().map(String::as_str).unwrap_or_default();
+                if name == "note" {
+                    if let Some(finished) = note.take() {
+                        on_note(finished)?;
+                    }
+                } else if let Some(note) = note.as_mut() {
+                    assign_note_field(note, parent, &name, std::mem::take(&mut text));
+                }
+                text.clear();
+            }
+            Event::Text(value) if note.is_some() => {
+                text.push_str(&value.decode().map_err(quick_xml::Error::from)?);
+            }
+            Event::CData(value) if note.is_some() => {
+                text.push_str(&value.decode().map_err(quick_xml::Error::from)?);
+            }
+            Event::GeneralRef(reference) if note.is_some() => {
+                if let Some(character) = reference.resolve_char_ref()? {
+                    text.push(character);
+                } else {
+                    let name = reference.decode().map_err(quick_xml::Error::from)?;
+                    match resolve_predefined_entity(&name) {
+                        Some(resolved) => text.push_str(resolved),
+                        None => {
+                            text.push('&');
+                            text.push_str(&name);
+                            text.push(';');
+                        }
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buffer.clear();
+    }
+
+    Ok(())
+}
+
+fn assign_note_field(note: &mut EnexNote, parent: &str, name: &str, value: String) {
+    match (parent, name) {
+        ("note", "title") => note.title = value.trim().to_owned(),
+        ("note", "content") => note.content = value,
+        ("note", "created") => note.created = parse_enex_timestamp(&value),
+        ("note", "updated") => note.updated = parse_enex_timestamp(&value),
+        ("note", "tag") => {
+            let tag = value.trim();
+            if !tag.is_empty() {
+                note.tags.push(tag.to_owned());
+            }
+        }
+        ("note-attributes", "source-url") => note.source_url = non_empty(value),
+        ("note-attributes", "author") => note.author = non_empty(value),
+        ("resource", "data") => {
+            if let Some(resource) = note.resources.last_mut() {
+                resource.encoded_data = value;
+            }
+        }
+        ("resource", "mime") => {
+            if let Some(resource) = note.resources.last_mut() {
+                resource.mime = value.trim().to_owned();
+            }
+        }
+        ("resource-attributes", "file-name") => {
+            if let Some(resource) = note.resources.last_mut() {
+                resource.file_name = non_empty(value);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn import_note(
+    documents_folder: &Path,
+    folder_path: &str,
+    source_path: &str,
+    note: EnexNote,
+    options: &ImportOptions,
+    result: &mut ImportResultPayload,
+) -> Result<(), ImportError> {
+    ensure_folder_path(documents_folder, folder_path, result)?;
+
+    let document_id = document_store::generate_document_id();
+    let root_prefix = relative_prefix_to_vault_root(folder_path);
+    let mut resources: HashMap<String, WrittenResource> = HashMap::new();
+    let mut resource_order: Vec<String> = Vec::new();
+    for (index, resource) in note.resources.iter().enumerate() {
+        let compact = resource
+            .encoded_data
+            .chars()
+            .filter(|character| !character.is_whitespace())
+            .collect::<String>();
+        let bytes = match base64::engine::general_purpose::STANDARD.decode(compact) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                result.skipped.push(ImportSkippedPayload {
+                    source_path: format!("{source_path}/resource-{}", index + 1),
+                    reason: format!("invalid base64 resource data: {error}"),
+                });
+                continue;
+            }
+        };
+
+        let hash = md5_hex(&bytes);
+        if resources.contains_key(&hash) {
+            continue;
+        }
+        let file_name = resource
+            .file_name
+            .clone()
+            .unwrap_or_else(|| default_resource_file_name(index, &resource.mime));
+        let attachment_path = match document_store::write_document_attachment(
+            documents_folder,
+            &document_id,
+            &file_name,
+            &bytes,
+        ) {
+            Ok(attachment_path) => attachment_path,
+            Err(error) => {
+                discard_attachments(documents_folder, &document_id);
+                return Err(error.into());
+            }
+        };
+        result.attachments_imported += 1;
+        resource_order.push(hash.clone());
+        resources.insert(
+            hash,
+            WrittenResource {
+                attachment_path,
+                file_name,
+                is_image: resource.mime.starts_with("image/"),
+            },
+        );
+    }
+
+    let mut referenced: HashSet<String> = HashSet::new();
+    let enml = expand_self_closing_elements(&note.content);
+    let document = Html::parse_document(&enml);
+    let mut body = element_to_markdown(document.root_element(), &mut |element| match element
+        .value()
+        .name()
+    {
+        "en-media" => {
+            let hash = element.value().attr("hash")?.trim().to_ascii_lowercase();
+            let written = resources.get(&hash)?;
+            referenced.insert(hash);
+            Some(render_resource_link(written, &root_prefix))
+        }
+        "en-crypt" => Some(String::new()),
+        _ => None,
+    });
+
+    let unreferenced = resource_order
+        .iter()
+        .filter(|hash| !referenced.contains(*hash))
+        .filter_map(|hash| resources.get(hash))
+        .map(|written| format!("- {}", render_resource_link(written, &root_prefix)))
+        .collect::<Vec<_>>();
+    if !unreferenced.is_empty() {
+        if !body.is_empty() {
+            body.push_str("\n\n");
+        }
+        body.push_str(&unreferenced.join("\n"));
+    }
+
+    let mut properties = BTreeMap::new();
+    if let Some(source_url) = note.source_url {
+        properties.insert("source".to_owned(), source_url);
+    }
+    if let Some(author) = note.author {
+        properties.insert("author".to_owned(), author);
+    }
+
+    let mut tags = options.tags.clone();
+    tags.extend(note.tags);
+    let created = match document_store::create_document(
+        documents_folder,
+        &CreateDocumentInput {
+            title: non_empty(note.title),
+            body: Some(body),
+            folder_path: Some(folder_path.to_owned()),
+            tags,
+            tags_locked: Some(false),
+            id: Some(document_id.clone()),
+            properties,
+            updated_at: note.updated.or_else(|| note.created.clone()),
+            created_at: note.created,
+        },
+    ) {
+        Ok(created) => created,
+        Err(error) => {
+            // The note's resources were already written under its id.
+            discard_attachments(documents_folder, &document_id);
+            return Err(error.into());
+        }
+    };
+
+    result.documents.push(ImportedDocumentPayload {
+        id: created.id,
+        title: created.title,
+        folder_path: created.folder_path,
+        source_path: source_path.to_owned(),
+    });
+    Ok(())
+}
+
+fn render_resource_link(resource: &WrittenResource, root_prefix: &str) -> String {
+    let target = format!("{root_prefix}{}", resource.attachment_path);
+    let target = if target.contains(' ') {
+        format!("<{target}>")
+    } else {
+        target
+    };
+    if resource.is_image {
+        format!("![{}]({target})", resource.file_name)
+    } else {
+        format!("[{}]({target})", resource.file_name)
+    }
+}
+
+/// ENML is XHTML, so `<en-media/>` is legal; an HTML parser would treat it as an open tag
+/// and swallow the following content. Rewrite those to explicit open/close pairs.
+fn expand_self_closing_elements(enml: &str) -> String {
+    let mut expanded = enml.to_owned();
+    for element in SELF_CLOSING_ENML_ELEMENTS {
+        let opening = format!("<{element}");
+        let mut output = String::with_capacity(expanded.len());
+        let mut remainder = expanded.as_str();
+        while let Some(start) = remainder.find(&opening) {
+            let (before, tag_and_rest) = remainder.split_at(start);
+            output.push_str(before);
+            let Some(end) = tag_and_rest.find('>') else {
+                output.push_str(tag_and_rest);
+                remainder = "";
+                break;
+            };
+            let tag = &tag_and_rest[..=end];
+            match tag.strip_suffix("/>") {
+                Some(open) => {
+                    output.push_str(open.trim_end());
+                    output.push_str("></");
+                    output.push_str(element);
+                    output.push('>');
+                }
+                None => output.push_str(tag),
+            }
+            remainder = &tag_and_rest[end + 1..];
+        }
+        output.push_str(remainder);
+        expanded = output;
+    }
+    expanded
+}
+
+/// Converts Evernote's compact `20230115T103000Z` timestamps to `2023-01-15T10:30:00Z`.
+fn parse_enex_timestamp(value: &str) -> Option<String> {
+    let value = value.trim();
+    let bytes = value.as_bytes();
+    let is_compact = bytes.len() == 16
+        && bytes[8] == b'T'
+        && bytes[15] == b'Z'
+        && bytes[..8].iter().all(u8::is_ascii_digit)
+        && bytes[9..15].iter().all(u8::is_ascii_digit);
+    if !is_compact {
+        return None;
+    }
+
+    Some(format!(
+        "{}-{}-{}T{}:{}:{}Z",
+        &value[0..4],
+        &value[4..6],
+        &value[6..8],
+        &value[9..11],
+        &value[11..13],
+        &value[13..15]
+    ))
+}
+
+fn default_resource_file_name(index: usize, mime: &str) -> String {
+    let extension = match mime {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        "image/webp" => "webp",
+        "application/pdf" => "pdf",
+        "audio/mpeg" => "mp3",
+        "audio/wav" => "wav",
+        "text/plain" => "txt",
+        _ => "bin",
+    };
+    format!("attachment-{}.{extension}", index + 1)
+}
+
+fn md5_hex(bytes: &[u8]) -> String {
+    Md5::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_path() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        std::env::temp_dir().join(format!("tentacle-enex-{nanos}"))
+    }
+
+    #[test]
+    fn parses_compact_enex_timestamps() {
+        assert_eq!(
+            parse_enex_timestamp("20230115T103000Z").as_deref(),
+            Some("2023-01-15T10:30:00Z")
+        );
+        assert_eq!(parse_enex_timestamp("2023-01-15"), None);
+        assert_eq!(
+            expand_self_closing_elements("<en-todo checked=\"true\"/>done<en-media hash=\"a\" />"),
+            "<en-todo checked=\"true\"></en-todo>done<en-media hash=\"a\"></en-media>"
+        );
+    }
+
+    #[test]
+    fn imports_notes_with_tags_timestamps_and_resources() {
+        let root = unique_temp_path();
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+
+        let data = b"fake png bytes";
+        let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+        let hash = md5_hex(data);
+        let export = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-export SYSTEM "http://xml.evernote.com/pub/evernote-export3.dtd">
+<en-export export-date="20240101T000000Z" application="Evernote">
+  <note>
+    <title>Trip &amp; Plans</title>
+    <content><![CDATA[<?xml version="1.0" encoding="UTF-8"?><!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd"><en-note><div><en-todo checked="true"/>Book flights</div><div><en-todo/>Pack</div><ul><li>Lisbon</li></ul><en-media type="image/png" hash="{hash}"/></en-note>]]></content>
+    <created>20230115T103000Z</created>
+    <updated>20230116T080000Z</updated>
+    <tag>travel</tag>
+    <note-attributes><source-url>https://example.com/trip</source-url></note-attributes>
+    <resource>
+      <data encoding="base64">{encoded}</data>
+      <mime>image/png</mime>
+      <resource-attributes><file-name>map.png</file-name></resource-attributes>
+    </resource>
+  </note>
+</en-export>"#
+        );
+        let source = root.join("Personal.enex");
+        fs::write(&source, export).expect("write export");
+
+        let result = import_enex(&vault, &source, &ImportOptions::default()).expect("import");
+        assert_eq!(result.documents.len(), 1);
+        assert_eq!(result.folders_created, vec!["Personal".to_owned()]);
+        assert_eq!(result.attachments_imported, 1);
+
+        let document = document_store::read_document(&vault, &result.documents[0].id)
+            .expect("read imported document");
+        assert_eq!(document.title, "Trip & Plans");
+        assert_eq!(document.created_at, "2023-01-15T10:30:00Z");
+        assert_eq!(document.updated_at, "2023-01-16T08:00:00Z");
+        assert_eq!(document.tags, vec!["travel".to_owned()]);
+        assert_eq!(
+            document.properties.get("source").map(String::as_str),
+            Some("https://example.com/trip")
+        );
+        assert_eq!(
+            document.body,
+            format!(
+                "- [x] Book flights\n- [ ] Pack\n\n- Lisbon\n\n![map.png](../.attachments/{}/map.png)",
+                document.id
+            )
+        );
+        assert!(vault
+            .join(format!(".attachments/{}/map.png", document.id))
+            .is_file());
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn removes_written_resources_when_the_note_cannot_be_created() {
+        let root = unique_temp_path();
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+
+        // A title longer than the filesystem allows fails after the resource was written.
+        let export = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export>
+  <note>
+    <title>{}</title>
+    <content><![CDATA[<en-note><div>Body</div></en-note>]]></content>
+    <resource>
+      <data encoding="base64">{}</data>
+      <mime>image/png</mime>
+    </resource>
+  </note>
+</en-export>"#,
+            "x".repeat(300),
+            base64::engine::general_purpose::STANDARD.encode(b"fake png bytes")
+        );
+        let source = root.join("Personal.enex");
+        fs::write(&source, export).expect("write export");
+
+        let error = import_enex(&vault, &source, &ImportOptions::default())
+            .expect_err("over-long title fails");
+        assert!(matches!(error, ImportError::DocumentStore(_)));
+        let attachments = fs::read_dir(vault.join(document_store::ATTACHMENTS_FOLDER))
+            .map(|entries| entries.count())
+            .unwrap_or(0);
+        assert_eq!(attachments, 0, "no orphaned attachment folder is left");
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
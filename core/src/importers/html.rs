@@ -0,0 +1,374 @@
+use scraper::{ElementRef, Html, Selector};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::document_store::{self, CreateDocumentInput};
+
+use super::html_to_markdown::element_to_markdown;
+use super::{
+    collect_source_files, ensure_folder_path, has_any_extension, link_imported_documents,
+    nested_folder_path, normalize_base_folder, percent_decode, relative_prefix_to_vault_root,
+    resolve_relative_path, rewrite_markdown_link_targets, ImportError, ImportOptions,
+    ImportResultPayload, ImportedDocumentPayload,
+};
+
+const HTML_EXTENSIONS: [&str; 2] = ["html", "htm"];
+
+/// Candidates for the main article content, most specific first. Covers semantic HTML plus
+/// the content containers of common wiki and blog exports (MediaWiki, Confluence, GitHub).
+const MAIN_CONTENT_SELECTORS: [&str; 10] = [
+    "article",
+    "main",
+    "[role=\"main\"]",
+    "#mw-content-text",
+    "#main-content",
+    ".markdown-body",
+    ".post-content",
+    ".entry-content",
+    "#content",
+    ".content",
+];
+
+/// Main content shorter than this is assumed to be a teaser and the whole body is used.
+const MIN_MAIN_CONTENT_CHARS: usize = 200;
+
+const BOILERPLATE_ELEMENTS: [&str; 6] = ["nav", "aside", "footer", "form", "button", "dialog"];
+
+#[derive(Debug)]
+struct PlannedHtmlDocument {
+    source_path: String,
+    id: String,
+    title: String,
+    folder_path: String,
+    body: String,
+    canonical_url: Option<String>,
+}
+
+/// Imports a saved web page, or every `.html`/`.htm` file under a directory.
+///
+/// The main article content is converted to markdown, `<title>` becomes the document title,
+/// and `<link rel="canonical">` is stored as the `source` property. Sub-directories become
+/// folders, links between imported pages are rewritten to relative paths of the imported
+/// files, and local images are copied into attachments.
+pub fn import_html(
+    documents_folder: &Path,
+    source: &Path,
+    options: &ImportOptions,
+) -> Result<ImportResultPayload, ImportError> {
+    let (root, files) = collect_source_files(source, &HTML_EXTENSIONS)?;
+    if files.is_empty() {
+        return Err(ImportError::Validation(format!(
+            "no .html files were found in \"{}\"",
+            source.display()
+        )));
+    }
+    if options.title.is_some() && files.len() > 1 {
+        return Err(ImportError::Validation(
+            "a title can only be set when importing a single HTML file".to_owned(),
+        ));
+    }
+
+    let base_folder = normalize_base_folder(options.folder_path.as_deref());
+    let mut planned = Vec::with_capacity(files.len());
+    for relative_path in files {
+        let html = String::from_utf8_lossy(&fs::read(root.join(&relative_path))?).into_owned();
+        let page = convert_html_page(&html);
+
+        let (parent, file_name) = relative_path
+            .rsplit_once('/')
+            .unwrap_or(("", &relative_path));
+        let folder_path = nested_folder_path(&base_folder, parent);
+        let file_stem = file_name
+            .rsplit_once('.')
+            .map_or(file_name, |(stem, _)| stem);
+        let title = options
+            .title
+            .clone()
+            .or(page.title)
+            .unwrap_or_else(|| file_stem.to_owned());
+
+        planned.push(PlannedHtmlDocument {
+            source_path: relative_path.clone(),
+            id: document_store::generate_document_id(),
+            body: strip_leading_title_heading(&page.body, &title),
+            title,
+            folder_path,
+            canonical_url: page.canonical_url,
+        });
+    }
+
+    let id_by_source: HashMap<String, String> = planned
+        .iter()
+        .map(|document| (document.source_path.clone(), document.id.clone()))
+        .collect();
+    let linked_document_id = |source_path: &str, target: &str| -> Option<String> {
+        if target.starts_with('#') || target.contains(':') || target.starts_with('/') {
+            return None;
+        }
+        let without_fragment = target.split(['#', '?']).next().unwrap_or_default();
+        let resolved = resolve_relative_path(source_path, &percent_decode(without_fragment))?;
+        id_by_source.get(&resolved).cloned()
+    };
+
+    let mut result = ImportResultPayload::default();
+    let mut created_documents = Vec::with_capacity(planned.len());
+    for document in planned {
+        ensure_folder_path(documents_folder, &document.folder_path, &mut result)?;
+
+        let root_prefix = relative_prefix_to_vault_root(&document.folder_path);
+        let mut written_assets: HashMap<String, String> = HashMap::new();
+        let mut write_error: Option<ImportError> = None;
+        // Links to other pages are rewritten once every page has been created.
+        let body = rewrite_markdown_link_targets(&document.body, |target| {
+            if target.starts_with('#') || target.contains(':') || target.starts_with('/') {
+                return None;
+            }
+            let without_fragment = target.split(['#', '?']).next().unwrap_or_default();
+            let resolved =
+                resolve_relative_path(&document.source_path, &percent_decode(without_fragment))?;
+            if id_by_source.contains_key(&resolved) {
+                return None;
+            }
+            if let Some(existing) = written_assets.get(&resolved) {
+                return Some(format!("{root_prefix}{existing}"));
+            }
+
+            let asset_path = root.join(&resolved);
+            if !asset_path.is_file() || is_html_file_name(&resolved) {
+                return None;
+            }
+            let file_name = resolved.rsplit('/').next().unwrap_or(&resolved).to_owned();
+            let written = fs::read(&asset_path)
+                .map_err(ImportError::from)
+                .and_then(|bytes| {
+                    document_store::write_document_attachment(
+                        documents_folder,
+                        &document.id,
+                        &file_name,
+                        &bytes,
+                    )
+                    .map_err(ImportError::from)
+                });
+            match written {
+                Ok(attachment_path) => {
+                    written_assets.insert(resolved, attachment_path.clone());
+                    Some(format!("{root_prefix}{attachment_path}"))
+                }
+                Err(error) => {
+                    write_error.get_or_insert(error);
+                    None
+                }
+            }
+        });
+        if let Some(error) = write_error {
+            return Err(error);
+        }
+        result.attachments_imported += written_assets.len();
+
+        let mut properties = BTreeMap::new();
+        if let Some(canonical_url) = document.canonical_url {
+            properties.insert("source".to_owned(), canonical_url);
+        }
+
+        let created = document_store::create_document(
+            documents_folder,
+            &CreateDocumentInput {
+                title: Some(document.title),
+                body: Some(body),
+                folder_path: Some(document.folder_path),
+                tags: options.tags.clone(),
+                tags_locked: Some(false),
+                id: Some(document.id),
+                properties,
+                ..CreateDocumentInput::default()
+            },
+        )?;
+
+        result.documents.push(ImportedDocumentPayload {
+            id: created.id.clone(),
+            title: created.title.clone(),
+            folder_path: created.folder_path.clone(),
+            source_path: document.source_path.clone(),
+        });
+        created_documents.push((created, document.source_path));
+    }
+
+    link_imported_documents(documents_folder, &created_documents, linked_document_id)?;
+    Ok(result)
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ConvertedHtmlPage {
+    title: Option<String>,
+    canonical_url: Option<String>,
+    body: String,
+}
+
+fn convert_html_page(html: &str) -> ConvertedHtmlPage {
+    let document = Html::parse_document(html);
+
+    let title = select_first(&document, "title")
+        .or_else(|| select_first(&document, "h1"))
+        .map(|element| element.text().collect::<Vec<_>>().join(" "))
+        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|text| !text.is_empty());
+
+    let canonical_url = select_first(&document, "link[rel~=\"canonical\"][href]")
+        .and_then(|element| element.value().attr("href"))
+        .or_else(|| {
+            select_first(&document, "meta[property=\"og:url\"][content]")
+                .and_then(|element| element.value().attr("content"))
+        })
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(str::to_owned);
+
+    let content_root = find_main_content(&document).unwrap_or_else(|| document.root_element());
+    let body = element_to_markdown(content_root, &mut |element| {
+        BOILERPLATE_ELEMENTS
+            .contains(&element.value().name())
+            .then(String::new)
+    });
+
+    ConvertedHtmlPage {
+        title,
+        canonical_url,
+        body,
+    }
+}
+
+fn find_main_content(document: &Html) -> Option<ElementRef<'_>> {
+    MAIN_CONTENT_SELECTORS.iter().find_map(|selector| {
+        let selector = Selector::parse(selector).ok()?;
+        document
+            .select(&selector)
+            .max_by_key(|element| text_length(*element))
+            .filter(|element| text_length(*element) >= MIN_MAIN_CONTENT_CHARS)
+    })
+}
+
+fn text_length(element: ElementRef<'_>) -> usize {
+    element.text().map(|text| text.trim().len()).sum()
+}
+
+fn select_first<'a>(document: &'a Html, selector: &str) -> Option<ElementRef<'a>> {
+    let selector = Selector::parse(selector).ok()?;
+    document.select(&selector).next()
+}
+
+/// Drops a leading `# Title` heading that only repeats the document title.
+fn strip_leading_title_heading(body: &str, title: &str) -> String {
+    let trimmed = body.trim_start();
+    if let Some(rest) = trimmed.strip_prefix("# ") {
+        let (heading, remainder) = rest.split_once('\n').unwrap_or((rest, ""));
+        if heading.trim() == title.trim() {
+            return remainder.trim_start().to_owned();
+        }
+    }
+    body.to_owned()
+}
+
+fn is_html_file_name(name: &str) -> bool {
+    has_any_extension(name, &HTML_EXTENSIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_path(prefix: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}-{timestamp}"))
+    }
+
+    #[test]
+    fn extracts_main_article_title_and_canonical_url() {
+        let paragraph = "Kubernetes rollouts need a rollback plan. ".repeat(8);
+        let page = convert_html_page(&format!(
+            r#"<html><head><title>Rollouts</title><link rel="canonical" href="https://wiki.example.com/rollouts"></head>
+<body><nav><a href="/">Home</a></nav><div id="sidebar">Recent changes</div>
+<article><h1>Rollouts</h1><p>{paragraph}</p><nav>Next page</nav><pre><code>kubectl rollout undo</code></pre></article>
+<footer>Copyright</footer></body></html>"#
+        ));
+
+        assert_eq!(page.title.as_deref(), Some("Rollouts"));
+        assert_eq!(
+            page.canonical_url.as_deref(),
+            Some("https://wiki.example.com/rollouts")
+        );
+        assert_eq!(
+            page.body,
+            format!(
+                "# Rollouts\n\n{}\n\n```\nkubectl rollout undo\n```",
+                paragraph.trim()
+            )
+        );
+        assert_eq!(
+            strip_leading_title_heading(&page.body, "Rollouts"),
+            format!("{}\n\n```\nkubectl rollout undo\n```", paragraph.trim())
+        );
+    }
+
+    #[test]
+    fn imports_directory_with_folders_links_and_images() {
+        let root = unique_temp_path("tentacle-html-import");
+        let source = root.join("wiki");
+        let vault = root.join("vault");
+        fs::create_dir_all(source.join("guides")).expect("create source");
+        fs::create_dir_all(&vault).expect("create vault");
+        fs::write(
+            source.join("index.html"),
+            "<title>Home</title><p>See <a href=\"guides/setup.html#install\">setup</a>.</p>",
+        )
+        .expect("write index");
+        fs::write(
+            source.join("guides/setup.html"),
+            "<title>Setup</title><p><img src=\"diagram.png\" alt=\"Diagram\"></p>",
+        )
+        .expect("write setup");
+        fs::write(source.join("guides/diagram.png"), b"png").expect("write image");
+
+        let result = import_html(
+            &vault,
+            &source,
+            &ImportOptions {
+                folder_path: Some("wiki".to_owned()),
+                ..ImportOptions::default()
+            },
+        )
+        .expect("import html directory");
+
+        assert_eq!(result.documents.len(), 2);
+        assert_eq!(result.attachments_imported, 1);
+        assert_eq!(
+            result.folders_created,
+            vec!["wiki".to_owned(), "wiki/guides".to_owned()]
+        );
+
+        let setup = result
+            .documents
+            .iter()
+            .find(|document| document.title == "Setup")
+            .expect("setup document");
+        let home = result
+            .documents
+            .iter()
+            .find(|document| document.title == "Home")
+            .expect("home document");
+        let home_document = document_store::read_document(&vault, &home.id).expect("read home");
+        assert_eq!(home_document.body, "See [setup](guides/Setup.md).");
+        let setup_document = document_store::read_document(&vault, &setup.id).expect("read setup");
+        assert_eq!(
+            setup_document.body,
+            format!("![Diagram](../../.attachments/{}/diagram.png)", setup.id)
+        );
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,532 @@
+use scraper::node::Node;
+use scraper::ElementRef;
+
+const SKIPPED_ELEMENTS: [&str; 9] = [
+    "script", "style", "noscript", "template", "head", "title", "svg", "iframe", "object",
+];
+
+const BLOCK_ELEMENTS: [&str; 35] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "en-note",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+];
+
+/// Hook that lets an importer render specific elements itself (e.g. Evernote `<en-media>`).
+/// Returning `None` falls back to the default conversion.
+pub(crate) type ElementOverride<'a> = dyn FnMut(ElementRef<'_>) -> Option<String> + 'a;
+
+/// Converts `element` and its descendants to markdown blocks separated by blank lines.
+pub(crate) fn element_to_markdown(
+    element: ElementRef<'_>,
+    overrides: &mut ElementOverride<'_>,
+) -> String {
+    let mut converter = Converter {
+        overrides,
+        list_item_depth: 0,
+    };
+    let mut blocks = Vec::new();
+    converter.blocks(element, &mut blocks);
+    join_blocks(&blocks)
+}
+
+struct Converter<'o, 'a> {
+    overrides: &'o mut ElementOverride<'a>,
+    /// List items being converted; checkboxes outside one become task-list items themselves.
+    list_item_depth: usize,
+}
+
+impl Converter<'_, '_> {
+    fn blocks(&mut self, element: ElementRef<'_>, out: &mut Vec<String>) {
+        let mut inline = String::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => inline.push_str(&collapse_text(text)),
+                Node::Element(child_element) => {
+                    let Some(child_ref) = ElementRef::wrap(child) else {
+                        continue;
+                    };
+                    let name = child_element.name();
+                    if SKIPPED_ELEMENTS.contains(&name) {
+                        continue;
+                    }
+                    if let Some(rendered) = (self.overrides)(child_ref) {
+                        inline.push_str(&rendered);
+                        continue;
+                    }
+                    if is_block_element(name) {
+                        flush_inline(&mut inline, out);
+                        self.block(child_ref, out);
+                    } else {
+                        inline.push_str(&self.inline(child_ref));
+                    }
+                }
+                _ => {}
+            }
+        }
+        flush_inline(&mut inline, out);
+    }
+
+    fn block(&mut self, element: ElementRef<'_>, out: &mut Vec<String>) {
+        let name = element.value().name();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                let text = single_line(&self.inline_children(element));
+                if !text.is_empty() {
+                    out.push(format!("{} {text}", "#".repeat(level)));
+                }
+            }
+            "hr" => out.push("---".to_owned()),
+            "pre" => {
+                if let Some(code) = render_code_block(element) {
+                    out.push(code);
+                }
+            }
+            "ul" | "ol" => {
+                let list = self.list(element, "");
+                if !list.is_empty() {
+                    out.push(list);
+                }
+            }
+            "table" => {
+                if let Some(table) = self.table(element) {
+                    out.push(table);
+                }
+            }
+            "blockquote" => {
+                let mut quoted = Vec::new();
+                self.blocks(element, &mut quoted);
+                if !quoted.is_empty() {
+                    out.push(
+                        join_blocks(&quoted)
+                            .lines()
+                            .map(|line| {
+                                if line.is_empty() {
+                                    ">".to_owned()
+                                } else {
+                                    format!("> {line}")
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    );
+                }
+            }
+            _ => self.blocks(element, out),
+        }
+    }
+
+    fn inline(&mut self, element: ElementRef<'_>) -> String {
+        let name = element.value().name();
+        match name {
+            "br" => "\n".to_owned(),
+            "strong" | "b" => wrap_inline(&self.inline_children(element), "**"),
+            "em" | "i" | "cite" => wrap_inline(&self.inline_children(element), "*"),
+            "s" | "del" | "strike" => wrap_inline(&self.inline_children(element), "~~"),
+            "code" | "kbd" | "samp" | "tt" => {
+                let text = element.text().collect::<String>();
+                let text = text.trim();
+                if text.is_empty() {
+                    String::new()
+                } else if text.contains('`') {
+                    format!("`` {text} ``")
+                } else {
+                    format!("`{text}`")
+                }
+            }
+            "a" => {
+                let text = single_line(&self.inline_children(element));
+                let href = element
+                    .value()
+                    .attr("href")
+                    .map(str::trim)
+                    .unwrap_or_default();
+                if href.is_empty() || href.starts_with("javascript:") {
+                    text
+                } else if text.is_empty() {
+                    format!("<{href}>")
+                } else {
+                    format!("[{text}]({})", format_link_target(href))
+                }
+            }
+            "img" => {
+                let src = element
+                    .value()
+                    .attr("src")
+                    .map(str::trim)
+                    .unwrap_or_default();
+                if src.is_empty() {
+                    return String::new();
+                }
+                let alt = element.value().attr("alt").unwrap_or_default().trim();
+                format!("![{}]({})", single_line(alt), format_link_target(src))
+            }
+            "input" => match element.value().attr("type") {
+                Some(kind) if kind.eq_ignore_ascii_case("checkbox") => {
+                    self.checkbox_marker(element.value().attr("checked").is_some())
+                }
+                _ => String::new(),
+            },
+            "en-todo" => {
+                let checked = element
+                    .value()
+                    .attr("checked")
+                    .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+                format!(
+                    "{}{}",
+                    self.checkbox_marker(checked),
+                    self.inline_children(element)
+                )
+            }
+            _ if SKIPPED_ELEMENTS.contains(&name) => String::new(),
+            _ => self.inline_children(element),
+        }
+    }
+
+    fn inline_children(&mut self, element: ElementRef<'_>) -> String {
+        let mut text = String::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(value) => text.push_str(&collapse_text(value)),
+                Node::Element(_) => {
+                    let Some(child_ref) = ElementRef::wrap(child) else {
+                        continue;
+                    };
+                    if let Some(rendered) = (self.overrides)(child_ref) {
+                        text.push_str(&rendered);
+                    } else if is_block_element(child_ref.value().name()) {
+                        let mut blocks = Vec::new();
+                        self.block(child_ref, &mut blocks);
+                        if !blocks.is_empty() {
+                            text.push(' ');
+                            text.push_str(&blocks.join(" "));
+                            text.push(' ');
+                        }
+                    } else {
+                        text.push_str(&self.inline(child_ref));
+                    }
+                }
+                _ => {}
+            }
+        }
+        text
+    }
+
+    fn list(&mut self, element: ElementRef<'_>, indent: &str) -> String {
+        let ordered = element.value().name() == "ol";
+        let start = element
+            .value()
+            .attr("start")
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .unwrap_or(1);
+
+        let mut lines: Vec<String> = Vec::new();
+        let mut number = start;
+        let mut previous_marker_width = 2usize;
+        for child in element.child_elements() {
+            let child_name = child.value().name();
+            if child_name == "ul" || child_name == "ol" {
+                // Evernote nests lists directly inside lists instead of inside an item.
+                let nested_indent = format!("{indent}{}", " ".repeat(previous_marker_width));
+                let nested = self.list(child, &nested_indent);
+                if !nested.is_empty() {
+                    lines.push(nested);
+                }
+                continue;
+            }
+            if child_name != "li" {
+                continue;
+            }
+
+            let marker = if ordered {
+                format!("{number}. ")
+            } else {
+                "- ".to_owned()
+            };
+            number += 1;
+            previous_marker_width = marker.len();
+            let item_indent = format!("{indent}{}", " ".repeat(marker.len()));
+
+            let mut item_blocks = Vec::new();
+            let mut nested_lists = Vec::new();
+            let mut inline = String::new();
+            self.list_item_depth += 1;
+            for item_child in child.children() {
+                match item_child.value() {
+                    Node::Text(text) => inline.push_str(&collapse_text(text)),
+                    Node::Element(_) => {
+                        let Some(item_ref) = ElementRef::wrap(item_child) else {
+                            continue;
+                        };
+                        let item_name = item_ref.value().name();
+                        if item_name == "ul" || item_name == "ol" {
+                            flush_inline(&mut inline, &mut item_blocks);
+                            nested_lists.push(self.list(item_ref, &item_indent));
+                        } else if let Some(rendered) = (self.overrides)(item_ref) {
+                            inline.push_str(&rendered);
+                        } else if is_block_element(item_name) {
+                            flush_inline(&mut inline, &mut item_blocks);
+                            self.block(item_ref, &mut item_blocks);
+                        } else {
+                            inline.push_str(&self.inline(item_ref));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            self.list_item_depth -= 1;
+            flush_inline(&mut inline, &mut item_blocks);
+
+            let mut item_lines = item_blocks
+                .join("\n")
+                .lines()
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            if item_lines.is_empty() {
+                item_lines.push(String::new());
+            }
+            let mut rendered = format!("{indent}{marker}{}", item_lines[0]);
+            for line in &item_lines[1..] {
+                rendered.push('\n');
+                if !line.is_empty() {
+                    rendered.push_str(&item_indent);
+                    rendered.push_str(line);
+                }
+            }
+            lines.push(rendered.trim_end().to_owned());
+            lines.extend(nested_lists.into_iter().filter(|nested| !nested.is_empty()));
+        }
+
+        lines.join("\n")
+    }
+
+    fn checkbox_marker(&self, checked: bool) -> String {
+        let marker = if checked { "[x] " } else { "[ ] " };
+        if self.list_item_depth > 0 {
+            marker.to_owned()
+        } else {
+            format!("- {marker}")
+        }
+    }
+
+    fn table(&mut self, element: ElementRef<'_>) -> Option<String> {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        collect_table_rows(element, &mut |row| {
+            let cells = row
+                .child_elements()
+                .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                .map(|cell| single_line(&self.inline_children(cell)).replace('|', "\\|"))
+                .collect::<Vec<_>>();
+            if !cells.is_empty() {
+                rows.push(cells);
+            }
+        });
+
+        let column_count = rows.iter().map(Vec::len).max()?;
+        let mut lines = Vec::with_capacity(rows.len() + 1);
+        for (index, row) in rows.iter().enumerate() {
+            let mut cells = row.clone();
+            cells.resize(column_count, String::new());
+            lines.push(format!("| {} |", cells.join(" | ")));
+            if index == 0 {
+                lines.push(format!("|{}", " --- |".repeat(column_count)));
+            }
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+fn collect_table_rows(element: ElementRef<'_>, visit: &mut dyn FnMut(ElementRef<'_>)) {
+    for child in element.child_elements() {
+        match child.value().name() {
+            "tr" => visit(child),
+            "thead" | "tbody" | "tfoot" => collect_table_rows(child, visit),
+            _ => {}
+        }
+    }
+}
+
+fn render_code_block(element: ElementRef<'_>) -> Option<String> {
+    let code = element.text().collect::<String>();
+    let code = code.trim_matches('\n').trim_end();
+    if code.trim().is_empty() {
+        return None;
+    }
+
+    let language = std::iter::once(element)
+        .chain(
+            element
+                .child_elements()
+                .filter(|child| child.value().name() == "code"),
+        )
+        .flat_map(|candidate| candidate.value().classes())
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+                .map(str::to_owned)
+        })
+        .unwrap_or_default();
+
+    let mut fence = "```".to_owned();
+    while code.contains(&fence) {
+        fence.push('`');
+    }
+    Some(format!("{fence}{language}\n{code}\n{fence}"))
+}
+
+fn is_block_element(name: &str) -> bool {
+    BLOCK_ELEMENTS.contains(&name)
+}
+
+fn flush_inline(inline: &mut String, out: &mut Vec<String>) {
+    let lines = inline
+        .split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>();
+    let paragraph = lines.join("\n").trim_matches('\n').to_owned();
+    if !paragraph.trim().is_empty() {
+        out.push(paragraph);
+    }
+    inline.clear();
+}
+
+fn collapse_text(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut previous_was_space = false;
+    for character in text.chars() {
+        if character.is_whitespace() {
+            if !previous_was_space {
+                collapsed.push(' ');
+            }
+            previous_was_space = true;
+        } else {
+            collapsed.push(character);
+            previous_was_space = false;
+        }
+    }
+    collapsed
+}
+
+fn wrap_inline(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_owned();
+    }
+    let leading = if text.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trailing = if text.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{leading}{marker}{trimmed}{marker}{trailing}")
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Joins blocks with blank lines, keeping consecutive task items (Evernote writes each
+/// checkbox in its own `<div>`) in one tight list.
+fn join_blocks(blocks: &[String]) -> String {
+    let mut joined = String::new();
+    for (index, block) in blocks.iter().enumerate() {
+        if index > 0 {
+            let tight = is_task_item(&blocks[index - 1]) && is_task_item(block);
+            joined.push_str(if tight { "\n" } else { "\n\n" });
+        }
+        joined.push_str(block);
+    }
+    joined
+}
+
+fn is_task_item(block: &str) -> bool {
+    !block.contains('\n') && (block.starts_with("- [ ] ") || block.starts_with("- [x] "))
+}
+
+fn format_link_target(target: &str) -> String {
+    if target.contains(char::is_whitespace) || target.contains('(') || target.contains(')') {
+        format!("<{target}>")
+    } else {
+        target.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scraper::Html;
+
+    fn html_to_markdown(html: &str) -> String {
+        let document = Html::parse_document(html);
+        element_to_markdown(document.root_element(), &mut |_| None)
+    }
+
+    #[test]
+    fn converts_headings_inline_formatting_and_links() {
+        let markdown = html_to_markdown(
+            "<html><head><title>x</title><style>p{}</style></head><body><h2>Plan</h2><p>Read <strong>this</strong> and <a href=\"https://example.com/a b\">that</a>.<br>Next <code>line</code></p></body></html>",
+        );
+        assert_eq!(
+            markdown,
+            "## Plan\n\nRead **this** and [that](<https://example.com/a b>).\nNext `line`"
+        );
+    }
+
+    #[test]
+    fn converts_nested_lists_checkboxes_tables_and_code() {
+        let markdown = html_to_markdown(concat!(
+            "<ul><li>one<ul><li>nested</li></ul></li><li><en-todo checked=\"true\"/>done</li></ul>",
+            "<ol start=\"3\"><li>three</li></ol>",
+            "<div><input type=\"checkbox\" checked>first</div><div><input type=\"checkbox\">second</div>",
+            "<table><tr><th>A</th><th>B</th></tr><tr><td>1</td><td>x|y</td></tr></table>",
+            "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>",
+        ));
+        assert_eq!(
+            markdown,
+            concat!(
+                "- one\n  - nested\n- [x] done\n\n",
+                "3. three\n\n",
+                "- [x] first\n- [ ] second\n\n",
+                "| A | B |\n| --- | --- |\n| 1 | x\\|y |\n\n",
+                "```rust\nfn main() {}\n```",
+            )
+        );
+    }
+}
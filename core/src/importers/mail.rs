@@ -0,0 +1,584 @@
+use mail_parser::mailbox::mbox::MessageIterator;
+use mail_parser::{Address, HeaderValue, Message, MessageParser, MimeHeaders};
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::document_store::{self, CreateDocumentInput};
+
+use super::html_to_markdown::element_to_markdown;
+use super::{
+    collect_source_files, ensure_folder_path, format_unix_seconds_utc, has_any_extension,
+    join_folder_path, nested_folder_path, normalize_base_folder, relative_prefix_to_vault_root,
+    rewrite_markdown_link_targets, sanitize_folder_segment, ImportError, ImportOptions,
+    ImportResultPayload, ImportSkippedPayload, ImportedDocumentPayload,
+};
+
+const MAIL_EXTENSIONS: [&str; 2] = ["eml", "mbox"];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MailImportOptions {
+    /// Import every message as its own document instead of one document per thread.
+    pub per_message: bool,
+}
+
+#[derive(Debug)]
+struct ParsedMail {
+    source_path: String,
+    folder_path: String,
+    message_id: Option<String>,
+    related_ids: Vec<String>,
+    subject: Option<String>,
+    from: Option<String>,
+    to: Vec<String>,
+    cc: Vec<String>,
+    date: Option<String>,
+    body: String,
+    attachments: Vec<MailAttachment>,
+}
+
+#[derive(Debug)]
+struct MailAttachment {
+    file_name: String,
+    content_id: Option<String>,
+    bytes: Vec<u8>,
+}
+
+/// Imports `.eml` files and mbox archives, or every one of them under a directory.
+///
+/// By default messages are grouped into threads through `In-Reply-To` and `References` and
+/// each thread becomes one document; `per_message` imports every message on its own. Each
+/// mbox archive becomes a folder named after the file. Sender, recipients, and date are stored
+/// as properties, HTML bodies are converted to markdown, and attachments are copied into
+/// `.attachments/<id>/`.
+pub fn import_mail(
+    documents_folder: &Path,
+    source: &Path,
+    options: &ImportOptions,
+    mail_options: &MailImportOptions,
+) -> Result<ImportResultPayload, ImportError> {
+    let (root, files) = collect_source_files(source, &MAIL_EXTENSIONS)?;
+    if files.is_empty() {
+        return Err(ImportError::Validation(format!(
+            "no .eml or .mbox files were found in \"{}\"",
+            source.display()
+        )));
+    }
+
+    let base_folder = normalize_base_folder(options.folder_path.as_deref());
+    let mut result = ImportResultPayload::default();
+    let mut messages = Vec::new();
+    let mut seen_message_ids = HashSet::new();
+    let mut collect =
+        |parsed: Option<ParsedMail>, source_path: String, result: &mut ImportResultPayload| {
+            let Some(parsed) = parsed else {
+                result.skipped.push(ImportSkippedPayload {
+                    source_path,
+                    reason: "could not parse message".to_owned(),
+                });
+                return;
+            };
+            if let Some(message_id) = parsed.message_id.as_ref() {
+                if !seen_message_ids.insert(message_id.clone()) {
+                    result.skipped.push(ImportSkippedPayload {
+                        source_path,
+                        reason: "duplicate of an already imported message".to_owned(),
+                    });
+                    return;
+                }
+            }
+            messages.push(parsed);
+        };
+
+    for relative_path in files {
+        let (parent, file_name) = relative_path
+            .rsplit_once('/')
+            .unwrap_or(("", relative_path.as_str()));
+        let mut reader = BufReader::new(File::open(root.join(&relative_path))?);
+        let is_mbox = has_any_extension(file_name, &["mbox"])
+            || (!has_any_extension(file_name, &["eml"])
+                && reader.fill_buf()?.starts_with(b"From "));
+
+        if !is_mbox {
+            let mut bytes = Vec::new();
+            std::io::Read::read_to_end(&mut reader, &mut bytes)?;
+            let folder_path = nested_folder_path(&base_folder, parent);
+            let parsed = parse_mail(&bytes, relative_path.clone(), folder_path);
+            collect(parsed, relative_path.clone(), &mut result);
+            continue;
+        }
+
+        let mailbox_name = file_name
+            .rsplit_once('.')
+            .map_or(file_name, |(stem, _)| stem);
+        let folder_path = join_folder_path(
+            &nested_folder_path(&base_folder, parent),
+            &sanitize_folder_segment(mailbox_name),
+        );
+        for (index, entry) in MessageIterator::new(reader).enumerate() {
+            let entry = entry?;
+            let source_path = format!("{relative_path}#{}", index + 1);
+            let parsed = parse_mail(entry.contents(), source_path.clone(), folder_path.clone());
+            collect(parsed, source_path, &mut result);
+        }
+    }
+
+    let groups = if mail_options.per_message {
+        (0..messages.len()).map(|index| vec![index]).collect()
+    } else {
+        group_threads(&messages)
+    };
+
+    for group in groups {
+        let thread = group
+            .iter()
+            .map(|index| &messages[*index])
+            .collect::<Vec<_>>();
+        let first = thread[0];
+        ensure_folder_path(documents_folder, &first.folder_path, &mut result)?;
+
+        let document_id = document_store::generate_document_id();
+        let root_prefix = relative_prefix_to_vault_root(&first.folder_path);
+        let mut sections = Vec::with_capacity(thread.len());
+        for message in &thread {
+            let mut linked_by_content_id = HashMap::new();
+            let mut listed = Vec::new();
+            for attachment in &message.attachments {
+                let attachment_path = document_store::write_document_attachment(
+                    documents_folder,
+                    &document_id,
+                    &attachment.file_name,
+                    &attachment.bytes,
+                )?;
+                result.attachments_imported += 1;
+                let target = format!("{root_prefix}{attachment_path}");
+                match attachment.content_id.as_ref() {
+                    Some(content_id) if message.body.contains(&format!("cid:{content_id}")) => {
+                        linked_by_content_id.insert(content_id.clone(), target);
+                    }
+                    _ => listed.push((attachment.file_name.clone(), target)),
+                }
+            }
+
+            let mut body = rewrite_markdown_link_targets(&message.body, |target| {
+                let content_id = target.strip_prefix("cid:")?;
+                linked_by_content_id
+                    .get(content_id)
+                    .map(|linked| markdown_link_target(linked))
+            });
+            if !listed.is_empty() {
+                let links = listed
+                    .iter()
+                    .map(|(name, target)| format!("- [{name}]({})", markdown_link_target(target)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                body = format!("{}\n\nAttachments:\n\n{links}", body.trim_end())
+                    .trim_start()
+                    .to_owned();
+            }
+
+            if thread.len() == 1 {
+                sections.push(body);
+            } else {
+                let sender = message.from.as_deref().unwrap_or("Unknown sender");
+                let heading = match message.date.as_deref() {
+                    Some(date) => format!("## {sender} — {date}"),
+                    None => format!("## {sender}"),
+                };
+                sections.push(format!("{heading}\n\n{body}").trim_end().to_owned());
+            }
+        }
+
+        let mut properties = BTreeMap::new();
+        if let Some(from) = first.from.as_ref() {
+            properties.insert("from".to_owned(), from.clone());
+        }
+        if !first.to.is_empty() {
+            properties.insert("to".to_owned(), first.to.join(", "));
+        }
+        if !first.cc.is_empty() {
+            properties.insert("cc".to_owned(), first.cc.join(", "));
+        }
+        if let Some(date) = first.date.as_ref() {
+            properties.insert("date".to_owned(), date.clone());
+        }
+        if let Some(message_id) = first.message_id.as_ref() {
+            properties.insert("message_id".to_owned(), message_id.clone());
+        }
+        if thread.len() > 1 {
+            properties.insert("messages".to_owned(), thread.len().to_string());
+            properties.insert("participants".to_owned(), thread_participants(&thread));
+        }
+
+        let created_at = thread.iter().find_map(|message| message.date.clone());
+        let updated_at = thread.iter().rev().find_map(|message| message.date.clone());
+        let created = document_store::create_document(
+            documents_folder,
+            &CreateDocumentInput {
+                title: Some(thread_title(first, !mail_options.per_message)),
+                body: Some(sections.join("\n\n")),
+                folder_path: Some(first.folder_path.clone()),
+                tags: options.tags.clone(),
+                tags_locked: Some(false),
+                id: Some(document_id),
+                properties,
+                created_at,
+                updated_at,
+            },
+        )?;
+
+        result.documents.push(ImportedDocumentPayload {
+            id: created.id,
+            title: created.title,
+            folder_path: created.folder_path,
+            source_path: first.source_path.clone(),
+        });
+    }
+
+    Ok(result)
+}
+
+fn parse_mail(bytes: &[u8], source_path: String, folder_path: String) -> Option<ParsedMail> {
+    let message = MessageParser::default().parse(bytes)?;
+
+    let mut related_ids = header_ids(message.in_reply_to());
+    for reference in header_ids(message.references()) {
+        if !related_ids.contains(&reference) {
+            related_ids.push(reference);
+        }
+    }
+
+    let attachments = message
+        .attachments()
+        .enumerate()
+        .map(|(index, part)| {
+            let file_name = part
+                .attachment_name()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_owned)
+                .unwrap_or_else(|| {
+                    let extension = part
+                        .content_type()
+                        .and_then(|content_type| content_type.subtype())
+                        .map(|subtype| subtype.to_ascii_lowercase())
+                        .filter(|subtype| subtype.chars().all(|c| c.is_ascii_alphanumeric()));
+                    match (part.is_message(), extension) {
+                        (true, _) => format!("message-{}.eml", index + 1),
+                        (false, Some(extension)) => format!("attachment-{}.{extension}", index + 1),
+                        (false, None) => format!("attachment-{}", index + 1),
+                    }
+                });
+            MailAttachment {
+                file_name,
+                content_id: part
+                    .content_id()
+                    .map(|content_id| content_id.trim_matches(['<', '>']).to_owned()),
+                bytes: part.contents().to_vec(),
+            }
+        })
+        .collect();
+
+    Some(ParsedMail {
+        source_path,
+        folder_path,
+        message_id: message.message_id().map(str::to_owned),
+        related_ids,
+        subject: message
+            .subject()
+            .map(str::trim)
+            .filter(|subject| !subject.is_empty())
+            .map(str::to_owned),
+        from: message
+            .from()
+            .and_then(|from| format_addresses(from).into_iter().next()),
+        to: message.to().map(format_addresses).unwrap_or_default(),
+        cc: message.cc().map(format_addresses).unwrap_or_default(),
+        date: message
+            .date()
+            .map(|date| format_unix_seconds_utc(date.to_timestamp())),
+        body: message_body_markdown(&message),
+        attachments,
+    })
+}
+
+/// Prefers the HTML body, converted to markdown, and falls back to the plain-text body.
+fn message_body_markdown(message: &Message<'_>) -> String {
+    let html = message
+        .html_bodies()
+        .find(|part| part.is_text_html())
+        .and_then(|part| part.text_contents());
+    if let Some(html) = html {
+        let document = Html::parse_document(html);
+        return element_to_markdown(document.root_element(), &mut |_| None);
+    }
+
+    message
+        .body_text(0)
+        .map(|text| text.replace("\r\n", "\n").trim().to_owned())
+        .unwrap_or_default()
+}
+
+fn header_ids(value: &HeaderValue<'_>) -> Vec<String> {
+    match value {
+        HeaderValue::Text(id) => vec![id.to_string()],
+        HeaderValue::TextList(ids) => ids.iter().map(ToString::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn format_addresses(address: &Address<'_>) -> Vec<String> {
+    address
+        .iter()
+        .filter_map(|addr| match (addr.name(), addr.address()) {
+            (Some(name), Some(email)) if !name.trim().is_empty() => {
+                Some(format!("{} <{email}>", name.trim()))
+            }
+            (_, Some(email)) => Some(email.to_owned()),
+            (Some(name), None) => Some(name.trim().to_owned()),
+            (None, None) => None,
+        })
+        .collect()
+}
+
+/// Groups messages into threads with a union-find over message ids, so replies that only
+/// reference a message missing from the archive still land in the same thread. Threads are
+/// ordered by their first message in the source and messages within a thread by date.
+fn group_threads(messages: &[ParsedMail]) -> Vec<Vec<usize>> {
+    fn find(parents: &mut HashMap<String, String>, key: &str) -> String {
+        let mut root = key.to_owned();
+        while let Some(parent) = parents.get(&root).filter(|parent| **parent != root) {
+            root = parent.clone();
+        }
+        parents.insert(key.to_owned(), root.clone());
+        root
+    }
+
+    let keys = messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            message
+                .message_id
+                .clone()
+                .unwrap_or_else(|| format!("#{index}"))
+        })
+        .collect::<Vec<_>>();
+
+    let mut parents: HashMap<String, String> = HashMap::new();
+    for (message, key) in messages.iter().zip(&keys) {
+        for related in &message.related_ids {
+            let left = find(&mut parents, key);
+            let right = find(&mut parents, related);
+            if left != right {
+                parents.insert(left, right);
+            }
+        }
+    }
+
+    let mut group_by_root: HashMap<String, usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (index, key) in keys.iter().enumerate() {
+        let root = find(&mut parents, key);
+        let group = *group_by_root.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(index);
+    }
+    for group in &mut groups {
+        group.sort_by(|left, right| {
+            let left_date = messages[*left].date.as_deref();
+            let right_date = messages[*right].date.as_deref();
+            (left_date.is_none(), left_date, *left).cmp(&(right_date.is_none(), right_date, *right))
+        });
+    }
+    groups
+}
+
+/// Uses the subject of the first message, dropping `Re:`/`Fwd:` prefixes for threads.
+fn thread_title(first: &ParsedMail, strip_reply_prefixes: bool) -> String {
+    let mut subject = first.subject.as_deref().unwrap_or_default().trim();
+    if strip_reply_prefixes {
+        while let Some(prefix_len) = ["re:", "fwd:", "fw:", "aw:", "wg:"]
+            .iter()
+            .find(|prefix| subject.to_ascii_lowercase().starts_with(**prefix))
+            .map(|prefix| prefix.len())
+        {
+            subject = subject[prefix_len..].trim_start();
+        }
+    }
+    if subject.is_empty() {
+        "(no subject)".to_owned()
+    } else {
+        subject.to_owned()
+    }
+}
+
+fn thread_participants(thread: &[&ParsedMail]) -> String {
+    let mut participants: Vec<&str> = Vec::new();
+    for message in thread {
+        for participant in message
+            .from
+            .iter()
+            .chain(message.to.iter())
+            .chain(message.cc.iter())
+        {
+            if !participants.contains(&participant.as_str()) {
+                participants.push(participant);
+            }
+        }
+    }
+    participants.join(", ")
+}
+
+fn markdown_link_target(target: &str) -> String {
+    if target.contains(' ') {
+        format!("<{target}>")
+    } else {
+        target.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_path(prefix: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}-{timestamp}"))
+    }
+
+    const THREAD_MBOX: &str = "From alice@example.com Mon Mar  3 09:00:00 2025\n\
+Message-ID: <m1@example.com>\n\
+From: Alice Smith <alice@example.com>\n\
+To: Bob <bob@example.com>\n\
+Subject: Database choice\n\
+Date: Mon, 3 Mar 2025 09:00:00 +0000\n\
+\n\
+Should we use Postgres or SQLite?\n\
+\n\
+From bob@example.com Mon Mar  3 10:00:00 2025\n\
+Message-ID: <m2@example.com>\n\
+In-Reply-To: <m1@example.com>\n\
+References: <m1@example.com>\n\
+From: Bob <bob@example.com>\n\
+To: Alice Smith <alice@example.com>\n\
+Cc: carol@example.com\n\
+Subject: Re: Database choice\n\
+Date: Mon, 3 Mar 2025 11:00:00 +0100\n\
+MIME-Version: 1.0\n\
+Content-Type: multipart/mixed; boundary=\"b1\"\n\
+\n\
+--b1\n\
+Content-Type: text/html; charset=utf-8\n\
+\n\
+<p>Decision: <strong>SQLite</strong>.</p><ul><li>Embedded</li><li>Zero ops</li></ul>\n\
+--b1\n\
+Content-Type: text/plain; name=\"notes.txt\"\n\
+Content-Disposition: attachment; filename=\"notes.txt\"\n\
+\n\
+benchmark notes\n\
+--b1--\n\
+\n\
+From dave@example.com Tue Mar  4 08:00:00 2025\n\
+Message-ID: <m3@example.com>\n\
+From: dave@example.com\n\
+To: team@example.com\n\
+Subject: Offsite\n\
+Date: Tue, 4 Mar 2025 08:00:00 +0000\n\
+\n\
+Offsite is on Friday.\n";
+
+    #[test]
+    fn groups_mbox_messages_into_threads() {
+        let root = unique_temp_path("tentacle-mail-import");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        let archive = root.join("Team.mbox");
+        fs::write(&archive, THREAD_MBOX).expect("write mbox");
+
+        let result = import_mail(
+            &vault,
+            &archive,
+            &ImportOptions::default(),
+            &MailImportOptions::default(),
+        )
+        .expect("import mbox");
+        assert_eq!(result.documents.len(), 2);
+        assert_eq!(result.folders_created, vec!["Team".to_owned()]);
+        assert_eq!(result.attachments_imported, 1);
+
+        let thread = document_store::read_document(&vault, &result.documents[0].id)
+            .expect("read thread document");
+        assert_eq!(thread.title, "Database choice");
+        assert_eq!(thread.created_at, "2025-03-03T09:00:00Z");
+        assert_eq!(thread.updated_at, "2025-03-03T10:00:00Z");
+        assert_eq!(
+            thread.properties.get("from").map(String::as_str),
+            Some("Alice Smith <alice@example.com>")
+        );
+        assert_eq!(
+            thread.properties.get("participants").map(String::as_str),
+            Some("Alice Smith <alice@example.com>, Bob <bob@example.com>, carol@example.com")
+        );
+        assert_eq!(
+            thread.body,
+            format!(
+                "## Alice Smith <alice@example.com> — 2025-03-03T09:00:00Z\n\n\
+Should we use Postgres or SQLite?\n\n\
+## Bob <bob@example.com> — 2025-03-03T10:00:00Z\n\n\
+Decision: **SQLite**.\n\n- Embedded\n- Zero ops\n\n\
+Attachments:\n\n- [notes.txt](../.attachments/{}/notes.txt)",
+                thread.id
+            )
+        );
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn imports_each_message_separately_when_threads_are_disabled() {
+        let root = unique_temp_path("tentacle-mail-per-message");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        fs::write(root.join("Team.mbox"), THREAD_MBOX).expect("write mbox");
+
+        let result = import_mail(
+            &vault,
+            &root,
+            &ImportOptions::default(),
+            &MailImportOptions { per_message: true },
+        )
+        .expect("import mbox");
+        assert_eq!(result.documents.len(), 3);
+        assert_eq!(result.documents[1].title, "Re Database choice");
+        assert_eq!(result.documents[1].source_path, "Team.mbox#2");
+
+        let reply =
+            document_store::read_document(&vault, &result.documents[1].id).expect("read reply");
+        assert_eq!(
+            reply.properties.get("to").map(String::as_str),
+            Some("Alice Smith <alice@example.com>")
+        );
+        assert_eq!(
+            reply.properties.get("cc").map(String::as_str),
+            Some("carol@example.com")
+        );
+        assert_eq!(
+            reply.properties.get("message_id").map(String::as_str),
+            Some("m2@example.com")
+        );
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
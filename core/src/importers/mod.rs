@@ -0,0 +1,587 @@
+pub mod bookmarks;
+pub mod conversations;
+pub mod enex;
+pub mod html;
+pub(crate) mod html_to_markdown;
+pub mod mail;
+pub mod notion;
+pub mod pdf;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::document_folders::{DocumentFoldersError, DocumentFoldersService};
+use crate::document_store::{self, DocumentStoreError, StoredDocument, UpdateDocumentInput};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// Vault folder the imported hierarchy is rooted at. Empty means the vault root.
+    pub folder_path: Option<String>,
+    /// Tags added to every imported document.
+    pub tags: Vec<String>,
+    /// Title override for importers that produce a single document.
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportedDocumentPayload {
+    pub id: String,
+    pub title: String,
+    pub folder_path: String,
+    pub source_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportSkippedPayload {
+    pub source_path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportResultPayload {
+    pub documents: Vec<ImportedDocumentPayload>,
+    pub folders_created: Vec<String>,
+    pub attachments_imported: usize,
+    pub skipped: Vec<ImportSkippedPayload>,
+}
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    DocumentStore(#[from] DocumentStoreError),
+    #[error(transparent)]
+    DocumentFolders(#[from] DocumentFoldersError),
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("csv error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("xml error: {0}")]
+    Xml(#[from] quick_xml::Error),
+    #[error("{0}")]
+    Validation(String),
+}
+
+/// Vault-relative markdown file a link `target` in the document stored at `document_path`
+/// points at. External, absolute and fragment-only targets, and links to files that are not
+/// markdown, give `None`.
+pub fn linked_document_path(document_path: &str, target: &str) -> Option<String> {
+    if target.starts_with(['#', '/']) || target.contains(':') {
+        return None;
+    }
+    let path = target.split(['#', '?']).next().unwrap_or_default();
+    let resolved = resolve_relative_path(document_path, &percent_decode(path))?;
+    has_any_extension(&resolved, &["md"]).then_some(resolved)
+}
+
+/// Vault-relative markdown files the document stored at `document_path` links to, in order.
+pub fn linked_document_paths(document_path: &str, body: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    rewrite_markdown_link_targets(body, |target| {
+        paths.extend(linked_document_path(document_path, target));
+        None
+    });
+    paths
+}
+
+/// Snake-cases a property name taken from imported data, e.g. a Notion column `Due Date`
+/// becomes `due_date`.
+pub(crate) fn normalize_property_key(raw_key: &str) -> String {
+    let mut key = String::with_capacity(raw_key.len());
+    for character in raw_key.trim().chars() {
+        if character.is_alphanumeric() {
+            key.extend(character.to_lowercase());
+        } else if !key.ends_with('_') {
+            key.push('_');
+        }
+    }
+    key.trim_matches('_').to_owned()
+}
+
+/// Reads every file under `source` into memory, keyed by `/`-separated relative path.
+/// Zip archives are expanded, including archives nested inside the top-level one.
+pub(crate) fn read_source_tree(source: &Path) -> Result<BTreeMap<String, Vec<u8>>, ImportError> {
+    let mut files = BTreeMap::new();
+    if source.is_dir() {
+        collect_directory_files(source, "", &mut files)?;
+    } else if source.is_file() {
+        let bytes = fs::read(source)?;
+        if is_zip_file_name(&source.to_string_lossy()) {
+            collect_zip_files(bytes, "", &mut files)?;
+        } else {
+            let name = source
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            files.insert(name, bytes);
+        }
+    } else {
+        return Err(ImportError::Validation(format!(
+            "import source \"{}\" was not found",
+            source.display()
+        )));
+    }
+
+    Ok(files)
+}
+
+fn collect_directory_files(
+    directory: &Path,
+    relative_path: &str,
+    files: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<(), ImportError> {
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let child_relative_path = join_folder_path(relative_path, &name);
+        if file_type.is_dir() {
+            collect_directory_files(&entry.path(), &child_relative_path, files)?;
+        } else if file_type.is_file() {
+            let bytes = fs::read(entry.path())?;
+            if is_zip_file_name(&name) {
+                collect_zip_files(bytes, relative_path, files)?;
+            } else {
+                files.insert(child_relative_path, bytes);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_zip_files(
+    bytes: Vec<u8>,
+    relative_path: &str,
+    files: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<(), ImportError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(enclosed_name) = entry.enclosed_name() else {
+            log::warn!(
+                "[importers] skipping unsafe archive entry \"{}\"",
+                entry.name()
+            );
+            continue;
+        };
+
+        let entry_path = enclosed_name
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        if entry_path
+            .split('/')
+            .any(|segment| segment.starts_with('.'))
+        {
+            continue;
+        }
+
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        if is_zip_file_name(&entry_path) {
+            let parent = entry_path
+                .rsplit_once('/')
+                .map(|(parent, _)| parent)
+                .unwrap_or_default();
+            collect_zip_files(contents, &join_folder_path(relative_path, parent), files)?;
+        } else {
+            files.insert(join_folder_path(relative_path, &entry_path), contents);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the directory an import is rooted at and the sorted `/`-separated relative paths
+/// of files under `source` whose extension is in `extensions`. A single file is returned as
+/// is, whatever its extension.
+pub(crate) fn collect_source_files(
+    source: &Path,
+    extensions: &[&str],
+) -> Result<(PathBuf, Vec<String>), ImportError> {
+    if source.is_file() {
+        let file_name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let root = source.parent().map(Path::to_path_buf).unwrap_or_default();
+        return Ok((root, vec![file_name]));
+    }
+    if !source.is_dir() {
+        return Err(ImportError::Validation(format!(
+            "import source \"{}\" was not found",
+            source.display()
+        )));
+    }
+
+    let mut files = Vec::new();
+    let mut pending = vec![String::new()];
+    while let Some(relative_directory) = pending.pop() {
+        for entry in fs::read_dir(source.join(&relative_directory))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let relative_path = join_folder_path(&relative_directory, &name);
+            if entry.file_type()?.is_dir() {
+                pending.push(relative_path);
+            } else if has_any_extension(&name, extensions) {
+                files.push(relative_path);
+            }
+        }
+    }
+    files.sort();
+    Ok((source.to_path_buf(), files))
+}
+
+pub(crate) fn has_any_extension(name: &str, extensions: &[&str]) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, extension)| {
+        extensions
+            .iter()
+            .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+    })
+}
+
+/// Maps a source sub-directory (`a/b`) to a vault folder under `base_folder`.
+pub(crate) fn nested_folder_path(base_folder: &str, relative_directory: &str) -> String {
+    relative_directory
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(base_folder.to_owned(), |folder, segment| {
+            join_folder_path(&folder, &sanitize_folder_segment(segment))
+        })
+}
+
+fn is_zip_file_name(name: &str) -> bool {
+    name.to_ascii_lowercase().ends_with(".zip")
+}
+
+/// Creates every missing segment of `folder_path`, recording newly created folders.
+pub(crate) fn ensure_folder_path(
+    documents_folder: &Path,
+    folder_path: &str,
+    result: &mut ImportResultPayload,
+) -> Result<(), ImportError> {
+    let mut current = String::new();
+    for segment in folder_path.split('/').filter(|segment| !segment.is_empty()) {
+        current = join_folder_path(&current, segment);
+        if documents_folder.join(&current).is_dir() {
+            continue;
+        }
+
+        match DocumentFoldersService::create_folder(documents_folder, &current) {
+            Ok(folder) => result.folders_created.push(folder.path),
+            Err(DocumentFoldersError::AlreadyExists(_)) => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes attachments written for a document that was then not created, so a failed import
+/// leaves no files behind without a note linking to them. The original error is what the
+/// caller reports, so a failed cleanup is only logged.
+pub(crate) fn discard_attachments(documents_folder: &Path, document_id: &str) {
+    if let Err(error) = document_store::delete_document_attachments(documents_folder, document_id) {
+        log::warn!(
+            "[importers] failed to remove attachments of \"{}\": {}",
+            document_id,
+            error
+        );
+    }
+}
+
+/// Normalizes the user-supplied `--folder` root into a `/`-separated vault path.
+pub(crate) fn normalize_base_folder(folder_path: Option<&str>) -> String {
+    folder_path
+        .unwrap_or_default()
+        .replace('\\', "/")
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .fold(String::new(), |folder, segment| {
+            join_folder_path(&folder, segment)
+        })
+}
+
+pub(crate) fn join_folder_path(prefix: &str, name: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+    let name = name.trim_matches('/');
+    match (prefix.is_empty(), name.is_empty()) {
+        (true, _) => name.to_owned(),
+        (_, true) => prefix.to_owned(),
+        _ => format!("{prefix}/{name}"),
+    }
+}
+
+/// Makes a single folder-name segment safe for the vault (no separators or reserved names).
+pub(crate) fn sanitize_folder_segment(raw: &str) -> String {
+    let cleaned = raw
+        .chars()
+        .map(|character| {
+            if character.is_control()
+                || matches!(
+                    character,
+                    '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*'
+                )
+            {
+                ' '
+            } else {
+                character
+            }
+        })
+        .collect::<String>();
+    let collapsed = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let trimmed = collapsed
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' '])
+        .trim();
+    if trimmed.is_empty() {
+        "Untitled".to_owned()
+    } else {
+        trimmed.to_owned()
+    }
+}
+
+/// Returns the `../` prefix that leads from a document in `folder_path` back to the vault root.
+pub(crate) fn relative_prefix_to_vault_root(folder_path: &str) -> String {
+    folder_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|_| "../")
+        .collect()
+}
+
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0usize;
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Resolves `target` relative to the folder containing `base_file`, collapsing `.` and `..`.
+pub(crate) fn resolve_relative_path(base_file: &str, target: &str) -> Option<String> {
+    let mut segments: Vec<&str> = base_file.split('/').collect();
+    segments.pop();
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            other => segments.push(other),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Points links between imported documents at the linked document's markdown file, relative
+/// to the linking document. Runs once every document exists so links use the file names the
+/// store picked. `documents` pairs each created document with its source path, and
+/// `linked_document_id` maps a `(source_path, link_target)` to the id it refers to.
+///
+/// Links are file paths rather than `tentacle://documents/<id>` URLs: `tentacle://` is the
+/// app's deep-link scheme, and paths still resolve in other editors and in exports.
+pub(crate) fn link_imported_documents(
+    documents_folder: &Path,
+    documents: &[(StoredDocument, String)],
+    linked_document_id: impl Fn(&str, &str) -> Option<String>,
+) -> Result<(), ImportError> {
+    let path_by_id: HashMap<&str, String> = documents
+        .iter()
+        .map(|(document, _)| {
+            let file_name = document_store::document_file_name(&document.title);
+            (
+                document.id.as_str(),
+                join_folder_path(&document.folder_path, &file_name),
+            )
+        })
+        .collect();
+
+    for (document, source_path) in documents {
+        let body = rewrite_markdown_link_targets(&document.body, |target| {
+            let id = linked_document_id(source_path, target)?;
+            let path = path_by_id.get(id.as_str())?;
+            Some(relative_file_link(&document.folder_path, path))
+        });
+        if body != document.body {
+            document_store::update_document(
+                documents_folder,
+                &document.id,
+                &UpdateDocumentInput {
+                    body: Some(body),
+                    updated_at: Some(document.updated_at.clone()),
+                    ..UpdateDocumentInput::default()
+                },
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Relative link from a document in `folder_path` to the vault-relative file `target_path`.
+pub(crate) fn relative_file_link(folder_path: &str, target_path: &str) -> String {
+    let folder = folder_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let target = target_path.split('/').collect::<Vec<_>>();
+    let shared = folder
+        .iter()
+        .zip(&target[..target.len() - 1])
+        .take_while(|(left, right)| left == right)
+        .count();
+
+    let mut link = "../".repeat(folder.len() - shared);
+    link.push_str(&target[shared..].join("/"));
+    link
+}
+
+/// Calls `rewrite` for every inline markdown link or image target and substitutes the
+/// returned value. Targets the callback returns `None` for are left untouched.
+pub(crate) fn rewrite_markdown_link_targets(
+    markdown: &str,
+    mut rewrite: impl FnMut(&str) -> Option<String>,
+) -> String {
+    let mut output = String::with_capacity(markdown.len());
+    let mut remainder = markdown;
+
+    while let Some(start) = remainder.find("](") {
+        let (before, after) = remainder.split_at(start + 2);
+        output.push_str(before);
+
+        let (target, rest) = if let Some(stripped) = after.strip_prefix('<') {
+            match stripped.find('>') {
+                Some(end) => (&stripped[..end], &stripped[end + 1..]),
+                None => {
+                    remainder = after;
+                    continue;
+                }
+            }
+        } else {
+            let end = after
+                .find(|character: char| character == ')' || character.is_whitespace())
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        match rewrite(target) {
+            Some(replacement) if replacement.contains(' ') => {
+                output.push('<');
+                output.push_str(&replacement);
+                output.push('>');
+            }
+            Some(replacement) => output.push_str(&replacement),
+            None if after.starts_with('<') => {
+                output.push('<');
+                output.push_str(target);
+                output.push('>');
+            }
+            None => output.push_str(target),
+        }
+        remainder = rest;
+    }
+
+    output.push_str(remainder);
+    output
+}
+
+/// Formats seconds since the unix epoch as a UTC ISO 8601 timestamp (`2024-05-01T12:00:00Z`).
+pub(crate) fn format_unix_seconds_utc(seconds_since_epoch: i64) -> String {
+    let days_since_epoch = seconds_since_epoch.div_euclid(86_400);
+    let seconds_within_day = seconds_since_epoch.rem_euclid(86_400);
+
+    let (year, month, day) = civil_from_days(days_since_epoch);
+    let hour = seconds_within_day / 3_600;
+    let minute = (seconds_within_day % 3_600) / 60;
+    let second = seconds_within_day % 60;
+
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+fn civil_from_days(days_since_unix_epoch: i64) -> (i64, i64, i64) {
+    let z = days_since_unix_epoch + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let mut year = year_of_era + era * 400;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_prime = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_prime + 2) / 5 + 1;
+    let month = month_prime + if month_prime < 10 { 3 } else { -9 };
+    if month <= 2 {
+        year += 1;
+    }
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_only_targets_the_callback_maps() {
+        let markdown = "See [A](A%20page.md) and ![img](<x y.png>) or [web](https://example.com).";
+        let rewritten = rewrite_markdown_link_targets(markdown, |target| match target {
+            "A%20page.md" => Some("../A page.md".to_owned()),
+            "x y.png" => Some(".attachments/abc/x y.png".to_owned()),
+            _ => None,
+        });
+        assert_eq!(
+            rewritten,
+            "See [A](<../A page.md>) and ![img](<.attachments/abc/x y.png>) or [web](https://example.com)."
+        );
+    }
+
+    #[test]
+    fn links_files_relative_to_the_linking_folder() {
+        assert_eq!(
+            relative_file_link("notion/Home", "notion/Tasks/Spec.md"),
+            "../Tasks/Spec.md"
+        );
+        assert_eq!(relative_file_link("notion", "notion/Home.md"), "Home.md");
+        assert_eq!(relative_file_link("", "a/b.md"), "a/b.md");
+        assert_eq!(relative_file_link("a/b", "c.md"), "../../c.md");
+    }
+
+    #[test]
+    fn resolves_relative_paths_and_decodes_percent_escapes() {
+        assert_eq!(
+            resolve_relative_path("a/b/page.md", "../c/d.md").as_deref(),
+            Some("a/c/d.md")
+        );
+        assert_eq!(resolve_relative_path("page.md", "../x.md"), None);
+        assert_eq!(percent_decode("My%20Page%E2%80%99s.md"), "My Page’s.md");
+        assert_eq!(relative_prefix_to_vault_root("a/b"), "../../");
+    }
+}
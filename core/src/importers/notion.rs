@@ -0,0 +1,596 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::document_store::{self, CreateDocumentInput};
+
+use super::{
+    ensure_folder_path, join_folder_path, link_imported_documents, normalize_base_folder,
+    normalize_property_key, percent_decode, read_source_tree, relative_prefix_to_vault_root,
+    resolve_relative_path, rewrite_markdown_link_targets, sanitize_folder_segment, ImportError,
+    ImportOptions, ImportResultPayload, ImportSkippedPayload, ImportedDocumentPayload,
+};
+
+const NOTION_HASH_LENGTH: usize = 32;
+const DATABASE_ALL_ROWS_SUFFIX: &str = "_all";
+
+#[derive(Debug, Clone)]
+struct PlannedDocument {
+    source_path: String,
+    id: String,
+    title: String,
+    folder_path: String,
+    body: String,
+    tags: Vec<String>,
+    properties: BTreeMap<String, String>,
+}
+
+/// Imports a Notion "Markdown & CSV" export (zip archive or extracted folder).
+///
+/// Page hierarchy becomes folder hierarchy, hash suffixes are stripped from names, links
+/// between pages are rewritten to relative paths of the imported files, and database rows
+/// become documents whose properties come from the CSV columns.
+pub fn import_notion_export(
+    documents_folder: &Path,
+    source: &Path,
+    options: &ImportOptions,
+) -> Result<ImportResultPayload, ImportError> {
+    let tree = strip_wrapper_folder(read_source_tree(source)?);
+    let base_folder = normalize_base_folder(options.folder_path.as_deref());
+    let mut result = ImportResultPayload::default();
+
+    let mut planned: Vec<PlannedDocument> = Vec::new();
+    for (path, bytes) in &tree {
+        if !has_extension(path, ".md") {
+            continue;
+        }
+
+        let content = String::from_utf8_lossy(bytes).replace("\r\n", "\n");
+        let (parent, file_name) = split_parent(path);
+        let (stem, _) = strip_notion_hash(trim_extension(file_name));
+        let (heading_title, body) = split_title_heading(&content);
+
+        planned.push(PlannedDocument {
+            source_path: path.clone(),
+            id: document_store::generate_document_id(),
+            title: heading_title.unwrap_or(stem),
+            folder_path: notion_folder_path(&base_folder, parent),
+            body,
+            tags: options.tags.clone(),
+            properties: BTreeMap::new(),
+        });
+    }
+
+    for (path, bytes) in &tree {
+        if !has_extension(path, ".csv") || is_superseded_database_csv(&tree, path) {
+            continue;
+        }
+        plan_database_rows(
+            path,
+            bytes,
+            &base_folder,
+            options,
+            &mut planned,
+            &mut result,
+        )?;
+    }
+
+    if planned.is_empty() {
+        return Err(ImportError::Validation(
+            "no Notion pages or databases were found in the import source".to_owned(),
+        ));
+    }
+
+    let id_by_source: HashMap<String, String> = planned
+        .iter()
+        .map(|document| (document.source_path.clone(), document.id.clone()))
+        .collect();
+    let id_by_hash: HashMap<String, String> = planned
+        .iter()
+        .filter_map(|document| {
+            let (_, file_name) = split_parent(&document.source_path);
+            let (_, hash) = strip_notion_hash(trim_extension(file_name));
+            hash.map(|hash| (hash, document.id.clone()))
+        })
+        .collect();
+
+    let linked_document_id = |source_path: &str, target: &str| -> Option<String> {
+        if let Some(hash) = notion_url_hash(target) {
+            return id_by_hash.get(&hash).cloned();
+        }
+        if target.starts_with('#') || target.contains("://") || target.starts_with("mailto:") {
+            return None;
+        }
+
+        let resolved = resolve_relative_path(source_path, &percent_decode(target))?;
+        if let Some(id) = id_by_source.get(&resolved) {
+            return Some(id.clone());
+        }
+        if !has_extension(&resolved, ".md") {
+            return None;
+        }
+        let (_, file_name) = split_parent(&resolved);
+        let (_, hash) = strip_notion_hash(trim_extension(file_name));
+        hash.and_then(|hash| id_by_hash.get(&hash)).cloned()
+    };
+
+    let mut referenced_assets: HashSet<String> = HashSet::new();
+    for document in &mut planned {
+        let mut written_assets: HashMap<String, String> = HashMap::new();
+        let root_prefix = relative_prefix_to_vault_root(&document.folder_path);
+        let source_path = document.source_path.clone();
+        let document_id = document.id.clone();
+        let mut write_error: Option<ImportError> = None;
+
+        // Links to other pages are rewritten once every page has been created.
+        document.body = rewrite_markdown_link_targets(&document.body, |target| {
+            if target.starts_with('#') || target.contains("://") || target.starts_with("mailto:") {
+                return None;
+            }
+
+            let resolved = resolve_relative_path(&source_path, &percent_decode(target))?;
+            if id_by_source.contains_key(&resolved) || has_extension(&resolved, ".md") {
+                return None;
+            }
+
+            let bytes = tree.get(&resolved)?;
+            referenced_assets.insert(resolved.clone());
+            if let Some(existing) = written_assets.get(&resolved) {
+                return Some(format!("{root_prefix}{existing}"));
+            }
+
+            let (_, file_name) = split_parent(&resolved);
+            match document_store::write_document_attachment(
+                documents_folder,
+                &document_id,
+                file_name,
+                bytes,
+            ) {
+                Ok(attachment_path) => {
+                    written_assets.insert(resolved, attachment_path.clone());
+                    Some(format!("{root_prefix}{attachment_path}"))
+                }
+                Err(error) => {
+                    write_error.get_or_insert(error.into());
+                    None
+                }
+            }
+        });
+
+        if let Some(error) = write_error {
+            return Err(error);
+        }
+        result.attachments_imported += written_assets.len();
+    }
+
+    for path in tree.keys() {
+        if has_extension(path, ".md") || has_extension(path, ".csv") {
+            continue;
+        }
+        if !referenced_assets.contains(path) {
+            result.skipped.push(ImportSkippedPayload {
+                source_path: path.clone(),
+                reason: "file is not referenced by any imported page".to_owned(),
+            });
+        }
+    }
+
+    let mut folder_paths: Vec<&str> = planned
+        .iter()
+        .map(|document| document.folder_path.as_str())
+        .collect();
+    folder_paths.sort_unstable();
+    folder_paths.dedup();
+    for folder_path in folder_paths {
+        ensure_folder_path(documents_folder, folder_path, &mut result)?;
+    }
+
+    let mut created_documents = Vec::with_capacity(planned.len());
+    for document in planned {
+        let created = document_store::create_document(
+            documents_folder,
+            &CreateDocumentInput {
+                title: Some(document.title),
+                body: Some(document.body),
+                folder_path: Some(document.folder_path),
+                tags: document.tags,
+                tags_locked: Some(false),
+                id: Some(document.id),
+                properties: document.properties,
+                ..CreateDocumentInput::default()
+            },
+        )?;
+
+        result.documents.push(ImportedDocumentPayload {
+            id: created.id.clone(),
+            title: created.title.clone(),
+            folder_path: created.folder_path.clone(),
+            source_path: document.source_path.clone(),
+        });
+        created_documents.push((created, document.source_path));
+    }
+
+    link_imported_documents(documents_folder, &created_documents, linked_document_id)?;
+    Ok(result)
+}
+
+fn plan_database_rows(
+    csv_path: &str,
+    bytes: &[u8],
+    base_folder: &str,
+    options: &ImportOptions,
+    planned: &mut Vec<PlannedDocument>,
+    result: &mut ImportResultPayload,
+) -> Result<(), ImportError> {
+    let content = String::from_utf8_lossy(bytes);
+    let content = content.trim_start_matches('\u{feff}');
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|header| header.trim().to_owned())
+        .collect();
+    if headers.is_empty() {
+        result.skipped.push(ImportSkippedPayload {
+            source_path: csv_path.to_owned(),
+            reason: "database export has no columns".to_owned(),
+        });
+        return Ok(());
+    }
+
+    // Row pages live in a sibling folder named like the CSV (without `_all`).
+    let rows_directory = database_rows_directory(csv_path);
+    let rows_folder_path = notion_folder_path(base_folder, &rows_directory);
+
+    for (row_index, record) in reader.records().enumerate() {
+        let record = record?;
+        let title = record.get(0).unwrap_or_default().trim().to_owned();
+        if title.is_empty() && record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+
+        let mut properties = BTreeMap::new();
+        let mut tags = options.tags.clone();
+        for (header, value) in headers.iter().zip(record.iter()).skip(1) {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            if header.eq_ignore_ascii_case("tags") {
+                tags.extend(value.split(',').map(|tag| tag.trim().to_owned()));
+            } else {
+                properties
+                    .entry(normalize_property_key(header))
+                    .or_insert_with(|| value.to_owned());
+            }
+        }
+
+        let row_page = planned.iter_mut().find(|document| {
+            let (parent, file_name) = split_parent(&document.source_path);
+            parent == rows_directory
+                && document.properties.is_empty()
+                && (document.title == title
+                    || strip_notion_hash(trim_extension(file_name)).0 == title)
+        });
+
+        match row_page {
+            Some(document) => {
+                document.body = strip_property_block(&document.body, &headers);
+                document.properties = properties;
+                document.tags = tags;
+            }
+            None => planned.push(PlannedDocument {
+                source_path: format!("{csv_path}#row-{}", row_index + 1),
+                id: document_store::generate_document_id(),
+                title: if title.is_empty() {
+                    "Untitled".to_owned()
+                } else {
+                    title
+                },
+                folder_path: rows_folder_path.clone(),
+                body: String::new(),
+                tags,
+                properties,
+            }),
+        }
+    }
+
+    Ok(())
+}
+
+fn database_rows_directory(csv_path: &str) -> String {
+    let without_extension = trim_extension(csv_path);
+    let (stem, hash) = strip_notion_hash(
+        without_extension
+            .strip_suffix(DATABASE_ALL_ROWS_SUFFIX)
+            .unwrap_or(without_extension),
+    );
+    match hash {
+        Some(hash) => format!("{stem} {hash}"),
+        None => stem,
+    }
+}
+
+/// Notion ships both `Name <hash>.csv` (current view) and `Name <hash>_all.csv` (every row);
+/// prefer the complete one when both are present.
+fn is_superseded_database_csv(tree: &BTreeMap<String, Vec<u8>>, path: &str) -> bool {
+    let without_extension = trim_extension(path);
+    if without_extension.ends_with(DATABASE_ALL_ROWS_SUFFIX) {
+        return false;
+    }
+    tree.contains_key(&format!(
+        "{without_extension}{DATABASE_ALL_ROWS_SUFFIX}.csv"
+    ))
+}
+
+fn strip_property_block(body: &str, headers: &[String]) -> String {
+    let lines: Vec<&str> = body.lines().collect();
+    let mut index = 0usize;
+    while index < lines.len() && lines[index].trim().is_empty() {
+        index += 1;
+    }
+
+    let start = index;
+    while index < lines.len() {
+        let line = lines[index];
+        let is_property_line = line.split_once(':').is_some_and(|(key, _)| {
+            headers
+                .iter()
+                .any(|header| header.eq_ignore_ascii_case(key.trim()))
+        });
+        if !is_property_line {
+            break;
+        }
+        index += 1;
+    }
+
+    if index == start {
+        return body.to_owned();
+    }
+    lines[index..].join("\n").trim().to_owned()
+}
+
+fn split_title_heading(content: &str) -> (Option<String>, String) {
+    let mut lines = content.lines().skip_while(|line| line.trim().is_empty());
+    if let Some(title) = lines
+        .next()
+        .and_then(|line| line.strip_prefix("# "))
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+    {
+        let body = lines.collect::<Vec<_>>().join("\n").trim().to_owned();
+        return (Some(title.to_owned()), body);
+    }
+
+    (None, content.trim().to_owned())
+}
+
+fn notion_folder_path(base_folder: &str, source_directory: &str) -> String {
+    source_directory
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(base_folder.to_owned(), |folder, segment| {
+            join_folder_path(
+                &folder,
+                &sanitize_folder_segment(&strip_notion_hash(segment).0),
+            )
+        })
+}
+
+/// Splits a trailing ` <32 hex chars>` Notion id off a file or folder name.
+fn strip_notion_hash(name: &str) -> (String, Option<String>) {
+    let trimmed = name.trim();
+    if trimmed.len() > NOTION_HASH_LENGTH {
+        let split_index = trimmed.len() - NOTION_HASH_LENGTH;
+        if trimmed.is_char_boundary(split_index) {
+            let (head, hash) = trimmed.split_at(split_index);
+            if head.ends_with(' ') && is_notion_hash(hash) {
+                return (head.trim().to_owned(), Some(hash.to_ascii_lowercase()));
+            }
+        }
+    }
+    (trimmed.to_owned(), None)
+}
+
+fn is_notion_hash(value: &str) -> bool {
+    value.len() == NOTION_HASH_LENGTH
+        && value.chars().all(|character| character.is_ascii_hexdigit())
+}
+
+/// Extracts the page id from `https://www.notion.so/...-<hash>` style links.
+fn notion_url_hash(target: &str) -> Option<String> {
+    let lower = target.to_ascii_lowercase();
+    if !(lower.starts_with("https://") || lower.starts_with("http://"))
+        || !(lower.contains("notion.so/") || lower.contains("notion.site/"))
+    {
+        return None;
+    }
+
+    let path = lower.split(['?', '#']).next().unwrap_or_default();
+    let last_segment = path.rsplit('/').next().unwrap_or_default();
+    let candidate = last_segment.rsplit('-').next().unwrap_or_default();
+    is_notion_hash(candidate).then(|| candidate.to_owned())
+}
+
+/// Notion wraps newer exports in a single `Export-<uuid>` folder; drop it so the
+/// workspace's top-level pages land at the import root.
+fn strip_wrapper_folder(tree: BTreeMap<String, Vec<u8>>) -> BTreeMap<String, Vec<u8>> {
+    let mut first_segments = tree
+        .keys()
+        .map(|path| path.split_once('/').map(|(first, _)| first));
+    let Some(Some(wrapper)) = first_segments.next() else {
+        return tree;
+    };
+    let wrapper = wrapper.to_owned();
+    if strip_notion_hash(&wrapper).1.is_some()
+        || !first_segments.all(|segment| segment == Some(wrapper.as_str()))
+    {
+        return tree;
+    }
+
+    let prefix = format!("{wrapper}/");
+    tree.into_iter()
+        .map(|(path, bytes)| (path[prefix.len()..].to_owned(), bytes))
+        .collect()
+}
+
+fn split_parent(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+fn trim_extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(index) if index > 0 && !name[index..].contains('/') => &name[..index],
+        _ => name,
+    }
+}
+
+fn has_extension(path: &str, extension: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const PAGE_HASH: &str = "0123456789abcdef0123456789abcdef";
+    const CHILD_HASH: &str = "fedcba9876543210fedcba9876543210";
+    const DATABASE_HASH: &str = "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbb";
+    const ROW_HASH: &str = "cccccccccccccccccccccccccccccccc";
+
+    fn unique_temp_path(prefix: &str) -> std::path::PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}-{timestamp}"))
+    }
+
+    fn write_file(root: &Path, relative_path: &str, content: &[u8]) {
+        let path = root.join(relative_path);
+        fs::create_dir_all(path.parent().expect("fixture path has a parent"))
+            .expect("create fixture directory");
+        fs::write(path, content).expect("write fixture file");
+    }
+
+    #[test]
+    fn strips_notion_hash_suffixes() {
+        assert_eq!(
+            strip_notion_hash(&format!("Project Plan {PAGE_HASH}")),
+            ("Project Plan".to_owned(), Some(PAGE_HASH.to_owned()))
+        );
+        assert_eq!(
+            strip_notion_hash("Plain Name"),
+            ("Plain Name".to_owned(), None)
+        );
+        assert_eq!(
+            notion_url_hash(&format!(
+                "https://www.notion.so/Project-Plan-{PAGE_HASH}?pvs=4"
+            )),
+            Some(PAGE_HASH.to_owned())
+        );
+    }
+
+    #[test]
+    fn imports_pages_folders_links_and_database_rows() {
+        let export_dir = unique_temp_path("tentacle-notion-export");
+        let vault_dir = unique_temp_path("tentacle-notion-vault");
+
+        write_file(
+            &export_dir,
+            &format!("Home {PAGE_HASH}.md"),
+            format!(
+                "# Home\n\nSee [Child](Home%20{PAGE_HASH}/Child%20{CHILD_HASH}.md) and ![diagram](Home%20{PAGE_HASH}/diagram.png)."
+            )
+            .as_bytes(),
+        );
+        write_file(
+            &export_dir,
+            &format!("Home {PAGE_HASH}/Child {CHILD_HASH}.md"),
+            format!("# Child\n\nBack to [Home](https://www.notion.so/Home-{PAGE_HASH}).")
+                .as_bytes(),
+        );
+        write_file(
+            &export_dir,
+            &format!("Home {PAGE_HASH}/diagram.png"),
+            b"png",
+        );
+        write_file(
+            &export_dir,
+            &format!("Tasks {DATABASE_HASH}_all.csv"),
+            "\u{feff}Name,Status,Tags\nWrite spec,Done,\"Work, Docs\"\nShip it,Todo,\n".as_bytes(),
+        );
+        write_file(
+            &export_dir,
+            &format!("Tasks {DATABASE_HASH}.csv"),
+            b"Name,Status\nWrite spec,Done\n",
+        );
+        write_file(
+            &export_dir,
+            &format!("Tasks {DATABASE_HASH}/Write spec {ROW_HASH}.md"),
+            b"# Write spec\n\nStatus: Done\nTags: Work, Docs\n\nDraft the importer spec.",
+        );
+
+        let result = import_notion_export(
+            &vault_dir,
+            &export_dir,
+            &ImportOptions {
+                folder_path: Some("notion".to_owned()),
+                tags: vec!["imported".to_owned()],
+                ..ImportOptions::default()
+            },
+        )
+        .expect("import notion export");
+
+        assert_eq!(result.documents.len(), 4);
+        assert_eq!(result.attachments_imported, 1);
+        assert!(result.folders_created.contains(&"notion/Home".to_owned()));
+        assert!(result.folders_created.contains(&"notion/Tasks".to_owned()));
+
+        let by_title: HashMap<String, ImportedDocumentPayload> = result
+            .documents
+            .iter()
+            .map(|document| (document.title.clone(), document.clone()))
+            .collect();
+        let home =
+            document_store::read_document(&vault_dir, &by_title["Home"].id).expect("read home");
+        let child_id = &by_title["Child"].id;
+        assert_eq!(home.folder_path, "notion");
+        assert!(home.body.contains("[Child](Home/Child.md)"));
+        assert!(home.body.contains(&format!(
+            "![diagram](../.attachments/{}/diagram.png)",
+            home.id
+        )));
+        assert!(vault_dir
+            .join(format!(".attachments/{}/diagram.png", home.id))
+            .exists());
+
+        let child = document_store::read_document(&vault_dir, child_id).expect("read child");
+        assert_eq!(child.folder_path, "notion/Home");
+        assert!(child.body.contains("[Home](../Home.md)"));
+
+        let spec = document_store::read_document(&vault_dir, &by_title["Write spec"].id)
+            .expect("read database row page");
+        assert_eq!(spec.folder_path, "notion/Tasks");
+        assert_eq!(spec.body, "Draft the importer spec.");
+        assert_eq!(
+            spec.properties.get("status").map(String::as_str),
+            Some("Done")
+        );
+        assert_eq!(spec.tags, vec!["imported", "work", "docs"]);
+
+        let ship = document_store::read_document(&vault_dir, &by_title["Ship it"].id)
+            .expect("read csv-only row");
+        assert_eq!(ship.folder_path, "notion/Tasks");
+        assert_eq!(
+            ship.properties.get("status").map(String::as_str),
+            Some("Todo")
+        );
+
+        let _ = fs::remove_dir_all(export_dir);
+        let _ = fs::remove_dir_all(vault_dir);
+    }
+}
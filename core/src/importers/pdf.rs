@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use crate::document_store::{self, CreateDocumentInput};
+use crate::text_processing::format_page_marker;
+
+use super::{
+    collect_source_files, discard_attachments, ensure_folder_path, nested_folder_path,
+    normalize_base_folder, relative_prefix_to_vault_root, ImportError, ImportOptions,
+    ImportResultPayload, ImportSkippedPayload, ImportedDocumentPayload,
+};
+
+const PDF_EXTENSIONS: [&str; 1] = ["pdf"];
+
+/// Imports a PDF file, or every PDF under a directory, as searchable documents.
+///
+/// Text is extracted page by page with a pure-Rust parser and written with page markers so
+/// chunking can attribute search hits to pages. The original PDF is stored as an attachment
+/// and `source_file`/`pages` are recorded as properties. PDFs that cannot be parsed are
+/// reported as skipped.
+pub fn import_pdf(
+    documents_folder: &Path,
+    source: &Path,
+    options: &ImportOptions,
+) -> Result<ImportResultPayload, ImportError> {
+    let (root, files) = collect_source_files(source, &PDF_EXTENSIONS)?;
+    if files.is_empty() {
+        return Err(ImportError::Validation(format!(
+            "no .pdf files were found in \"{}\"",
+            source.display()
+        )));
+    }
+    if options.title.is_some() && files.len() > 1 {
+        return Err(ImportError::Validation(
+            "a title can only be set when importing a single PDF file".to_owned(),
+        ));
+    }
+
+    let base_folder = normalize_base_folder(options.folder_path.as_deref());
+    let mut result = ImportResultPayload::default();
+    for relative_path in files {
+        let bytes = fs::read(root.join(&relative_path))?;
+        let pages = match extract_pdf_pages(&bytes) {
+            Ok(pages) => pages,
+            Err(reason) => {
+                result.skipped.push(ImportSkippedPayload {
+                    source_path: relative_path,
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        let (parent, file_name) = relative_path
+            .rsplit_once('/')
+            .unwrap_or(("", relative_path.as_str()));
+        let folder_path = nested_folder_path(&base_folder, parent);
+        ensure_folder_path(documents_folder, &folder_path, &mut result)?;
+
+        let document_id = document_store::generate_document_id();
+        let attachment_path = document_store::write_document_attachment(
+            documents_folder,
+            &document_id,
+            file_name,
+            &bytes,
+        )?;
+        result.attachments_imported += 1;
+
+        let attachment_target = format!(
+            "{}{attachment_path}",
+            relative_prefix_to_vault_root(&folder_path)
+        );
+        let body = build_paginated_body(file_name, &attachment_target, &pages);
+
+        let mut properties = BTreeMap::new();
+        properties.insert("source_file".to_owned(), file_name.to_owned());
+        properties.insert("pages".to_owned(), pages.len().to_string());
+
+        let file_stem = file_name
+            .rsplit_once('.')
+            .map_or(file_name, |(stem, _)| stem);
+        let created = match document_store::create_document(
+            documents_folder,
+            &CreateDocumentInput {
+                title: Some(
+                    options
+                        .title
+                        .clone()
+                        .unwrap_or_else(|| file_stem.to_owned()),
+                ),
+                body: Some(body),
+                folder_path: Some(folder_path),
+                tags: options.tags.clone(),
+                tags_locked: Some(false),
+                id: Some(document_id.clone()),
+                properties,
+                ..CreateDocumentInput::default()
+            },
+        ) {
+            Ok(created) => created,
+            Err(error) => {
+                // The PDF was already copied under the new id; don't leave it without a note.
+                discard_attachments(documents_folder, &document_id);
+                return Err(error.into());
+            }
+        };
+
+        result.documents.push(ImportedDocumentPayload {
+            id: created.id,
+            title: created.title,
+            folder_path: created.folder_path,
+            source_path: relative_path.clone(),
+        });
+    }
+
+    Ok(result)
+}
+
+/// Extracts the text of every page. The parser panics on some malformed files, so panics are
+/// turned into an error for that file instead of aborting the whole import.
+fn extract_pdf_pages(bytes: &[u8]) -> Result<Vec<String>, String> {
+    let extracted = panic::catch_unwind(AssertUnwindSafe(|| {
+        pdf_extract::extract_text_from_mem_by_pages(bytes)
+    }));
+    match extracted {
+        Ok(Ok(pages)) if !pages.is_empty() => Ok(pages),
+        Ok(Ok(_)) => Err("PDF has no readable pages".to_owned()),
+        Ok(Err(error)) => Err(format!("could not read PDF: {error}")),
+        Err(_) => Err("could not read PDF: parser failed on this file".to_owned()),
+    }
+}
+
+fn build_paginated_body(file_name: &str, attachment_target: &str, pages: &[String]) -> String {
+    let target = if attachment_target.contains(' ') {
+        format!("<{attachment_target}>")
+    } else {
+        attachment_target.to_owned()
+    };
+
+    let mut sections = vec![format!("[{file_name}]({target})")];
+    for (index, text) in pages.iter().enumerate() {
+        let page_number = u32::try_from(index + 1).unwrap_or(u32::MAX);
+        let cleaned = clean_page_text(text);
+        if cleaned.is_empty() {
+            sections.push(format_page_marker(page_number));
+        } else {
+            sections.push(format!("{}\n\n{cleaned}", format_page_marker(page_number)));
+        }
+    }
+    sections.join("\n\n")
+}
+
+/// Trims trailing whitespace and collapses runs of blank lines left by the text extractor.
+fn clean_page_text(text: &str) -> String {
+    let mut cleaned = String::with_capacity(text.len());
+    let mut blank_run = 0usize;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank_run += 1;
+            continue;
+        }
+        if !cleaned.is_empty() {
+            cleaned.push_str(if blank_run > 0 { "\n\n" } else { "\n" });
+        }
+        cleaned.push_str(line);
+        blank_run = 0;
+    }
+    cleaned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_processing::chunk_document_text;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_path(prefix: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}-{timestamp}"))
+    }
+
+    /// Builds a minimal PDF with one Helvetica text line per page and a valid xref table.
+    fn build_test_pdf(page_texts: &[&str]) -> Vec<u8> {
+        let page_count = page_texts.len();
+        let font_id = 3 + page_count * 2;
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {page_count} >>",
+                (0..page_count)
+                    .map(|index| format!("{} 0 R", 3 + index * 2))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        ];
+        for (index, text) in page_texts.iter().enumerate() {
+            let content = format!("BT /F1 12 Tf 72 720 Td ({text}) Tj ET");
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Resources << /Font << /F1 {font_id} 0 R >> >> /Contents {} 0 R >>",
+                4 + index * 2
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{content}\nendstream",
+                content.len()
+            ));
+        }
+        objects.push(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_owned(),
+        );
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", index + 1).as_bytes());
+        }
+        let xref_offset = pdf.len();
+        pdf.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+                objects.len() + 1
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+
+    #[test]
+    fn imports_pdf_pages_with_markers_properties_and_attachment() {
+        let root = unique_temp_path("tentacle-pdf-import");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        let source = root.join("Runbook.pdf");
+        fs::write(
+            &source,
+            build_test_pdf(&["Restart the ingest workers", "Rotate the signing keys"]),
+        )
+        .expect("write pdf");
+
+        let result = import_pdf(
+            &vault,
+            &source,
+            &ImportOptions {
+                folder_path: Some("reference".to_owned()),
+                ..ImportOptions::default()
+            },
+        )
+        .expect("import pdf");
+        assert_eq!(result.documents.len(), 1);
+        assert_eq!(result.attachments_imported, 1);
+
+        let document =
+            document_store::read_document(&vault, &result.documents[0].id).expect("read pdf doc");
+        assert_eq!(document.title, "Runbook");
+        assert_eq!(
+            document.properties.get("source_file").map(String::as_str),
+            Some("Runbook.pdf")
+        );
+        assert_eq!(
+            document.properties.get("pages").map(String::as_str),
+            Some("2")
+        );
+        assert!(document.body.starts_with(&format!(
+            "[Runbook.pdf](../.attachments/{}/Runbook.pdf)",
+            document.id
+        )));
+        assert!(vault
+            .join(format!(".attachments/{}/Runbook.pdf", document.id))
+            .is_file());
+
+        let chunks = chunk_document_text(&document.title, &document.body);
+        let signing_chunk = chunks
+            .iter()
+            .find(|chunk| chunk.text.contains("signing keys"))
+            .expect("chunk with page 2 text");
+        assert_eq!(signing_chunk.page, Some(2));
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn removes_the_attachment_when_the_document_cannot_be_created() {
+        let root = unique_temp_path("tentacle-pdf-create-fails");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        let source = root.join("Runbook.pdf");
+        fs::write(&source, build_test_pdf(&["Restart the ingest workers"])).expect("write pdf");
+
+        // A file name longer than the filesystem allows fails after the PDF was copied.
+        let error = import_pdf(
+            &vault,
+            &source,
+            &ImportOptions {
+                title: Some("x".repeat(300)),
+                ..ImportOptions::default()
+            },
+        )
+        .expect_err("over-long title fails");
+        assert!(matches!(error, ImportError::DocumentStore(_)));
+        let attachments = fs::read_dir(vault.join(document_store::ATTACHMENTS_FOLDER))
+            .map(|entries| entries.count())
+            .unwrap_or(0);
+        assert_eq!(attachments, 0, "no orphaned attachment folder is left");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn skips_unreadable_pdfs() {
+        let root = unique_temp_path("tentacle-pdf-invalid");
+        let vault = root.join("vault");
+        fs::create_dir_all(&vault).expect("create vault");
+        fs::write(root.join("broken.pdf"), b"not a pdf").expect("write broken pdf");
+
+        let result = import_pdf(&vault, &root, &ImportOptions::default()).expect("import dir");
+        assert!(result.documents.is_empty());
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].source_path, "broken.pdf");
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -2,22 +2,34 @@ use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::ErrorKind;
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::document_cache::{CachedDocumentPayload, DocumentCacheError, DocumentCacheStore};
+use crate::document_cache::{
+    CachedDocumentPayload, DocumentCacheError, DocumentCacheStore, HighlightedTextPayload,
+    HybridHitExplanationPayload, HybridSearchHitPayload, MatchRangePayload,
+    RelatedChunkPairPayload, SearchExplanationPayload, SearchPassagePayload,
+};
 use crate::document_folders::{DocumentFoldersError, DocumentFoldersService};
 use crate::document_store::{self, DocumentStoreError};
 use crate::embeddings::{
-    hybrid_search_documents_by_query, sync_documents_embeddings_batch_with_progress,
-    EmbeddingError, EmbeddingSyncDocumentPayload,
+    configured_embedding_model, explain_hybrid_search_by_search_query,
+    sync_documents_embeddings_batch_with_progress, EmbeddingError, EmbeddingSyncDocumentPayload,
+};
+use crate::query::{
+    abbreviation_dictionary, parse_search_query, preprocess_search_query, DateFilter,
+    PreprocessedQuery, QueryParseError, SearchQuery,
 };
+use crate::rerank::{rerank_search_hits, DEFAULT_RERANK_CANDIDATES};
+use crate::vault_settings::{load_vault_settings, VaultSettingsError};
 
 const CACHE_DB_FILE_NAME: &str = ".document-data.db";
 const LOCAL_USER_ID: &str = "local";
+/// Body characters given to the reranker for hits without a matching passage.
+const RERANK_BODY_PREVIEW_CHARS: usize = 2000;
 
 pub type ProgressCallback = Box<dyn FnMut(ProgressEvent) + Send>;
 
@@ -64,8 +76,38 @@ pub struct SearchOptions {
     pub folder_filter: Option<String>,
     pub semantic_query: Option<String>,
     pub exclude_document_id: Option<String>,
+    /// Tags every result must have, compared case-insensitively.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub excluded_tags: Vec<String>,
+    /// Folders whose documents (subfolders included) are left out.
+    #[serde(default)]
+    pub excluded_folders: Vec<String>,
+    /// Created/updated date bounds, compared by calendar day.
+    #[serde(default)]
+    pub dates: Vec<DateFilter>,
     pub semantic_weight: f32,
     pub bm25_weight: f32,
+    /// Searches the query as plain words instead of query syntax, for queries built from
+    /// document text.
+    #[serde(default)]
+    pub plain_text: bool,
+    /// Expands abbreviations for the semantic leg and picks the leg weights by query length
+    /// (`query::preprocess_search_query`), replacing `semantic_weight` and `bm25_weight`.
+    /// An explicit `semantic_query` is still embedded as given.
+    #[serde(default)]
+    pub preprocess: bool,
+    /// Rescores the top fused candidates with the local cross-encoder before applying
+    /// `limit`.
+    #[serde(default)]
+    pub rerank: bool,
+    /// Fused candidates the reranker rescores (default `DEFAULT_RERANK_CANDIDATES`).
+    #[serde(default)]
+    pub rerank_candidates: Option<usize>,
+    /// Returns how each hit was scored and what the search dropped.
+    #[serde(default)]
+    pub explain: bool,
 }
 
 impl Default for SearchOptions {
@@ -76,8 +118,17 @@ impl Default for SearchOptions {
             folder_filter: None,
             semantic_query: None,
             exclude_document_id: None,
+            tags: Vec::new(),
+            excluded_tags: Vec::new(),
+            excluded_folders: Vec::new(),
+            dates: Vec::new(),
             semantic_weight: 1.0,
             bm25_weight: 1.0,
+            plain_text: false,
+            preprocess: false,
+            rerank: false,
+            rerank_candidates: None,
+            explain: false,
         }
     }
 }
@@ -90,6 +141,20 @@ pub struct SearchResultPayload {
     pub folder_path: String,
     pub tags: Vec<String>,
     pub relevance_score: f32,
+    /// Page of the best-matching passage, for paginated documents such as imported PDFs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub title_matches: Vec<MatchRangePayload>,
+    /// Highlighted body excerpt from the full-text index, for lexical hits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<HighlightedTextPayload>,
+    /// Best-matching chunks of the document with their locations, highest score first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub passages: Vec<SearchPassagePayload>,
+    /// Score breakdown, with `SearchOptions.explain`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<HybridHitExplanationPayload>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,6 +163,39 @@ pub struct SearchResponsePayload {
     pub query: String,
     pub results: Vec<SearchResultPayload>,
     pub total_results: usize,
+    /// Time spent in the rerank stage, when it ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank_time_ms: Option<u64>,
+    /// Search-wide ranking inputs, with `SearchOptions.explain`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<SearchExplanationPayload>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RelatedResultPayload {
+    pub id: String,
+    pub title: String,
+    pub folder_path: String,
+    pub tags: Vec<String>,
+    pub relevance_score: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_tags: Vec<String>,
+    #[serde(default)]
+    pub linked: bool,
+    /// Closest chunks of the two documents, explaining the similarity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_pair: Option<RelatedChunkPairPayload>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RelatedResponsePayload {
+    pub document_id: String,
+    pub results: Vec<RelatedResultPayload>,
+    pub total_results: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,6 +213,13 @@ pub struct StatusPayload {
     pub tags: usize,
     pub last_indexed: Option<String>,
     pub index_size_bytes: u64,
+    /// Model the vault is configured to embed with.
+    pub embedding_model: String,
+    /// Vector size of the cache's stored embeddings.
+    pub embedding_dimensions: usize,
+    /// Documents whose stored embeddings were built by another model; they re-embed on the
+    /// next reindex.
+    pub stale_embeddings: usize,
 }
 
 pub struct KnowledgeBaseService;
@@ -196,25 +301,59 @@ impl KnowledgeBaseService {
         options: SearchOptions,
     ) -> Result<SearchResponsePayload, KnowledgeBaseError> {
         let normalized_query = query.trim().to_owned();
-        let normalized_filter = normalize_optional_folder_filter(options.folder_filter.as_deref())?;
-        let requested_limit = options.limit;
-        let candidate_limit = if normalized_filter.is_some() {
-            requested_limit.saturating_mul(5).max(requested_limit)
+        let mut parsed_query = if options.plain_text {
+            SearchQuery::plain_text(&normalized_query)
         } else {
-            requested_limit
+            parse_search_query(&normalized_query)?
         };
 
+        // Option filters join the query's own, so the cache applies them before ranking.
+        parsed_query.within_folder =
+            normalize_optional_folder_filter(options.folder_filter.as_deref())?;
+        for folder in &options.excluded_folders {
+            parsed_query
+                .excluded_folders
+                .extend(normalize_folder_filter(folder).map_err(KnowledgeBaseError::Validation)?);
+        }
+        parsed_query
+            .tags
+            .extend(normalize_tag_filters(&options.tags));
+        parsed_query
+            .excluded_tags
+            .extend(normalize_tag_filters(&options.excluded_tags));
+        parsed_query.dates.extend(options.dates);
+        parsed_query
+            .excluded_document_ids
+            .extend(options.exclude_document_id);
+
+        let rerank_candidates = if options.rerank {
+            options
+                .rerank_candidates
+                .unwrap_or(DEFAULT_RERANK_CANDIDATES)
+        } else {
+            0
+        };
+
+        let mut semantic_query = options.semantic_query.clone();
+        let (mut semantic_weight, mut bm25_weight) = (options.semantic_weight, options.bm25_weight);
+        if options.preprocess {
+            let processed = Self::preprocess_parsed_query(documents_folder, &parsed_query)?;
+            semantic_query.get_or_insert(processed.semantic_text);
+            semantic_weight = processed.semantic_weight;
+            bm25_weight = processed.bm25_weight;
+        }
+
         let store = DocumentCacheStore::new(documents_folder)?;
-        let hits = hybrid_search_documents_by_query(
+        let explained = explain_hybrid_search_by_search_query(
             &store,
-            &normalized_query,
-            options.semantic_query.as_deref(),
-            candidate_limit,
+            &parsed_query,
+            semantic_query.as_deref(),
+            options.limit.max(rerank_candidates),
             options.min_score,
-            options.exclude_document_id,
-            options.semantic_weight,
-            options.bm25_weight,
+            semantic_weight,
+            bm25_weight,
         )?;
+        let mut hits = explained.hits;
 
         let documents = store.list_documents()?;
         let mut documents_by_id: HashMap<String, CachedDocumentPayload> =
@@ -223,29 +362,47 @@ impl KnowledgeBaseService {
             documents_by_id.insert(document.id.clone(), document);
         }
 
-        let mut results = Vec::new();
-        for hit in hits {
-            if results.len() == requested_limit {
-                break;
+        let mut rerank_time_ms = None;
+        let rerank_query = options
+            .semantic_query
+            .unwrap_or_else(|| parsed_query.semantic_text());
+        if rerank_candidates > 0 && !hits.is_empty() && !rerank_query.trim().is_empty() {
+            let started = Instant::now();
+            let passages = hits
+                .iter()
+                .take(rerank_candidates)
+                .map(|hit| rerank_passage_text(hit, documents_by_id.get(&hit.document_id)))
+                .collect::<Vec<_>>();
+            // Like a failed query embedding, a reranker that cannot load keeps the fused order.
+            match rerank_search_hits(&rerank_query, &mut hits, &passages) {
+                Ok(()) => {
+                    rerank_time_ms =
+                        Some(u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX));
+                }
+                Err(error) => {
+                    log::warn!("[search] Rerank failed, keeping fused order: {}", error);
+                }
             }
+        }
+        hits.truncate(options.limit);
 
+        let mut results = Vec::new();
+        for hit in hits {
             let Some(document) = documents_by_id.get(&hit.document_id) else {
                 continue;
             };
 
-            if normalized_filter
-                .as_deref()
-                .is_some_and(|folder| !folder_matches_filter(&document.folder_path, folder))
-            {
-                continue;
-            }
-
             results.push(SearchResultPayload {
                 id: document.id.clone(),
                 title: document.title.clone(),
                 folder_path: document.folder_path.clone(),
                 tags: document.tags.clone(),
                 relevance_score: hit.score,
+                page: hit.page,
+                title_matches: hit.title_matches,
+                snippet: hit.snippet,
+                passages: hit.passages,
+                explanation: hit.explanation.filter(|_| options.explain),
             });
         }
 
@@ -253,6 +410,71 @@ impl KnowledgeBaseService {
             query: normalized_query,
             total_results: results.len(),
             results,
+            rerank_time_ms,
+            explanation: options.explain.then_some(explained.explanation),
+        })
+    }
+
+    /// Semantic text and leg weights for `query`, with the vault's abbreviations. Malformed
+    /// queries are preprocessed as plain words, as hybrid search treats them.
+    pub fn preprocess_query(
+        documents_folder: &Path,
+        query: &str,
+    ) -> Result<PreprocessedQuery, KnowledgeBaseError> {
+        let normalized_query = query.trim();
+        let parsed_query = parse_search_query(normalized_query)
+            .unwrap_or_else(|_| SearchQuery::plain_text(normalized_query));
+        Self::preprocess_parsed_query(documents_folder, &parsed_query)
+    }
+
+    fn preprocess_parsed_query(
+        documents_folder: &Path,
+        query: &SearchQuery,
+    ) -> Result<PreprocessedQuery, KnowledgeBaseError> {
+        let settings = load_vault_settings(documents_folder)?;
+        Ok(preprocess_search_query(
+            query,
+            &abbreviation_dictionary(&settings.abbreviations),
+        ))
+    }
+
+    /// Documents related to `document_id`, from its stored vectors, shared tags, and links.
+    pub fn related(
+        documents_folder: &Path,
+        document_id: &str,
+        limit: usize,
+    ) -> Result<RelatedResponsePayload, KnowledgeBaseError> {
+        let store = DocumentCacheStore::new(documents_folder)?;
+        let hits = store.related_documents(document_id, limit)?;
+
+        let documents_by_id: HashMap<String, CachedDocumentPayload> = store
+            .list_documents()?
+            .into_iter()
+            .map(|document| (document.id.clone(), document))
+            .collect();
+
+        let results = hits
+            .into_iter()
+            .filter_map(|hit| {
+                let document = documents_by_id.get(&hit.document_id)?;
+                Some(RelatedResultPayload {
+                    id: document.id.clone(),
+                    title: document.title.clone(),
+                    folder_path: document.folder_path.clone(),
+                    tags: document.tags.clone(),
+                    relevance_score: hit.score,
+                    similarity: hit.similarity,
+                    shared_tags: hit.shared_tags,
+                    linked: hit.linked,
+                    chunk_pair: hit.chunk_pair,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(RelatedResponsePayload {
+            document_id: document_id.to_owned(),
+            total_results: results.len(),
+            results,
         })
     }
 
@@ -261,6 +483,8 @@ impl KnowledgeBaseService {
         let documents = store.list_documents()?;
         let tags = store.list_document_tags()?;
         let folders = DocumentFoldersService::list_folders(documents_folder)?;
+        let embedding_model = configured_embedding_model(documents_folder)?;
+        let stale_embeddings = store.count_documents_with_stale_embeddings(&embedding_model)?;
 
         let mut by_folder: BTreeMap<String, usize> = BTreeMap::new();
         for document in &documents {
@@ -297,6 +521,9 @@ impl KnowledgeBaseService {
             tags: tags.len(),
             last_indexed: modified_last_indexed.or(fallback_last_indexed),
             index_size_bytes,
+            embedding_dimensions: store.embedding_dimensions(),
+            embedding_model,
+            stale_embeddings,
         })
     }
 }
@@ -418,6 +645,36 @@ fn normalize_optional_folder_filter(
     normalize_folder_filter(folder_filter).map_err(KnowledgeBaseError::Validation)
 }
 
+/// Text the reranker reads for a hit: the title and best passage, or the start of the body.
+fn rerank_passage_text(
+    hit: &HybridSearchHitPayload,
+    document: Option<&CachedDocumentPayload>,
+) -> String {
+    let title = document
+        .map(|document| document.title.as_str())
+        .unwrap_or("");
+    let text = match hit.passages.first() {
+        Some(passage) => passage.text.clone(),
+        None => document
+            .map(|document| {
+                document
+                    .body
+                    .chars()
+                    .take(RERANK_BODY_PREVIEW_CHARS)
+                    .collect::<String>()
+            })
+            .unwrap_or_default(),
+    };
+
+    format!("{title}\n{text}").trim().to_owned()
+}
+
+fn normalize_tag_filters(tags: &[String]) -> impl Iterator<Item = String> + '_ {
+    tags.iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+}
+
 fn normalize_folder_filter(folder_filter: &str) -> Result<Option<String>, String> {
     let replaced = folder_filter.replace('\\', "/");
     let trimmed = replaced.trim();
@@ -502,6 +759,10 @@ pub enum KnowledgeBaseError {
     Embedding(#[from] EmbeddingError),
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("invalid search query: {0}")]
+    InvalidQuery(#[from] QueryParseError),
+    #[error("vault settings error: {0}")]
+    VaultSettings(#[from] VaultSettingsError),
     #[error("{0}")]
     Validation(String),
 }
@@ -552,6 +813,35 @@ mod tests {
         assert!(matches!(error, KnowledgeBaseError::Validation(_)));
     }
 
+    #[test]
+    fn knowledge_base_search_rejects_malformed_queries() {
+        let temp_dir = unique_temp_path();
+
+        let error = KnowledgeBaseService::search(
+            &temp_dir,
+            "tag:infra \"on-call",
+            SearchOptions::default(),
+        )
+        .expect_err("unterminated quote should fail");
+        assert!(matches!(
+            error,
+            KnowledgeBaseError::InvalidQuery(ref parse_error) if parse_error.column == 11
+        ));
+
+        let response = KnowledgeBaseService::search(
+            &temp_dir,
+            "tag:infra \"on-call",
+            SearchOptions {
+                plain_text: true,
+                ..SearchOptions::default()
+            },
+        )
+        .expect("plain text search should not parse query syntax");
+        assert_eq!(response.total_results, 0);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
     #[test]
     fn knowledge_base_search_preserves_order_when_filtering() {
         let temp_dir = unique_temp_path();
@@ -717,6 +1007,15 @@ mod tests {
         assert_eq!(status.tags, 3);
         assert!(status.last_indexed.is_some());
         assert!(status.index_size_bytes > 0);
+        assert_eq!(
+            status.embedding_model,
+            crate::embeddings::LOCAL_EMBEDDING_MODEL_ID
+        );
+        assert_eq!(
+            status.embedding_dimensions,
+            crate::document_cache::EMBEDDING_VECTOR_DIMENSIONS
+        );
+        assert_eq!(status.stale_embeddings, 0);
 
         fs::remove_dir_all(&temp_dir).expect("cleanup temp folder");
     }
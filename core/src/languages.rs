@@ -0,0 +1,236 @@
+//! Languages of a vault's notes for lexical search: the stopwords dropped from BM25 queries
+//! and the light suffix stemming that widens query terms to prefix matches.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SearchLanguage {
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "pt")]
+    Portuguese,
+    #[serde(rename = "es")]
+    Spanish,
+}
+
+/// Languages of vaults that do not configure `VaultSettings.search_languages`.
+pub const DEFAULT_SEARCH_LANGUAGES: [SearchLanguage; 1] = [SearchLanguage::English];
+
+/// Shortest stem, in characters, used as a prefix query.
+const MIN_STEM_CHARS: usize = 3;
+
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "how", "if", "in",
+    "into", "is", "it", "no", "not", "of", "on", "or", "that", "the", "their", "then", "there",
+    "these", "they", "this", "to", "was", "will", "with",
+];
+
+const PORTUGUESE_STOPWORDS: &[&str] = &[
+    "a", "à", "ao", "aos", "as", "às", "com", "como", "da", "das", "de", "do", "dos", "e", "é",
+    "ela", "elas", "ele", "eles", "em", "essa", "esse", "esta", "está", "este", "eu", "foi",
+    "isso", "mais", "mas", "na", "nas", "no", "nos", "nós", "o", "os", "ou", "para", "pela",
+    "pelo", "por", "que", "se", "sem", "ser", "seu", "sua", "são", "um", "uma", "umas", "uns",
+];
+
+// "con", "sin" and "son" are left out: they are English words too.
+const SPANISH_STOPWORDS: &[&str] = &[
+    "al", "como", "de", "del", "el", "él", "ella", "ellos", "en", "es", "esa", "ese", "esta",
+    "está", "este", "fue", "la", "las", "le", "les", "lo", "los", "más", "o", "para", "pero",
+    "por", "que", "se", "ser", "su", "sus", "un", "una", "unas", "unos", "y", "yo",
+];
+
+/// Plural and derivational endings, longest first, with what replaces them. The first
+/// match is stripped, then a final `a`/`o`/`e` (gender, or the singular of `-es` plurals).
+const PORTUGUESE_SUFFIXES: &[(&str, &str)] = &[
+    ("mente", ""),
+    ("ões", ""),
+    ("ães", ""),
+    ("ãos", ""),
+    ("ão", ""),
+    ("ais", "a"),
+    ("éis", ""),
+    ("res", "r"),
+    ("zes", "z"),
+    ("les", "l"),
+    ("ns", "m"),
+    ("s", ""),
+];
+
+const SPANISH_SUFFIXES: &[(&str, &str)] = &[
+    ("mente", ""),
+    ("iones", "i"),
+    ("ión", "i"),
+    ("es", ""),
+    ("s", ""),
+];
+
+impl SearchLanguage {
+    pub const ALL: [Self; 3] = [Self::English, Self::Portuguese, Self::Spanish];
+
+    /// ISO 639-1 code, as stored in the vault settings.
+    pub const fn code(self) -> &'static str {
+        match self {
+            Self::English => "en",
+            Self::Portuguese => "pt",
+            Self::Spanish => "es",
+        }
+    }
+
+    /// Whether the lowercase `token` is too common in this language to search for.
+    pub fn is_stopword(self, token: &str) -> bool {
+        let stopwords = match self {
+            Self::English => ENGLISH_STOPWORDS,
+            Self::Portuguese => PORTUGUESE_STOPWORDS,
+            Self::Spanish => SPANISH_STOPWORDS,
+        };
+        stopwords.contains(&token)
+    }
+
+    /// Prefix shared by the inflections of `token`, or `None` when the token has no ending
+    /// to strip or the stem would be too short to search for.
+    pub fn stem(self, token: &str) -> Option<String> {
+        let lower = token.to_lowercase();
+        let stem = match self {
+            Self::English => english_stem(&lower),
+            Self::Portuguese => romance_stem(&lower, PORTUGUESE_SUFFIXES),
+            Self::Spanish => romance_stem(&lower, SPANISH_SUFFIXES),
+        };
+
+        if stem == lower || stem.chars().count() < MIN_STEM_CHARS {
+            return None;
+        }
+
+        Some(stem)
+    }
+}
+
+impl fmt::Display for SearchLanguage {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.code())
+    }
+}
+
+impl FromStr for SearchLanguage {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "en" | "english" => Ok(Self::English),
+            "pt" | "portuguese" | "português" => Ok(Self::Portuguese),
+            "es" | "spanish" | "español" => Ok(Self::Spanish),
+            other => Err(format!(
+                "unsupported search language \"{other}\"; supported: en, pt, es"
+            )),
+        }
+    }
+}
+
+fn english_stem(lower: &str) -> String {
+    let mut stem = lower.to_owned();
+
+    if stem.len() > 6 && stem.ends_with("ing") {
+        let new_len = stem.len().saturating_sub(3);
+        stem.truncate(new_len);
+    } else if stem.len() > 5 && stem.ends_with("edly") {
+        let new_len = stem.len().saturating_sub(4);
+        stem.truncate(new_len);
+    } else if stem.len() > 4 && stem.ends_with("ed") {
+        let new_len = stem.len().saturating_sub(2);
+        stem.truncate(new_len);
+    } else if stem.len() > 4 && stem.ends_with("ies") {
+        let new_len = stem.len().saturating_sub(3);
+        stem.truncate(new_len);
+        stem.push('y');
+    } else if stem.len() > 4 && stem.ends_with("es") {
+        let new_len = stem.len().saturating_sub(2);
+        stem.truncate(new_len);
+    } else if stem.len() > 4 && stem.ends_with("s") {
+        let new_len = stem.len().saturating_sub(1);
+        stem.truncate(new_len);
+    } else if stem.len() > 5 && stem.ends_with("al") {
+        let new_len = stem.len().saturating_sub(2);
+        stem.truncate(new_len);
+    } else if stem.len() > 5 && stem.ends_with("ly") {
+        let new_len = stem.len().saturating_sub(2);
+        stem.truncate(new_len);
+    }
+
+    stem
+}
+
+fn romance_stem(lower: &str, suffixes: &[(&str, &str)]) -> String {
+    let mut stem = lower.to_owned();
+    for (suffix, replacement) in suffixes {
+        if let Some(base) = lower.strip_suffix(suffix) {
+            if base.chars().count() >= MIN_STEM_CHARS {
+                stem = format!("{base}{replacement}");
+            }
+            break;
+        }
+    }
+
+    if let Some(base) = stem.strip_suffix(['a', 'o', 'e']) {
+        if base.chars().count() >= MIN_STEM_CHARS {
+            stem = base.to_owned();
+        }
+    }
+
+    stem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchLanguage;
+
+    #[test]
+    fn stems_share_a_prefix_across_inflections() {
+        let cases = [
+            (SearchLanguage::English, "deploying", Some("deploy")),
+            (SearchLanguage::English, "hands-only", Some("hands-on")),
+            (SearchLanguage::English, "api", None),
+            (SearchLanguage::Portuguese, "informações", Some("informaç")),
+            (SearchLanguage::Portuguese, "informação", Some("informaç")),
+            (SearchLanguage::Portuguese, "animais", Some("anim")),
+            (SearchLanguage::Portuguese, "mulheres", Some("mulher")),
+            (SearchLanguage::Portuguese, "casas", Some("cas")),
+            (SearchLanguage::Portuguese, "mão", None),
+            (SearchLanguage::Spanish, "canciones", Some("canci")),
+            (SearchLanguage::Spanish, "canción", Some("canci")),
+            (SearchLanguage::Spanish, "ciudades", Some("ciudad")),
+            (SearchLanguage::Spanish, "rápidamente", Some("rápid")),
+        ];
+        for (language, token, expected) in cases {
+            assert_eq!(
+                language.stem(token).as_deref(),
+                expected,
+                "{language} stem of {token}"
+            );
+        }
+    }
+
+    #[test]
+    fn stopwords_and_codes_are_per_language() {
+        assert!(SearchLanguage::English.is_stopword("the"));
+        assert!(!SearchLanguage::English.is_stopword("para"));
+        assert!(SearchLanguage::Portuguese.is_stopword("para"));
+        assert!(SearchLanguage::Spanish.is_stopword("los"));
+        assert!(!SearchLanguage::Spanish.is_stopword("son"));
+
+        assert_eq!(
+            "PT".parse::<SearchLanguage>(),
+            Ok(SearchLanguage::Portuguese)
+        );
+        assert_eq!(
+            "spanish".parse::<SearchLanguage>(),
+            Ok(SearchLanguage::Spanish)
+        );
+        assert!("klingon".parse::<SearchLanguage>().is_err());
+        assert_eq!(
+            serde_json::to_string(&SearchLanguage::ALL).expect("languages serialize"),
+            "[\"en\",\"pt\",\"es\"]"
+        );
+    }
+}
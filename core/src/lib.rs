@@ -1,7 +1,16 @@
+pub mod backup;
 pub mod config;
 pub mod document_cache;
 pub mod document_folders;
 pub mod document_store;
 pub mod embeddings;
+pub mod evaluation;
+pub mod export;
+pub mod importers;
 pub mod knowledge_base;
+pub mod languages;
+pub mod models;
+pub mod query;
+pub mod rerank;
 pub mod text_processing;
+pub mod vault_settings;
@@ -0,0 +1,627 @@
+//! Embedding models installed from disk for machines without network access: a model
+//! directory or tar archive is copied under the app data folder with a manifest recording
+//! every artifact's size and SHA-256, so the copy can be verified later.
+
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::document_store;
+use crate::embeddings::{local_model_path, local_tokenizer_path};
+
+/// Manifest written next to the artifacts of an installed model.
+pub const MODEL_MANIFEST_FILE_NAME: &str = "tentacle-model.json";
+/// Folder under the app data directory holding installed models.
+pub const MODELS_DIRECTORY_NAME: &str = "models";
+const MODEL_MANIFEST_FORMAT_VERSION: u32 = 1;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Tokenizer and model configuration copied along when the source has them.
+const OPTIONAL_MODEL_FILES: [&str; 4] = [
+    "config.json",
+    "tokenizer_config.json",
+    "special_tokens_map.json",
+    "1_Pooling/config.json",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelArtifactEntry {
+    /// Path relative to the model directory.
+    pub path: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelManifestPayload {
+    pub format_version: u32,
+    pub model_id: String,
+    /// Directory or archive the model was installed from.
+    pub source: String,
+    pub installed_at: String,
+    pub artifacts: Vec<ModelArtifactEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InstalledModelPayload {
+    pub model_id: String,
+    pub directory: String,
+    pub source: String,
+    pub installed_at: String,
+    pub total_bytes: u64,
+    pub artifacts: Vec<ModelArtifactEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelArtifactStatus {
+    Ok,
+    Missing,
+    SizeMismatch,
+    HashMismatch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelArtifactCheckPayload {
+    pub path: String,
+    pub status: ModelArtifactStatus,
+    pub expected_size_bytes: u64,
+    /// Size on disk; `None` when the file is missing.
+    pub size_bytes: Option<u64>,
+    pub expected_sha256: String,
+    /// Checksum on disk; only computed when the size matches.
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelVerificationPayload {
+    pub model_id: String,
+    pub directory: String,
+    pub verified: bool,
+    pub artifacts: Vec<ModelArtifactCheckPayload>,
+}
+
+#[derive(Debug, Error)]
+pub enum ModelStoreError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Validation(String),
+    #[error("no installed model found in {0}")]
+    NotInstalled(String),
+}
+
+/// Folder holding the models installed for `app_data_dir`.
+pub fn models_directory(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(MODELS_DIRECTORY_NAME)
+}
+
+/// Directory `model_id` is installed into under `models_dir`, e.g.
+/// `Xenova/multilingual-e5-small` → `Xenova--multilingual-e5-small`. Ids that leave no name,
+/// such as `...`, are rejected so the result is never `models_dir` itself.
+pub fn installed_model_directory(
+    models_dir: &Path,
+    model_id: &str,
+) -> Result<PathBuf, ModelStoreError> {
+    let name = model_id
+        .trim()
+        .replace('/', "--")
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || matches!(character, '-' | '_' | '.') {
+                character
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    let name = name.trim_start_matches('.');
+    if name.is_empty() {
+        return Err(ModelStoreError::Validation(format!(
+            "model id \"{model_id}\" is not a valid install name"
+        )));
+    }
+    Ok(models_dir.join(name))
+}
+
+/// Checks that `directory` holds a tokenizer and an ONNX file the embedding engine can load.
+pub fn validate_model_directory(directory: &Path) -> Result<(), ModelStoreError> {
+    if !directory.is_dir() {
+        return Err(ModelStoreError::Validation(format!(
+            "model directory \"{}\" does not exist",
+            directory.display()
+        )));
+    }
+    local_tokenizer_path(directory)
+        .map_err(|error| ModelStoreError::Validation(error.to_string()))?;
+    if local_model_path(directory).is_none() {
+        return Err(ModelStoreError::Validation(format!(
+            "no .onnx file found in {}",
+            directory.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Copies the tokenizer, ONNX file (with its external-data sidecars) and model configuration
+/// from `source`, a model directory or a `.tar`/`.tar.gz` archive of one, into the install
+/// directory of `model_id`, replacing a previous install. The copy is staged next to the
+/// target and the previous install is only deleted once the new one is in place, so a failed
+/// install leaves the previous one.
+pub fn install_model(
+    source: &Path,
+    models_dir: &Path,
+    model_id: &str,
+) -> Result<InstalledModelPayload, ModelStoreError> {
+    let model_id = model_id.trim();
+    if model_id.is_empty() {
+        return Err(ModelStoreError::Validation(
+            "model id must not be empty".to_owned(),
+        ));
+    }
+    let target = installed_model_directory(models_dir, model_id)?;
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let staging = models_dir.join(format!(".{file_name}.partial"));
+    let extracted = models_dir.join(format!(".{file_name}.extract"));
+    let previous = models_dir.join(format!(".{file_name}.previous"));
+    remove_dir_if_exists(&staging)?;
+    remove_dir_if_exists(&extracted)?;
+    remove_dir_if_exists(&previous)?;
+    fs::create_dir_all(models_dir)?;
+
+    let result = (|| {
+        let source_root = if source.is_dir() {
+            find_model_root(source)?
+        } else if source.is_file() {
+            unpack_archive(source, &extracted)?;
+            find_model_root(&extracted)?
+        } else {
+            return Err(ModelStoreError::Validation(format!(
+                "model source \"{}\" does not exist",
+                source.display()
+            )));
+        };
+
+        let mut artifacts = Vec::new();
+        for relative in model_artifact_paths(&source_root)? {
+            let destination = staging.join(&relative);
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut reader = fs::File::open(source_root.join(&relative))?;
+            let mut writer = fs::File::create(&destination)?;
+            let (size_bytes, sha256) = copy_and_hash(&mut reader, &mut writer)?;
+            artifacts.push(ModelArtifactEntry {
+                path: relative_path_string(&relative),
+                size_bytes,
+                sha256,
+            });
+        }
+
+        let manifest = ModelManifestPayload {
+            format_version: MODEL_MANIFEST_FORMAT_VERSION,
+            model_id: model_id.to_owned(),
+            source: source.to_string_lossy().into_owned(),
+            installed_at: document_store::now_iso_string_utc(),
+            artifacts,
+        };
+        fs::write(
+            staging.join(MODEL_MANIFEST_FILE_NAME),
+            serde_json::to_vec_pretty(&manifest)?,
+        )?;
+        replace_directory(&staging, &target, &previous)?;
+        Ok(installed_model_payload(&target, manifest))
+    })();
+
+    let _ = remove_dir_if_exists(&extracted);
+    if result.is_err() {
+        let _ = remove_dir_if_exists(&staging);
+    }
+    result
+}
+
+/// Manifest of the model installed in `directory`, or `None` when it has none.
+pub fn read_model_manifest(
+    directory: &Path,
+) -> Result<Option<ModelManifestPayload>, ModelStoreError> {
+    let manifest_path = directory.join(MODEL_MANIFEST_FILE_NAME);
+    if !manifest_path.is_file() {
+        return Ok(None);
+    }
+    let manifest = serde_json::from_slice(&fs::read(manifest_path)?)?;
+    Ok(Some(manifest))
+}
+
+/// The model installed in `directory`, as recorded by its manifest.
+pub fn read_installed_model(directory: &Path) -> Result<InstalledModelPayload, ModelStoreError> {
+    let manifest = read_model_manifest(directory)?
+        .ok_or_else(|| ModelStoreError::NotInstalled(directory.display().to_string()))?;
+    Ok(installed_model_payload(directory, manifest))
+}
+
+/// Every model installed under `models_dir`, sorted by model id. Interrupted installs are
+/// skipped.
+pub fn list_installed_models(
+    models_dir: &Path,
+) -> Result<Vec<InstalledModelPayload>, ModelStoreError> {
+    if !models_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut models = Vec::new();
+    for entry in fs::read_dir(models_dir)? {
+        let path = entry?.path();
+        if !path.is_dir()
+            || path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+        if let Some(manifest) = read_model_manifest(&path)? {
+            models.push(installed_model_payload(&path, manifest));
+        }
+    }
+    models.sort_by(|left, right| left.model_id.cmp(&right.model_id));
+    Ok(models)
+}
+
+/// Checks every artifact in the manifest of `directory` against its recorded size and
+/// SHA-256.
+pub fn verify_model(directory: &Path) -> Result<ModelVerificationPayload, ModelStoreError> {
+    let installed = read_installed_model(directory)?;
+    let mut artifacts = Vec::new();
+    for entry in &installed.artifacts {
+        let path = directory.join(&entry.path);
+        let mut check = ModelArtifactCheckPayload {
+            path: entry.path.clone(),
+            status: ModelArtifactStatus::Missing,
+            expected_size_bytes: entry.size_bytes,
+            size_bytes: None,
+            expected_sha256: entry.sha256.clone(),
+            sha256: None,
+        };
+        if path.is_file() {
+            let size_bytes = fs::metadata(&path)?.len();
+            check.size_bytes = Some(size_bytes);
+            check.status = if size_bytes != entry.size_bytes {
+                ModelArtifactStatus::SizeMismatch
+            } else {
+                let (_, sha256) = copy_and_hash(&mut fs::File::open(&path)?, &mut io::sink())?;
+                let status = if sha256 == entry.sha256 {
+                    ModelArtifactStatus::Ok
+                } else {
+                    ModelArtifactStatus::HashMismatch
+                };
+                check.sha256 = Some(sha256);
+                status
+            };
+        }
+        artifacts.push(check);
+    }
+
+    Ok(ModelVerificationPayload {
+        verified: artifacts
+            .iter()
+            .all(|check| check.status == ModelArtifactStatus::Ok),
+        model_id: installed.model_id,
+        directory: installed.directory,
+        artifacts,
+    })
+}
+
+/// Deletes the model installed in `directory`. Directories without a manifest are refused so
+/// a mistyped path cannot remove unrelated files.
+pub fn remove_model(directory: &Path) -> Result<InstalledModelPayload, ModelStoreError> {
+    let installed = read_installed_model(directory)?;
+    fs::remove_dir_all(directory)?;
+    Ok(installed)
+}
+
+fn installed_model_payload(
+    directory: &Path,
+    manifest: ModelManifestPayload,
+) -> InstalledModelPayload {
+    InstalledModelPayload {
+        model_id: manifest.model_id,
+        directory: directory.to_string_lossy().into_owned(),
+        source: manifest.source,
+        installed_at: manifest.installed_at,
+        total_bytes: manifest
+            .artifacts
+            .iter()
+            .map(|entry| entry.size_bytes)
+            .sum(),
+        artifacts: manifest.artifacts,
+    }
+}
+
+/// `directory` itself, or its only subdirectory holding a model, as archives of a model
+/// folder usually wrap it in one top-level folder.
+fn find_model_root(directory: &Path) -> Result<PathBuf, ModelStoreError> {
+    if validate_model_directory(directory).is_ok() {
+        return Ok(directory.to_path_buf());
+    }
+
+    let mut candidates = fs::read_dir(directory)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && validate_model_directory(path).is_ok())
+        .collect::<Vec<_>>();
+    match candidates.len() {
+        1 => Ok(candidates.remove(0)),
+        0 => Err(ModelStoreError::Validation(format!(
+            "no tokenizer.json and .onnx model found in {}",
+            directory.display()
+        ))),
+        _ => Err(ModelStoreError::Validation(format!(
+            "{} holds more than one model; point --from at one of them",
+            directory.display()
+        ))),
+    }
+}
+
+/// Paths, relative to `root`, of the files an install copies.
+fn model_artifact_paths(root: &Path) -> Result<Vec<PathBuf>, ModelStoreError> {
+    let tokenizer = local_tokenizer_path(root)
+        .map_err(|error| ModelStoreError::Validation(error.to_string()))?;
+    let model = local_model_path(root).ok_or_else(|| {
+        ModelStoreError::Validation(format!("no .onnx file found in {}", root.display()))
+    })?;
+
+    let tokenizer_folder = tokenizer.parent().map(Path::to_path_buf);
+    let mut files = vec![tokenizer, model.clone()];
+    for sidecar in [
+        format!("{}_data", model.display()),
+        format!("{}.data", model.display()),
+    ] {
+        files.push(PathBuf::from(sidecar));
+    }
+    for name in OPTIONAL_MODEL_FILES {
+        files.push(root.join(name));
+        if let Some(folder) = &tokenizer_folder {
+            files.push(folder.join(name));
+        }
+    }
+
+    let mut relative = files
+        .into_iter()
+        .filter(|path| path.is_file())
+        .filter_map(|path| path.strip_prefix(root).ok().map(Path::to_path_buf))
+        .collect::<Vec<_>>();
+    relative.sort();
+    relative.dedup();
+    Ok(relative)
+}
+
+/// Extracts a tar archive, gzip-compressed or not, into `destination`. Entries that would
+/// land outside it are skipped by the tar crate.
+fn unpack_archive(archive_path: &Path, destination: &Path) -> Result<(), ModelStoreError> {
+    let mut file = fs::File::open(archive_path)?;
+    let mut magic = [0u8; 2];
+    let is_gzip = file.read(&mut magic)? == magic.len() && magic == GZIP_MAGIC;
+    file.seek(SeekFrom::Start(0))?;
+
+    fs::create_dir_all(destination)?;
+    let unpacked = if is_gzip {
+        tar::Archive::new(GzDecoder::new(file)).unpack(destination)
+    } else {
+        tar::Archive::new(file).unpack(destination)
+    };
+    unpacked.map_err(|error| {
+        ModelStoreError::Validation(format!(
+            "failed to extract {} as a tar archive: {error}",
+            archive_path.display()
+        ))
+    })
+}
+
+fn relative_path_string(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Moves `staging` to `target`. An existing `target` is first moved to `previous` and put
+/// back if the rename fails, then deleted.
+fn replace_directory(staging: &Path, target: &Path, previous: &Path) -> io::Result<()> {
+    let had_previous = target.exists();
+    if had_previous {
+        fs::rename(target, previous)?;
+    }
+    if let Err(error) = fs::rename(staging, target) {
+        if had_previous {
+            let _ = fs::rename(previous, target);
+        }
+        return Err(error);
+    }
+    if had_previous {
+        if let Err(error) = remove_dir_if_exists(previous) {
+            log::warn!(
+                "[models] Failed to remove previous install {}: {}",
+                previous.display(),
+                error
+            );
+        }
+    }
+    Ok(())
+}
+
+fn remove_dir_if_exists(directory: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(directory) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn copy_and_hash(reader: &mut impl Read, writer: &mut impl io::Write) -> io::Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+        size += read as u64;
+    }
+    let sha256 = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    Ok((size, sha256))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_temp_path(prefix: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}-{timestamp}"))
+    }
+
+    fn write_model_folder(folder: &Path) {
+        fs::create_dir_all(folder.join("onnx")).expect("create model folder");
+        fs::write(folder.join("tokenizer.json"), b"{}").expect("write tokenizer");
+        fs::write(folder.join("config.json"), b"{\"hidden_size\": 4}").expect("write config");
+        fs::write(folder.join("onnx/model.onnx"), b"graph").expect("write model");
+        fs::write(folder.join("onnx/model.onnx_data"), b"weights").expect("write weights");
+        fs::write(folder.join("README.md"), b"not copied").expect("write readme");
+    }
+
+    #[test]
+    fn install_copies_artifacts_and_verify_detects_tampering() {
+        let root = unique_temp_path("tentacle-model-install");
+        let source = root.join("download");
+        write_model_folder(&source);
+        let models_dir = models_directory(&root.join("app"));
+
+        let installed =
+            install_model(&source, &models_dir, "Xenova/multilingual-e5-small").expect("install");
+        let directory = installed_model_directory(&models_dir, "Xenova/multilingual-e5-small")
+            .expect("install directory");
+        assert_eq!(installed.directory, directory.to_string_lossy());
+        assert!(directory.ends_with("Xenova--multilingual-e5-small"));
+        assert_eq!(
+            installed
+                .artifacts
+                .iter()
+                .map(|entry| entry.path.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "config.json",
+                "onnx/model.onnx",
+                "onnx/model.onnx_data",
+                "tokenizer.json"
+            ]
+        );
+        assert_eq!(installed.total_bytes, 2 + 18 + 5 + 7);
+        assert!(!directory.join("README.md").exists());
+        validate_model_directory(&directory).expect("installed folder is loadable");
+        assert_eq!(
+            list_installed_models(&models_dir).expect("list"),
+            vec![installed.clone()]
+        );
+        assert!(verify_model(&directory).expect("verify").verified);
+
+        fs::write(directory.join("onnx/model.onnx"), b"GRAPH").expect("tamper model");
+        fs::remove_file(directory.join("config.json")).expect("remove config");
+        let verification = verify_model(&directory).expect("verify tampered");
+        assert!(!verification.verified);
+        let statuses = verification
+            .artifacts
+            .iter()
+            .map(|check| (check.path.as_str(), check.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                ("config.json", ModelArtifactStatus::Missing),
+                ("onnx/model.onnx", ModelArtifactStatus::HashMismatch),
+                ("onnx/model.onnx_data", ModelArtifactStatus::Ok),
+                ("tokenizer.json", ModelArtifactStatus::Ok),
+            ]
+        );
+
+        assert_eq!(
+            remove_model(&directory).expect("remove").model_id,
+            installed.model_id
+        );
+        assert!(!directory.exists());
+        assert!(matches!(
+            remove_model(&directory),
+            Err(ModelStoreError::NotInstalled(_))
+        ));
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn install_extracts_wrapped_tar_archives() {
+        let root = unique_temp_path("tentacle-model-tar");
+        let source = root.join("all-MiniLM-L6-v2");
+        write_model_folder(&source);
+        let archive_path = root.join("model.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            fs::File::create(&archive_path).expect("create archive"),
+            flate2::Compression::default(),
+        ));
+        builder
+            .append_dir_all("all-MiniLM-L6-v2", &source)
+            .expect("append model folder");
+        builder
+            .into_inner()
+            .expect("finish tar")
+            .finish()
+            .expect("finish gzip");
+        let models_dir = root.join("models");
+
+        let installed = install_model(&archive_path, &models_dir, "local/minilm").expect("install");
+        assert_eq!(installed.artifacts.len(), 4);
+        assert_eq!(installed.source, archive_path.to_string_lossy());
+        assert_eq!(
+            fs::read_dir(&models_dir).expect("read models").count(),
+            1,
+            "staging folders are cleaned up"
+        );
+
+        let error = install_model(&root.join("missing.tar"), &models_dir, "local/minilm")
+            .expect_err("missing source");
+        assert!(matches!(error, ModelStoreError::Validation(_)));
+        let error = install_model(&source, &models_dir, "...").expect_err("id without a name");
+        assert!(matches!(error, ModelStoreError::Validation(_)));
+
+        let reinstalled = install_model(&source, &models_dir, "local/minilm").expect("reinstall");
+        assert_eq!(reinstalled.directory, installed.directory);
+        assert_eq!(reinstalled.source, source.to_string_lossy());
+        assert_eq!(
+            fs::read_dir(&models_dir).expect("read models").count(),
+            1,
+            "the previous install is removed once replaced"
+        );
+        assert!(
+            verify_model(Path::new(&installed.directory))
+                .expect("previous install is kept")
+                .verified
+        );
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
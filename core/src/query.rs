@@ -0,0 +1,702 @@
+//! Search query language. Free text is split into clauses that all have to match; a clause
+//! is a word, a `"quoted phrase"`, or several of them joined with `OR`. A leading `-`
+//! negates a clause or filter, and `field:value` terms filter documents:
+//!
+//! - `tag:infra`, `folder:runbooks` (the folder and its subfolders)
+//! - `title:"on-call"` (text that must match the title)
+//! - `created:>2026-01-01`, `updated:<=2026-02-01` (`modified:` is an alias of `updated:`);
+//!   comparisons are `>`, `>=`, `<`, `<=`, and `=` (the default)
+//!
+//! Words that look like fields but use an unknown name (`std::fs`, `http://…`) are plain text.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "text", rename_all = "snake_case")]
+pub enum QueryTerm {
+    Word(String),
+    Phrase(String),
+}
+
+impl QueryTerm {
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Word(text) | Self::Phrase(text) => text,
+        }
+    }
+}
+
+/// Free-text clause: any of `alternatives` has to match, or none when `negated`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextClause {
+    pub alternatives: Vec<QueryTerm>,
+    pub negated: bool,
+    /// Matches against the title only (`title:` terms).
+    pub title_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateField {
+    Created,
+    Updated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateComparison {
+    Before,
+    OnOrBefore,
+    On,
+    OnOrAfter,
+    After,
+}
+
+impl DateComparison {
+    pub const fn sql_operator(self) -> &'static str {
+        match self {
+            Self::Before => "<",
+            Self::OnOrBefore => "<=",
+            Self::On => "=",
+            Self::OnOrAfter => ">=",
+            Self::After => ">",
+        }
+    }
+}
+
+/// Compares the calendar day (`YYYY-MM-DD`) of a document timestamp with `date`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateFilter {
+    pub field: DateField,
+    pub comparison: DateComparison,
+    pub date: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub clauses: Vec<TextClause>,
+    /// Lowercased tags every result must have.
+    pub tags: Vec<String>,
+    pub excluded_tags: Vec<String>,
+    /// Folder paths, without surrounding slashes, results must be in (any of them).
+    pub folders: Vec<String>,
+    pub excluded_folders: Vec<String>,
+    pub dates: Vec<DateFilter>,
+    /// Folder subtree every result must be in, on top of `folders`. Not part of the query
+    /// syntax.
+    pub within_folder: Option<String>,
+    /// Documents never returned, such as the one a related-notes search starts from. Not
+    /// part of the query syntax.
+    pub excluded_document_ids: Vec<String>,
+}
+
+impl SearchQuery {
+    /// A query that treats all of `text` as plain words, for callers that cannot surface
+    /// parse errors.
+    pub fn plain_text(text: &str) -> Self {
+        Self {
+            clauses: text
+                .split_whitespace()
+                .map(|word| TextClause {
+                    alternatives: vec![QueryTerm::Word(word.to_owned())],
+                    negated: false,
+                    title_only: false,
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Whether filters or negated clauses restrict the results.
+    pub fn has_filters(&self) -> bool {
+        !self.tags.is_empty()
+            || !self.excluded_tags.is_empty()
+            || !self.folders.is_empty()
+            || !self.excluded_folders.is_empty()
+            || !self.dates.is_empty()
+            || self.within_folder.is_some()
+            || self.clauses.iter().any(|clause| clause.negated)
+    }
+
+    pub fn positive_clauses(&self) -> impl Iterator<Item = &TextClause> {
+        self.clauses.iter().filter(|clause| !clause.negated)
+    }
+
+    /// Every positive term, title terms included, in query order. Used for lexical
+    /// scoring outside FTS (title boost, passage term coverage).
+    pub fn lexical_text(&self) -> String {
+        join_terms(self.positive_clauses())
+    }
+
+    /// Positive body terms in query order, the text the semantic leg embeds.
+    pub fn semantic_text(&self) -> String {
+        join_terms(self.positive_clauses().filter(|clause| !clause.title_only))
+    }
+}
+
+fn join_terms<'a>(clauses: impl Iterator<Item = &'a TextClause>) -> String {
+    clauses
+        .flat_map(|clause| clause.alternatives.iter().map(QueryTerm::text))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("{message} at column {column}")]
+pub struct QueryParseError {
+    pub message: String,
+    /// 1-based character position in the query where the problem starts.
+    pub column: usize,
+}
+
+impl QueryParseError {
+    fn new(message: impl Into<String>, column: usize) -> Self {
+        Self {
+            message: message.into(),
+            column,
+        }
+    }
+}
+
+pub fn parse_search_query(input: &str) -> Result<SearchQuery, QueryParseError> {
+    let mut parser = QueryParser {
+        characters: input.chars().collect(),
+        position: 0,
+    };
+    let mut query = SearchQuery::default();
+    let mut pending_or: Option<usize> = None;
+    let mut can_join = false;
+
+    while let Some((column, token)) = parser.next_token()? {
+        match token {
+            QueryToken::Or => {
+                if !can_join || pending_or.is_some() {
+                    return Err(QueryParseError::new(
+                        "`OR` must stand between two search terms",
+                        column,
+                    ));
+                }
+                pending_or = Some(column);
+            }
+            QueryToken::Clause(clause) => {
+                let joinable = !clause.negated && !clause.title_only;
+                if pending_or.take().is_some() {
+                    if !joinable {
+                        return Err(QueryParseError::new(
+                            "`OR` can only join plain words and phrases",
+                            column,
+                        ));
+                    }
+                    if let Some(previous) = query.clauses.last_mut() {
+                        previous.alternatives.extend(clause.alternatives);
+                    }
+                } else {
+                    query.clauses.push(clause);
+                }
+                can_join = joinable;
+            }
+            QueryToken::Filter(filter) => {
+                if pending_or.is_some() {
+                    return Err(QueryParseError::new(
+                        "`OR` can only join plain words and phrases",
+                        column,
+                    ));
+                }
+                match filter {
+                    QueryFilter::Tag {
+                        tag,
+                        negated: false,
+                    } => query.tags.push(tag),
+                    QueryFilter::Tag { tag, negated: true } => query.excluded_tags.push(tag),
+                    QueryFilter::Folder {
+                        folder,
+                        negated: false,
+                    } => query.folders.push(folder),
+                    QueryFilter::Folder {
+                        folder,
+                        negated: true,
+                    } => query.excluded_folders.push(folder),
+                    QueryFilter::Date(filter) => query.dates.push(filter),
+                }
+                can_join = false;
+            }
+        }
+    }
+
+    if let Some(column) = pending_or {
+        return Err(QueryParseError::new(
+            "`OR` must stand between two search terms",
+            column,
+        ));
+    }
+    Ok(query)
+}
+
+enum QueryToken {
+    Or,
+    Clause(TextClause),
+    Filter(QueryFilter),
+}
+
+enum QueryFilter {
+    Tag { tag: String, negated: bool },
+    Folder { folder: String, negated: bool },
+    Date(DateFilter),
+}
+
+struct QueryParser {
+    characters: Vec<char>,
+    position: usize,
+}
+
+impl QueryParser {
+    fn peek(&self) -> Option<char> {
+        self.characters.get(self.position).copied()
+    }
+
+    fn column(&self) -> usize {
+        self.position + 1
+    }
+
+    /// Next token with the column it starts at.
+    fn next_token(&mut self) -> Result<Option<(usize, QueryToken)>, QueryParseError> {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+        let Some(first) = self.peek() else {
+            return Ok(None);
+        };
+        let column = self.column();
+
+        let negated = first == '-'
+            && self
+                .characters
+                .get(self.position + 1)
+                .is_some_and(|next| !next.is_whitespace());
+        if negated {
+            self.position += 1;
+        }
+
+        if self.peek() == Some('"') {
+            let phrase = self.read_quoted()?;
+            return Ok(Some((
+                column,
+                text_clause(QueryTerm::Phrase(phrase), negated, false),
+            )));
+        }
+
+        if let Some(field) = self.read_field_name() {
+            let token = self.read_field_value(&field, negated, column)?;
+            return Ok(Some((column, token)));
+        }
+
+        let word = self.read_bare();
+        if word == "OR" && !negated {
+            return Ok(Some((column, QueryToken::Or)));
+        }
+        Ok(Some((
+            column,
+            text_clause(QueryTerm::Word(word), negated, false),
+        )))
+    }
+
+    /// Consumes `name:` when it names a known field.
+    fn read_field_name(&mut self) -> Option<String> {
+        let name = self.characters[self.position..]
+            .iter()
+            .take_while(|character| character.is_ascii_alphabetic())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let is_field = matches!(
+            name.as_str(),
+            "tag" | "folder" | "title" | "created" | "updated" | "modified"
+        ) && self.characters.get(self.position + name.len()) == Some(&':');
+        if !is_field {
+            return None;
+        }
+
+        self.position += name.len() + 1;
+        Some(name)
+    }
+
+    fn read_field_value(
+        &mut self,
+        field: &str,
+        negated: bool,
+        column: usize,
+    ) -> Result<QueryToken, QueryParseError> {
+        let value_column = self.column();
+        let (value, quoted) = if self.peek() == Some('"') {
+            (self.read_quoted()?, true)
+        } else {
+            (self.read_bare(), false)
+        };
+        if value.trim().is_empty() {
+            return Err(QueryParseError::new(
+                format!("`{field}:` needs a value"),
+                column,
+            ));
+        }
+
+        let token = match field {
+            "tag" => QueryToken::Filter(QueryFilter::Tag {
+                tag: value.trim().to_lowercase(),
+                negated,
+            }),
+            "folder" => {
+                let folder = value.trim().trim_matches('/').to_owned();
+                if folder.is_empty() {
+                    return Err(QueryParseError::new("`folder:` needs a path", value_column));
+                }
+                QueryToken::Filter(QueryFilter::Folder { folder, negated })
+            }
+            "title" => {
+                let term = if quoted {
+                    QueryTerm::Phrase(value)
+                } else {
+                    QueryTerm::Word(value)
+                };
+                text_clause(term, negated, true)
+            }
+            _ => {
+                if negated {
+                    return Err(QueryParseError::new(
+                        format!("`{field}:` cannot be negated; use `<` or `>` instead"),
+                        column,
+                    ));
+                }
+                let field = if field == "created" {
+                    DateField::Created
+                } else {
+                    DateField::Updated
+                };
+                QueryToken::Filter(QueryFilter::Date(parse_date_filter(
+                    field,
+                    &value,
+                    value_column,
+                )?))
+            }
+        };
+        Ok(token)
+    }
+
+    /// Reads a `"…"` phrase starting at the opening quote.
+    fn read_quoted(&mut self) -> Result<String, QueryParseError> {
+        let column = self.column();
+        self.position += 1;
+        let start = self.position;
+        while self.peek().is_some_and(|character| character != '"') {
+            self.position += 1;
+        }
+        if self.peek().is_none() {
+            return Err(QueryParseError::new("unterminated quote", column));
+        }
+
+        let phrase = self.characters[start..self.position]
+            .iter()
+            .collect::<String>();
+        self.position += 1;
+        if phrase.trim().is_empty() {
+            return Err(QueryParseError::new("empty phrase", column));
+        }
+        Ok(phrase.trim().to_owned())
+    }
+
+    fn read_bare(&mut self) -> String {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|character| !character.is_whitespace())
+        {
+            self.position += 1;
+        }
+        self.characters[start..self.position].iter().collect()
+    }
+}
+
+fn text_clause(term: QueryTerm, negated: bool, title_only: bool) -> QueryToken {
+    QueryToken::Clause(TextClause {
+        alternatives: vec![term],
+        negated,
+        title_only,
+    })
+}
+
+fn parse_date_filter(
+    field: DateField,
+    value: &str,
+    column: usize,
+) -> Result<DateFilter, QueryParseError> {
+    let (comparison, date) = [
+        (">=", DateComparison::OnOrAfter),
+        ("<=", DateComparison::OnOrBefore),
+        (">", DateComparison::After),
+        ("<", DateComparison::Before),
+        ("=", DateComparison::On),
+    ]
+    .into_iter()
+    .find_map(|(prefix, comparison)| value.strip_prefix(prefix).map(|date| (comparison, date)))
+    .unwrap_or((DateComparison::On, value));
+
+    if !is_calendar_date(date) {
+        return Err(QueryParseError::new(
+            format!("invalid date `{date}`, expected YYYY-MM-DD"),
+            column + (value.chars().count() - date.chars().count()),
+        ));
+    }
+
+    Ok(DateFilter {
+        field,
+        comparison,
+        date: date.to_owned(),
+    })
+}
+
+fn is_calendar_date(value: &str) -> bool {
+    let parts = value.split('-').collect::<Vec<_>>();
+    let [year, month, day] = parts.as_slice() else {
+        return false;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (
+        year.parse::<u32>(),
+        month.parse::<u32>(),
+        day.parse::<u32>(),
+    ) else {
+        return false;
+    };
+
+    let leap_year = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap_year => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days_in_month).contains(&day)
+}
+
+/// Abbreviations expanded in the text the semantic leg embeds. Vaults extend or override
+/// them with `VaultSettings::abbreviations`.
+pub const DEFAULT_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("ml", "machine learning"),
+    ("ai", "artificial intelligence"),
+    ("db", "database"),
+    ("api", "application programming interface"),
+    ("cli", "command line interface"),
+    ("sdk", "software development kit"),
+    ("os", "operating system"),
+    ("ui", "user interface"),
+    ("ux", "user experience"),
+    ("ci", "continuous integration"),
+    ("cd", "continuous deployment"),
+    ("devops", "development operations"),
+    ("auth", "authentication"),
+    ("sso", "single sign on"),
+    ("jwt", "json web token"),
+    ("oauth", "open authorization"),
+    ("http", "hypertext transfer protocol"),
+    ("https", "hypertext transfer protocol secure"),
+    ("url", "uniform resource locator"),
+    ("sql", "structured query language"),
+    ("nosql", "non relational database"),
+    ("css", "cascading style sheets"),
+    ("html", "hypertext markup language"),
+    ("js", "javascript"),
+    ("ts", "typescript"),
+    ("py", "python"),
+    ("k8s", "kubernetes"),
+    ("aws", "amazon web services"),
+    ("gcp", "google cloud platform"),
+];
+
+/// Semantic text and leg weights chosen for a query before it is searched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreprocessedQuery {
+    /// Text the semantic leg embeds, with abbreviations expanded. The lexical leg keeps the
+    /// original terms so exact matches still hit.
+    pub semantic_text: String,
+    pub semantic_weight: f32,
+    pub bm25_weight: f32,
+}
+
+/// The default abbreviations with `custom` entries added on top; keys are lowercase.
+pub fn abbreviation_dictionary(custom: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut dictionary = DEFAULT_ABBREVIATIONS
+        .iter()
+        .map(|(abbreviation, expansion)| ((*abbreviation).to_owned(), (*expansion).to_owned()))
+        .collect::<BTreeMap<_, _>>();
+    for (abbreviation, expansion) in custom {
+        dictionary.insert(abbreviation.to_lowercase(), expansion.clone());
+    }
+    dictionary
+}
+
+/// Expands abbreviations in the query's semantic text and weights the two legs by query
+/// length: a single short word searches lexically only, and longer queries lean on meaning.
+pub fn preprocess_search_query(
+    query: &SearchQuery,
+    abbreviations: &BTreeMap<String, String>,
+) -> PreprocessedQuery {
+    let lexical_text = query.lexical_text();
+    let words = lexical_text.split_whitespace().collect::<Vec<_>>();
+    let (semantic_weight, bm25_weight) = match words.as_slice() {
+        [word] if word.chars().count() >= 5 => (0.2, 0.8),
+        [] | [_] => (0.0, 1.0),
+        _ if words.len() <= 4 => (0.35, 0.65),
+        _ => (0.55, 0.45),
+    };
+
+    let semantic_text = query
+        .semantic_text()
+        .split_whitespace()
+        .map(|word| {
+            abbreviations
+                .get(&word.to_lowercase())
+                .map_or(word, String::as_str)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    PreprocessedQuery {
+        semantic_text,
+        semantic_weight,
+        bm25_weight,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{
+        abbreviation_dictionary, parse_search_query, preprocess_search_query, DateComparison,
+        DateField, DateFilter, QueryParseError, QueryTerm, SearchQuery, TextClause,
+    };
+
+    fn clause(alternatives: Vec<QueryTerm>, negated: bool, title_only: bool) -> TextClause {
+        TextClause {
+            alternatives,
+            negated,
+            title_only,
+        }
+    }
+
+    #[test]
+    fn parses_filters_phrases_negation_and_or() {
+        let query = parse_search_query(
+            r#"tag:infra folder:/runbooks/ -tag:draft created:>2026-01-01 title:"on-call" "exact phrase" kubernetes OR k8s -legacy std::fs"#,
+        )
+        .expect("query should parse");
+
+        assert_eq!(
+            query,
+            SearchQuery {
+                clauses: vec![
+                    clause(vec![QueryTerm::Phrase("on-call".into())], false, true),
+                    clause(vec![QueryTerm::Phrase("exact phrase".into())], false, false),
+                    clause(
+                        vec![
+                            QueryTerm::Word("kubernetes".into()),
+                            QueryTerm::Word("k8s".into())
+                        ],
+                        false,
+                        false
+                    ),
+                    clause(vec![QueryTerm::Word("legacy".into())], true, false),
+                    clause(vec![QueryTerm::Word("std::fs".into())], false, false),
+                ],
+                tags: vec!["infra".into()],
+                excluded_tags: vec!["draft".into()],
+                folders: vec!["runbooks".into()],
+                excluded_folders: Vec::new(),
+                dates: vec![DateFilter {
+                    field: DateField::Created,
+                    comparison: DateComparison::After,
+                    date: "2026-01-01".into(),
+                }],
+                within_folder: None,
+                excluded_document_ids: Vec::new(),
+            }
+        );
+        assert_eq!(query.semantic_text(), "exact phrase kubernetes k8s std::fs");
+        assert_eq!(
+            query.lexical_text(),
+            "on-call exact phrase kubernetes k8s std::fs"
+        );
+    }
+
+    #[test]
+    fn reports_the_column_of_syntax_errors() {
+        let error = |input: &str| parse_search_query(input).expect_err("query should not parse");
+
+        assert_eq!(
+            error(r#"deploy "never closed"#),
+            QueryParseError {
+                message: "unterminated quote".into(),
+                column: 8,
+            }
+        );
+        assert_eq!(error("kubernetes OR").column, 12);
+        assert_eq!(error("OR kubernetes").column, 1);
+        assert_eq!(error("notes tag:").column, 7);
+        assert_eq!(
+            error("updated:<=2026-02-30"),
+            QueryParseError {
+                message: "invalid date `2026-02-30`, expected YYYY-MM-DD".into(),
+                column: 11,
+            }
+        );
+        assert_eq!(error("alpha OR -beta").column, 10);
+    }
+
+    #[test]
+    fn preprocess_expands_abbreviations_and_weights_by_length() {
+        let mut custom = BTreeMap::new();
+        custom.insert("PR".to_owned(), "pull request".to_owned());
+        custom.insert("db".to_owned(), "data warehouse".to_owned());
+        let abbreviations = abbreviation_dictionary(&custom);
+
+        let single = preprocess_search_query(&SearchQuery::plain_text("db"), &abbreviations);
+        assert_eq!(single.semantic_text, "data warehouse");
+        assert_eq!((single.semantic_weight, single.bm25_weight), (0.0, 1.0));
+
+        let long_word =
+            preprocess_search_query(&SearchQuery::plain_text("kubernetes"), &abbreviations);
+        assert_eq!(
+            (long_word.semantic_weight, long_word.bm25_weight),
+            (0.2, 0.8)
+        );
+
+        let query =
+            parse_search_query("tag:infra review the K8s PR -draft").expect("query should parse");
+        let processed = preprocess_search_query(&query, &abbreviations);
+        assert_eq!(
+            processed.semantic_text,
+            "review the kubernetes pull request"
+        );
+        assert_eq!(
+            (processed.semantic_weight, processed.bm25_weight),
+            (0.35, 0.65)
+        );
+
+        let question = preprocess_search_query(
+            &SearchQuery::plain_text("how do we rotate the jwt signing keys"),
+            &abbreviations,
+        );
+        assert_eq!(
+            question.semantic_text,
+            "how do we rotate the json web token signing keys"
+        );
+        assert_eq!(
+            (question.semantic_weight, question.bm25_weight),
+            (0.55, 0.45)
+        );
+    }
+}
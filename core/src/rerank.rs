@@ -0,0 +1,367 @@
+use std::sync::Mutex;
+
+use hf_hub::api::sync::Api as HfApi;
+use once_cell::sync::OnceCell;
+use ort::session::{builder::GraphOptimizationLevel, Session};
+use ort::value::{DynTensor, Tensor};
+use tokenizers::{Tokenizer, TruncationParams, TruncationStrategy};
+
+use crate::document_cache::HybridSearchHitPayload;
+use crate::embeddings::{
+    embeddings_runtime_enabled, installed_model_directory_for, positive_dim, resolve_artifacts,
+    resolve_local_artifacts, EmbeddingError,
+};
+
+const HF_RERANK_REPO_ID: &str = "Xenova/ms-marco-MiniLM-L-6-v2";
+pub const LOCAL_RERANK_MODEL_ID: &str = HF_RERANK_REPO_ID;
+/// Fused candidates rescored when the caller does not choose how many.
+pub const DEFAULT_RERANK_CANDIDATES: usize = 30;
+const RERANK_MAX_SEQUENCE_LENGTH: usize = 512;
+const RERANK_MICRO_BATCH_SIZE: usize = 8;
+const INPUT_IDS_NAME: &str = "input_ids";
+const ATTENTION_MASK_NAME: &str = "attention_mask";
+const TOKEN_TYPE_IDS_NAME: &str = "token_type_ids";
+
+/// Cross-encoder that reads a query and a passage together and returns one relevance logit.
+struct RerankEngine {
+    tokenizer: Tokenizer,
+    session: Session,
+    input_names: Vec<String>,
+}
+
+static RERANK_ENGINE: OnceCell<Mutex<RerankEngine>> = OnceCell::new();
+
+impl RerankEngine {
+    fn initialize() -> Result<Self, EmbeddingError> {
+        // Installed like the embedding model (`tentacle model install --model <id>`), so an
+        // offline machine never reaches hf-hub.
+        let artifacts = match installed_model_directory_for(LOCAL_RERANK_MODEL_ID) {
+            Some(directory) => resolve_local_artifacts(&directory)?,
+            None => {
+                let api = HfApi::new().map_err(|error| {
+                    EmbeddingError::ModelArtifacts(format!(
+                        "failed to create hf-hub client: {error}"
+                    ))
+                })?;
+                resolve_artifacts(&api.model(HF_RERANK_REPO_ID.to_owned()))?
+            }
+        };
+
+        let mut tokenizer = Tokenizer::from_file(&artifacts.tokenizer_path).map_err(|error| {
+            EmbeddingError::ModelArtifacts(format!(
+                "failed to load tokenizer from {}: {error}",
+                artifacts.tokenizer_path.display()
+            ))
+        })?;
+        set_pair_truncation(&mut tokenizer)?;
+
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .with_intra_threads(1)?
+            .commit_from_file(&artifacts.model_path)?;
+        let input_names = session
+            .inputs()
+            .iter()
+            .map(|input| input.name().to_owned())
+            .collect::<Vec<_>>();
+        if input_names.is_empty() {
+            return Err(EmbeddingError::ModelArtifacts(
+                "model has no inputs".to_owned(),
+            ));
+        }
+
+        log::info!(
+            "[rerank] Initialized ONNX model {} ({})",
+            HF_RERANK_REPO_ID,
+            artifacts.model_path.display()
+        );
+
+        Ok(Self {
+            tokenizer,
+            session,
+            input_names,
+        })
+    }
+
+    fn engine() -> Result<&'static Mutex<Self>, EmbeddingError> {
+        RERANK_ENGINE.get_or_try_init(|| Self::initialize().map(Mutex::new))
+    }
+
+    fn score_pairs(&mut self, query: &str, passages: &[&str]) -> Result<Vec<f32>, EmbeddingError> {
+        if passages.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut encodings = Vec::with_capacity(passages.len());
+        for passage in passages {
+            let encoding = self
+                .tokenizer
+                .encode((query, *passage), true)
+                .map_err(|error| EmbeddingError::Tokenization(error.to_string()))?;
+            encodings.push(encoding);
+        }
+
+        let batch_size = encodings.len();
+        let sequence_length = encodings
+            .iter()
+            .map(|encoding| encoding.get_ids().len())
+            .max()
+            .unwrap_or(0);
+        if sequence_length == 0 {
+            return Err(EmbeddingError::EmptyInput);
+        }
+
+        let pad_token_id = self
+            .tokenizer
+            .get_padding()
+            .map(|padding| i64::from(padding.pad_id))
+            .unwrap_or(0_i64);
+
+        let mut input_ids = Vec::with_capacity(batch_size * sequence_length);
+        let mut attention_mask = Vec::with_capacity(batch_size * sequence_length);
+        let mut token_type_ids = Vec::with_capacity(batch_size * sequence_length);
+        for encoding in &encodings {
+            let ids = encoding.get_ids();
+            let type_ids = encoding.get_type_ids();
+            for token_index in 0..sequence_length {
+                if let Some(id) = ids.get(token_index) {
+                    input_ids.push(i64::from(*id));
+                    attention_mask.push(1_i64);
+                    token_type_ids.push(i64::from(*type_ids.get(token_index).unwrap_or(&0)));
+                } else {
+                    input_ids.push(pad_token_id);
+                    attention_mask.push(0_i64);
+                    token_type_ids.push(0_i64);
+                }
+            }
+        }
+
+        let mut inputs: Vec<(String, DynTensor)> = Vec::with_capacity(self.input_names.len());
+        for name in &self.input_names {
+            let key = name.to_ascii_lowercase();
+            let values = if key.contains(INPUT_IDS_NAME) {
+                input_ids.clone()
+            } else if key.contains(ATTENTION_MASK_NAME) {
+                attention_mask.clone()
+            } else if key.contains(TOKEN_TYPE_IDS_NAME) {
+                token_type_ids.clone()
+            } else {
+                return Err(EmbeddingError::UnsupportedModelInput(name.clone()));
+            };
+            let tensor = Tensor::from_array(([batch_size, sequence_length], values))?.upcast();
+            inputs.push((name.clone(), tensor));
+        }
+
+        let outputs = self.session.run(inputs)?;
+        if outputs.len() == 0 {
+            return Err(EmbeddingError::MissingModelOutput);
+        }
+        let (shape, data) = outputs[0].try_extract_tensor::<f32>()?;
+        relevance_logits(data, shape, batch_size)
+    }
+}
+
+/// Fits query + passage pairs into `RERANK_MAX_SEQUENCE_LENGTH` by cutting the longer side
+/// first: long passages give up their tail, and a query longer than the limit is cut too
+/// instead of failing the pair.
+fn set_pair_truncation(tokenizer: &mut Tokenizer) -> Result<(), EmbeddingError> {
+    tokenizer
+        .with_truncation(Some(TruncationParams {
+            max_length: RERANK_MAX_SEQUENCE_LENGTH,
+            strategy: TruncationStrategy::LongestFirst,
+            ..TruncationParams::default()
+        }))
+        .map_err(|error| EmbeddingError::Tokenization(error.to_string()))?;
+    Ok(())
+}
+
+/// One logit per batch row. Single-logit heads score relevance directly; for two-class
+/// heads the last column is the "relevant" class.
+fn relevance_logits(
+    output_data: &[f32],
+    output_shape: &[i64],
+    batch_size: usize,
+) -> Result<Vec<f32>, EmbeddingError> {
+    let columns = match output_shape.len() {
+        1 => 1,
+        2 => positive_dim(output_shape, 1, "logit")?,
+        _ => {
+            return Err(EmbeddingError::InvalidOutputShape(format!(
+                "expected [batch] or [batch, classes] logits, got {output_shape:?}"
+            )))
+        }
+    };
+    if output_data.len() != batch_size * columns {
+        return Err(EmbeddingError::InvalidOutputShape(format!(
+            "expected {batch_size} rows of {columns} logits, got {} values",
+            output_data.len()
+        )));
+    }
+
+    Ok(output_data
+        .chunks(columns)
+        .map(|row| row[columns - 1])
+        .collect())
+}
+
+fn sigmoid(value: f32) -> f32 {
+    1.0 / (1.0 + (-value).exp())
+}
+
+/// Cross-encoder relevance of each passage to `query`, in `0.0..=1.0`.
+pub fn rerank_scores(query: &str, passages: &[&str]) -> Result<Vec<f32>, EmbeddingError> {
+    if passages.is_empty() {
+        return Ok(Vec::new());
+    }
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(EmbeddingError::EmptyInput);
+    }
+    if !embeddings_runtime_enabled() {
+        return Err(EmbeddingError::ModelArtifacts(
+            "ONNX runtime is not available on this platform".to_owned(),
+        ));
+    }
+
+    let engine = RerankEngine::engine()?;
+    let mut guard = engine.lock().map_err(|_| EmbeddingError::EnginePoisoned)?;
+    let mut scores = Vec::with_capacity(passages.len());
+    for batch in passages.chunks(RERANK_MICRO_BATCH_SIZE) {
+        let logits = guard.score_pairs(query, batch)?;
+        scores.extend(logits.into_iter().map(sigmoid));
+    }
+
+    Ok(scores)
+}
+
+/// Rescores the first `passages.len()` hits against `query` with the cross-encoder, one
+/// passage text per hit, and orders them by that score. Later hits keep their fused order
+/// and scores after them.
+pub fn rerank_search_hits(
+    query: &str,
+    hits: &mut [HybridSearchHitPayload],
+    passages: &[String],
+) -> Result<(), EmbeddingError> {
+    let candidate_count = passages.len().min(hits.len());
+    let passage_refs = passages[..candidate_count]
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let scores = rerank_scores(query, &passage_refs)?;
+    apply_rerank_scores(&mut hits[..candidate_count], &scores);
+    Ok(())
+}
+
+fn apply_rerank_scores(hits: &mut [HybridSearchHitPayload], scores: &[f32]) {
+    for (hit, score) in hits.iter_mut().zip(scores) {
+        hit.score = *score;
+        if let Some(explanation) = hit.explanation.as_mut() {
+            explanation.rerank_score = Some(*score);
+        }
+    }
+    // Stable sort: equal scores keep their fused order.
+    hits.sort_by(|left, right| right.score.total_cmp(&left.score));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tokenizers::Tokenizer;
+
+    use crate::document_cache::HybridSearchHitPayload;
+
+    use super::{
+        apply_rerank_scores, relevance_logits, rerank_search_hits, set_pair_truncation,
+        RERANK_MAX_SEQUENCE_LENGTH,
+    };
+
+    fn hit(document_id: &str, score: f32) -> HybridSearchHitPayload {
+        HybridSearchHitPayload {
+            document_id: document_id.to_owned(),
+            score,
+            page: None,
+            title: None,
+            title_matches: Vec::new(),
+            snippet: None,
+            passages: Vec::new(),
+            explanation: None,
+        }
+    }
+
+    #[test]
+    fn relevance_logits_reads_single_and_two_class_heads() {
+        assert_eq!(
+            relevance_logits(&[0.5, -1.0], &[2, 1], 2).expect("single logit"),
+            vec![0.5, -1.0]
+        );
+        assert_eq!(
+            relevance_logits(&[0.1, 0.9, 0.8, 0.2], &[2, 2], 2).expect("two classes"),
+            vec![0.9, 0.2]
+        );
+        assert!(relevance_logits(&[0.5], &[2, 1], 2).is_err());
+    }
+
+    #[test]
+    fn pair_truncation_cuts_a_query_longer_than_the_limit() {
+        let mut tokenizer = Tokenizer::from_str(
+            r#"{
+                "version": "1.0",
+                "truncation": null,
+                "padding": null,
+                "added_tokens": [],
+                "normalizer": null,
+                "pre_tokenizer": {"type": "Whitespace"},
+                "post_processor": null,
+                "decoder": null,
+                "model": {
+                    "type": "WordLevel",
+                    "vocab": {"[UNK]": 0, "query": 1, "passage": 2},
+                    "unk_token": "[UNK]"
+                }
+            }"#,
+        )
+        .expect("word-level tokenizer");
+        set_pair_truncation(&mut tokenizer).expect("truncation");
+
+        let long_query = "query ".repeat(RERANK_MAX_SEQUENCE_LENGTH + 100);
+        let encoding = tokenizer
+            .encode((long_query.as_str(), "passage passage"), true)
+            .expect("over-long query still encodes");
+        assert_eq!(encoding.get_ids().len(), RERANK_MAX_SEQUENCE_LENGTH);
+        assert!(encoding.get_ids().contains(&2), "passage tokens are kept");
+
+        let long_passage = "passage ".repeat(RERANK_MAX_SEQUENCE_LENGTH);
+        let encoding = tokenizer
+            .encode(("query query", long_passage.as_str()), true)
+            .expect("long passage encodes");
+        assert_eq!(encoding.get_ids().len(), RERANK_MAX_SEQUENCE_LENGTH);
+        assert_eq!(
+            &encoding.get_ids()[..2],
+            &[1, 1],
+            "the short query is kept whole"
+        );
+    }
+
+    #[test]
+    fn apply_rerank_scores_orders_candidates_and_keeps_ties_stable() {
+        let mut hits = vec![hit("a", 0.03), hit("b", 0.02), hit("c", 0.01)];
+        apply_rerank_scores(&mut hits, &[0.2, 0.9, 0.2]);
+
+        let ids = hits
+            .iter()
+            .map(|hit| hit.document_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["b", "a", "c"]);
+        assert_eq!(hits[0].score, 0.9);
+    }
+
+    #[test]
+    fn rerank_search_hits_without_passages_keeps_hits() {
+        let mut hits = vec![hit("a", 0.03), hit("b", 0.02)];
+        rerank_search_hits("query", &mut hits, &[]).expect("nothing to rerank");
+
+        assert_eq!(hits[0].document_id, "a");
+        assert_eq!(hits[1].score, 0.02);
+    }
+}
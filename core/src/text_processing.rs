@@ -1,12 +1,84 @@
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-const TARGET_CHUNK_CHARS: usize = 800;
-const OVERLAP_CHARS: usize = 200;
+pub const DEFAULT_CHUNK_TOKENS: usize = 256;
+pub const DEFAULT_CHUNK_OVERLAP_TOKENS: usize = 32;
+const ESTIMATED_CHARS_PER_TOKEN: usize = 4;
+const PAGE_MARKER_PREFIX: &str = "<!-- page ";
+const PAGE_MARKER_SUFFIX: &str = " -->";
 
 #[derive(Debug, Clone)]
 pub struct DocumentChunk {
     pub text: String,
     pub index: usize,
+    /// Source page the chunk was taken from, for bodies that carry page markers.
+    pub page: Option<u32>,
+    /// Headings enclosing the chunk, outermost first, without their `#` markers.
+    pub heading_path: Vec<String>,
+    /// Character range of the chunk's source text in the chunked body, end exclusive.
+    pub char_start: usize,
+    pub char_end: usize,
+    /// 1-based, inclusive line range of the chunk's source text in the chunked body.
+    pub line_start: usize,
+    pub line_end: usize,
+}
+
+/// Chunk sizes in embedding-model tokens. Stored per vault in
+/// [`crate::vault_settings::VaultSettings`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ChunkingOptions {
+    pub chunk_tokens: usize,
+    pub overlap_tokens: usize,
+}
+
+impl Default for ChunkingOptions {
+    fn default() -> Self {
+        Self {
+            chunk_tokens: DEFAULT_CHUNK_TOKENS,
+            overlap_tokens: DEFAULT_CHUNK_OVERLAP_TOKENS,
+        }
+    }
+}
+
+/// Marker written between pages of paginated imports (e.g. PDFs). It renders invisibly in
+/// markdown and lets the chunker attribute each chunk to a page.
+pub fn format_page_marker(page: u32) -> String {
+    format!("{PAGE_MARKER_PREFIX}{page}{PAGE_MARKER_SUFFIX}")
+}
+
+/// Splits `body` at page markers into `(page, text)` segments. Text before the first marker
+/// is attributed to no page. Returns a single `(None, body)` segment when there are no markers.
+pub fn split_page_segments(body: &str) -> Vec<(Option<u32>, &str)> {
+    let mut segments = Vec::new();
+    let mut current_page = None;
+    let mut remainder = body;
+
+    while let Some(start) = remainder.find(PAGE_MARKER_PREFIX) {
+        let after_prefix = &remainder[start + PAGE_MARKER_PREFIX.len()..];
+        let parsed = after_prefix.find(PAGE_MARKER_SUFFIX).and_then(|end| {
+            after_prefix[..end]
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .map(|page| (page, &after_prefix[end + PAGE_MARKER_SUFFIX.len()..]))
+        });
+        let Some((page, rest)) = parsed else {
+            break;
+        };
+
+        let before = &remainder[..start];
+        if !before.trim().is_empty() {
+            segments.push((current_page, before));
+        }
+        current_page = Some(page);
+        remainder = rest;
+    }
+
+    if !remainder.trim().is_empty() || segments.is_empty() {
+        segments.push((current_page, remainder));
+    }
+    segments
 }
 
 fn normalize_whitespace(value: &str) -> String {
@@ -67,6 +139,367 @@ pub fn extract_plain_text_from_tiptap_or_raw(body: &str) -> String {
     normalize_whitespace(trimmed)
 }
 
+/// Converts a Tiptap/ProseMirror JSON document body to markdown. Returns `None` when `body`
+/// is not a Tiptap document (a JSON object with `"type": "doc"`), so raw markdown passes
+/// through callers untouched.
+pub fn tiptap_json_to_markdown(body: &str) -> Option<String> {
+    let parsed = serde_json::from_str::<Value>(body.trim()).ok()?;
+    if parsed.get("type").and_then(Value::as_str) != Some("doc") {
+        return None;
+    }
+
+    Some(render_tiptap_blocks(node_children(&parsed)))
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or_default()
+}
+
+fn node_children(node: &Value) -> &[Value] {
+    node.get("content")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn node_attr<'a>(node: &'a Value, name: &str) -> Option<&'a Value> {
+    node.get("attrs")
+        .and_then(|attrs| attrs.get(name))
+        .filter(|value| !value.is_null())
+}
+
+fn node_attr_str<'a>(node: &'a Value, name: &str) -> Option<&'a str> {
+    node_attr(node, name)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+fn render_tiptap_blocks(nodes: &[Value]) -> String {
+    nodes
+        .iter()
+        .map(render_tiptap_block)
+        .filter(|block| !block.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn render_tiptap_block(node: &Value) -> String {
+    match node_type(node) {
+        "paragraph" => escape_markdown_block_start(&render_tiptap_inline(node_children(node))),
+        "heading" => {
+            let level = node_attr(node, "level")
+                .and_then(Value::as_u64)
+                .unwrap_or(1)
+                .clamp(1, 6) as usize;
+            format!(
+                "{} {}",
+                "#".repeat(level),
+                render_tiptap_inline(node_children(node)).replace('\n', " ")
+            )
+        }
+        "bulletList" => render_tiptap_list(node, |_| "- ".to_owned()),
+        "orderedList" => {
+            let start = node_attr(node, "start")
+                .and_then(Value::as_u64)
+                .unwrap_or(1);
+            render_tiptap_list(node, |index| format!("{}. ", start + index as u64))
+        }
+        "taskList" => render_tiptap_list(node, |_| "- ".to_owned()),
+        "listItem" | "taskItem" => render_tiptap_list_item(node, "- "),
+        "blockquote" => render_tiptap_blocks(node_children(node))
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    ">".to_owned()
+                } else {
+                    format!("> {line}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "codeBlock" => {
+            let code = node_children(node)
+                .iter()
+                .filter_map(|child| child.get("text").and_then(Value::as_str))
+                .collect::<String>();
+            let fence = "`".repeat((longest_run(&code, '`') + 1).max(3));
+            let language = node_attr_str(node, "language").unwrap_or_default();
+            format!(
+                "{fence}{language}\n{}\n{fence}",
+                code.trim_end_matches('\n')
+            )
+        }
+        "horizontalRule" => "---".to_owned(),
+        "image" => render_tiptap_image(node),
+        "table" => render_tiptap_table(node),
+        "text" | "hardBreak" => render_tiptap_inline(std::slice::from_ref(node)),
+        _ => {
+            let children = node_children(node);
+            if children
+                .iter()
+                .all(|child| matches!(node_type(child), "text" | "hardBreak" | "image"))
+            {
+                render_tiptap_inline(children)
+            } else {
+                render_tiptap_blocks(children)
+            }
+        }
+    }
+}
+
+fn render_tiptap_list(node: &Value, marker: impl Fn(usize) -> String) -> String {
+    node_children(node)
+        .iter()
+        .enumerate()
+        .map(|(index, item)| render_tiptap_list_item(item, &marker(index)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders one list item: the first block follows the marker and later blocks are indented
+/// to the item's content column. Nested lists stay tight; other blocks keep a blank line.
+fn render_tiptap_list_item(item: &Value, marker: &str) -> String {
+    let indent = " ".repeat(marker.len());
+    let marker = match node_attr(item, "checked").and_then(Value::as_bool) {
+        Some(true) => format!("{marker}[x] "),
+        Some(false) => format!("{marker}[ ] "),
+        None if node_type(item) == "taskItem" => format!("{marker}[ ] "),
+        None => marker.to_owned(),
+    };
+
+    let mut output = marker.clone();
+    for (index, child) in node_children(item).iter().enumerate() {
+        let block = render_tiptap_block(child);
+        if block.trim().is_empty() {
+            continue;
+        }
+        if index > 0 {
+            let is_list = matches!(node_type(child), "bulletList" | "orderedList" | "taskList");
+            output.push_str(if is_list { "\n" } else { "\n\n" });
+        }
+        for (line_index, line) in block.lines().enumerate() {
+            if line_index > 0 {
+                output.push('\n');
+            }
+            if (index > 0 || line_index > 0) && !line.is_empty() {
+                output.push_str(&indent);
+            }
+            output.push_str(line);
+        }
+    }
+    output.trim_end().to_owned()
+}
+
+fn render_tiptap_table(node: &Value) -> String {
+    let rows = node_children(node)
+        .iter()
+        .map(|row| {
+            node_children(row)
+                .iter()
+                .map(|cell| {
+                    node_children(cell)
+                        .iter()
+                        .map(|block| render_tiptap_block(block).replace('\n', "<br>"))
+                        .filter(|block| !block.is_empty())
+                        .collect::<Vec<_>>()
+                        .join("<br>")
+                        .replace('|', "\\|")
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|cells| !cells.is_empty())
+        .collect::<Vec<_>>();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    let format_row = |cells: &[String]| {
+        let mut padded = cells.to_vec();
+        padded.resize(columns, String::new());
+        format!("| {} |", padded.join(" | "))
+    };
+    let mut lines = vec![
+        format_row(&rows[0]),
+        format!("|{}", " --- |".repeat(columns)),
+    ];
+    lines.extend(rows[1..].iter().map(|cells| format_row(cells)));
+    lines.join("\n")
+}
+
+fn render_tiptap_image(node: &Value) -> String {
+    let source = node_attr_str(node, "src").unwrap_or_default();
+    let alt = node_attr_str(node, "alt").unwrap_or_default();
+    match node_attr_str(node, "title") {
+        Some(title) => format!(
+            "![{}]({} \"{}\")",
+            escape_markdown_text(alt),
+            markdown_link_destination(source),
+            title.replace('"', "\\\"")
+        ),
+        None => format!(
+            "![{}]({})",
+            escape_markdown_text(alt),
+            markdown_link_destination(source)
+        ),
+    }
+}
+
+fn render_tiptap_inline(nodes: &[Value]) -> String {
+    let mut output = String::new();
+    for node in nodes {
+        match node_type(node) {
+            "text" => {
+                let text = node.get("text").and_then(Value::as_str).unwrap_or_default();
+                let marks = node
+                    .get("marks")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                output.push_str(&apply_tiptap_marks(text, marks));
+            }
+            "hardBreak" => output.push_str("\\\n"),
+            "image" => output.push_str(&render_tiptap_image(node)),
+            _ => output.push_str(&render_tiptap_inline(node_children(node))),
+        }
+    }
+    output
+}
+
+/// Wraps `text` in markdown for its marks. Code is innermost and links outermost, and
+/// surrounding whitespace is kept outside the delimiters so emphasis stays valid.
+fn apply_tiptap_marks(text: &str, marks: &[Value]) -> String {
+    let has_mark = |name: &str| marks.iter().any(|mark| node_type(mark) == name);
+    if has_mark("code") {
+        let ticks = "`".repeat(longest_run(text, '`') + 1);
+        let padding = if text.starts_with('`') || text.ends_with('`') {
+            " "
+        } else {
+            ""
+        };
+        let code = format!("{ticks}{padding}{text}{padding}{ticks}");
+        return match marks.iter().find(|mark| node_type(mark) == "link") {
+            Some(link) => wrap_tiptap_link(&code, link),
+            None => code,
+        };
+    }
+
+    let core = text.trim();
+    if core.is_empty() {
+        return escape_markdown_text(text);
+    }
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+
+    let mut wrapped = escape_markdown_text(core);
+    for (mark, open, close) in [
+        ("italic", "*", "*"),
+        ("bold", "**", "**"),
+        ("strike", "~~", "~~"),
+        ("underline", "<u>", "</u>"),
+        ("highlight", "<mark>", "</mark>"),
+        ("subscript", "<sub>", "</sub>"),
+        ("superscript", "<sup>", "</sup>"),
+    ] {
+        if has_mark(mark) {
+            wrapped = format!("{open}{wrapped}{close}");
+        }
+    }
+    if let Some(link) = marks.iter().find(|mark| node_type(mark) == "link") {
+        wrapped = wrap_tiptap_link(&wrapped, link);
+    }
+    format!("{leading}{wrapped}{trailing}")
+}
+
+fn wrap_tiptap_link(text: &str, link: &Value) -> String {
+    let Some(href) = node_attr_str(link, "href") else {
+        return text.to_owned();
+    };
+    match node_attr_str(link, "title") {
+        Some(title) => format!(
+            "[{text}]({} \"{}\")",
+            markdown_link_destination(href),
+            title.replace('"', "\\\"")
+        ),
+        None => format!("[{text}]({})", markdown_link_destination(href)),
+    }
+}
+
+/// `destination` as a markdown link target, wrapped in `<...>` when spaces or parentheses
+/// would otherwise end the link early.
+pub(crate) fn markdown_link_destination(destination: &str) -> String {
+    if destination.contains([' ', '(', ')']) {
+        format!("<{}>", destination.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        destination.to_owned()
+    }
+}
+
+/// Escapes characters that would otherwise start inline markdown syntax. Underscores inside
+/// words are left alone since they cannot open emphasis there.
+fn escape_markdown_text(text: &str) -> String {
+    let characters = text.chars().collect::<Vec<_>>();
+    let mut escaped = String::with_capacity(text.len());
+    for (index, character) in characters.iter().enumerate() {
+        let needs_escape = match character {
+            '\\' | '*' | '`' | '[' | ']' | '<' => true,
+            '_' => {
+                let before = index
+                    .checked_sub(1)
+                    .and_then(|previous| characters.get(previous));
+                let after = characters.get(index + 1);
+                !(before.is_some_and(|value| value.is_alphanumeric())
+                    && after.is_some_and(|value| value.is_alphanumeric()))
+            }
+            _ => false,
+        };
+        if needs_escape {
+            escaped.push('\\');
+        }
+        escaped.push(*character);
+    }
+    escaped
+}
+
+/// Keeps paragraph text that happens to start like a heading, quote, list item, or rule
+/// from turning into one.
+fn escape_markdown_block_start(paragraph: &str) -> String {
+    let trimmed = paragraph.trim_start();
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    let starts_block = trimmed.starts_with('#')
+        || trimmed.starts_with('>')
+        || trimmed.starts_with("- ")
+        || trimmed.starts_with("+ ")
+        || trimmed == "-"
+        || trimmed.starts_with("---")
+        || trimmed.starts_with("===")
+        || (digits > 0
+            && matches!(trimmed[digits..].chars().next(), Some('.') | Some(')'))
+            && trimmed[digits + 1..]
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace));
+    if !starts_block {
+        return paragraph.to_owned();
+    }
+    let offset = paragraph.len() - trimmed.len() + digits;
+    format!("{}\\{}", &paragraph[..offset], &paragraph[offset..])
+}
+
+fn longest_run(text: &str, target: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for character in text.chars() {
+        if character == target {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
 fn build_chunk_text(title: &str, body: &str) -> String {
     if title.is_empty() {
         return body.to_owned();
@@ -75,81 +508,696 @@ fn build_chunk_text(title: &str, body: &str) -> String {
     format!("{title}\n\n{body}")
 }
 
-fn tail_chars(value: &str, count: usize) -> String {
-    let total_chars = value.chars().count();
-    let skip_chars = total_chars.saturating_sub(count);
-    value.chars().skip(skip_chars).collect()
+/// Rough token count for callers without a tokenizer: about four characters per token for
+/// English text, which slightly overestimates for most subword vocabularies.
+pub fn estimate_token_count(text: &str) -> usize {
+    text.chars().count().div_ceil(ESTIMATED_CHARS_PER_TOKEN)
 }
 
+/// Splits a document into embedding chunks with the default options and estimated token
+/// counts. Prefer [`chunk_markdown_document`] when the embedding tokenizer is available.
 pub fn chunk_document_text(title: &str, body_text: &str) -> Vec<DocumentChunk> {
-    let normalized_title = title.trim();
-    let normalized_body = body_text.trim();
+    chunk_markdown_document(
+        title,
+        body_text,
+        &ChunkingOptions::default(),
+        &estimate_token_count,
+    )
+}
 
-    if normalized_body.is_empty() {
-        return vec![DocumentChunk {
-            text: normalized_title.to_owned(),
-            index: 0,
-        }];
-    }
+/// Splits a markdown document into embedding chunks of at most `options.chunk_tokens`
+/// tokens (as measured by `count_tokens`), not counting the title every chunk is prefixed
+/// with. Chunks start at headings, and fenced code blocks, lists, and tables are only split
+/// when they do not fit in a chunk on their own. Bodies with page markers are chunked page
+/// by page so every chunk keeps the page it came from; chunks never span two pages.
+///
+/// Each chunk records the headings it sits under and the character and line range of the
+/// body text it was built from. Repeated section headings and overlap carried over from the
+/// previous chunk are not part of that range.
+pub fn chunk_markdown_document(
+    title: &str,
+    body: &str,
+    options: &ChunkingOptions,
+    count_tokens: &dyn Fn(&str) -> usize,
+) -> Vec<DocumentChunk> {
+    let chunker = MarkdownChunker {
+        title: title.trim(),
+        budget: options.chunk_tokens.max(1),
+        overlap: options.overlap_tokens.min(options.chunk_tokens / 2),
+        count_tokens,
+        source: LineIndex::new(body),
+    };
 
-    if normalized_body.len() <= TARGET_CHUNK_CHARS {
-        return vec![DocumentChunk {
-            text: build_chunk_text(normalized_title, normalized_body),
-            index: 0,
-        }];
+    let segments = split_page_segments(body);
+    if segments.len() == 1 && segments[0].0.is_none() {
+        return chunker.chunk_segment(body, 0, None, 0);
     }
 
     let mut chunks: Vec<DocumentChunk> = Vec::new();
-    let mut current = String::new();
-    let mut chunk_index = 0;
-
-    for paragraph in normalized_body.split("\n\n") {
-        let trimmed = paragraph.trim();
-        if trimmed.is_empty() {
+    for (page, segment) in segments {
+        if segment.trim().is_empty() {
             continue;
         }
+        // Segments are slices of `body`, so their position gives the byte offset.
+        let offset = segment.as_ptr() as usize - body.as_ptr() as usize;
+        let page_chunks = chunker.chunk_segment(segment, offset, page, chunks.len());
+        chunks.extend(page_chunks);
+    }
+
+    if chunks.is_empty() {
+        return chunker.chunk_segment("", 0, None, 0);
+    }
+    chunks
+}
+
+/// First and last line (0-based, inclusive) of a piece of text, relative to the segment
+/// being chunked.
+type LineSpan = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MarkdownBlockKind {
+    Heading,
+    Paragraph,
+    List,
+    Code,
+    Table,
+}
+
+#[derive(Debug)]
+struct MarkdownBlock {
+    kind: MarkdownBlockKind,
+    text: String,
+    lines: LineSpan,
+}
+
+#[derive(Debug)]
+struct ChunkPiece {
+    text: String,
+    lines: LineSpan,
+}
+
+struct PackedChunk {
+    text: String,
+    lines: LineSpan,
+    heading_path: Vec<String>,
+}
+
+/// Byte and character offsets of every line start in the chunked body.
+struct LineIndex<'a> {
+    text: &'a str,
+    byte_starts: Vec<usize>,
+    char_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut byte_starts = vec![0];
+        let mut char_starts = vec![0];
+        for (characters, (offset, character)) in text.char_indices().enumerate() {
+            if character == '\n' {
+                byte_starts.push(offset + 1);
+                char_starts.push(characters + 1);
+            }
+        }
+        Self {
+            text,
+            byte_starts,
+            char_starts,
+        }
+    }
+
+    fn line_of(&self, byte: usize) -> usize {
+        self.byte_starts
+            .partition_point(|start| *start <= byte)
+            .saturating_sub(1)
+    }
+
+    /// Byte offset just past the last character of `line`, excluding its line break.
+    fn line_end(&self, line: usize) -> usize {
+        let end = self
+            .byte_starts
+            .get(line + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        let start = self.byte_starts[line.min(self.byte_starts.len() - 1)];
+        if end > start && self.text.as_bytes()[end - 1] == b'\r' {
+            end - 1
+        } else {
+            end
+        }
+    }
+
+    fn char_offset(&self, byte: usize) -> usize {
+        let line = self.line_of(byte);
+        let start = self.byte_starts[line];
+        self.char_starts[line] + self.text[start..byte].chars().count()
+    }
+}
+
+struct MarkdownChunker<'a> {
+    title: &'a str,
+    budget: usize,
+    overlap: usize,
+    count_tokens: &'a dyn Fn(&str) -> usize,
+    source: LineIndex<'a>,
+}
+
+impl MarkdownChunker<'_> {
+    fn tokens(&self, text: &str) -> usize {
+        (self.count_tokens)(text)
+    }
 
-        let candidate = if current.is_empty() {
-            trimmed.to_owned()
+    /// Chunks one page segment starting `offset` bytes into the body.
+    fn chunk_segment(
+        &self,
+        segment: &str,
+        offset: usize,
+        page: Option<u32>,
+        first_index: usize,
+    ) -> Vec<DocumentChunk> {
+        let body = segment.trim();
+        let body_start = offset + (segment.len() - segment.trim_start().len());
+        let packed = if body.is_empty() {
+            vec![PackedChunk {
+                text: String::new(),
+                lines: (0, 0),
+                heading_path: Vec::new(),
+            }]
+        } else if self.tokens(body) <= self.budget {
+            vec![PackedChunk {
+                text: body.to_owned(),
+                lines: (0, body.lines().count().saturating_sub(1)),
+                heading_path: Vec::new(),
+            }]
         } else {
-            format!("{current}\n\n{trimmed}")
+            self.pack_blocks(parse_markdown_blocks(body))
         };
 
-        if candidate.len() > TARGET_CHUNK_CHARS && !current.is_empty() {
-            chunks.push(DocumentChunk {
-                text: build_chunk_text(normalized_title, &current),
-                index: chunk_index,
+        let first_line = self.source.line_of(body_start);
+        let body_end = body_start + body.len();
+        packed
+            .into_iter()
+            .enumerate()
+            .map(|(position, chunk)| {
+                let line_start = first_line + chunk.lines.0;
+                let line_end = first_line + chunk.lines.1;
+                let byte_start = self.source.byte_starts[line_start].max(body_start);
+                let byte_end = self.source.line_end(line_end).min(body_end).max(byte_start);
+                let text = if chunk.text.is_empty() {
+                    self.title.to_owned()
+                } else {
+                    build_chunk_text(self.title, &chunk.text)
+                };
+                DocumentChunk {
+                    text,
+                    index: first_index + position,
+                    page,
+                    heading_path: chunk.heading_path,
+                    char_start: self.source.char_offset(byte_start),
+                    char_end: self.source.char_offset(byte_end),
+                    line_start: line_start + 1,
+                    line_end: line_end + 1,
+                }
+            })
+            .collect()
+    }
+
+    /// Packs blocks greedily into chunks. A heading always closes the running chunk, and a
+    /// chunk that continues a section repeats the section heading plus up to `overlap`
+    /// tokens from the end of the previous chunk.
+    fn pack_blocks(&self, blocks: Vec<MarkdownBlock>) -> Vec<PackedChunk> {
+        let mut chunks = Vec::new();
+        let mut outline: Vec<(usize, String)> = Vec::new();
+        let mut section_heading: Option<String> = None;
+        let mut headings: Vec<String> = Vec::new();
+        let mut heading_tokens = 0;
+        let mut current: Vec<String> = Vec::new();
+        let mut current_tokens = 0;
+        let mut span: Option<LineSpan> = None;
+
+        for block in blocks {
+            if block.kind == MarkdownBlockKind::Heading {
+                if !current.is_empty() {
+                    chunks.push(PackedChunk {
+                        text: join_blocks(&headings, &current),
+                        lines: span.take().unwrap_or_default(),
+                        heading_path: heading_path(&outline),
+                    });
+                    headings.clear();
+                    heading_tokens = 0;
+                    current.clear();
+                    current_tokens = 0;
+                }
+                push_outline_heading(&mut outline, &block.text);
+                span = Some(extend_span(span, block.lines));
+                heading_tokens += self.tokens(&block.text);
+                section_heading = Some(block.text.clone());
+                headings.push(block.text);
+                continue;
+            }
+
+            let pieces = if self.tokens(&block.text) > self.budget {
+                self.split_block(&block)
+            } else {
+                vec![ChunkPiece {
+                    text: block.text,
+                    lines: block.lines,
+                }]
+            };
+
+            for piece in pieces {
+                let piece_tokens = self.tokens(&piece.text);
+                if !current.is_empty()
+                    && heading_tokens + current_tokens + piece_tokens > self.budget
+                {
+                    chunks.push(PackedChunk {
+                        text: join_blocks(&headings, &current),
+                        lines: span.take().unwrap_or_default(),
+                        heading_path: heading_path(&outline),
+                    });
+                    let overlap = self.overlap_tail(&current);
+                    current.clear();
+                    current_tokens = 0;
+
+                    headings = section_heading.iter().cloned().collect();
+                    heading_tokens = headings.iter().map(|line| self.tokens(line)).sum();
+                    if heading_tokens + piece_tokens > self.budget {
+                        headings.clear();
+                        heading_tokens = 0;
+                    }
+                    if let Some(overlap) = overlap {
+                        let overlap_tokens = self.tokens(&overlap);
+                        if heading_tokens + overlap_tokens + piece_tokens <= self.budget {
+                            current.push(overlap);
+                            current_tokens = overlap_tokens;
+                        }
+                    }
+                }
+
+                current_tokens += piece_tokens;
+                span = Some(extend_span(span, piece.lines));
+                current.push(piece.text);
+            }
+        }
+
+        if !current.is_empty() || !headings.is_empty() {
+            chunks.push(PackedChunk {
+                text: join_blocks(&headings, &current),
+                lines: span.unwrap_or_default(),
+                heading_path: heading_path(&outline),
             });
-            chunk_index += 1;
+        }
+        chunks
+    }
 
-            let overlap_text = tail_chars(&current, OVERLAP_CHARS);
+    /// Trailing blocks of a finished chunk that fit in the overlap budget, or the last words
+    /// of its final block when that is prose too long to repeat whole.
+    fn overlap_tail(&self, blocks: &[String]) -> Option<String> {
+        if self.overlap == 0 {
+            return None;
+        }
 
-            current = format!("{overlap_text}\n\n{trimmed}");
-        } else {
-            current = candidate;
+        let mut tail: Vec<&str> = Vec::new();
+        let mut tail_tokens = 0;
+        for block in blocks.iter().rev() {
+            let block_tokens = self.tokens(block);
+            if tail_tokens + block_tokens > self.overlap {
+                break;
+            }
+            tail_tokens += block_tokens;
+            tail.push(block);
         }
+        if !tail.is_empty() {
+            tail.reverse();
+            return Some(tail.join("\n\n"));
+        }
+
+        let last = blocks.last()?.trim_start();
+        if code_fence(last).is_some() || last.starts_with('|') {
+            return None;
+        }
+        let words = last.split_whitespace().collect::<Vec<_>>();
+        let mut start = words.len();
+        let mut tail_tokens = 0;
+        while start > 0 {
+            let word_tokens = self.tokens(words[start - 1]);
+            if tail_tokens + word_tokens > self.overlap {
+                break;
+            }
+            tail_tokens += word_tokens;
+            start -= 1;
+        }
+        (start < words.len()).then(|| words[start..].join(" "))
     }
 
-    if !current.is_empty() {
-        chunks.push(DocumentChunk {
-            text: build_chunk_text(normalized_title, &current),
-            index: chunk_index,
+    /// Splits a block that is larger than a chunk along its own structure: code by lines
+    /// (re-opening the fence), tables by rows (repeating the header), lists by items, and
+    /// prose by sentences and finally words.
+    fn split_block(&self, block: &MarkdownBlock) -> Vec<ChunkPiece> {
+        let lines = block.text.lines().collect::<Vec<_>>();
+        let first_line = block.lines.0;
+        let mut pieces = match block.kind {
+            MarkdownBlockKind::Code if lines.len() > 2 => {
+                let (open, close) = (lines[0], lines[lines.len() - 1]);
+                let body = lines[1..lines.len() - 1]
+                    .iter()
+                    .enumerate()
+                    .map(|(position, line)| line_piece(line, first_line + 1 + position))
+                    .collect();
+                self.pack_units(body, &format!("{open}\n"), &format!("\n{close}"), "\n")
+            }
+            MarkdownBlockKind::Table if lines.len() > 2 => {
+                let header = format!("{}\n{}\n", lines[0], lines[1]);
+                let rows = lines[2..]
+                    .iter()
+                    .enumerate()
+                    .map(|(position, line)| line_piece(line, first_line + 2 + position))
+                    .collect();
+                self.pack_units(rows, &header, "", "\n")
+            }
+            MarkdownBlockKind::List => {
+                let items = split_list_items(&block.text, first_line);
+                if items.len() > 1 {
+                    self.pack_units(items, "", "", "\n")
+                } else {
+                    self.pack_units(split_sentences(&block.text, first_line), "", "", " ")
+                }
+            }
+            _ => self.pack_units(split_sentences(&block.text, first_line), "", "", " "),
+        };
+
+        // The fence and table header lines belong to the pieces that open and close the block.
+        if let Some(first) = pieces.first_mut() {
+            first.lines.0 = block.lines.0;
+        }
+        if matches!(block.kind, MarkdownBlockKind::Code) {
+            if let Some(last) = pieces.last_mut() {
+                last.lines.1 = block.lines.1;
+            }
+        }
+        pieces
+    }
+
+    /// Greedily joins `units` with `separator` into pieces that fit the budget together with
+    /// `prefix` and `suffix`. Units that are too large on their own are split into words.
+    fn pack_units(
+        &self,
+        units: Vec<ChunkPiece>,
+        prefix: &str,
+        suffix: &str,
+        separator: &str,
+    ) -> Vec<ChunkPiece> {
+        let budget = self
+            .budget
+            .saturating_sub(self.tokens(prefix) + self.tokens(suffix))
+            .max(1);
+        let units = units
+            .into_iter()
+            .flat_map(|unit| {
+                if self.tokens(&unit.text) > budget {
+                    self.pack_words(&unit, budget)
+                } else {
+                    vec![unit]
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut pieces = Vec::new();
+        let mut current: Vec<String> = Vec::new();
+        let mut current_tokens = 0;
+        let mut span: Option<LineSpan> = None;
+        for unit in units {
+            let unit_tokens = self.tokens(&unit.text);
+            if !current.is_empty() && current_tokens + unit_tokens > budget {
+                pieces.push(ChunkPiece {
+                    text: format!("{prefix}{}{suffix}", current.join(separator)),
+                    lines: span.take().unwrap_or_default(),
+                });
+                current.clear();
+                current_tokens = 0;
+            }
+            current_tokens += unit_tokens;
+            span = Some(extend_span(span, unit.lines));
+            current.push(unit.text);
+        }
+        if !current.is_empty() {
+            pieces.push(ChunkPiece {
+                text: format!("{prefix}{}{suffix}", current.join(separator)),
+                lines: span.unwrap_or_default(),
+            });
+        }
+        pieces
+    }
+
+    fn pack_words(&self, unit: &ChunkPiece, budget: usize) -> Vec<ChunkPiece> {
+        let mut pieces = Vec::new();
+        let mut current = String::new();
+        let mut current_tokens = 0;
+        let mut span: Option<LineSpan> = None;
+        for (position, line) in unit.text.lines().enumerate() {
+            let line_number = unit.lines.0 + position;
+            for word in line.split_whitespace() {
+                let word_tokens = self.tokens(word);
+                if !current.is_empty() && current_tokens + word_tokens > budget {
+                    pieces.push(ChunkPiece {
+                        text: std::mem::take(&mut current),
+                        lines: span.take().unwrap_or_default(),
+                    });
+                    current_tokens = 0;
+                }
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(word);
+                current_tokens += word_tokens;
+                span = Some(extend_span(span, (line_number, line_number)));
+            }
+        }
+        if !current.is_empty() {
+            pieces.push(ChunkPiece {
+                text: current,
+                lines: span.unwrap_or_default(),
+            });
+        }
+        pieces
+    }
+}
+
+fn join_blocks(headings: &[String], blocks: &[String]) -> String {
+    headings
+        .iter()
+        .chain(blocks)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn extend_span(span: Option<LineSpan>, lines: LineSpan) -> LineSpan {
+    span.map_or(lines, |(first, last)| {
+        (first.min(lines.0), last.max(lines.1))
+    })
+}
+
+fn line_piece(line: &str, line_number: usize) -> ChunkPiece {
+    ChunkPiece {
+        text: line.to_owned(),
+        lines: (line_number, line_number),
+    }
+}
+
+/// Replaces the headings at `heading`'s level and below with it, keeping its ancestors.
+fn push_outline_heading(outline: &mut Vec<(usize, String)>, heading: &str) {
+    let heading = heading.trim_start();
+    let level = heading
+        .chars()
+        .take_while(|character| *character == '#')
+        .count();
+    while outline.last().is_some_and(|(parent, _)| *parent >= level) {
+        outline.pop();
+    }
+    outline.push((level, heading[level..].trim().to_owned()));
+}
+
+fn heading_path(outline: &[(usize, String)]) -> Vec<String> {
+    outline.iter().map(|(_, text)| text.clone()).collect()
+}
+
+/// Splits a markdown body into top-level blocks. Fenced code keeps its blank lines, and a
+/// list runs until a blank line that is not followed by another item or indented line.
+fn parse_markdown_blocks(body: &str) -> Vec<MarkdownBlock> {
+    let lines = body.lines().collect::<Vec<_>>();
+    let mut blocks = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            index += 1;
+            continue;
+        }
+
+        let start = index;
+        let kind = if let Some(fence) = code_fence(trimmed) {
+            index += 1;
+            while index < lines.len() {
+                let closing = lines[index].trim();
+                index += 1;
+                if closing.starts_with(fence)
+                    && closing
+                        .chars()
+                        .all(|character| fence.starts_with(character))
+                {
+                    break;
+                }
+            }
+            MarkdownBlockKind::Code
+        } else if is_markdown_heading(trimmed) {
+            index += 1;
+            MarkdownBlockKind::Heading
+        } else if trimmed.starts_with('|') {
+            while index < lines.len() && lines[index].trim_start().starts_with('|') {
+                index += 1;
+            }
+            MarkdownBlockKind::Table
+        } else if is_list_item(line) {
+            index += 1;
+            while index < lines.len() {
+                let next = lines[index];
+                if next.trim().is_empty() {
+                    let continues = lines[index + 1..]
+                        .iter()
+                        .find(|candidate| !candidate.trim().is_empty())
+                        .is_some_and(|candidate| {
+                            is_list_item(candidate) || candidate.starts_with([' ', '\t'])
+                        });
+                    if !continues {
+                        break;
+                    }
+                } else if !is_list_item(next) && !next.starts_with([' ', '\t']) {
+                    break;
+                }
+                index += 1;
+            }
+            MarkdownBlockKind::List
+        } else {
+            index += 1;
+            while index < lines.len() {
+                let next = lines[index].trim_start();
+                if next.is_empty() || code_fence(next).is_some() || is_markdown_heading(next) {
+                    break;
+                }
+                index += 1;
+            }
+            MarkdownBlockKind::Paragraph
+        };
+
+        let text = lines[start..index].join("\n").trim_end().to_owned();
+        let last_line = start + text.lines().count().max(1) - 1;
+        blocks.push(MarkdownBlock {
+            kind,
+            text,
+            lines: (start, last_line),
         });
     }
 
-    if chunks.is_empty() {
-        return vec![DocumentChunk {
-            text: build_chunk_text(normalized_title, normalized_body),
-            index: 0,
-        }];
+    blocks
+}
+
+fn code_fence(line: &str) -> Option<&str> {
+    let fence_char = line
+        .chars()
+        .next()
+        .filter(|character| matches!(character, '`' | '~'))?;
+    let length = line
+        .chars()
+        .take_while(|character| *character == fence_char)
+        .count();
+    (length >= 3).then(|| &line[..length])
+}
+
+fn is_markdown_heading(line: &str) -> bool {
+    let level = line
+        .chars()
+        .take_while(|character| *character == '#')
+        .count();
+    (1..=6).contains(&level) && line[level..].chars().next().is_none_or(char::is_whitespace)
+}
+
+fn is_list_item(line: &str) -> bool {
+    let indent = line.len() - line.trim_start().len();
+    if indent > 3 {
+        return false;
+    }
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("- ") || trimmed.starts_with("* ") || trimmed.starts_with("+ ") {
+        return true;
     }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && (trimmed[digits..].starts_with(". ") || trimmed[digits..].starts_with(") "))
+}
 
-    chunks
+/// Splits a list block at its top-level items, keeping nested lines with their item.
+/// `first_line` is the line the list starts on.
+fn split_list_items(list: &str, first_line: usize) -> Vec<ChunkPiece> {
+    let mut items: Vec<ChunkPiece> = Vec::new();
+    for (position, line) in list.lines().enumerate() {
+        let line_number = first_line + position;
+        match items.last_mut() {
+            Some(item) if !is_list_item(line) || line.starts_with([' ', '\t']) => {
+                item.text.push('\n');
+                item.text.push_str(line);
+                item.lines.1 = line_number;
+            }
+            _ => items.push(line_piece(line, line_number)),
+        }
+    }
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let text = item.text.trim_end();
+            let line_count = text.lines().count();
+            (line_count > 0).then(|| ChunkPiece {
+                text: text.to_owned(),
+                lines: (item.lines.0, item.lines.0 + line_count - 1),
+            })
+        })
+        .collect()
+}
+
+/// Splits prose after sentence-ending punctuation and at line breaks. `first_line` is the
+/// line the text starts on.
+fn split_sentences(text: &str, first_line: usize) -> Vec<ChunkPiece> {
+    let mut sentences = Vec::new();
+    for (position, line) in text.lines().enumerate() {
+        let line_number = first_line + position;
+        let mut start = 0;
+        let characters = line.char_indices().collect::<Vec<_>>();
+        for (index, (offset, character)) in characters.iter().enumerate() {
+            let ends_sentence = matches!(character, '.' | '!' | '?')
+                && characters
+                    .get(index + 1)
+                    .is_some_and(|(_, next)| next.is_whitespace());
+            if ends_sentence {
+                let end = offset + character.len_utf8();
+                sentences.push(line_piece(line[start..end].trim(), line_number));
+                start = end;
+            }
+        }
+        sentences.push(line_piece(line[start..].trim(), line_number));
+    }
+    sentences.retain(|sentence| !sentence.text.is_empty());
+    sentences
 }
 
 pub fn build_document_embedding_source_text(title: &str, body: &str) -> String {
     let normalized_title = title.trim();
     let plain_body = extract_plain_text_from_tiptap_or_raw(body);
+    let plain_body = split_page_segments(&plain_body)
+        .into_iter()
+        .map(|(_, segment)| segment.trim())
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
     if plain_body.is_empty() {
         return normalized_title.to_owned();
     }
@@ -161,17 +1209,29 @@ pub fn build_document_embedding_source_text(title: &str, body: &str) -> String {
     format!("{normalized_title}\n\n{plain_body}")
 }
 
-pub fn format_query_for_embedding(query: &str) -> String {
-    query.trim().to_owned()
+/// Query text as the embedding model expects it: `prefix` is the model's query instruction
+/// (e.g. `"query: "` for e5 models), empty for models trained without one.
+pub fn format_query_for_embedding(query: &str, prefix: &str) -> String {
+    format!("{prefix}{}", query.trim())
+}
+
+/// Document or chunk text as the embedding model expects it, after its passage `prefix`.
+pub fn format_passage_for_embedding(text: &str, prefix: &str) -> String {
+    format!("{prefix}{text}")
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        build_document_embedding_source_text, chunk_document_text,
-        extract_plain_text_from_tiptap_or_raw, format_query_for_embedding,
+        build_document_embedding_source_text, chunk_document_text, chunk_markdown_document,
+        extract_plain_text_from_tiptap_or_raw, format_page_marker, format_passage_for_embedding,
+        format_query_for_embedding, split_page_segments, tiptap_json_to_markdown, ChunkingOptions,
     };
 
+    fn count_words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
     #[test]
     fn extracts_plain_text_from_tiptap_json() {
         let body = r#"{"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"hello"},{"type":"text","text":"world"}]}]}"#;
@@ -187,6 +1247,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn converts_tiptap_document_to_markdown() {
+        let body = r#"{"type":"doc","content":[
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"Plan"}]},
+            {"type":"paragraph","content":[
+                {"type":"text","text":"Read "},
+                {"type":"text","text":"the docs ","marks":[{"type":"bold"}]},
+                {"type":"text","text":"here","marks":[{"type":"link","attrs":{"href":"https://example.com"}}]},
+                {"type":"text","text":" and run "},
+                {"type":"text","text":"cargo test","marks":[{"type":"code"}]}
+            ]},
+            {"type":"bulletList","content":[
+                {"type":"listItem","content":[
+                    {"type":"paragraph","content":[{"type":"text","text":"one"}]},
+                    {"type":"orderedList","attrs":{"start":3},"content":[
+                        {"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"nested"}]}]}
+                    ]}
+                ]}
+            ]},
+            {"type":"taskList","content":[
+                {"type":"taskItem","attrs":{"checked":true},"content":[{"type":"paragraph","content":[{"type":"text","text":"done"}]}]},
+                {"type":"taskItem","attrs":{"checked":false},"content":[{"type":"paragraph","content":[{"type":"text","text":"todo"}]}]}
+            ]},
+            {"type":"codeBlock","attrs":{"language":"rust"},"content":[{"type":"text","text":"fn main() {}"}]},
+            {"type":"table","content":[
+                {"type":"tableRow","content":[
+                    {"type":"tableHeader","content":[{"type":"paragraph","content":[{"type":"text","text":"Name"}]}]},
+                    {"type":"tableHeader","content":[{"type":"paragraph","content":[{"type":"text","text":"Value"}]}]}
+                ]},
+                {"type":"tableRow","content":[
+                    {"type":"tableCell","content":[{"type":"paragraph","content":[{"type":"text","text":"a|b"}]}]},
+                    {"type":"tableCell","content":[{"type":"paragraph","content":[{"type":"text","text":"1"}]}]}
+                ]}
+            ]},
+            {"type":"image","attrs":{"src":"../.attachments/x/photo.png","alt":"Photo","title":null}},
+            {"type":"horizontalRule"}
+        ]}"#;
+
+        let expected = [
+            "## Plan",
+            "Read **the docs** [here](https://example.com) and run `cargo test`",
+            "- one\n  3. nested",
+            "- [x] done\n- [ ] todo",
+            "```rust\nfn main() {}\n```",
+            "| Name | Value |\n| --- | --- |\n| a\\|b | 1 |",
+            "![Photo](../.attachments/x/photo.png)",
+            "---",
+        ]
+        .join("\n\n");
+        assert_eq!(
+            tiptap_json_to_markdown(body).as_deref(),
+            Some(expected.as_str())
+        );
+    }
+
+    #[test]
+    fn escapes_markdown_syntax_in_tiptap_text() {
+        let body = r##"{"type":"doc","content":[
+            {"type":"paragraph","content":[{"type":"text","text":"# not a heading"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"1. not a list, *not* emphasis, snake_case"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"line"},{"type":"hardBreak"},{"type":"text","text":"break"}]}
+        ]}"##;
+
+        assert_eq!(
+            tiptap_json_to_markdown(body).as_deref(),
+            Some("\\# not a heading\n\n1\\. not a list, \\*not\\* emphasis, snake_case\n\nline\\\nbreak")
+        );
+        assert_eq!(tiptap_json_to_markdown("# Already markdown"), None);
+        assert_eq!(tiptap_json_to_markdown(r#"{"type":"paragraph"}"#), None);
+    }
+
     #[test]
     fn chunks_long_body_and_keeps_indices() {
         let long = format!("{}\n\n{}", "a".repeat(900), "b".repeat(900));
@@ -196,6 +1327,168 @@ mod tests {
         assert_eq!(chunks[1].index, 1);
     }
 
+    #[test]
+    fn chunks_markdown_at_headings_and_keeps_blocks_whole() {
+        let body = [
+            "# Setup",
+            "Install the toolchain before anything else.",
+            "```sh\ncargo install tentacle\n\ntentacle init\n```",
+            "- first step\n- second step\n  with details",
+            "## Results",
+            "| name | value |\n| --- | --- |\n| speed | fast |",
+        ]
+        .join("\n\n");
+        let options = ChunkingOptions {
+            chunk_tokens: 24,
+            overlap_tokens: 0,
+        };
+
+        let chunks = chunk_markdown_document("Guide", &body, &options, &count_words);
+        let texts = chunks
+            .iter()
+            .map(|chunk| chunk.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            vec![
+                "Guide\n\n# Setup\n\nInstall the toolchain before anything else.\n\n```sh\ncargo install tentacle\n\ntentacle init\n```\n\n- first step\n- second step\n  with details",
+                "Guide\n\n## Results\n\n| name | value |\n| --- | --- |\n| speed | fast |",
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_oversized_blocks_along_their_structure() {
+        let code_lines = (1..=12)
+            .map(|line| format!("let value_{line} = {line};"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let body = format!(
+            "## Code\n\n```rust\n{code_lines}\n```\n\nFirst sentence here. Second sentence follows. Third one ends it."
+        );
+        let options = ChunkingOptions {
+            chunk_tokens: 20,
+            overlap_tokens: 4,
+        };
+
+        let chunks = chunk_markdown_document("", &body, &options, &count_words);
+        assert!(chunks.len() > 2);
+        for chunk in &chunks {
+            assert!(count_words(&chunk.text) <= 20, "{:?}", chunk.text);
+            assert!(chunk.text.starts_with("## Code"), "{:?}", chunk.text);
+            if chunk.text.contains("let value_") {
+                assert!(chunk.text.contains("```rust\n") && chunk.text.ends_with("```"));
+            }
+        }
+        let last = &chunks[chunks.len() - 1].text;
+        assert_eq!(
+            last,
+            "## Code\n\nFirst sentence here. Second sentence follows. Third one ends it."
+        );
+    }
+
+    #[test]
+    fn repeats_tail_of_previous_chunk_as_overlap() {
+        let body = "Alpha beta gamma delta epsilon.\n\nZeta eta theta iota kappa.\n\nLambda mu nu xi omicron.";
+        let options = ChunkingOptions {
+            chunk_tokens: 10,
+            overlap_tokens: 2,
+        };
+
+        let chunks = chunk_markdown_document("", body, &options, &count_words);
+        let texts = chunks
+            .iter()
+            .map(|chunk| chunk.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            vec![
+                "Alpha beta gamma delta epsilon.\n\nZeta eta theta iota kappa.",
+                "iota kappa.\n\nLambda mu nu xi omicron.",
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_paginated_body_per_page() {
+        let body = format!(
+            "{} intro {} {} {}",
+            format_page_marker(1),
+            format_page_marker(2),
+            "x".repeat(900),
+            format_page_marker(12)
+        ) + " closing words";
+        let segments = split_page_segments(&body);
+        assert_eq!(segments.len(), 3);
+
+        let chunks = chunk_document_text("Manual", &body);
+        let pages = chunks.iter().map(|chunk| chunk.page).collect::<Vec<_>>();
+        assert_eq!(pages, vec![Some(1), Some(2), Some(12)]);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.index).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(chunks[2].text, "Manual\n\nclosing words");
+        assert!(chunks.iter().all(|chunk| !chunk.text.contains("<!--")));
+        assert_eq!(
+            (chunks[2].line_start, chunks[2].line_end),
+            (1, 1),
+            "single-line bodies keep every page on line 1"
+        );
+    }
+
+    #[test]
+    fn records_heading_path_and_source_range_of_chunks() {
+        let body = [
+            "# Guide",
+            "Welcome to the guïde.",
+            "## Install",
+            "Run the installer.\nThen restart.",
+            "### Linux",
+            "Use the package manager.",
+            "## Usage",
+            "Open the app.",
+        ]
+        .join("\n\n");
+        let options = ChunkingOptions {
+            chunk_tokens: 8,
+            overlap_tokens: 0,
+        };
+
+        let chunks = chunk_markdown_document("Guide", &body, &options, &count_words);
+        let paths = chunks
+            .iter()
+            .map(|chunk| chunk.heading_path.join(" > "))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "Guide",
+                "Guide > Install",
+                "Guide > Install > Linux",
+                "Guide > Usage"
+            ]
+        );
+        let lines = chunks
+            .iter()
+            .map(|chunk| (chunk.line_start, chunk.line_end))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![(1, 3), (5, 8), (10, 12), (14, 16)]);
+
+        let source = |chunk: &super::DocumentChunk| {
+            body.chars()
+                .skip(chunk.char_start)
+                .take(chunk.char_end - chunk.char_start)
+                .collect::<String>()
+        };
+        assert_eq!(source(&chunks[0]), "# Guide\n\nWelcome to the guïde.");
+        assert_eq!(
+            source(&chunks[1]),
+            "## Install\n\nRun the installer.\nThen restart."
+        );
+        assert_eq!(source(&chunks[3]), "## Usage\n\nOpen the app.");
+    }
+
     #[test]
     fn builds_embedding_source_text() {
         let source = build_document_embedding_source_text(
@@ -207,7 +1500,14 @@ mod tests {
 
     #[test]
     fn formats_query_instruction() {
-        let formatted = format_query_for_embedding("my query");
-        assert_eq!(formatted, "my query");
+        assert_eq!(format_query_for_embedding(" my query ", ""), "my query");
+        assert_eq!(
+            format_query_for_embedding("my query", "query: "),
+            "query: my query"
+        );
+        assert_eq!(
+            format_passage_for_embedding("Title\n\nbody", "passage: "),
+            "passage: Title\n\nbody"
+        );
     }
 }
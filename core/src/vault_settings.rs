@@ -0,0 +1,182 @@
+//! Settings stored inside the documents folder rather than in `config.db`, so they belong to
+//! the vault: they travel with backups and apply on every machine that opens it.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::languages::{SearchLanguage, DEFAULT_SEARCH_LANGUAGES};
+use crate::text_processing::ChunkingOptions;
+
+pub const VAULT_SETTINGS_FILE_NAME: &str = ".tentacle-settings.json";
+pub const MIN_CHUNK_TOKENS: usize = 32;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct VaultSettings {
+    pub chunking: ChunkingOptions,
+    /// Abbreviations expanded for semantic search, on top of `query::DEFAULT_ABBREVIATIONS`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub abbreviations: BTreeMap<String, String>,
+    /// Sentence-transformer ONNX model (Hugging Face repo id or local directory) used for
+    /// embeddings; `embeddings::LOCAL_EMBEDDING_MODEL_ID` when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    /// Languages whose stopwords and stemming apply to BM25 queries; English when empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub search_languages: Vec<SearchLanguage>,
+}
+
+impl VaultSettings {
+    /// Configured search languages, or [`DEFAULT_SEARCH_LANGUAGES`].
+    pub fn search_languages(&self) -> Vec<SearchLanguage> {
+        if self.search_languages.is_empty() {
+            DEFAULT_SEARCH_LANGUAGES.to_vec()
+        } else {
+            self.search_languages.clone()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), VaultSettingsError> {
+        let chunking = &self.chunking;
+        if chunking.chunk_tokens < MIN_CHUNK_TOKENS {
+            return Err(VaultSettingsError::Validation(format!(
+                "chunk_tokens must be at least {MIN_CHUNK_TOKENS}"
+            )));
+        }
+        if chunking.overlap_tokens * 2 > chunking.chunk_tokens {
+            return Err(VaultSettingsError::Validation(
+                "chunk_overlap_tokens must be at most half of chunk_tokens".to_owned(),
+            ));
+        }
+        if self
+            .embedding_model
+            .as_deref()
+            .is_some_and(|model| model.trim().is_empty() || model.trim() != model)
+        {
+            return Err(VaultSettingsError::Validation(
+                "embedding_model must be a non-empty model id or path without surrounding spaces"
+                    .to_owned(),
+            ));
+        }
+        for (abbreviation, expansion) in &self.abbreviations {
+            if abbreviation.is_empty()
+                || abbreviation.chars().any(char::is_whitespace)
+                || abbreviation.to_lowercase() != *abbreviation
+            {
+                return Err(VaultSettingsError::Validation(format!(
+                    "abbreviation \"{abbreviation}\" must be one lowercase word"
+                )));
+            }
+            if expansion.trim().is_empty() {
+                return Err(VaultSettingsError::Validation(format!(
+                    "abbreviation \"{abbreviation}\" needs an expansion"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum VaultSettingsError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid vault settings: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("validation error: {0}")]
+    Validation(String),
+}
+
+/// Reads the vault settings, falling back to defaults when the file does not exist.
+pub fn load_vault_settings(documents_folder: &Path) -> Result<VaultSettings, VaultSettingsError> {
+    let path = documents_folder.join(VAULT_SETTINGS_FILE_NAME);
+    if !path.is_file() {
+        return Ok(VaultSettings::default());
+    }
+
+    let settings = serde_json::from_str::<VaultSettings>(&std::fs::read_to_string(path)?)?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+pub fn save_vault_settings(
+    documents_folder: &Path,
+    settings: &VaultSettings,
+) -> Result<(), VaultSettingsError> {
+    settings.validate()?;
+    std::fs::create_dir_all(documents_folder)?;
+
+    let mut contents = serde_json::to_string_pretty(settings)?;
+    contents.push('\n');
+    std::fs::write(documents_folder.join(VAULT_SETTINGS_FILE_NAME), contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use super::{load_vault_settings, save_vault_settings, VaultSettings};
+    use crate::languages::{SearchLanguage, DEFAULT_SEARCH_LANGUAGES};
+    use crate::text_processing::ChunkingOptions;
+
+    #[test]
+    fn saves_and_loads_settings_with_defaults_for_missing_file() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock must be after unix epoch")
+            .as_nanos();
+        let folder = std::env::temp_dir().join(format!("tentacle-vault-settings-{timestamp}"));
+
+        assert_eq!(
+            load_vault_settings(&folder).expect("defaults should load"),
+            VaultSettings::default()
+        );
+        assert_eq!(
+            VaultSettings::default().search_languages(),
+            DEFAULT_SEARCH_LANGUAGES.to_vec()
+        );
+
+        let settings = VaultSettings {
+            chunking: ChunkingOptions {
+                chunk_tokens: 128,
+                overlap_tokens: 16,
+            },
+            abbreviations: [("pr".to_owned(), "pull request".to_owned())].into(),
+            embedding_model: Some("intfloat/multilingual-e5-small".to_owned()),
+            search_languages: vec![SearchLanguage::Portuguese, SearchLanguage::English],
+        };
+        save_vault_settings(&folder, &settings).expect("settings should save");
+        assert_eq!(
+            load_vault_settings(&folder).expect("settings should load"),
+            settings
+        );
+
+        let invalid = VaultSettings {
+            chunking: ChunkingOptions {
+                chunk_tokens: 64,
+                overlap_tokens: 40,
+            },
+            ..VaultSettings::default()
+        };
+        assert!(save_vault_settings(&folder, &invalid).is_err());
+
+        let invalid_abbreviation = VaultSettings {
+            abbreviations: [("Pull Request".to_owned(), "pr".to_owned())].into(),
+            ..VaultSettings::default()
+        };
+        assert!(save_vault_settings(&folder, &invalid_abbreviation).is_err());
+
+        let blank_model = VaultSettings {
+            embedding_model: Some(" ".to_owned()),
+            ..VaultSettings::default()
+        };
+        assert!(save_vault_settings(&folder, &blank_model).is_err());
+
+        let _ = std::fs::remove_dir_all(folder);
+    }
+}
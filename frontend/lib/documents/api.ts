@@ -9,6 +9,7 @@ import {
 import {
   deleteDocumentEmbeddings,
   hybridSearchDocumentsByQuery,
+  preprocessSearchQuery,
   syncDocumentEmbeddings,
 } from '@/lib/documents/embeddings-cache'
 import {
@@ -27,7 +28,6 @@ import type {
   HybridSearchHit,
   UpdateDocumentPayload,
 } from '@/types/documents'
-import { preprocessQuery } from '@/lib/ai/query-preprocessor'
 const STORAGE_UNAVAILABLE_ERROR_MESSAGE = 'Local documents storage is unavailable. Open Tentacle in the desktop app to access your files.'
 const DEFAULT_TITLE = 'Untitled'
 const TRASH_FOLDER_NAME = '.trash'
@@ -1152,15 +1152,14 @@ export async function semanticSearchDocuments(
     return []
   }
 
-  const { normalized, ftsQuery, semanticWeight, bm25Weight } = preprocessQuery(normalizedQuery)
-
   const normalizedOptions = options ?? {}
   const folder = await getConfiguredDocumentsFolder()
+  const processed = await preprocessSearchQuery(folder, normalizedQuery)
   return await hybridSearchDocumentsByQuery(folder, {
-    query_text: ftsQuery,
-    semantic_query_text: normalized,
-    semantic_weight: semanticWeight,
-    bm25_weight: bm25Weight,
+    query_text: normalizedQuery,
+    semantic_query_text: processed.semantic_text,
+    semantic_weight: processed.semantic_weight,
+    bm25_weight: processed.bm25_weight,
     limit: normalizedOptions.limit,
     min_score: normalizedOptions.min_score,
     exclude_document_id: normalizedOptions.exclude_document_id,
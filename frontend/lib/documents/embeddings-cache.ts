@@ -1,7 +1,11 @@
 import { invoke } from '@tauri-apps/api/core'
 import type {
   DocumentEmbeddingMetadata,
+  HighlightedSearchText,
   HybridSearchHit,
+  PreprocessedSearchQuery,
+  RelatedDocumentHit,
+  SearchMatchRange,
 } from '@/types/documents'
 
 const DEFAULT_LIMIT = 20
@@ -137,6 +141,39 @@ function toDocumentEmbeddingMetadata(payload: unknown): DocumentEmbeddingMetadat
   }
 }
 
+// The core counts match offsets in Unicode code points; JS strings index by UTF-16 code units,
+// so ranges are converted against their text or they drift after emoji and other non-BMP text.
+function toUtf16MatchRanges(text: string, payload: unknown): SearchMatchRange[] {
+  if (!Array.isArray(payload)) {
+    return []
+  }
+
+  const utf16Offsets = [0]
+  for (const character of text) {
+    utf16Offsets.push(utf16Offsets[utf16Offsets.length - 1] + character.length)
+  }
+  const toUtf16Offset = (value: unknown): number => {
+    const codePointOffset = Math.trunc(normalizeFiniteNumber(value, 0))
+    return utf16Offsets[Math.min(Math.max(codePointOffset, 0), utf16Offsets.length - 1)]
+  }
+
+  return payload
+    .filter((range): range is RecordLike => !!range && typeof range === 'object')
+    .map((range) => ({ start: toUtf16Offset(range.start), end: toUtf16Offset(range.end) }))
+    .filter((range) => range.end > range.start)
+}
+
+function toHighlightedSearchText(text: unknown, matches: unknown): HighlightedSearchText | null {
+  if (typeof text !== 'string' || text.length === 0) {
+    return null
+  }
+
+  return {
+    text,
+    matches: toUtf16MatchRanges(text, matches),
+  }
+}
+
 function toHybridSearchHit(payload: unknown): HybridSearchHit | null {
   if (!payload || typeof payload !== 'object') {
     return null
@@ -149,9 +186,33 @@ function toHybridSearchHit(payload: unknown): HybridSearchHit | null {
   }
 
   const score = normalizeFiniteNumber(cached.score, 0)
+  const snippet = cached.snippet && typeof cached.snippet === 'object'
+    ? cached.snippet as RecordLike
+    : {}
   return {
     document_id: documentId,
     score,
+    page: typeof cached.page === 'number' && Number.isFinite(cached.page) ? cached.page : null,
+    title: toHighlightedSearchText(cached.title, cached.title_matches ?? cached.titleMatches),
+    snippet: toHighlightedSearchText(snippet.text, snippet.matches),
+  }
+}
+
+function toRelatedDocumentHit(payload: unknown): RelatedDocumentHit | null {
+  const hit = toHybridSearchHit(payload)
+  if (!hit) {
+    return null
+  }
+
+  const cached = payload as RecordLike
+  const sharedTags = Array.isArray(cached.shared_tags)
+    ? cached.shared_tags.filter((tag): tag is string => typeof tag === 'string')
+    : []
+  return {
+    document_id: hit.document_id,
+    score: hit.score,
+    shared_tags: sharedTags,
+    linked: cached.linked === true,
   }
 }
 
@@ -307,3 +368,50 @@ export async function hybridSearchDocumentsByQuery(
       return a.document_id.localeCompare(b.document_id)
     })
 }
+
+export async function preprocessSearchQuery(
+  folder: string,
+  queryText: string,
+): Promise<PreprocessedSearchQuery> {
+  const normalizedFolder = normalizeFolder(folder)
+  const normalizedQuery = normalizeString(queryText)
+
+  const payload = await invoke<unknown>('preprocess_search_query', {
+    ...createFolderArgs(normalizedFolder),
+    queryText: normalizedQuery,
+    query_text: normalizedQuery,
+  })
+
+  const processed = (payload ?? {}) as RecordLike
+  return {
+    semantic_text: normalizeString(processed.semantic_text, normalizedQuery),
+    semantic_weight: normalizeFiniteNumber(processed.semantic_weight, 0.5),
+    bm25_weight: normalizeFiniteNumber(processed.bm25_weight, 0.5),
+  }
+}
+
+export async function findRelatedDocuments(
+  folder: string,
+  documentId: string,
+  limit?: number,
+): Promise<RelatedDocumentHit[]> {
+  const normalizedFolder = normalizeFolder(folder)
+  const normalizedDocumentId = normalizeString(documentId)
+  if (normalizedDocumentId.length === 0) {
+    return []
+  }
+
+  const payload = await invoke<unknown>('find_related_cached_documents', {
+    ...createFolderArgs(normalizedFolder),
+    ...createDocumentIdArgs(normalizedDocumentId),
+    limit: normalizeLimit(limit),
+  })
+
+  if (!Array.isArray(payload)) {
+    return []
+  }
+
+  return payload
+    .map(toRelatedDocumentHit)
+    .filter((hit): hit is RelatedDocumentHit => hit !== null)
+}
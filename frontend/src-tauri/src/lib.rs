@@ -2,11 +2,12 @@ use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use tauri::{Emitter, Manager};
-use tentacle_core::config::ConfigStore;
+use tentacle_core::config::{ConfigStore, KEY_MODEL_PATH};
 use tentacle_core::document_cache::{
     CachedDocumentChunkEmbeddingPayload, CachedDocumentEmbeddingMetadataPayload,
     CachedDocumentEmbeddingPayload, CachedDocumentPayload, CachedDocumentTagPayload,
-    DocumentCacheStore, HybridSearchHitPayload, SemanticSearchHitPayload,
+    DocumentCacheStore, HybridSearchHitPayload, RelatedDocumentHitPayload,
+    SemanticSearchHitPayload,
 };
 use tentacle_core::document_folders::{
     DeleteDocumentFolderInputPayload, DocumentFolderPayload, DocumentFoldersService,
@@ -15,11 +16,14 @@ use tentacle_core::document_folders::{
 use tentacle_core::embeddings::{
     delete_document_embeddings as delete_document_embeddings_in_core,
     hybrid_search_documents_by_query as hybrid_search_documents_by_query_in_core,
-    preload_embedding_model as preload_embedding_model_in_core,
-    sync_document_embeddings as sync_document_embeddings_in_core,
+    preload_embedding_model as preload_embedding_model_in_core, set_embedding_model_path,
+    set_installed_models_directory, sync_document_embeddings as sync_document_embeddings_in_core,
     sync_documents_embeddings_batch as sync_documents_embeddings_batch_in_core,
     EmbeddingBatchSyncResultPayload, EmbeddingModelLoadStatePayload, EmbeddingSyncDocumentPayload,
 };
+use tentacle_core::knowledge_base::KnowledgeBaseService;
+use tentacle_core::models::models_directory;
+use tentacle_core::query::PreprocessedQuery;
 
 const EMBEDDING_MODEL_LOAD_EVENT: &str = "embedding-model-load-state";
 
@@ -105,7 +109,20 @@ fn set_config(
     store: tauri::State<'_, Mutex<ConfigStore>>,
 ) -> Result<(), String> {
     let store = store.lock().map_err(|err| err.to_string())?;
-    store.set(&key, &value).map_err(|err| err.to_string())
+    store.set(&key, &value).map_err(|err| err.to_string())?;
+    if key == KEY_MODEL_PATH {
+        apply_model_path_setting(&store);
+    }
+    Ok(())
+}
+
+/// Points the embedding engine at the configured `model_path`, so models installed for
+/// offline use load without hf-hub.
+fn apply_model_path_setting(store: &ConfigStore) {
+    match store.model_path() {
+        Ok(model_path) => set_embedding_model_path(model_path),
+        Err(error) => log::warn!("[config] Failed to read model_path: {}", error),
+    }
 }
 
 #[tauri::command]
@@ -253,6 +270,19 @@ fn hybrid_search_cached_documents(
         .map_err(|err| err.to_string())
 }
 
+#[tauri::command]
+fn find_related_cached_documents(
+    documents_folder: String,
+    document_id: String,
+    limit: usize,
+) -> Result<Vec<RelatedDocumentHitPayload>, String> {
+    let store =
+        DocumentCacheStore::new(Path::new(&documents_folder)).map_err(|err| err.to_string())?;
+    store
+        .related_documents(&document_id, limit)
+        .map_err(|err| err.to_string())
+}
+
 #[tauri::command]
 fn replace_cached_document_chunk_embeddings(
     documents_folder: String,
@@ -319,6 +349,15 @@ fn hybrid_search_documents_by_query(
     .map_err(|err| err.to_string())
 }
 
+#[tauri::command]
+fn preprocess_search_query(
+    documents_folder: String,
+    query_text: String,
+) -> Result<PreprocessedQuery, String> {
+    KnowledgeBaseService::preprocess_query(Path::new(&documents_folder), &query_text)
+        .map_err(|err| err.to_string())
+}
+
 #[tauri::command]
 fn list_document_folders(documents_folder: String) -> Result<Vec<DocumentFolderPayload>, String> {
     DocumentFoldersService::list_folders(Path::new(&documents_folder))
@@ -424,11 +463,13 @@ pub fn run() {
             replace_cached_document_embeddings,
             semantic_search_cached_documents,
             hybrid_search_cached_documents,
+            find_related_cached_documents,
             replace_cached_document_chunk_embeddings,
             sync_document_embeddings,
             sync_documents_embeddings_batch,
             delete_document_embeddings,
             hybrid_search_documents_by_query,
+            preprocess_search_query,
             list_document_folders,
             create_document_folder,
             rename_document_folder,
@@ -451,6 +492,8 @@ pub fn run() {
                 tentacle_core::config::default_data_dir().expect("no data dir")
             });
             let store = ConfigStore::new(&data_dir).expect("failed to init config");
+            apply_model_path_setting(&store);
+            set_installed_models_directory(Some(models_directory(&data_dir)));
             app.manage(Mutex::new(store));
             let embedding_runtime = EmbeddingRuntimeState::new();
             app.manage(embedding_runtime.clone());
@@ -63,9 +63,38 @@ export interface SemanticSearchHit {
   score: number
 }
 
+/** Query match in a text as UTF-16 offsets (end exclusive), ready for `String.prototype.slice`. */
+export interface SearchMatchRange {
+  start: number
+  end: number
+}
+
+export interface HighlightedSearchText {
+  text: string
+  matches: SearchMatchRange[]
+}
+
 export interface HybridSearchHit {
   document_id: string
   score: number
+  page: number | null
+  /** Title the `title.matches` index into; null for hits without lexical matches. */
+  title: HighlightedSearchText | null
+  /** Body excerpt around the query matches; only lexical hits have one. */
+  snippet: HighlightedSearchText | null
+}
+
+export interface RelatedDocumentHit {
+  document_id: string
+  score: number
+  shared_tags: string[]
+  linked: boolean
+}
+
+export interface PreprocessedSearchQuery {
+  semantic_text: string
+  semantic_weight: number
+  bm25_weight: number
 }
 
 export interface CachedDocumentChunkEmbeddingPayload {